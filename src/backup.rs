@@ -0,0 +1,100 @@
+use std::io::{ErrorKind as IoErrorKind, Read, Write};
+use crate::{ErrorKind, MiniBaseError, MiniBaseResult};
+
+// 导出的每个key value写入为[key长度 u32][key][value长度 u32][value]，长度按小端序编码
+// 与页的布局无关，可以导入到页大小或版本不同的schema中
+pub(crate) fn write_key_value<W: Write>(writer: &mut W, key: &[u8], value: &[u8]) -> MiniBaseResult<()> {
+    writer.write_all(&(key.len() as u32).to_le_bytes())?;
+    writer.write_all(key)?;
+    writer.write_all(&(value.len() as u32).to_le_bytes())?;
+    writer.write_all(value)?;
+    Ok(())
+}
+
+// 依次读取导出的key value，读取出错或数据不完整时返回错误并停止
+pub(crate) struct BackupReader<'a, R: Read> {
+    reader: &'a mut R,
+    finished: bool,
+}
+
+impl<'a, R: Read> BackupReader<'a, R> {
+    pub(crate) fn new(reader: &'a mut R) -> BackupReader<'a, R> {
+        BackupReader { reader, finished: false }
+    }
+
+    // 在记录的开头读取到结尾表示正常结束，返回None
+    fn read_key_value(&mut self) -> MiniBaseResult<Option<(Vec<u8>, Vec<u8>)>> {
+        let mut length = [0; 4];
+        let mut read = 0;
+        while read < length.len() {
+            match self.reader.read(&mut length[read..]) {
+                Ok(0) if read == 0 => return Ok(None),
+                Ok(0) => return Err(truncated()),
+                Ok(size) => read += size,
+                Err(error) if error.kind() == IoErrorKind::Interrupted => {}
+                Err(error) => return Err(Box::from(error)),
+            }
+        }
+        let key = self.read_bytes(u32::from_le_bytes(length) as usize)?;
+        let value_length = self.read_bytes(4)?;
+        let value = self.read_bytes(u32::from_le_bytes(value_length.try_into().unwrap()) as usize)?;
+        Ok(Some((key, value)))
+    }
+
+    fn read_bytes(&mut self, length: usize) -> MiniBaseResult<Vec<u8>> {
+        let mut data = Vec::new();
+        // 长度来自外部数据，按实际读取到的数据分配空间
+        self.reader.by_ref().take(length as u64).read_to_end(&mut data)?;
+        if data.len() != length {
+            return Err(truncated());
+        }
+        Ok(data)
+    }
+}
+
+impl<R: Read> Iterator for BackupReader<'_, R> {
+    type Item = MiniBaseResult<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let result = self.read_key_value().transpose();
+        if !matches!(result, Some(Ok(_))) {
+            self.finished = true;
+        }
+        result
+    }
+}
+
+fn truncated() -> Box<MiniBaseError> {
+    Box::from(MiniBaseError::new(ErrorKind::Corruption, "backup data truncated"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backup::{write_key_value, BackupReader};
+    use crate::{error_kind, ErrorKind};
+
+    #[test]
+    fn backup_key_values() {
+        let mut data = Vec::new();
+        write_key_value(&mut data, b"key", b"value").unwrap();
+        write_key_value(&mut data, b"", b"").unwrap();
+        let key_values: Vec<_> = BackupReader::new(&mut data.as_slice()).collect::<Result<_, _>>().unwrap();
+        assert_eq!(vec![(b"key".to_vec(), b"value".to_vec()), (Vec::new(), Vec::new())], key_values);
+        assert_eq!(0, BackupReader::new(&mut [].as_slice()).count());
+
+        // 在记录中间截断时返回错误，第一个记录长度为16
+        for length in (1..data.len()).filter(|length| *length != 16) {
+            let mut truncated = &data[..length];
+            let mut reader = BackupReader::new(&mut truncated);
+            if length > 16 {
+                assert!(reader.next().unwrap().is_ok());
+            }
+            let error = reader.next().unwrap().err().unwrap();
+            assert_eq!(ErrorKind::Corruption, error_kind(error.as_ref()));
+            assert!(reader.next().is_none());
+        }
+    }
+}
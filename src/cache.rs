@@ -0,0 +1,154 @@
+use std::collections::{HashMap, HashSet};
+use crate::MiniBaseResult;
+use crate::node::Node;
+
+// 按页偏移缓存已经映射的节点，避免每次从根节点查找时重新映射同一个页
+// 使用节点时从缓存中取出，使用结束后放回，超过容量时淘汰最久未使用的节点
+pub(crate) struct PageCache {
+    capacity: usize,
+    tick: u64,
+    nodes: HashMap<u64, (Node, u64)>,
+    // 被修改过还没有写回文件的页，节点被取出时仍然保留
+    dirty_offsets: HashSet<u64>,
+    // 写回文件的页数量，用于统计写入的字节数
+    flushed_page_count: u64,
+}
+
+impl PageCache {
+    pub(crate) fn new(capacity: usize) -> PageCache {
+        PageCache { capacity, tick: 0, nodes: HashMap::new(), dirty_offsets: HashSet::new(), flushed_page_count: 0 }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub(crate) fn get_flushed_page_count(&self) -> u64 {
+        self.flushed_page_count
+    }
+
+    pub(crate) fn reset_flushed_page_count(&mut self) {
+        self.flushed_page_count = 0;
+    }
+
+    // 修改容量，超出新容量的节点会被淘汰
+    pub(crate) fn set_capacity(&mut self, capacity: usize) -> MiniBaseResult<()> {
+        self.capacity = capacity;
+        while self.nodes.len() > self.capacity {
+            self.evict()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn take(&mut self, offset: u64) -> Option<Node> {
+        self.nodes.remove(&offset).map(|(node, _)| node)
+    }
+
+    // 将使用结束的节点放回缓存，dirty表示使用期间是否修改了页
+    pub(crate) fn release(&mut self, node: Node, dirty: bool) -> MiniBaseResult<()> {
+        let offset = node.get_offset();
+        if dirty {
+            self.dirty_offsets.insert(offset);
+        }
+        if self.capacity == 0 {
+            return self.write_back(node);
+        }
+        self.tick += 1;
+        self.nodes.insert(offset, (node, self.tick));
+        while self.nodes.len() > self.capacity {
+            self.evict()?;
+        }
+        Ok(())
+    }
+
+    // 页被释放或重新分配后，缓存中的节点已经失效
+    pub(crate) fn invalidate(&mut self, offset: u64) {
+        self.nodes.remove(&offset);
+        self.dirty_offsets.remove(&offset);
+    }
+
+    // 将缓存中所有修改过的页写回文件，不在缓存中的页由调用方通过fsync写回
+    pub(crate) fn flush(&mut self) -> MiniBaseResult<()> {
+        for (offset, (node, _)) in self.nodes.iter_mut() {
+            if self.dirty_offsets.contains(offset) {
+                node.flush()?;
+                self.flushed_page_count += 1;
+            }
+        }
+        self.dirty_offsets.clear();
+        Ok(())
+    }
+
+    // 写回修改过的页并清空缓存，页在文件中的位置改变之前需要先解除映射
+    pub(crate) fn clear(&mut self) -> MiniBaseResult<()> {
+        self.flush()?;
+        self.nodes.clear();
+        Ok(())
+    }
+
+    // 淘汰最久未使用的节点，修改过的页先写回文件
+    fn evict(&mut self) -> MiniBaseResult<()> {
+        let offset = match self.nodes.iter().min_by_key(|(_, (_, last_used))| *last_used) {
+            Some((offset, _)) => *offset,
+            None => return Ok(()),
+        };
+        let (node, _) = self.nodes.remove(&offset).unwrap();
+        self.write_back(node)
+    }
+
+    fn write_back(&mut self, mut node: Node) -> MiniBaseResult<()> {
+        if self.dirty_offsets.remove(&node.get_offset()) {
+            node.flush()?;
+            self.flushed_page_count += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::cache::PageCache;
+    use crate::create_schema;
+    use crate::node::{Node, NodeType};
+    use crate::storage::Storage;
+
+    const PAGE_SIZE: u32 = 512;
+
+    #[test]
+    fn page_cache_evict() {
+        let dir_name = "page_cache_evict";
+        fs::create_dir_all(dir_name).unwrap();
+        let mete_data = create_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap();
+        let data_file = Storage::from(fs::OpenOptions::new().read(true).write(true).create(true).truncate(false)
+            .open(format!("{}/test.d", dir_name)).unwrap());
+        data_file.set_len(4 * PAGE_SIZE as u64).unwrap();
+
+        let page_size = PAGE_SIZE as u64;
+        let mut page_cache = PageCache::new(2);
+        for offset in [0, page_size, 2 * page_size] {
+            let node = Node::new(&mete_data, &data_file, offset, NodeType::Leaf).unwrap();
+            page_cache.release(node, offset == 0).unwrap();
+        }
+        // 超过容量时淘汰最久未使用的节点
+        assert_eq!(2, page_cache.len());
+        assert!(page_cache.take(0).is_none());
+        // 淘汰的节点修改过，需要写回文件
+        assert_eq!(1, page_cache.get_flushed_page_count());
+        let node = page_cache.take(page_size).unwrap();
+        assert_eq!(page_size, node.get_offset());
+        page_cache.release(node, true).unwrap();
+        page_cache.release(Node::new(&mete_data, &data_file, 3 * page_size, NodeType::Leaf).unwrap(), false).unwrap();
+        assert!(page_cache.take(2 * page_size).is_none());
+
+        page_cache.invalidate(page_size);
+        assert!(page_cache.take(page_size).is_none());
+        page_cache.set_capacity(0).unwrap();
+        assert_eq!(0, page_cache.len());
+        assert_eq!(1, page_cache.get_flushed_page_count());
+        page_cache.release(Node::new(&mete_data, &data_file, 0, NodeType::Leaf).unwrap(), true).unwrap();
+        assert_eq!(2, page_cache.get_flushed_page_count());
+
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+}
@@ -0,0 +1,120 @@
+// CRC32（IEEE 802.3）校验
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+// 在已有的crc基础上继续计算data的crc，用于分段计算
+pub(crate) fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for byte in data {
+        crc = CRC32_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+// 数据中一段从old修改为new后，根据修改前的crc计算修改后的crc，trailing为修改位置之后的数据长度
+// 长度相同的数据的crc之差只与两者的异或有关，只需计算异或部分的crc，再乘以x^(8 * trailing)移到末尾
+// 计算量与修改的长度和trailing的对数成正比，与整个数据的长度无关
+pub(crate) fn crc32_patch(crc: u32, old: &[u8], new: &[u8], trailing: usize) -> u32 {
+    let mut delta = 0u32;
+    for (old, new) in old.iter().zip(new) {
+        delta = CRC32_TABLE[((delta ^ (old ^ new) as u32) & 0xff) as usize] ^ (delta >> 8);
+    }
+    if delta == 0 {
+        return crc;
+    }
+    crc ^ multiply_mod(x_pow_8n_mod(trailing), delta)
+}
+
+const POLYNOMIAL: u32 = 0xedb8_8320;
+
+// 反射表示下两个多项式模POLYNOMIAL的乘积，最高位表示x^0，a不能为0
+const fn multiply_mod(a: u32, mut b: u32) -> u32 {
+    let mut mask = 1u32 << 31;
+    let mut product = 0;
+    loop {
+        if a & mask != 0 {
+            product ^= b;
+            if a & (mask - 1) == 0 {
+                return product;
+            }
+        }
+        mask >>= 1;
+        b = if b & 1 == 1 { (b >> 1) ^ POLYNOMIAL } else { b >> 1 };
+    }
+}
+
+// X2N_TABLE[k]为x^(2^k)模POLYNOMIAL
+const X2N_TABLE: [u32; 64] = x2n_table();
+
+const fn x2n_table() -> [u32; 64] {
+    let mut table = [0u32; 64];
+    let mut power = 1u32 << 30;
+    let mut k = 0;
+    while k < 64 {
+        table[k] = power;
+        power = multiply_mod(power, power);
+        k += 1;
+    }
+    table
+}
+
+// x^(8 * n)模POLYNOMIAL，即在数据末尾追加n个0字节对crc的影响
+fn x_pow_8n_mod(mut n: usize) -> u32 {
+    let mut power = 1u32 << 31;
+    let mut k = 3;
+    while n != 0 {
+        if n & 1 == 1 {
+            power = multiply_mod(X2N_TABLE[k], power);
+        }
+        n >>= 1;
+        k += 1;
+    }
+    power
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checksum::{crc32, crc32_patch, crc32_update};
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(0xcbf4_3926, crc32("123456789".as_bytes()));
+        assert_eq!(crc32("123456789".as_bytes()), crc32_update(crc32("1234".as_bytes()), "56789".as_bytes()));
+    }
+
+    #[test]
+    fn crc32_patch_matches_recompute() {
+        let mut data: Vec<u8> = (0..3000u32).map(|i| (i * 7 + i / 13) as u8).collect();
+        let mut crc = crc32(&data);
+        let mut seed = 1u32;
+        for _ in 0..500 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let offset = (seed >> 8) as usize % data.len();
+            let length = ((seed >> 20) as usize % 9).min(data.len() - offset);
+            let new: Vec<u8> = (0..length).map(|i| (seed >> (i % 4 * 8)) as u8 ^ i as u8).collect();
+            crc = crc32_patch(crc, &data[offset..offset + length], &new, data.len() - offset - length);
+            data[offset..offset + length].copy_from_slice(&new);
+            assert_eq!(crc32(&data), crc);
+        }
+        assert_eq!(crc, crc32_patch(crc, b"same", b"same", 10));
+    }
+}
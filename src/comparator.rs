@@ -0,0 +1,154 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use crate::{ErrorKind, MiniBaseError, MiniBaseResult};
+use crate::checksum::crc32;
+
+// 比较函数的类型，多个线程可能同时调用
+pub type CompareFn = dyn Fn(&[u8], &[u8]) -> Ordering + Send + Sync;
+
+// 用户提供的比较函数及其名称，函数本身无法保存到元数据文件中，元数据文件只保存名称的哈希
+// 每次打开schema都需要通过MeteData::set_custom_comparator提供同样的比较函数，否则树中key的顺序会错乱
+#[derive(Clone)]
+pub struct CustomComparator {
+    name_hash: u32,
+    // 打开schema后还没有提供比较函数时为None，此时不能创建controller
+    compare: Option<Arc<CompareFn>>,
+}
+
+impl CustomComparator {
+    // 每个页都保存一份比较方式，共享同一个比较函数，最后一个引用释放时比较函数随之释放
+    // name用于检查打开时提供的比较函数与创建时是否相同，修改比较函数的顺序时也应当修改name
+    pub fn new(name: &str, compare: Box<CompareFn>) -> CustomComparator {
+        CustomComparator { name_hash: crc32(name.as_bytes()), compare: Some(Arc::from(compare)) }
+    }
+
+    pub(crate) fn is_resolved(&self) -> bool {
+        self.compare.is_some()
+    }
+}
+
+impl Debug for CustomComparator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CustomComparator({:08x})", self.name_hash)
+    }
+}
+
+// 名称相同的比较函数视为相同
+impl PartialEq for CustomComparator {
+    fn eq(&self, other: &CustomComparator) -> bool {
+        self.name_hash == other.name_hash
+    }
+}
+
+impl Eq for CustomComparator {}
+
+// key的排序方式，创建schema时指定并保存在元数据文件中，重新打开后使用相同的排序
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Comparator {
+    // 按字节的字典序比较
+    ByteLexicographic,
+    // 将key视为大端序的无符号整数比较，忽略开头的0，长度不限于8字节
+    U64BigEndian,
+    // 忽略ASCII字母的大小写比较，只有大小写不同的key视为同一个key
+    CaseInsensitiveAscii,
+    // 用户提供的比较函数，只保存名称的哈希
+    Custom(CustomComparator),
+}
+
+impl Comparator {
+    pub(crate) fn compare(&self, a: &[u8], b: &[u8]) -> Ordering {
+        match self {
+            Comparator::ByteLexicographic => a.cmp(b),
+            Comparator::U64BigEndian => {
+                let a = trim_leading_zero(a);
+                let b = trim_leading_zero(b);
+                a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+            }
+            Comparator::CaseInsensitiveAscii => {
+                a.iter().map(u8::to_ascii_lowercase).cmp(b.iter().map(u8::to_ascii_lowercase))
+            }
+            // 没有提供比较函数时不能创建controller，不会比较任何key
+            Comparator::Custom(custom) => (custom.compare.as_ref().expect("custom comparator not set"))(a, b),
+        }
+    }
+
+    pub(crate) fn to_tag(&self) -> u32 {
+        match self {
+            Comparator::ByteLexicographic => 0,
+            Comparator::U64BigEndian => 1,
+            Comparator::CaseInsensitiveAscii => 2,
+            Comparator::Custom(_) => 3,
+        }
+    }
+
+    // 比较结果相等的key转换为相同的字节，用于计算布隆过滤器的哈希，自定义的排序方式无法转换，返回None
+    pub(crate) fn normalize<'a>(&self, key: &'a [u8]) -> Option<Cow<'a, [u8]>> {
+        match self {
+            Comparator::ByteLexicographic => Some(Cow::Borrowed(key)),
+            Comparator::U64BigEndian => Some(Cow::Borrowed(trim_leading_zero(key))),
+            Comparator::CaseInsensitiveAscii => Some(Cow::Owned(key.to_ascii_lowercase())),
+            Comparator::Custom(_) => None,
+        }
+    }
+
+    // 比较函数名称的哈希，内置的排序方式为0
+    pub(crate) fn get_name_hash(&self) -> u32 {
+        match self {
+            Comparator::Custom(custom) => custom.name_hash,
+            _ => 0,
+        }
+    }
+
+    // 自定义的排序方式只能恢复名称的哈希，需要再提供比较函数
+    pub(crate) fn from_tag(tag: u32, name_hash: u32) -> MiniBaseResult<Comparator> {
+        match tag {
+            0 => Ok(Comparator::ByteLexicographic),
+            1 => Ok(Comparator::U64BigEndian),
+            2 => Ok(Comparator::CaseInsensitiveAscii),
+            3 => Ok(Comparator::Custom(CustomComparator { name_hash, compare: None })),
+            _ => Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("comparator tag {} invalid", tag)))),
+        }
+    }
+}
+
+fn trim_leading_zero(data: &[u8]) -> &[u8] {
+    let start = data.iter().position(|byte| *byte != 0).unwrap_or(data.len());
+    &data[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use crate::comparator::{Comparator, CustomComparator};
+
+    #[test]
+    fn comparator_compare() {
+        let comparator = Comparator::ByteLexicographic;
+        assert_eq!(Ordering::Greater, comparator.compare("b".as_bytes(), "abc".as_bytes()));
+        assert_eq!(Ordering::Less, comparator.compare("B".as_bytes(), "a".as_bytes()));
+
+        let comparator = Comparator::U64BigEndian;
+        assert_eq!(Ordering::Less, comparator.compare(&2u64.to_be_bytes(), &256u64.to_be_bytes()));
+        assert_eq!(Ordering::Greater, comparator.compare(&[1, 0], &[255]));
+        assert_eq!(Ordering::Equal, comparator.compare(&[0, 0, 7], &[7]));
+
+        let comparator = Comparator::CaseInsensitiveAscii;
+        assert_eq!(Ordering::Greater, comparator.compare("B".as_bytes(), "a".as_bytes()));
+        assert_eq!(Ordering::Equal, comparator.compare("Test".as_bytes(), "tEST".as_bytes()));
+        assert_eq!(Ordering::Less, comparator.compare("abc".as_bytes(), "ABCD".as_bytes()));
+
+        for comparator in [Comparator::ByteLexicographic, Comparator::U64BigEndian, Comparator::CaseInsensitiveAscii] {
+            assert_eq!(comparator, Comparator::from_tag(comparator.to_tag(), comparator.get_name_hash()).unwrap());
+        }
+        assert!(Comparator::from_tag(4, 0).is_err());
+
+        let comparator = Comparator::Custom(CustomComparator::new("reverse", Box::new(|a, b| b.cmp(a))));
+        assert_eq!(Ordering::Less, comparator.compare("b".as_bytes(), "a".as_bytes()));
+        assert_eq!(Ordering::Equal, comparator.compare("a".as_bytes(), "a".as_bytes()));
+        let restored = Comparator::from_tag(comparator.to_tag(), comparator.get_name_hash()).unwrap();
+        assert_eq!(comparator, restored);
+        assert_ne!(comparator, Comparator::Custom(CustomComparator::new("other", Box::new(|a, b| b.cmp(a)))));
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{ErrorKind, MiniBaseError, MiniBaseResult};
+
+// 每个分量中的0x00编码为0x00 0xFF，分量以0x00 0x01结尾
+// 分量结尾的0x01小于转义的0xFF和其它任意字节之后的内容，因此一个分量是另一个分量的前缀时排在前面
+// 编码后按字节序比较的结果与逐个分量按字节序比较的结果一致
+const ESCAPE: u8 = 0x00;
+const ESCAPED_ZERO: u8 = 0xFF;
+const TERMINATOR: u8 = 0x01;
+
+// 解码后的分量
+pub type CompositeKey = Vec<Vec<u8>>;
+
+// 将多个分量编码为一个key，例如["a", "b"]排在["a", "bb"]之前，["a"]排在["a", "b"]之前
+pub fn encode_composite_key(components: &[&[u8]]) -> Vec<u8> {
+    let mut key = Vec::with_capacity(components.iter().map(|component| component.len() + 2).sum());
+    for component in components {
+        for byte in component.iter() {
+            key.push(*byte);
+            if *byte == ESCAPE {
+                key.push(ESCAPED_ZERO);
+            }
+        }
+        key.extend_from_slice(&[ESCAPE, TERMINATOR]);
+    }
+    key
+}
+
+// 将encode_composite_key编码的key还原为分量，key不是合法的编码时返回InvalidArgument
+pub fn decode_composite_key(key: &[u8]) -> MiniBaseResult<CompositeKey> {
+    let mut components = Vec::new();
+    let mut component = Vec::new();
+    let mut bytes = key.iter();
+    while let Some(byte) = bytes.next() {
+        if *byte != ESCAPE {
+            component.push(*byte);
+            continue;
+        }
+        match bytes.next() {
+            Some(&ESCAPED_ZERO) => component.push(ESCAPE),
+            Some(&TERMINATOR) => components.push(std::mem::take(&mut component)),
+            _ => return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, "composite key invalid escape"))),
+        }
+    }
+    if !component.is_empty() {
+        return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, "composite key not terminated")));
+    }
+    Ok(components)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::composite::{decode_composite_key, encode_composite_key};
+
+    #[test]
+    fn composite_key_order() {
+        let ordered: [&[&[u8]]; 8] = [
+            &[],
+            &[b""],
+            &[b"", b"a"],
+            &[b"a"],
+            &[b"a", b"b"],
+            &[b"a", b"bb"],
+            &[b"a\0", b""],
+            &[b"ab"],
+        ];
+        let keys: Vec<_> = ordered.iter().map(|components| encode_composite_key(components)).collect();
+        for pair in keys.windows(2) {
+            assert!(pair[0] < pair[1], "{:?} {:?}", pair[0], pair[1]);
+        }
+        for (components, key) in ordered.iter().zip(keys.iter()) {
+            assert_eq!(components.to_vec(), decode_composite_key(key).unwrap());
+        }
+        // 直接拼接时["a", "bb"]会排在["ab"]之后，编码后按分量比较
+        assert!(encode_composite_key(&[b"a", b"bb"]) < encode_composite_key(&[b"ab", b""]));
+
+        assert!(decode_composite_key(b"a").is_err());
+        assert!(decode_composite_key(b"a\0").is_err());
+        assert!(decode_composite_key(b"a\0\x02").is_err());
+    }
+}
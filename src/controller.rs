@@ -1,59 +1,4913 @@
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fs::OpenOptions;
-use crate::{MeteData, MiniBaseResult};
-use crate::node::{Node, NodeType};
-use crate::page::{LeafPage, Page, Pager};
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use std::vec::IntoIter;
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
+#[cfg(feature = "serde")]
+use crate::typed::{decode_typed, encode_typed};
+use crate::{decode_composite_key, encode_composite_key, Comparator, CompositeKey, ErrorKind, MeteData, MiniBaseError, MiniBaseResult};
+use crate::backup::{write_key_value, BackupReader};
+use crate::extra::{now_millis, ExtraFile, StoredValue, NULL_CHUNK};
+use crate::node::{with_message_prefix, IoStats, Node, NodeType, Split, Stats, StructuralEvent};
+use crate::page::{AccessPattern, InnerPage, InsertResult, LeafPage, NULL_PAGE};
+use crate::repair::{salvage, CorruptionMode, LostRange, VerificationError, VerificationErrorKind, VerifiedLeaf, VerifyScan};
+use crate::storage::Storage;
+use crate::wal::WalRecord;
 
+// 分段读取的结果，包括读取到的key value和继续读取时使用的key，已经读取完时key为None
+pub type ScanChunk = (Vec<(Vec<u8>, Vec<u8>)>, Option<Vec<u8>>);
+
+// write_batch中的一个写操作
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum WriteOp {
+    Put { key: Vec<u8>, value: Vec<u8> },
+    // 多值模式下删除key的所有value
+    Delete { key: Vec<u8> },
+}
+
+impl WriteOp {
+    fn get_key(&self) -> &[u8] {
+        match self {
+            WriteOp::Put { key, .. } | WriteOp::Delete { key } => key,
+        }
+    }
+
+    fn to_record(&self) -> WalRecord {
+        match self {
+            WriteOp::Put { key, value } => put_record(key, value),
+            WriteOp::Delete { key } => WalRecord::Remove { key: key.clone() },
+        }
+    }
+}
+
+// scan_with_deadline每读取多少个叶节点检查一次是否超过deadline
+const DEADLINE_CHECK_INTERVAL: usize = 4;
+
+// 写操作之后何时将修改写入磁盘，只在运行时生效，不会保存到元数据文件，打开schema后为Buffered
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum SyncMode {
+    // 只在flush、close和drop时写入磁盘，写操作最快，系统崩溃或断电可能丢失上次flush之后的写操作
+    #[default]
+    Buffered,
+    // 每个写操作成功后flush，返回Ok后写操作已经持久化
+    EveryWrite,
+    // 后台线程每隔一段时间flush一次，最多丢失一个间隔内的写操作
+    Periodic(Duration),
+}
+
+// 后台整理叶节点的设置，只在运行时生效
+// 每隔interval检查最多max_pages个叶节点，整理可回收空间超过容量的garbage_ratio的叶节点，依次检查完所有叶节点后从头开始
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct AutoVacuum {
+    pub interval: Duration,
+    pub garbage_ratio: f32,
+    pub max_pages: usize,
+}
+
+impl Default for AutoVacuum {
+    fn default() -> AutoVacuum {
+        AutoVacuum { interval: Duration::from_secs(1), garbage_ratio: 0.25, max_pages: 64 }
+    }
+}
+
+impl AutoVacuum {
+    pub(crate) fn check(&self) -> MiniBaseResult<()> {
+        if self.interval.is_zero() || self.max_pages == 0 {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, "auto_vacuum interval and max_pages must be positive")));
+        }
+        if !(self.garbage_ratio > 0.0 && self.garbage_ratio < 1.0) {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, format!("auto_vacuum garbage_ratio {} invalid, must be in (0, 1)", self.garbage_ratio))));
+        }
+        Ok(())
+    }
+}
+
+// key不存在时get返回Ok(None)，remove返回Ok(false)，需要把key不存在视为错误时使用get_required
+// 返回Err只表示读写失败、数据损坏或参数不合法，不表示key不存在
 pub trait Operate {
     fn put(&self, key: &str, value: &str) -> MiniBaseResult<()>;
     fn get(&self, key: &str) -> MiniBaseResult<Option<String>>;
-    fn scan(&self, begin: &str, end: &str) -> MiniBaseResult<Vec<String>>;
+    // 与get相同，key不存在或已删除时返回NotFound错误，可以直接通过?返回
+    fn get_required(&self, key: &str) -> MiniBaseResult<String> {
+        self.get(key)?.ok_or_else(|| Box::from(MiniBaseError::new(ErrorKind::NotFound, format!("key {} not found", key))))
+    }
+    // 范围读取[begin, end)内的key value，按key升序排列
+    fn scan(&self, begin: &str, end: &str) -> MiniBaseResult<Vec<(String, String)>>;
+    // 范围读取[begin, end]内的key value，按key升序排列
+    fn scan_inclusive(&self, begin: &str, end: &str) -> MiniBaseResult<Vec<(String, String)>>;
+    // 返回是否删除了key，key不存在或已经删除时返回Ok(false)
     fn remove(&self, key: &str) -> MiniBaseResult<bool>;
 }
 
+// 锁的粒度为整棵树：读操作持有读锁，多个读操作可以同时从根节点查找，写操作持有写锁，与其它所有操作互斥
+// 多个读操作共享的页缓存由MeteData内部的互斥锁保护，只在取出和放回节点时加锁
+// 所有操作都只需要&self，可以通过Arc在多个线程间共享同一个Controller
 pub struct Controller {
-    mete_data: MeteData,
-    root_node: Node,
+    // 后台线程只持有弱引用，不会延长树的生命周期
+    tree: Arc<RwLock<Tree>>,
+    periodic_flush: Mutex<Option<BackgroundThread>>,
+    auto_vacuum: Mutex<Option<BackgroundThread>>,
+}
+
+// 后台flush线程或整理线程，drop发送端后线程退出
+struct BackgroundThread {
+    stop: Sender<()>,
+    handle: JoinHandle<()>,
 }
 
 impl Controller {
     pub(crate) fn new(mete_data: MeteData) -> MiniBaseResult<Controller> {
-        let data_file_path = mete_data.get_data_file_path();
-        let data_file = OpenOptions::new().read(true).write(true).create(true).open(data_file_path.as_str())?;
-        data_file.set_len(mete_data.page_size as u64)?;
-        let root_node = Node::new(&data_file, 0, mete_data.page_size, NodeType::Leaf)?;
-        Ok(Controller { mete_data, root_node })
+        Ok(Controller::with_tree(Tree::new(mete_data)?))
     }
 
     pub(crate) fn from(mete_data: MeteData) -> MiniBaseResult<Controller> {
-        let data_file_path = mete_data.get_data_file_path();
-        let data_file = OpenOptions::new().read(true).write(true).create(true).open(data_file_path.as_str())?;
-        let root_page_offset = mete_data.get_root_page_offset();
-        let root_page = Page::new(&data_file, root_page_offset, mete_data.page_size)?;
-        let page_header = root_page.read_u8(0);
-        let node_type = if page_header == LeafPage::HEADER {
-            NodeType::Leaf
+        Ok(Controller::with_tree(Tree::from(mete_data)?))
+    }
+
+    fn with_tree(tree: Tree) -> Controller {
+        let auto_vacuum = tree.mete_data.auto_vacuum;
+        let controller = Controller { tree: Arc::new(RwLock::new(tree)), periodic_flush: Mutex::new(None), auto_vacuum: Mutex::new(None) };
+        // 设置已经在MeteData::set_auto_vacuum中检查过
+        if let Some(auto_vacuum) = auto_vacuum {
+            *controller.auto_vacuum.lock().unwrap_or_else(PoisonError::into_inner) = Some(start_auto_vacuum(Arc::downgrade(&controller.tree), auto_vacuum));
+        }
+        controller
+    }
+
+    // 写操作中途panic时树可能不完整，与进程在写操作中途崩溃的情况相同，因此忽略锁中毒
+    fn read(&self) -> RwLockReadGuard<'_, Tree> {
+        self.tree.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Tree> {
+        self.tree.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // put、remove等写操作只修改映射的内存，由操作系统决定何时写回文件，进程崩溃不会丢失数据，但系统崩溃或断电可能丢失
+    // flush将所有修改过的数据页和元数据页同步写入磁盘，返回Ok后之前的写操作都已持久化
+    pub fn flush(&self) -> MiniBaseResult<()> {
+        self.write().flush()
+    }
+
+    // 修改写操作之后写入磁盘的方式，切换到其它模式时停止Periodic模式的后台线程
+    pub fn set_sync_mode(&self, sync_mode: SyncMode) -> MiniBaseResult<()> {
+        if let SyncMode::Periodic(interval) = sync_mode {
+            if interval.is_zero() {
+                return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, "sync_mode periodic interval must be positive")));
+            }
+        }
+        let mut periodic_flush = self.periodic_flush.lock().unwrap_or_else(PoisonError::into_inner);
+        stop_background_thread(periodic_flush.take());
+        self.write().sync_mode = sync_mode;
+        if let SyncMode::Periodic(interval) = sync_mode {
+            *periodic_flush = Some(start_periodic_flush(Arc::downgrade(&self.tree), interval));
+        }
+        Ok(())
+    }
+
+    pub fn get_sync_mode(&self) -> SyncMode {
+        self.read().sync_mode
+    }
+
+    // 启动后台整理线程，已经启动时按新的设置重新启动，与读写操作共用读写锁，每次只持有写锁处理max_pages个叶节点
+    // 可以在打开时通过MeteData::set_auto_vacuum启动
+    pub fn start_auto_vacuum(&self, auto_vacuum: AutoVacuum) -> MiniBaseResult<()> {
+        auto_vacuum.check()?;
+        self.read().mete_data.check_writable()?;
+        let mut thread = self.auto_vacuum.lock().unwrap_or_else(PoisonError::into_inner);
+        stop_background_thread(thread.take());
+        *thread = Some(start_auto_vacuum(Arc::downgrade(&self.tree), auto_vacuum));
+        Ok(())
+    }
+
+    // 停止后台整理线程并等待正在进行的整理结束，没有启动时不做任何操作
+    pub fn stop_auto_vacuum(&self) {
+        stop_background_thread(self.auto_vacuum.lock().unwrap_or_else(PoisonError::into_inner).take());
+    }
+
+    // 关闭controller，停止后台线程并将所有修改写入磁盘，与drop不同的是可以得到写入失败的错误
+    pub fn close(self) -> MiniBaseResult<()> {
+        self.stop_auto_vacuum();
+        // 之后drop时再次flush已经没有需要写回的数据
+        self.flush()
+    }
+
+    // 多值模式下追加value，不会覆盖key已有的value
+    pub fn put_bytes(&self, key: &[u8], value: &[u8]) -> MiniBaseResult<()> {
+        self.write().write_ahead(|tree| tree.put_bytes(key, value), |_| vec![put_record(key, value)])
+    }
+
+    // 写入value并设置过期时间，过期之后读取、遍历和范围读取都不再返回key，再次写入时会覆盖
+    // 只能用于启用了CreateOptions::ttl的schema，之后通过put_bytes写入的value不再过期
+    pub fn put_with_ttl(&self, key: &[u8], value: &[u8], ttl: Duration) -> MiniBaseResult<()> {
+        // 日志记录过期的时刻，重放时不会延长过期时间
+        let expiry = now_millis().saturating_add(ttl.as_millis().min(u64::MAX as u128) as u64);
+        let record = |_: &()| vec![WalRecord::PutWithExpiry { key: key.to_vec(), value: value.to_vec(), expiry }];
+        self.write().write_ahead(|tree| tree.put_with_expiry(key, value, expiry), record)
+    }
+
+    // 删除所有已经过期的key，返回删除的数量，过期的key在删除之前仍然占用空间，也会计入len和count_range
+    pub fn purge_expired(&self) -> MiniBaseResult<usize> {
+        let record = |keys: &Vec<Vec<u8>>| keys.iter().map(|key| WalRecord::Remove { key: key.clone() }).collect();
+        Ok(self.write().write_ahead(|tree| tree.purge_expired(), record)?.len())
+    }
+
+    // 流式写入key的value，数据达到value_threshold后逐个chunk写入额外数据文件，不需要在内存中保存完整的value，不支持多值模式
+    // 通过finish或flush写入叶节点，drop时自动完成写入并忽略错误，writer存在期间持有写锁
+    // 启用预写日志时日志需要记录完整的value，value在完成写入之前保存在内存中
+    pub fn put_writer(&self, key: &[u8]) -> MiniBaseResult<ValueWriter<'_>> {
+        let tree = self.write();
+        tree.mete_data.check_writable()?;
+        tree.check_key(key)?;
+        tree.check_single_value("put_writer")?;
+        Ok(ValueWriter { tree, key: key.to_vec(), buffer: Vec::new(), chunks: Vec::new(), size: 0, finished: false })
+    }
+
+    // 读取key的所有value，多值模式下按写入顺序排列，否则最多只有一个value
+    pub fn get_all(&self, key: &[u8]) -> MiniBaseResult<Vec<Vec<u8>>> {
+        self.read().get_all(key)
+    }
+
+    // 删除key中等于value的所有值，返回删除的数量，不是多值模式时只在key当前的value等于value时删除key
+    pub fn remove_value(&self, key: &[u8], value: &[u8]) -> MiniBaseResult<usize> {
+        let record = |_: &usize| vec![WalRecord::RemoveValue { key: key.to_vec(), value: value.to_vec() }];
+        self.write().write_ahead(|tree| tree.remove_value(key, value), record)
+    }
+
+    // key存在时返回已有的value，否则写入default_value并返回，只需要从根节点查找一次叶节点
+    pub fn get_or_insert(&self, key: &[u8], default_value: &[u8]) -> MiniBaseResult<Vec<u8>> {
+        self.write().write_ahead(|tree| tree.get_or_insert(key, default_value), |value| vec![put_record(key, value)])
+    }
+
+    // key当前的value等于expected时写入new_value并返回true，expected为None表示key不存在，否则不修改并返回false
+    // 读取和写入都在写锁内完成，多个compare_and_set同时执行时只有一个能成功
+    pub fn compare_and_set(&self, key: &[u8], expected: Option<&[u8]>, new_value: &[u8]) -> MiniBaseResult<bool> {
+        let record = |swapped: &bool| if *swapped { vec![put_record(key, new_value)] } else { Vec::new() };
+        self.write().write_ahead(|tree| tree.compare_and_set(key, expected, new_value), record)
+    }
+
+    // key不存在或已删除时写入value并返回true，key存在时不修改并返回false，只需要从根节点查找一次叶节点
+    pub fn insert(&self, key: &[u8], value: &[u8]) -> MiniBaseResult<bool> {
+        let record = |inserted: &bool| if *inserted { vec![put_record(key, value)] } else { Vec::new() };
+        self.write().write_ahead(|tree| tree.insert(key, value), record)
+    }
+
+    // 获取key对应的Entry，Entry存在期间持有写锁，读取和修改之间不会有其它写操作
+    pub fn entry(&self, key: &[u8]) -> Entry<'_> {
+        Entry { tree: self.write(), key: key.to_vec(), modify: None }
+    }
+
+    // 将operand与key当前的value合并，f的参数为当前的value（key不存在时为None）和operand，返回值作为新的value写入并返回
+    pub fn merge<F>(&self, key: &[u8], operand: &[u8], f: F) -> MiniBaseResult<Vec<u8>>
+        where F: Fn(Option<&[u8]>, &[u8]) -> Vec<u8> {
+        self.write().write_ahead(|tree| tree.merge(key, operand, f), |value| vec![put_record(key, value)])
+    }
+
+    // 从按key严格升序排列的key value构建树，只能用于空树，返回写入的key数量
+    // 叶节点从左到右依次写满，比逐个写入更快，页也更紧凑，返回Ok后所有数据已经写入磁盘
+    // key不是严格升序时返回错误，已经写入的数据会被丢弃
+    pub fn bulk_load<I>(&self, sorted_pairs: I) -> MiniBaseResult<usize>
+        where I: Iterator<Item = (Vec<u8>, Vec<u8>)> {
+        self.bulk_load_results(sorted_pairs.map(Ok))
+    }
+
+    // 将所有未删除的key value按key升序导出到writer，返回导出的数量，导出期间持有读锁
+    // 格式与页的布局无关，可以通过import导入页大小不同的schema
+    pub fn export<W: Write>(&self, writer: &mut W) -> MiniBaseResult<usize> {
+        let mut count = 0;
+        for key_value in self.iter() {
+            let (key, value) = key_value?;
+            write_key_value(writer, &key, &value)?;
+            count += 1;
+        }
+        writer.flush()?;
+        Ok(count)
+    }
+
+    // 导入export导出的key value，与bulk_load相同只能用于空树，返回导入的数量
+    // 数据不完整或key不是严格升序时返回错误，已经导入的数据会被丢弃
+    pub fn import<R: Read>(&self, reader: &mut R) -> MiniBaseResult<usize> {
+        self.bulk_load_results(BackupReader::new(reader))
+    }
+
+    fn bulk_load_results<I>(&self, sorted_pairs: I) -> MiniBaseResult<usize>
+        where I: Iterator<Item = MiniBaseResult<(Vec<u8>, Vec<u8>)>> {
+        // 启用预写日志时不记录每个key value，完成前崩溃会回滚到空树
+        self.write().write_ahead(|tree| {
+            let count = tree.bulk_load(sorted_pairs)?;
+            tree.flush()?;
+            Ok(count)
+        }, |_| Vec::new())
+    }
+
+    // 修改value_threshold，并迁移保存方式与新的value_threshold不一致的value，返回迁移的value数量
+    // 需要读取所有叶节点并重新写入迁移的value，开销较大，只适合偶尔执行，返回Ok后所有数据已经写入磁盘
+    // 中途失败时已经迁移和未迁移的value都可以正常读取
+    pub fn set_value_threshold(&self, value_threshold: u32) -> MiniBaseResult<usize> {
+        // 启用预写日志时不记录迁移的value，完成前崩溃会回滚到修改之前
+        self.write().write_ahead(|tree| {
+            let count = tree.set_value_threshold(value_threshold)?;
+            tree.flush()?;
+            Ok(count)
+        }, |_| Vec::new())
+    }
+
+    // 将数据文件末尾的页移动到前面的空闲页，之后截断文件，把空闲页占用的磁盘空间还给操作系统，返回释放的字节数
+    // 需要读取所有页，返回Ok后所有数据已经写入磁盘
+    pub fn shrink_to_fit(&self) -> MiniBaseResult<u64> {
+        // 启用预写日志时移动的页和截断的页都会记录原始数据，完成前崩溃会回滚到移动之前
+        self.write().write_ahead(|tree| {
+            let released = tree.shrink_to_fit()?;
+            tree.flush()?;
+            Ok(released)
+        }, |_| Vec::new())
+    }
+
+    // 将额外数据文件末尾仍然被引用的chunk移动到前面的空闲chunk，之后截断文件，回收删除和覆盖的value占用的空间，返回释放的字节数
+    // 需要读取所有叶节点并修改指向移动了的chunk的value，返回Ok后所有数据已经写入磁盘
+    pub fn compact_extra(&self) -> MiniBaseResult<u64> {
+        self.write().compact_extra()
+    }
+
+    // 删除所有数据，数据文件只保留一个空的根叶节点，额外数据文件只保留文件头，schema的设置不变
+    // 先清空数据文件并写入磁盘，再清空额外数据文件，中途崩溃时额外数据文件中最多留下不再引用的value
+    pub fn clear(&self) -> MiniBaseResult<()> {
+        let mut tree = self.write();
+        tree.write_ahead(|tree| {
+            tree.clear_pages()?;
+            tree.flush()
+        }, |_| Vec::new())?;
+        tree.extra_file.clear()
+    }
+
+    // 批量写入，返回新插入的key数量和覆盖的key数量
+    pub fn put_batch(&self, pairs: &[(&[u8], &[u8])]) -> MiniBaseResult<(usize, usize)> {
+        self.write().write_ahead(|tree| tree.put_batch(pairs), |_| pairs.iter().map(|(key, value)| put_record(key, value)).collect())
+    }
+
+    // 按顺序执行一批写入和删除，执行期间持有写锁，读操作只能看到执行之前或全部完成之后的数据
+    // key不合法时在修改任何数据之前返回错误；启用预写日志时先flush作为检查点，中途出错时将数据回滚到执行之前
+    // 未启用预写日志时中途的读写错误可能留下部分修改，回滚的操作中发生的分裂和合并仍然会通知on_structural_change
+    pub fn write_batch(&self, ops: Vec<WriteOp>) -> MiniBaseResult<()> {
+        self.write().write_batch(&ops)
+    }
+
+    // 多值模式下返回key最早写入的value
+    pub fn get_bytes(&self, key: &[u8]) -> MiniBaseResult<Option<Vec<u8>>> {
+        self.read().get_bytes(key)
+    }
+
+    // 与get_bytes相同，key不存在或已删除时返回NotFound错误
+    pub fn get_bytes_required(&self, key: &[u8]) -> MiniBaseResult<Vec<u8>> {
+        self.get_bytes(key)?.ok_or_else(|| Box::from(MiniBaseError::new(ErrorKind::NotFound, format!("key {:?} not found", key))))
+    }
+
+    // 只读取value的长度，不复制value，value保存在额外数据文件中时也不读取额外数据文件，不支持多值模式
+    pub fn value_len(&self, key: &[u8]) -> MiniBaseResult<Option<u32>> {
+        self.read().value_len(key)
+    }
+
+    // 流式读取value，保存在额外数据文件中的value在读取时逐个chunk加载，不会一次性读入内存，不支持多值模式
+    // reader存在期间持有读锁，同一个线程在reader drop之前执行写操作会死锁
+    pub fn get_reader(&self, key: &[u8]) -> MiniBaseResult<Option<ValueReader<'_>>> {
+        let tree = self.read();
+        let stored_value = tree.stored_value(key)?;
+        Ok(stored_value.map(|stored_value| match stored_value {
+            StoredValue::Inline(value) => ValueReader { tree, buffer: value, position: 0, next_chunk: NULL_CHUNK, remaining: 0 },
+            StoredValue::Chunks(offset, size) => ValueReader { tree, buffer: Vec::new(), position: 0, next_chunk: offset, remaining: size },
+        }))
+    }
+
+    // 返回保存的key和value，比较方式不区分大小写时保存的key可能与参数的大小写不同
+    pub fn get_entry(&self, key: &[u8]) -> MiniBaseResult<Option<(Vec<u8>, Vec<u8>)>> {
+        self.read().get_entry(key)
+    }
+
+    // 小于等于key的最大的key及其value
+    pub fn floor(&self, key: &[u8]) -> MiniBaseResult<Option<(Vec<u8>, Vec<u8>)>> {
+        self.read().get_nearest(key, true, false)
+    }
+
+    // 大于等于key的最小的key及其value
+    pub fn ceiling(&self, key: &[u8]) -> MiniBaseResult<Option<(Vec<u8>, Vec<u8>)>> {
+        self.read().get_nearest(key, true, true)
+    }
+
+    // 大于key的最小的key及其value
+    pub fn higher(&self, key: &[u8]) -> MiniBaseResult<Option<(Vec<u8>, Vec<u8>)>> {
+        self.read().get_nearest(key, false, true)
+    }
+
+    // 小于key的最大的key及其value
+    pub fn lower(&self, key: &[u8]) -> MiniBaseResult<Option<(Vec<u8>, Vec<u8>)>> {
+        self.read().get_nearest(key, false, false)
+    }
+
+    // 批量读取，返回的value与keys的顺序一一对应
+    pub fn get_many(&self, keys: &[&[u8]]) -> MiniBaseResult<Vec<Option<Vec<u8>>>> {
+        self.read().get_many(keys)
+    }
+
+    // 判断key是否存在，不会读取value，适合value较大时只关心key是否存在的场景
+    pub fn contains_key(&self, key: &[u8]) -> MiniBaseResult<bool> {
+        self.read().contains_key(key)
+    }
+
+    // 范围读取[begin, end)内的key value，按key升序排列
+    // 多值模式下每个value返回一项，同一个key的value连续出现，按写入顺序排列，scan_inclusive_bytes、scan_prefix和iter相同
+    pub fn scan_bytes(&self, begin: &[u8], end: &[u8]) -> MiniBaseResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.read().scan_range(begin, end, false)
+    }
+
+    // 范围读取[begin, end]内的key value，按key升序排列
+    pub fn scan_inclusive_bytes(&self, begin: &[u8], end: &[u8]) -> MiniBaseResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.read().scan_range(begin, end, true)
+    }
+
+    // 读取key以prefix开头的所有key value，按key升序排列
+    pub fn scan_prefix(&self, prefix: &[u8]) -> MiniBaseResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.read().scan_prefix(prefix)
+    }
+
+    // 范围读取[begin, end)内的key value，超过deadline时返回已经读取的部分和继续读取的key
+    // 继续读取时以返回的key作为begin，每读取几个叶节点检查一次时间，返回时可能稍微超过deadline
+    pub fn scan_with_deadline(&self, begin: &[u8], end: &[u8], deadline: Instant) -> MiniBaseResult<ScanChunk> {
+        self.read().scan_with_deadline(begin, end, deadline)
+    }
+
+    // 分页读取last_key之后的最多limit个key value，last_key为None时从第一个key开始
+    // 返回的key为本页最后一个key，作为下一页的last_key，没有更多数据时为None
+    pub fn scan_from(&self, last_key: Option<&[u8]>, limit: usize) -> MiniBaseResult<ScanChunk> {
+        self.read().scan_from(last_key, limit)
+    }
+
+    // 统计[begin, end)内未删除的key数量，只读取key和删除标记，不读取value，包括已经过期但还未清除的key
+    pub fn count_range(&self, begin: &[u8], end: &[u8]) -> MiniBaseResult<u64> {
+        self.read().count_range(begin, end)
+    }
+
+    // [begin, end)内未删除的key value占用的字节数，不支持多值模式，包括已经过期但还未清除的key
+    // 对于范围内的数据是精确值：叶节点中的key、value数据及其长度和偏移，加上保存在额外数据文件中的value长度
+    // 作为占用磁盘空间的估计是近似值：不包括页头、空闲空间、垃圾数据、内部节点以及chunk头和最后一个chunk的剩余空间
+    // 与count_range相同只读取范围内的叶节点，不复制value，也不读取额外数据文件
+    pub fn size_of_range(&self, begin: &[u8], end: &[u8]) -> MiniBaseResult<u64> {
+        self.read().size_of_range(begin, end)
+    }
+
+    // 所有未删除的key数量，多值模式下为value的数量，需要读取所有叶节点，包括已经过期但还未清除的key
+    pub fn len(&self) -> MiniBaseResult<u64> {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> MiniBaseResult<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    // 多值模式下删除key的所有value
+    pub fn remove_bytes(&self, key: &[u8]) -> MiniBaseResult<bool> {
+        let record = |_: &bool| vec![WalRecord::Remove { key: key.to_vec() }];
+        self.write().write_ahead(|tree| tree.remove_bytes(key), record)
+    }
+
+    // 统计树中所有页的空间使用情况，需要读取所有页，可以用于判断是否需要整理
+    pub fn stats(&self) -> MiniBaseResult<Stats> {
+        self.read().stats()
+    }
+
+    // 以Lenient或Repair打开时发现的损坏的叶节点中丢失的数据范围，按key的顺序排列
+    pub fn lost_ranges(&self) -> Vec<LostRange> {
+        self.read().lost_ranges.clone()
+    }
+
+    // 打开之后读写数据文件的统计，只读取计数器，不需要读取页
+    pub fn io_stats(&self) -> IoStats {
+        self.read().mete_data.io_stats()
+    }
+
+    // 注册页分裂、合并和根节点改变时调用的回调，替换之前注册的回调，用于记录树结构的变化和统计写放大
+    // 回调在写操作持有写锁期间调用，不能再调用同一个controller的方法，否则会死锁
+    pub fn on_structural_change(&self, hook: Box<dyn Fn(StructuralEvent) + Send + Sync>) {
+        self.write().mete_data.structural_hook = Some(hook);
+    }
+
+    // 将io_stats的所有计数清零，用于统计一段负载的读写
+    pub fn reset_io_stats(&self) {
+        self.read().mete_data.reset_io_stats()
+    }
+
+    // 检查B+树的结构是否完整，包括每个页的数据、key的顺序、分隔key与子节点的关系、叶节点之间的链表以及额外数据文件中的value
+    // 发现问题时返回描述第一个问题的Corruption错误，需要读取所有页
+    pub fn verify(&self) -> MiniBaseResult<()> {
+        self.read().verify()
+    }
+
+    // 与verify的检查相同，但不在第一个问题处停止，返回发现的所有问题，每个问题包含页偏移、类型和页中数据应有的范围
+    // 无法读取的页中的子树会被跳过，读写文件出错时返回错误
+    pub fn verify_all(&self) -> MiniBaseResult<Vec<VerificationError>> {
+        self.read().verify_all()
+    }
+
+    // 从根节点开始按层级输出树的结构，用于调试和检查B+树的结构
+    pub fn debug_tree(&self) -> MiniBaseResult<String> {
+        let tree = self.read();
+        let mut output = String::new();
+        tree.root_node.debug_tree(&tree.mete_data, &tree.data_file, 0, &mut output)?;
+        Ok(output)
+    }
+
+    // 按key升序遍历所有未删除的key value，遍历期间持有读锁
+    pub fn iter(&self) -> Iter<'_> {
+        let tree = self.read();
+        let cursor = LeafCursor::new(&tree, false);
+        Iter { tree, key_values: Vec::new().into_iter(), cursor }
+    }
+
+    // 按key降序遍历所有未删除的key value，遍历期间持有读锁
+    pub fn iter_rev(&self) -> Iter<'_> {
+        let tree = self.read();
+        let cursor = LeafCursor::new(&tree, true);
+        Iter { tree, key_values: Vec::new().into_iter(), cursor }
+    }
+
+    // 按key升序遍历所有未删除的key，只读取叶节点中的key，不读取value和额外数据文件，遍历期间持有读锁
+    pub fn keys(&self) -> Keys<'_> {
+        let tree = self.read();
+        let cursor = LeafCursor::new(&tree, false);
+        Keys { tree, keys: Vec::new().into_iter(), cursor }
+    }
+
+    // 以u64为key的读写，key按大端序编码为8字节，按字节序排列时与数值的顺序一致
+    // 使用其它排序方式时顺序不一定与数值一致
+    pub fn put_u64(&self, key: u64, value: &[u8]) -> MiniBaseResult<()> {
+        self.put_bytes(&key.to_be_bytes(), value)
+    }
+
+    pub fn get_u64(&self, key: u64) -> MiniBaseResult<Option<Vec<u8>>> {
+        self.get_bytes(&key.to_be_bytes())
+    }
+
+    pub fn remove_u64(&self, key: u64) -> MiniBaseResult<bool> {
+        self.remove_bytes(&key.to_be_bytes())
+    }
+
+    // 范围读取[begin, end)内的key value，不是8字节的key会被忽略
+    pub fn scan_u64(&self, begin: u64, end: u64) -> MiniBaseResult<Vec<(u64, Vec<u8>)>> {
+        let key_values = self.scan_bytes(&begin.to_be_bytes(), &end.to_be_bytes())?;
+        Ok(key_values.into_iter()
+            .filter_map(|(key, value)| Some((u64::from_be_bytes(key.try_into().ok()?), value)))
+            .collect())
+    }
+
+    // 以多个分量组成的key读写，key由encode_composite_key编码，按字节序排列时与逐个分量比较的顺序一致
+    // 使用其它排序方式时顺序不一定与分量的顺序一致
+    pub fn put_composite(&self, components: &[&[u8]], value: &[u8]) -> MiniBaseResult<()> {
+        self.put_bytes(&encode_composite_key(components), value)
+    }
+
+    pub fn get_composite(&self, components: &[&[u8]]) -> MiniBaseResult<Option<Vec<u8>>> {
+        self.get_bytes(&encode_composite_key(components))
+    }
+
+    pub fn remove_composite(&self, components: &[&[u8]]) -> MiniBaseResult<bool> {
+        self.remove_bytes(&encode_composite_key(components))
+    }
+
+    // 读取前几个分量等于prefix的所有key value，按分量的顺序排列，不是合法编码的key会被忽略
+    pub fn scan_composite_prefix(&self, prefix: &[&[u8]]) -> MiniBaseResult<Vec<(CompositeKey, Vec<u8>)>> {
+        let key_values = self.scan_prefix(&encode_composite_key(prefix))?;
+        Ok(key_values.into_iter()
+            .filter_map(|(key, value)| Some((decode_composite_key(&key).ok()?, value)))
+            .collect())
+    }
+
+    // 以可序列化的类型读写，key和value由encode_typed编码，按字节序排列时与key的字段逐个比较的顺序一致
+    // 使用其它排序方式时顺序不一定与key的顺序一致，读取时value的类型必须与写入时相同
+    #[cfg(feature = "serde")]
+    pub fn put_typed<K: Serialize, V: Serialize>(&self, key: &K, value: &V) -> MiniBaseResult<()> {
+        self.put_bytes(&encode_typed(key)?, &encode_typed(value)?)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn get_typed<K: Serialize, V: DeserializeOwned>(&self, key: &K) -> MiniBaseResult<Option<V>> {
+        self.get_bytes(&encode_typed(key)?)?.map(|value| decode_typed(&value)).transpose()
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn remove_typed<K: Serialize>(&self, key: &K) -> MiniBaseResult<bool> {
+        self.remove_bytes(&encode_typed(key)?)
+    }
+
+    // 开始只读事务，事务期间的所有读取看到同一个快照
+    pub fn begin_read(&self) -> ReadTxn<'_> {
+        ReadTxn { tree: self.read() }
+    }
+}
+
+// drop时将所有修改写入磁盘，drop无法返回错误，需要知道是否写入成功时使用close
+impl Drop for Controller {
+    fn drop(&mut self) {
+        stop_background_thread(self.auto_vacuum.get_mut().unwrap_or_else(PoisonError::into_inner).take());
+        stop_background_thread(self.periodic_flush.get_mut().unwrap_or_else(PoisonError::into_inner).take());
+        let _ = self.write().flush();
+    }
+}
+
+// 每隔interval获取写锁flush一次，flush失败时在下一个间隔重试，controller drop后退出
+fn start_periodic_flush(tree: Weak<RwLock<Tree>>, interval: Duration) -> BackgroundThread {
+    let (stop, receiver) = mpsc::channel::<()>();
+    let handle = thread::spawn(move || {
+        while let Err(RecvTimeoutError::Timeout) = receiver.recv_timeout(interval) {
+            let Some(tree) = tree.upgrade() else { break };
+            let _ = tree.write().unwrap_or_else(PoisonError::into_inner).flush();
+        }
+    });
+    BackgroundThread { stop, handle }
+}
+
+// 每隔interval获取写锁整理一批叶节点，记录下一批开始的key，出错时下一次从头开始，controller drop后退出
+fn start_auto_vacuum(tree: Weak<RwLock<Tree>>, auto_vacuum: AutoVacuum) -> BackgroundThread {
+    let (stop, receiver) = mpsc::channel::<()>();
+    let handle = thread::spawn(move || {
+        let mut next_key: Option<Vec<u8>> = None;
+        while let Err(RecvTimeoutError::Timeout) = receiver.recv_timeout(auto_vacuum.interval) {
+            let Some(tree) = tree.upgrade() else { break };
+            let mut tree = tree.write().unwrap_or_else(PoisonError::into_inner);
+            let AutoVacuum { garbage_ratio, max_pages, .. } = auto_vacuum;
+            next_key = tree.write_ahead(|tree| tree.vacuum_leaves(next_key.as_deref(), garbage_ratio, max_pages), |_| Vec::new()).unwrap_or(None);
+        }
+    });
+    BackgroundThread { stop, handle }
+}
+
+fn stop_background_thread(thread: Option<BackgroundThread>) {
+    if let Some(BackgroundThread { stop, handle }) = thread {
+        drop(stop);
+        let _ = handle.join();
+    }
+}
+
+// controller的数据和各个操作的实现，由Controller中的读写锁保护
+struct Tree {
+    mete_data: MeteData,
+    data_file: Storage,
+    extra_file: ExtraFile,
+    // 缓存的根节点，读操作直接从这里开始查找，不需要读取元数据页中的根节点偏移
+    root_node: Node,
+    // 打开时发现的损坏的叶节点中丢失的数据范围
+    lost_ranges: Vec<LostRange>,
+    sync_mode: SyncMode,
+}
+
+impl Tree {
+    fn new(mete_data: MeteData) -> MiniBaseResult<Tree> {
+        let (data_file, extra_file) = if mete_data.memory {
+            (Storage::memory(mete_data.page_size), open_extra_file(&mete_data)?)
+        } else if mete_data.single_file {
+            // 单文件中的额外数据区域在这里写入文件头，之后只读打开时不需要创建
+            let (data_file, extra_file) = mete_data.open_single_file()?;
+            (data_file, ExtraFile::new(extra_file, mete_data.page_size)?)
         } else {
-            NodeType::Inner
+            let data_file_path = mete_data.get_data_file_path()?;
+            let data_file = Storage::from(OpenOptions::new().read(true).write(true).create(true).truncate(false).open(data_file_path.as_str())?);
+            (data_file.with_endian(mete_data.endian), open_extra_file(&mete_data)?)
         };
-        let root_node = Node::from(&data_file, 0, mete_data.page_size, node_type)?;
-        Ok(Controller { mete_data, root_node })
+        data_file.set_len(mete_data.page_size as u64)?;
+        let root_node = Node::new(&mete_data, &data_file, 0, NodeType::Leaf)?;
+        let mut tree = Tree { mete_data, data_file, extra_file, root_node, lost_ranges: Vec::new(), sync_mode: SyncMode::Buffered };
+        // 数据文件不存在时日志中的内容已经没有意义
+        tree.mete_data.open_wal()?;
+        tree.flush()?;
+        Ok(tree)
     }
-}
 
-impl Operate for Controller {
-    fn put(&self, key: &str, value: &str) -> MiniBaseResult<()> {
-        self.root_node.put(key, value)
+    fn from(mut mete_data: MeteData) -> MiniBaseResult<Tree> {
+        let (data_file, extra_file) = if mete_data.single_file {
+            let (data_file, extra_file) = mete_data.open_single_file()?;
+            (data_file, ExtraFile::new(extra_file, mete_data.page_size)?)
+        } else {
+            let data_file_path = mete_data.get_data_file_path()?;
+            let data_file = Storage::from(if mete_data.read_only {
+                OpenOptions::new().read(true).open(data_file_path.as_str())?
+            } else {
+                OpenOptions::new().read(true).write(true).create(true).truncate(false).open(data_file_path.as_str())?
+            });
+            (data_file.with_endian(mete_data.endian), open_extra_file(&mete_data)?)
+        };
+        mete_data.open_wal()?;
+        let operations = mete_data.recover_wal(&data_file)?;
+        let lost_ranges = match mete_data.corruption_mode {
+            CorruptionMode::Strict => Vec::new(),
+            mode => salvage(&mut mete_data, &data_file, mode)?,
+        };
+        // 根节点分裂或收缩后会更新元数据文件中的根节点偏移，根节点不一定位于数据文件的开头
+        let root_node = Node::load(&mete_data, &data_file, mete_data.get_root_page_offset())?;
+        let mut tree = Tree { mete_data, data_file, extra_file, root_node, lost_ranges, sync_mode: SyncMode::Buffered };
+        if tree.mete_data.has_wal() {
+            tree.replay(operations)?;
+        }
+        Ok(tree)
     }
 
-    fn get(&self, key: &str) -> MiniBaseResult<Option<String>> {
-        &self.root_node.get(key)
+    // 重放检查点之后的写操作，重放期间崩溃时日志中已有的记录不变，下次打开时再次回滚和重放
+    fn replay(&mut self, operations: Vec<WalRecord>) -> MiniBaseResult<()> {
+        for operation in operations {
+            match operation {
+                WalRecord::Put { key, value } => self.write_ahead(|tree| tree.put_bytes(&key, &value), |_| Vec::new())?,
+                WalRecord::PutWithExpiry { key, value, expiry } => self.write_ahead(|tree| tree.put_with_expiry(&key, &value, expiry), |_| Vec::new())?,
+                WalRecord::Remove { key } => self.write_ahead(|tree| tree.remove_bytes(&key), |_| Vec::new()).map(|_| ())?,
+                WalRecord::RemoveValue { key, value } => self.write_ahead(|tree| tree.remove_value(&key, &value), |_| Vec::new()).map(|_| ())?,
+                _ => {}
+            }
+        }
+        self.flush()
+    }
+
+    // 启用预写日志时，操作修改的页在修改前记录原始数据，操作成功后记录records返回的写操作
+    fn write_ahead<T, O, R>(&mut self, operate: O, records: R) -> MiniBaseResult<T>
+        where O: FnOnce(&mut Tree) -> MiniBaseResult<T>, R: FnOnce(&T) -> Vec<WalRecord> {
+        // 所有写操作都通过write_ahead执行，只读打开时在修改任何页之前返回错误
+        self.mete_data.check_writable()?;
+        let result = if self.mete_data.has_wal() {
+            self.mete_data.begin_wal(&self.data_file)?;
+            // 根节点一直保持映射，不会通过Node::load加载
+            let result = self.mete_data.journal_page(&self.data_file, self.root_node.get_offset()).and_then(|_| operate(self));
+            let records = result.as_ref().map(records).unwrap_or_default();
+            self.mete_data.end_wal(&records)?;
+            result
+        } else {
+            operate(self)
+        };
+        // 写操作失败时也可能已经修改了部分页，同样需要写入磁盘
+        if self.sync_mode == SyncMode::EveryWrite {
+            self.flush()?;
+        }
+        result
     }
 
-    fn scan(&self, begin: &str, end: &str) -> MiniBaseResult<Vec<String>> {
-        todo!()
+    fn flush(&mut self) -> MiniBaseResult<()> {
+        if self.mete_data.read_only {
+            return Ok(());
+        }
+        self.root_node.flush()?;
+        self.mete_data.flush()?;
+        // 其它数据页在操作结束后已经解除映射，修改保留在文件的页缓存中，需要通过fsync写入磁盘
+        self.data_file.sync_all()?;
+        self.mete_data.io_counters.record_sync();
+        self.extra_file.flush()?;
+        self.mete_data.checkpoint_wal()?;
+        // 检查点之后恢复不会回滚到删除之前，删除的value占用的chunk可以复用
+        self.extra_file.release_freed_chunks()
     }
 
-    fn remove(&self, key: &str) -> MiniBaseResult<bool> {
-        todo!()
+    fn put_bytes(&mut self, key: &[u8], value: &[u8]) -> MiniBaseResult<()> {
+        self.check_key(key)?;
+        let value_data = self.extra_file.encode_value(value, self.mete_data.value_threshold)?;
+        if self.mete_data.multi_value {
+            // 序号为key当前最后一个value的序号加1，删除最后一个value后序号可能重复使用，不影响顺序
+            let (begin, end) = multi_value_range(key);
+            let sequence = match self.scan_stored(&begin, &end, false)?.last() {
+                Some((stored_key, _)) => decode_multi_value_key(stored_key)?.1 + 1,
+                None => 0,
+            };
+            return self.put_value_data(&multi_value_key(key, sequence), &value_data);
+        }
+        self.put_value_data(key, &value_data)
+    }
+
+    // expiry为自UNIX_EPOCH起的毫秒数，value数据前加上过期时间
+    fn put_with_expiry(&mut self, key: &[u8], value: &[u8], expiry: u64) -> MiniBaseResult<()> {
+        self.check_key(key)?;
+        if !self.mete_data.ttl {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, "put_with_ttl not supported in schema without ttl")));
+        }
+        let value_data = self.extra_file.encode_value(value, self.mete_data.value_threshold)?;
+        let value_data = self.extra_file.with_expiry(Some(expiry), value_data);
+        self.put_value_data(key, &value_data)
+    }
+
+    // 支持过期时间的schema中value数据是否已经过期，过期的key视为不存在
+    fn is_expired(&self, value_data: &[u8]) -> bool {
+        self.mete_data.ttl && self.extra_file.is_expired(value_data)
+    }
+
+    // 叶节点中未删除且未过期的key value
+    fn get_live_key_values(&self, leaf: &Node) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut key_values = leaf.get_key_values();
+        if self.mete_data.ttl {
+            key_values.retain(|(_, value_data)| !self.extra_file.is_expired(value_data));
+        }
+        key_values
+    }
+
+    fn get_all(&self, key: &[u8]) -> MiniBaseResult<Vec<Vec<u8>>> {
+        if !self.mete_data.multi_value {
+            return Ok(self.get_bytes(key)?.into_iter().collect());
+        }
+        let (begin, end) = multi_value_range(key);
+        Ok(self.scan_stored(&begin, &end, false)?.into_iter().map(|(_, value)| value).collect())
+    }
+
+    fn remove_value(&mut self, key: &[u8], value: &[u8]) -> MiniBaseResult<usize> {
+        if !self.mete_data.multi_value {
+            if self.get_bytes(key)?.as_deref() != Some(value) {
+                return Ok(0);
+            }
+            return Ok(self.remove_key(key)? as usize);
+        }
+        let (begin, end) = multi_value_range(key);
+        let mut count = 0;
+        for (stored_key, _) in self.scan_stored(&begin, &end, false)?.into_iter().filter(|(_, stored_value)| stored_value == value) {
+            count += self.remove_key(&stored_key)? as usize;
+        }
+        Ok(count)
+    }
+
+    // 叶节点中保存的key还原为写入时的key，多值模式下去掉序号
+    fn decode_key(&self, key: Vec<u8>) -> MiniBaseResult<Vec<u8>> {
+        match self.mete_data.multi_value {
+            true => Ok(decode_multi_value_key(&key)?.0),
+            false => Ok(key),
+        }
+    }
+
+    // 多值模式不支持的操作返回InvalidArgument
+    fn check_single_value(&self, operation: &str) -> MiniBaseResult<()> {
+        if self.mete_data.multi_value {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, format!("{} not supported in multi-value schema", operation))));
+        }
+        Ok(())
+    }
+
+    fn write_batch(&mut self, ops: &[WriteOp]) -> MiniBaseResult<()> {
+        self.mete_data.check_writable()?;
+        for op in ops {
+            self.check_key(op.get_key())?;
+        }
+        // 检查点之后只有这一批操作，出错时回滚到检查点即可撤销已经执行的操作
+        if self.mete_data.has_wal() {
+            self.flush()?;
+        }
+        let records = |_: &()| ops.iter().map(WriteOp::to_record).collect();
+        self.write_ahead(|tree| {
+            let result = ops.iter().try_for_each(|op| match op {
+                WriteOp::Put { key, value } => tree.put_bytes(key, value),
+                WriteOp::Delete { key } => tree.remove_bytes(key).map(|_| ()),
+            });
+            if result.is_err() && tree.mete_data.has_wal() {
+                tree.roll_back()?;
+            }
+            result
+        }, records)
+    }
+
+    // 通过预写日志将数据文件和元数据回滚到检查点，丢弃检查点之后的所有修改，重新加载根节点
+    // 回滚的操作写入额外数据文件的chunk不再被引用，整理额外数据文件时回收
+    fn roll_back(&mut self) -> MiniBaseResult<()> {
+        // 缓存中的节点可能映射了回滚时截断的页
+        self.mete_data.page_cache_mut().clear()?;
+        self.mete_data.recover_wal(&self.data_file)?;
+        // 检查点时已经释放了之前删除的chunk，之后删除的value回滚后仍然被引用
+        self.extra_file.discard_freed_chunks();
+        self.root_node = Node::load(&self.mete_data, &self.data_file, self.mete_data.get_root_page_offset())?;
+        Ok(())
+    }
+
+    // 写入编码后的value数据，被覆盖的value保存在额外数据文件中时释放占用的chunk
+    fn put_value_data(&mut self, key: &[u8], value_data: &[u8]) -> MiniBaseResult<()> {
+        if let Some(replaced) = self.replace_value_data(key, value_data)? {
+            self.extra_file.free_value(&replaced)?;
+        }
+        Ok(())
+    }
+
+    // 写入编码后的value数据，返回被覆盖的value数据，由调用方决定是否释放
+    fn replace_value_data(&mut self, key: &[u8], value_data: &[u8]) -> MiniBaseResult<Option<Vec<u8>>> {
+        let (replaced, split) = self.root_node.put(&mut self.mete_data, &self.data_file, key, value_data)?;
+        if let Some(split) = split {
+            self.split_root(split)?;
+        }
+        Ok(replaced)
+    }
+
+    // default_value需要写入额外数据文件时先写入，key已经存在或写入失败时释放占用的chunk，flush之后复用
+    fn get_or_insert(&mut self, key: &[u8], default_value: &[u8]) -> MiniBaseResult<Vec<u8>> {
+        self.check_key(key)?;
+        self.check_single_value("get_or_insert")?;
+        match self.insert_value(key, default_value)? {
+            Some(value_data) => self.extra_file.decode_value(&value_data),
+            None => Ok(default_value.to_vec()),
+        }
+    }
+
+    fn insert(&mut self, key: &[u8], value: &[u8]) -> MiniBaseResult<bool> {
+        self.check_key(key)?;
+        self.check_single_value("insert")?;
+        Ok(self.insert_value(key, value)?.is_none())
+    }
+
+    // key不存在或已删除时写入value并返回None，否则不修改key并返回已有的value数据
+    fn insert_value(&mut self, key: &[u8], value: &[u8]) -> MiniBaseResult<Option<Vec<u8>>> {
+        // 已经过期的key先删除，之后按不存在的key写入
+        if self.mete_data.ttl && self.root_node.read_value(&self.mete_data, &self.data_file, key, &|value_data| Ok(self.is_expired(value_data)))? == Some(true) {
+            self.remove_key(key)?;
+        }
+        let value_data = self.extra_file.encode_value(value, self.mete_data.value_threshold)?;
+        let (existing, split) = match self.root_node.get_or_insert(&mut self.mete_data, &self.data_file, key, &value_data) {
+            Ok(result) => result,
+            Err(error) => {
+                // 例如叶节点无法分裂时返回PageFull，value没有写入叶节点，需要释放
+                self.extra_file.free_value(&value_data)?;
+                return Err(error);
+            }
+        };
+        if let Some(split) = split {
+            self.split_root(split)?;
+        }
+        // 没有写入时value可能已经写入额外数据文件，需要释放
+        if existing.is_some() {
+            self.extra_file.free_value(&value_data)?;
+        }
+        Ok(existing)
+    }
+
+    fn set_value_threshold(&mut self, value_threshold: u32) -> MiniBaseResult<usize> {
+        self.mete_data.set_value_threshold(value_threshold);
+        // 先收集需要迁移的key value，遍历叶节点期间不修改树
+        let mut migrations = Vec::new();
+        let root = Node::load(&self.mete_data, &self.data_file, self.root_node.get_offset())?;
+        let mut leaf = root.leftmost_leaf(&self.mete_data, &self.data_file)?;
+        loop {
+            for (key, value_data) in leaf.get_key_values() {
+                if self.extra_file.needs_reencode(&value_data, value_threshold)? {
+                    migrations.push((key, value_data));
+                }
+            }
+            let next_page = leaf.get_next_page();
+            leaf.release(&self.mete_data, false)?;
+            if next_page == NULL_PAGE {
+                break;
+            }
+            leaf = Node::load(&self.mete_data, &self.data_file, next_page)?;
+        }
+        for (key, value_data) in migrations.iter() {
+            let new_data = self.extra_file.reencode_value(value_data, value_threshold)?;
+            self.put_value_data(key, &new_data)?;
+        }
+        Ok(migrations.len())
+    }
+
+    fn compare_and_set(&mut self, key: &[u8], expected: Option<&[u8]>, new_value: &[u8]) -> MiniBaseResult<bool> {
+        self.check_key(key)?;
+        self.check_single_value("compare_and_set")?;
+        if self.get_bytes(key)?.as_deref() != expected {
+            return Ok(false);
+        }
+        self.put_bytes(key, new_value)?;
+        Ok(true)
+    }
+
+    // 读取当前value、计算新value和写入只需要从根节点查找一次叶节点
+    fn merge<F>(&mut self, key: &[u8], operand: &[u8], f: F) -> MiniBaseResult<Vec<u8>>
+        where F: Fn(Option<&[u8]>, &[u8]) -> Vec<u8> {
+        self.check_key(key)?;
+        self.check_single_value("merge")?;
+        let (extra_file, value_threshold) = (&self.extra_file, self.mete_data.value_threshold);
+        let mut merged_value = Vec::new();
+        let (replaced, split) = self.root_node.merge(&mut self.mete_data, &self.data_file, key, &mut |current_data| {
+            let current_value = match current_data {
+                Some(value_data) if !extra_file.is_expired(value_data) => Some(extra_file.decode_value(value_data)?),
+                _ => None,
+            };
+            merged_value = f(current_value.as_deref(), operand);
+            extra_file.encode_value(&merged_value, value_threshold)
+        })?;
+        if let Some(split) = split {
+            self.split_root(split)?;
+        }
+        if let Some(replaced) = &replaced {
+            self.extra_file.free_value(replaced)?;
+        }
+        Ok(merged_value)
+    }
+
+    fn compact_extra(&mut self) -> MiniBaseResult<u64> {
+        self.mete_data.check_writable()?;
+        // 删除的value占用的chunk在flush之后才放入空闲链表，整理前先写入磁盘
+        self.flush()?;
+        let mut keys = Vec::new();
+        let mut values = Vec::new();
+        let root = Node::load(&self.mete_data, &self.data_file, self.root_node.get_offset())?;
+        let mut leaf = root.leftmost_leaf(&self.mete_data, &self.data_file)?;
+        loop {
+            for (key, value_data) in leaf.get_key_values() {
+                if self.extra_file.is_extra_value(&value_data)? {
+                    keys.push(key);
+                    values.push(value_data);
+                }
+            }
+            let next_page = leaf.get_next_page();
+            leaf.release(&self.mete_data, false)?;
+            if next_page == NULL_PAGE {
+                break;
+            }
+            leaf = Node::load(&self.mete_data, &self.data_file, next_page)?;
+        }
+        let Some((compacted_length, moved)) = self.extra_file.compact(&values)? else {
+            return Ok(0);
+        };
+        // 叶节点中的指针修改并写入磁盘之后，截断位置之后的chunk才不再被引用，完成前崩溃会回滚到移动之前
+        // 原来的chunk已经移动，不能释放
+        self.write_ahead(|tree| {
+            for (index, value_data) in moved.iter() {
+                tree.replace_value_data(&keys[*index], value_data)?;
+            }
+            tree.flush()
+        }, |_| Vec::new())?;
+        self.extra_file.truncate(compacted_length)
+    }
+
+    // 先收集已经过期的key再逐个删除，返回删除的key
+    fn purge_expired(&mut self) -> MiniBaseResult<Vec<Vec<u8>>> {
+        let mut expired_keys = Vec::new();
+        if !self.mete_data.ttl {
+            return Ok(expired_keys);
+        }
+        let root = Node::load(&self.mete_data, &self.data_file, self.root_node.get_offset())?;
+        let mut leaf = root.leftmost_leaf(&self.mete_data, &self.data_file)?;
+        loop {
+            for (key, value_data) in leaf.get_key_values() {
+                if self.extra_file.is_expired(&value_data) {
+                    expired_keys.push(key);
+                }
+            }
+            let next_page = leaf.get_next_page();
+            leaf.release(&self.mete_data, false)?;
+            if next_page == NULL_PAGE {
+                break;
+            }
+            leaf = Node::load(&self.mete_data, &self.data_file, next_page)?;
+        }
+        for key in expired_keys.iter() {
+            self.remove_key(key)?;
+        }
+        Ok(expired_keys)
+    }
+
+    // 移动数据文件末尾的页到前面的空闲页并截断文件，返回释放的字节数
+    fn shrink_to_fit(&mut self) -> MiniBaseResult<u64> {
+        let (page_size, file_length) = (self.mete_data.page_size as u64, self.data_file.len()?);
+        let mut pages = HashMap::new();
+        self.root_node.collect_pages(&self.mete_data, &self.data_file, NULL_PAGE, &mut pages)?;
+        // 树中的页移动到文件的前部之后，截断位置之前不再有空闲页，不在树中的页也一起回收
+        let shrunk_length = pages.len() as u64 * page_size;
+        if shrunk_length >= file_length {
+            return Ok(0);
+        }
+        // 缓存中的节点可能映射了将被移动或截断的页
+        self.mete_data.page_cache_mut().clear()?;
+        let mut moving: Vec<_> = pages.keys().copied().filter(|offset| *offset >= shrunk_length).collect();
+        moving.sort_unstable();
+        let targets = (0..shrunk_length).step_by(page_size as usize).filter(|offset| !pages.contains_key(offset));
+        let mut relocated = HashMap::new();
+        for (old_offset, new_offset) in moving.into_iter().zip(targets) {
+            self.relocate_page(old_offset, new_offset, &pages, &relocated)?;
+            relocated.insert(old_offset, new_offset);
+        }
+        self.mete_data.set_free_page_head(NULL_PAGE);
+        // 截断的页在预写日志中同样需要原始数据，恢复时文件会延长到检查点时的长度
+        for offset in (shrunk_length..file_length).step_by(page_size as usize) {
+            self.mete_data.journal_page(&self.data_file, offset)?;
+        }
+        self.data_file.set_len(shrunk_length)?;
+        Ok(file_length - shrunk_length)
+    }
+
+    // 数据文件截断为只有一个页，第一个页重新初始化为空的根叶节点，空闲链表清空
+    fn clear_pages(&mut self) -> MiniBaseResult<()> {
+        let (page_size, file_length) = (self.mete_data.page_size as u64, self.data_file.len()?);
+        // 缓存中的节点可能映射了将被截断的页
+        self.mete_data.page_cache_mut().clear()?;
+        for offset in (0..file_length).step_by(page_size as usize) {
+            self.mete_data.journal_page(&self.data_file, offset)?;
+        }
+        self.data_file.set_len(page_size)?;
+        let root_node = Node::new(&self.mete_data, &self.data_file, 0, NodeType::Leaf)?;
+        self.replace_root(root_node);
+        self.mete_data.set_free_page_head(NULL_PAGE);
+        self.mete_data.skipped_leaves.clear();
+        self.lost_ranges.clear();
+        Ok(())
+    }
+
+    // 将old_offset的页复制到new_offset，并修改父节点中的指针和相邻叶节点的链表，relocated为已经移动的页的新位置
+    fn relocate_page(&mut self, old_offset: u64, new_offset: u64, pages: &HashMap<u64, (u64, bool)>, relocated: &HashMap<u64, u64>) -> MiniBaseResult<()> {
+        let (page_size, verify_checksum, comparator) = (self.mete_data.page_size, self.mete_data.verify_checksum, self.mete_data.comparator.clone());
+        let data_file = &self.data_file;
+        let current_offset = |offset: u64| relocated.get(&offset).copied().unwrap_or(offset);
+        self.mete_data.journal_page(data_file, new_offset)?;
+        let mut data = vec![0; page_size as usize];
+        data_file.read_exact_at(&mut data, old_offset)?;
+        data_file.write_all_at(&data, new_offset)?;
+        let (parent, is_leaf) = pages[&old_offset];
+        if parent == NULL_PAGE {
+            let root_node = Node::load(&self.mete_data, data_file, new_offset)?;
+            self.replace_root(root_node);
+        } else {
+            let parent = current_offset(parent);
+            self.mete_data.journal_page(data_file, parent)?;
+            let mut parent_page = InnerPage::from(data_file, parent, page_size, verify_checksum, comparator.clone())?;
+            if !parent_page.replace_child(old_offset, new_offset) {
+                return Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("page {}: child {} not found", parent, old_offset))));
+            }
+        }
+        if is_leaf {
+            let data_file = &self.data_file;
+            let leaf_page = LeafPage::from(data_file, new_offset, page_size, verify_checksum, comparator.clone())?;
+            let (previous_page, next_page) = (leaf_page.get_previous_page(), leaf_page.get_next_page());
+            if previous_page != NULL_PAGE {
+                self.mete_data.journal_page(data_file, previous_page)?;
+                LeafPage::from(data_file, previous_page, page_size, verify_checksum, comparator.clone())?.update_next_page(new_offset);
+            }
+            if next_page != NULL_PAGE {
+                self.mete_data.journal_page(data_file, next_page)?;
+                LeafPage::from(data_file, next_page, page_size, verify_checksum, comparator)?.update_previous_page(new_offset);
+            }
+        }
+        Ok(())
+    }
+
+    // 从start所在的叶节点开始检查最多max_pages个叶节点，整理可回收空间超过garbage_ratio的叶节点
+    // 返回下一个叶节点中最小的key，作为下次开始检查的位置，已经检查到最后一个叶节点时返回None
+    fn vacuum_leaves(&mut self, start: Option<&[u8]>, garbage_ratio: f32, max_pages: usize) -> MiniBaseResult<Option<Vec<u8>>> {
+        let (mete_data, data_file) = (&self.mete_data, &self.data_file);
+        let root = Node::load(mete_data, data_file, self.root_node.get_offset())?;
+        let mut leaf = match start {
+            Some(key) => root.find_leaf(mete_data, data_file, key, None)?.0,
+            None => root.leftmost_leaf(mete_data, data_file)?,
+        };
+        for _ in 0..max_pages {
+            let compacted = leaf.vacuum(mete_data.verify_checksum, garbage_ratio);
+            let next_page = leaf.get_next_page();
+            leaf.release(mete_data, compacted)?;
+            if next_page == NULL_PAGE {
+                return Ok(None);
+            }
+            leaf = Node::load(mete_data, data_file, next_page)?;
+        }
+        let next_key = leaf.get_first_key();
+        leaf.release(mete_data, false)?;
+        Ok(next_key)
+    }
+
+    // 替换缓存的根节点，同时更新元数据文件中的根节点偏移，根节点只在这里改变
+    fn replace_root(&mut self, root_node: Node) {
+        let (old, new) = (self.root_node.get_offset(), root_node.get_offset());
+        self.mete_data.set_root_page_offset(new);
+        self.root_node = root_node;
+        self.mete_data.notify_structural_change(|| StructuralEvent::RootChange { old, new });
     }
-}
\ No newline at end of file
+
+    // 根节点分裂，创建新的根节点
+    fn split_root(&mut self, split: Split) -> MiniBaseResult<()> {
+        let root_offset = self.mete_data.allocate_page(&self.data_file)?;
+        let left = self.root_node.get_offset();
+        let root_node = Node::new_root(&self.mete_data, &self.data_file, root_offset, left, &split)?;
+        self.mete_data.notify_structural_change(|| StructuralEvent::Split { parent: root_offset, left, right: split.right_offset, separator: split.separator.clone() });
+        self.replace_root(root_node);
+        Ok(())
+    }
+
+    // 批量写入，按key排序后依次写入，连续的key位于同一个叶节点时不需要重新从根节点查找
+    fn put_batch(&mut self, pairs: &[(&[u8], &[u8])]) -> MiniBaseResult<(usize, usize)> {
+        self.check_single_value("put_batch")?;
+        for (key, _) in pairs {
+            self.check_key(key)?;
+        }
+        let mut pairs = pairs.to_vec();
+        let comparator = self.mete_data.comparator.clone();
+        pairs.sort_by(|a, b| comparator.compare(a.0, b.0));
+        let (mut inserted, mut overwritten) = (0, 0);
+        // 当前的叶节点和叶节点中数据的上界
+        let mut current_leaf: Option<(Node, Option<Vec<u8>>)> = None;
+        for (key, value) in pairs {
+            let value_data = self.extra_file.encode_value(value, self.mete_data.value_threshold)?;
+            if let Some((_, Some(upper_bound))) = &current_leaf {
+                if comparator.compare(key, upper_bound) != Ordering::Less {
+                    current_leaf = None;
+                }
+            }
+            let (leaf, _) = match &mut current_leaf {
+                Some(current_leaf) => current_leaf,
+                None => {
+                    let root = Node::load(&self.mete_data, &self.data_file, self.root_node.get_offset())?;
+                    current_leaf.insert(root.find_leaf(&self.mete_data, &self.data_file, key, None)?)
+                }
+            };
+            match leaf.insert_key_value(key, &value_data, self.mete_data.garbage_ratio) {
+                (InsertResult::Inserted, _) => inserted += 1,
+                (InsertResult::Overwritten, replaced) => {
+                    overwritten += 1;
+                    if let Some(replaced) = &replaced {
+                        self.extra_file.free_value(replaced)?;
+                    }
+                }
+                (InsertResult::NeedsSplit, _) => {
+                    if leaf.get(&self.mete_data, &self.data_file, key)?.is_some() {
+                        overwritten += 1;
+                    } else {
+                        inserted += 1;
+                    }
+                    // 叶节点空间不足，通过完整的写入流程完成分裂，之后需要重新查找叶节点
+                    current_leaf = None;
+                    self.put_value_data(key, &value_data)?;
+                }
+            }
+        }
+        Ok((inserted, overwritten))
+    }
+
+    fn bulk_load<I>(&mut self, pairs: I) -> MiniBaseResult<usize>
+        where I: Iterator<Item = MiniBaseResult<(Vec<u8>, Vec<u8>)>> {
+        self.check_single_value("bulk_load")?;
+        if !self.root_node.is_leaf() || self.len()? != 0 {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, "bulk_load requires an empty tree")));
+        }
+        let root_offset = self.root_node.get_offset();
+        let (mut allocated, mut count) = (Vec::new(), 0);
+        let result = self.bulk_load_leaves(pairs, root_offset, &mut allocated, &mut count)
+            .and_then(|leaves| self.bulk_load_inner_levels(leaves, &mut allocated));
+        match result {
+            Ok(offset) => {
+                if offset != root_offset {
+                    let root_node = Node::load(&self.mete_data, &self.data_file, offset)?;
+                    self.replace_root(root_node);
+                }
+                Ok(count)
+            }
+            Err(error) => {
+                // 丢弃已经写入的数据，恢复为空树
+                for offset in allocated {
+                    self.mete_data.free_page(&self.data_file, offset)?;
+                }
+                LeafPage::new(&self.data_file, root_offset, self.mete_data.page_size, self.mete_data.comparator.clone())?.with_bloom_filter(self.mete_data.bloom_filter);
+                Err(error)
+            }
+        }
+    }
+
+    // 依次写满叶节点，返回每个叶节点中最小的key及叶节点的偏移，第一个叶节点为原来的根节点，count为写入的key数量
+    fn bulk_load_leaves<I>(&mut self, pairs: I, first_offset: u64, allocated: &mut Vec<u64>, count: &mut usize) -> MiniBaseResult<Vec<(Vec<u8>, u64)>>
+        where I: Iterator<Item = MiniBaseResult<(Vec<u8>, Vec<u8>)>> {
+        let (page_size, comparator) = (self.mete_data.page_size, self.mete_data.comparator.clone());
+        let mut leaf = LeafPage::new(&self.data_file, first_offset, page_size, comparator.clone())?.with_bloom_filter(self.mete_data.bloom_filter);
+        let mut leaf_offset = first_offset;
+        let mut leaves = vec![(Vec::new(), first_offset)];
+        let mut previous_key: Option<Vec<u8>> = None;
+        for pair in pairs {
+            let (key, value) = pair?;
+            self.check_key(&key)?;
+            if previous_key.as_ref().is_some_and(|previous_key| comparator.compare(previous_key, &key) != Ordering::Less) {
+                return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, format!("bulk_load keys not strictly ascending at index {}", count))));
+            }
+            let value_data = self.extra_file.encode_value(&value, self.mete_data.value_threshold)?;
+            if leaf.insert_key_value(&key, &value_data) == InsertResult::NeedsSplit {
+                let offset = self.mete_data.allocate_page(&self.data_file)?;
+                allocated.push(offset);
+                let mut next_leaf = LeafPage::new(&self.data_file, offset, page_size, comparator.clone())?.with_bloom_filter(self.mete_data.bloom_filter);
+                leaf.update_next_page(offset);
+                next_leaf.update_previous_page(leaf_offset);
+                if next_leaf.insert_key_value(&key, &value_data) == InsertResult::NeedsSplit {
+                    return Err(Box::from(MiniBaseError::new(ErrorKind::PageFull, format!("key value at index {} exceeds page size", count))));
+                }
+                leaves.push((key.clone(), offset));
+                leaf = next_leaf;
+                leaf_offset = offset;
+            }
+            previous_key = Some(key);
+            *count += 1;
+        }
+        Ok(leaves)
+    }
+
+    // 自底向上构建内部节点，children为每个子节点中最小的key及子节点的偏移，返回根节点的偏移
+    fn bulk_load_inner_levels(&mut self, mut children: Vec<(Vec<u8>, u64)>, allocated: &mut Vec<u64>) -> MiniBaseResult<u64> {
+        let (page_size, comparator) = (self.mete_data.page_size, self.mete_data.comparator.clone());
+        while children.len() > 1 {
+            let mut parents: Vec<(Vec<u8>, u64)> = Vec::new();
+            let (mut previous, mut current): (Option<InnerPage>, Option<InnerPage>) = (None, None);
+            for (separator, child) in children {
+                if let Some(page) = current.as_mut() {
+                    if page.insert_separator(&separator, page.get_last_pointer(), child) {
+                        continue;
+                    }
+                }
+                let offset = self.mete_data.allocate_page(&self.data_file)?;
+                allocated.push(offset);
+                let mut page = InnerPage::new(&self.data_file, offset, page_size, comparator.clone())?;
+                page.update_last_pointer(child);
+                parents.push((separator, offset));
+                previous = current.replace(page);
+            }
+            // 最后一个节点只有一个子节点时，从前一个节点移动一个子节点过来
+            if let (Some(previous), Some(current)) = (previous.as_mut(), current.as_mut()) {
+                let mut separators = previous.get_separators();
+                if current.get_separators().is_empty() && separators.len() > 1 {
+                    let (separator, child) = separators.pop().unwrap();
+                    let moved_child = previous.get_last_pointer();
+                    previous.rewrite(&separators, child);
+                    let (current_separator, _) = parents.last_mut().unwrap();
+                    current.rewrite(&[(std::mem::replace(current_separator, separator), moved_child)], current.get_last_pointer());
+                }
+            }
+            children = parents;
+        }
+        Ok(children[0].1)
+    }
+
+    fn value_len(&self, key: &[u8]) -> MiniBaseResult<Option<u32>> {
+        self.check_single_value("value_len")?;
+        self.read_live_value(key, &|value_data| self.extra_file.value_length(value_data))
+    }
+
+    fn stored_value(&self, key: &[u8]) -> MiniBaseResult<Option<StoredValue>> {
+        self.check_single_value("get_reader")?;
+        self.read_live_value(key, &|value_data| self.extra_file.stored_value(value_data))
+    }
+
+    // 通过read读取key未过期的value数据，key不存在或已经过期时返回None
+    fn read_live_value<T>(&self, key: &[u8], read: &dyn Fn(&[u8]) -> MiniBaseResult<T>) -> MiniBaseResult<Option<T>> {
+        let value = self.root_node.read_value(&self.mete_data, &self.data_file, key, &|value_data| match self.is_expired(value_data) {
+            true => Ok(None),
+            false => read(value_data).map(Some),
+        })?;
+        Ok(value.flatten())
+    }
+
+    fn get_bytes(&self, key: &[u8]) -> MiniBaseResult<Option<Vec<u8>>> {
+        if self.mete_data.multi_value {
+            let (begin, end) = multi_value_range(key);
+            return match self.find_nearest(&begin, true, true)? {
+                Some((stored_key, value)) if self.mete_data.comparator.compare(&stored_key, &end) == Ordering::Less => Ok(Some(value)),
+                _ => Ok(None),
+            };
+        }
+        match self.root_node.get(&self.mete_data, &self.data_file, key)? {
+            Some(value_data) if !self.is_expired(&value_data) => Ok(Some(self.extra_file.decode_value(&value_data)?)),
+            _ => Ok(None),
+        }
+    }
+
+    fn get_entry(&self, key: &[u8]) -> MiniBaseResult<Option<(Vec<u8>, Vec<u8>)>> {
+        self.check_single_value("get_entry")?;
+        match self.root_node.get_entry(&self.mete_data, &self.data_file, key)? {
+            Some((key, value_data)) if !self.is_expired(&value_data) => Ok(Some((key, self.extra_file.decode_value(&value_data)?))),
+            _ => Ok(None),
+        }
+    }
+
+    // 从key所在的叶节点开始查找，叶节点中没有满足条件的key时沿NEXT_PAGE（forward为false时沿PREVIOUS_PAGE）继续查找
+    fn get_nearest(&self, key: &[u8], inclusive: bool, forward: bool) -> MiniBaseResult<Option<(Vec<u8>, Vec<u8>)>> {
+        self.check_single_value("floor, ceiling, higher and lower")?;
+        self.find_nearest(key, inclusive, forward)
+    }
+
+    fn find_nearest(&self, key: &[u8], inclusive: bool, forward: bool) -> MiniBaseResult<Option<(Vec<u8>, Vec<u8>)>> {
+        let root = Node::load(&self.mete_data, &self.data_file, self.root_node.get_offset())?;
+        let (mut leaf, _) = root.find_leaf(&self.mete_data, &self.data_file, key, None)?;
+        let (mut search_key, mut inclusive) = (Some(key.to_vec()), inclusive);
+        loop {
+            let entry = leaf.get_nearest(search_key.as_deref(), inclusive, forward);
+            // 过期的key视为不存在，从这个key之后继续查找
+            if let Some((key, _)) = entry.as_ref().filter(|(_, value_data)| self.is_expired(value_data)) {
+                (search_key, inclusive) = (Some(key.clone()), false);
+                continue;
+            }
+            let next_page = if forward { leaf.get_next_page() } else { leaf.get_previous_page() };
+            leaf.release(&self.mete_data, false)?;
+            if let Some((key, value_data)) = entry {
+                return Ok(Some((key, self.extra_file.decode_value(&value_data)?)));
+            }
+            if next_page == NULL_PAGE {
+                return Ok(None);
+            }
+            leaf = Node::load(&self.mete_data, &self.data_file, next_page)?;
+            search_key = None;
+        }
+    }
+
+    // 批量读取，按key排序后依次读取，连续的key位于同一个叶节点时不需要重新从根节点查找
+    fn get_many(&self, keys: &[&[u8]]) -> MiniBaseResult<Vec<Option<Vec<u8>>>> {
+        self.check_single_value("get_many")?;
+        let comparator = self.mete_data.comparator.clone();
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+        order.sort_by(|a, b| comparator.compare(keys[*a], keys[*b]));
+        let mut result = vec![None; keys.len()];
+        // 当前的叶节点和叶节点中数据的上界
+        let mut current_leaf: Option<(Node, Option<Vec<u8>>)> = None;
+        for index in order {
+            let key = keys[index];
+            if let Some((_, Some(upper_bound))) = &current_leaf {
+                if comparator.compare(key, upper_bound) != Ordering::Less {
+                    let (leaf, _) = current_leaf.take().unwrap();
+                    leaf.release(&self.mete_data, false)?;
+                }
+            }
+            let (leaf, _) = match &mut current_leaf {
+                Some(current_leaf) => current_leaf,
+                None => {
+                    let root = Node::load(&self.mete_data, &self.data_file, self.root_node.get_offset())?;
+                    current_leaf.insert(root.find_leaf(&self.mete_data, &self.data_file, key, None)?)
+                }
+            };
+            if let Some(value_data) = leaf.get(&self.mete_data, &self.data_file, key)?.filter(|value_data| !self.is_expired(value_data)) {
+                result[index] = Some(self.extra_file.decode_value(&value_data)?);
+            }
+        }
+        if let Some((leaf, _)) = current_leaf {
+            leaf.release(&self.mete_data, false)?;
+        }
+        Ok(result)
+    }
+
+    fn contains_key(&self, key: &[u8]) -> MiniBaseResult<bool> {
+        if self.mete_data.multi_value {
+            return Ok(self.get_bytes(key)?.is_some());
+        }
+        if self.mete_data.ttl {
+            return Ok(self.read_live_value(key, &|_| Ok(()))?.is_some());
+        }
+        self.root_node.contains_key(&self.mete_data, &self.data_file, key)
+    }
+
+    // 按字节序排列时只需要读取[prefix, prefix的后继)的范围，其它排序方式下以prefix开头的key不一定连续，需要读取全部数据
+    fn scan_prefix(&self, prefix: &[u8]) -> MiniBaseResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        if self.mete_data.multi_value {
+            // 编码只转义key中的0，key以prefix开头时编码后以转义后的prefix开头
+            let (mut begin, _) = multi_value_range(prefix);
+            begin.truncate(begin.len() - 2);
+            let result = match prefix_successor(&begin) {
+                Some(end) => self.scan_stored(&begin, &end, false)?,
+                None => self.scan_stored(&begin, &self.multi_value_max_key(), true)?,
+            };
+            return decode_multi_value_keys(result);
+        }
+        if let Comparator::Custom(_) = self.mete_data.comparator {
+            return self.scan_leaves_with_prefix(prefix);
+        }
+        // key的长度不超过key_max_length，因此所有key都不大于key_max_length个0xFF
+        let max_key = vec![0xFF; self.mete_data.key_max_length as usize];
+        let mut result = match prefix_successor(prefix) {
+            Some(end) if self.mete_data.comparator == Comparator::ByteLexicographic => self.scan_range(prefix, &end, false)?,
+            // prefix全部为0xFF时，大于等于prefix的key都以prefix开头
+            None if self.mete_data.comparator == Comparator::ByteLexicographic => self.scan_range(prefix, &max_key, true)?,
+            _ => self.scan_range(&[], &max_key, true)?,
+        };
+        result.retain(|(key, _)| key.starts_with(prefix));
+        Ok(result)
+    }
+
+    // 自定义的排序方式下空key和0xFF不一定是最小和最大的key，从最左侧的叶节点开始沿NEXT_PAGE读取所有叶节点
+    fn scan_leaves_with_prefix(&self, prefix: &[u8]) -> MiniBaseResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut result = Vec::new();
+        let mut cursor = LeafCursor::new(self, false);
+        while let Some(key_values) = cursor.next_leaf(self, |leaf| self.get_live_key_values(leaf)) {
+            result.extend(key_values?.into_iter().filter(|(key, _)| key.starts_with(prefix)));
+        }
+        for (_, value) in result.iter_mut() {
+            *value = self.extra_file.decode_value(value)?;
+        }
+        Ok(result)
+    }
+
+    // 从last_key所在的叶节点开始沿NEXT_PAGE读取，多读取一个key用于判断是否还有更多数据
+    fn scan_from(&self, last_key: Option<&[u8]>, limit: usize) -> MiniBaseResult<ScanChunk> {
+        self.check_single_value("scan_from")?;
+        if limit == 0 {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, "limit must be greater than 0")));
+        }
+        let comparator = self.mete_data.comparator.clone();
+        let root = Node::load(&self.mete_data, &self.data_file, self.root_node.get_offset())?;
+        let mut leaf = match last_key {
+            Some(key) => root.find_leaf(&self.mete_data, &self.data_file, key, None)?.0,
+            None => root.leftmost_leaf(&self.mete_data, &self.data_file)?,
+        };
+        let mut result = Vec::new();
+        loop {
+            let key_values = self.get_live_key_values(&leaf).into_iter()
+                .filter(|(key, _)| last_key.is_none_or(|last_key| comparator.compare(key, last_key) == Ordering::Greater));
+            result.extend(key_values.take(limit + 1 - result.len()));
+            let next_page = leaf.get_next_page();
+            leaf.release(&self.mete_data, false)?;
+            if result.len() > limit || next_page == NULL_PAGE {
+                break;
+            }
+            leaf = Node::load(&self.mete_data, &self.data_file, next_page)?;
+            leaf.advise(AccessPattern::Sequential)?;
+        }
+        let next_key = if result.len() > limit {
+            result.truncate(limit);
+            result.last().map(|(key, _)| key.clone())
+        } else {
+            None
+        };
+        for (_, value) in result.iter_mut() {
+            *value = self.extra_file.decode_value(value)?;
+        }
+        Ok((result, next_key))
+    }
+
+    // 与scan_range相同，每读取DEADLINE_CHECK_INTERVAL个叶节点检查一次时间，范围读取的叶节点都提示为顺序访问
+    fn scan_with_deadline(&self, begin: &[u8], end: &[u8], deadline: Instant) -> MiniBaseResult<ScanChunk> {
+        self.check_single_value("scan_with_deadline")?;
+        let mut result = Vec::new();
+        let mut next_page = self.root_node.scan(&self.mete_data, &self.data_file, begin, end, false, &mut result)?;
+        let mut leaf_count = 1;
+        let mut resume_key = None;
+        while let Some(offset) = next_page {
+            let node = Node::load(&self.mete_data, &self.data_file, offset)?;
+            node.advise(AccessPattern::Sequential)?;
+            if leaf_count % DEADLINE_CHECK_INTERVAL == 0 && Instant::now() >= deadline {
+                // 下一个叶节点中的key都大于已经读取的key，从其中第一个未删除的key继续读取，没有未删除的key时继续读取下一个叶节点
+                if let Some((key, _)) = node.get_nearest(None, true, true) {
+                    node.release(&self.mete_data, false)?;
+                    if self.mete_data.comparator.compare(&key, end) == Ordering::Less {
+                        resume_key = Some(key);
+                    }
+                    break;
+                }
+            }
+            next_page = node.scan(&self.mete_data, &self.data_file, begin, end, false, &mut result)?;
+            node.release(&self.mete_data, false)?;
+            leaf_count += 1;
+        }
+        if self.mete_data.ttl {
+            result.retain(|(_, value_data)| !self.extra_file.is_expired(value_data));
+        }
+        for (_, value) in result.iter_mut() {
+            *value = self.extra_file.decode_value(value)?;
+        }
+        Ok((result, resume_key))
+    }
+
+    // 从begin所在的叶节点开始沿NEXT_PAGE统计，直到超过end
+    fn count_range(&self, begin: &[u8], end: &[u8]) -> MiniBaseResult<u64> {
+        self.check_single_value("count_range")?;
+        let mut count = 0;
+        let mut next_page = self.root_node.count(&self.mete_data, &self.data_file, begin, end, false, &mut count)?;
+        while let Some(offset) = next_page {
+            let node = Node::load(&self.mete_data, &self.data_file, offset)?;
+            next_page = node.count(&self.mete_data, &self.data_file, begin, end, false, &mut count)?;
+            node.release(&self.mete_data, false)?;
+        }
+        Ok(count)
+    }
+
+    fn size_of_range(&self, begin: &[u8], end: &[u8]) -> MiniBaseResult<u64> {
+        self.check_single_value("size_of_range")?;
+        let extra_size = |value_data: &[u8]| match self.extra_file.is_extra_value(value_data)? {
+            true => Ok(self.extra_file.value_length(value_data)? as u64),
+            false => Ok(0),
+        };
+        let mut size = 0;
+        let mut next_page = self.root_node.measure(&self.mete_data, &self.data_file, begin, end, &extra_size, &mut size)?;
+        while let Some(offset) = next_page {
+            let node = Node::load(&self.mete_data, &self.data_file, offset)?;
+            let measured = node.measure(&self.mete_data, &self.data_file, begin, end, &extra_size, &mut size);
+            node.release(&self.mete_data, false)?;
+            next_page = measured?;
+        }
+        Ok(size)
+    }
+
+    // 从最左侧的叶节点开始沿NEXT_PAGE统计
+    fn len(&self) -> MiniBaseResult<u64> {
+        let root = Node::load(&self.mete_data, &self.data_file, self.root_node.get_offset())?;
+        let mut leaf = root.leftmost_leaf(&self.mete_data, &self.data_file)?;
+        let mut count = 0;
+        loop {
+            count += leaf.get_live_key_count() as u64;
+            let next_page = leaf.get_next_page();
+            leaf.release(&self.mete_data, false)?;
+            if next_page == NULL_PAGE {
+                return Ok(count);
+            }
+            leaf = Node::load(&self.mete_data, &self.data_file, next_page)?;
+        }
+    }
+
+    fn remove_bytes(&mut self, key: &[u8]) -> MiniBaseResult<bool> {
+        if self.mete_data.multi_value {
+            let (begin, end) = multi_value_range(key);
+            let stored_keys: Vec<_> = self.scan_stored(&begin, &end, false)?.into_iter().map(|(stored_key, _)| stored_key).collect();
+            for stored_key in stored_keys.iter() {
+                self.remove_key(stored_key)?;
+            }
+            return Ok(!stored_keys.is_empty());
+        }
+        self.remove_key(key)
+    }
+
+    fn remove_key(&mut self, key: &[u8]) -> MiniBaseResult<bool> {
+        let removed = self.root_node.remove(&mut self.mete_data, &self.data_file, key)?;
+        // 保存在额外数据文件中的value占用的chunk在flush之后复用，覆盖的value在写入时同样释放
+        if let Some(value_data) = &removed {
+            self.extra_file.free_value(value_data)?;
+        }
+        // 根节点只剩一个子节点时，由子节点作为新的根节点，释放原来的根节点
+        while let Some(child) = self.root_node.get_only_child() {
+            let root_offset = self.root_node.get_offset();
+            let root_node = Node::load(&self.mete_data, &self.data_file, child)?;
+            self.replace_root(root_node);
+            self.mete_data.free_page(&self.data_file, root_offset)?;
+        }
+        // 删除已经过期的key与key不存在相同
+        Ok(removed.is_some_and(|value_data| !self.is_expired(&value_data)))
+    }
+
+    // 多值模式下大于所有编码后的key的key，key和序号中的每个字节编码后最多为2个字节，每个分量之后还有2个字节
+    fn multi_value_max_key(&self) -> Vec<u8> {
+        vec![0xFF; (self.mete_data.key_max_length as usize + 8) * 2 + 5]
+    }
+
+    fn check_key(&self, key: &[u8]) -> MiniBaseResult<()> {
+        if key.len() > self.mete_data.key_max_length as usize {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, format!("key length {} exceeds max length {}", key.len(), self.mete_data.key_max_length))));
+        }
+        // 多值模式页中保存的是编码后的key，序号为0时转义的字节最多，按最长的编码检查
+        if self.mete_data.multi_value {
+            let encoded_length = multi_value_key(key, 0).len();
+            if encoded_length > self.mete_data.key_max_length as usize {
+                return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, format!("encoded multi-value key length {} exceeds max length {}", encoded_length, self.mete_data.key_max_length))));
+            }
+        }
+        Ok(())
+    }
+
+    fn stats(&self) -> MiniBaseResult<Stats> {
+        let mut stats = Stats::default();
+        self.root_node.collect_stats(&self.mete_data, &self.data_file, 1, &mut stats)?;
+        stats.cached_page_count = self.mete_data.page_cache().len();
+        Ok(stats)
+    }
+
+    fn verify(&self) -> MiniBaseResult<()> {
+        self.verify_pages(true).map(|_| ())
+    }
+
+    fn verify_all(&self) -> MiniBaseResult<Vec<VerificationError>> {
+        self.verify_pages(false)
+    }
+
+    // fail_fast时返回描述第一个问题的Corruption错误，否则返回所有问题，读写文件出错时返回错误
+    fn verify_pages(&self, fail_fast: bool) -> MiniBaseResult<Vec<VerificationError>> {
+        let (mete_data, data_file) = (&self.mete_data, &self.data_file);
+        let mut scan = VerifyScan::new(fail_fast);
+        self.root_node.verify(mete_data, data_file, 1, None, None, &mut scan)?;
+        // 所有叶节点位于同一层，并且按key的顺序通过NEXT_PAGE和PREVIOUS_PAGE串联，与无法读取的子树相邻的链接无法确定，不做检查
+        let leaves = std::mem::take(&mut scan.leaves);
+        let leaf_depth = leaves.iter().flatten().next().map_or(0, |leaf| leaf.depth);
+        for (index, leaf) in leaves.iter().enumerate() {
+            let Some(VerifiedLeaf { offset, depth, begin, end }) = leaf else {
+                continue;
+            };
+            let (offset, begin, end) = (*offset, begin.as_deref(), end.as_deref());
+            if *depth != leaf_depth {
+                scan.report(offset, VerificationErrorKind::LeafDepth, format!("leaf at depth {}, expect {}", depth, leaf_depth), begin, end)?;
+            }
+            let leaf = Node::load(mete_data, data_file, offset)?;
+            let previous_page = if index == 0 { Some(NULL_PAGE) } else { leaves[index - 1].as_ref().map(|leaf| leaf.offset) };
+            let next_page = match leaves.get(index + 1) {
+                None => Some(NULL_PAGE),
+                Some(next) => next.as_ref().map(|leaf| leaf.offset),
+            };
+            if previous_page.is_some_and(|page| page != leaf.get_previous_page()) || next_page.is_some_and(|page| page != leaf.get_next_page()) {
+                let expect = |page: Option<u64>| page.map_or("?".to_string(), |page| page.to_string());
+                let message = format!("leaf links ({}, {}) invalid, expect ({}, {})", leaf.get_previous_page(), leaf.get_next_page(), expect(previous_page), expect(next_page));
+                scan.report(offset, VerificationErrorKind::LeafLinks, message, begin, end)?;
+            }
+            for (key, value_data) in leaf.get_key_values() {
+                if let Err(error) = self.extra_file.verify_value(&value_data) {
+                    let error = with_message_prefix(&format!("key {}", key.escape_ascii()), error);
+                    scan.report_error(offset, VerificationErrorKind::ExtraValue, error, begin, end)?;
+                }
+            }
+            leaf.release(mete_data, false)?;
+        }
+        Ok(scan.errors)
+    }
+
+    // 范围读取begin到end内的key value，按key升序排列，end_inclusive表示是否包含end
+    fn scan_range(&self, begin: &[u8], end: &[u8], end_inclusive: bool) -> MiniBaseResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        if self.mete_data.multi_value {
+            let (begin, _) = multi_value_range(begin);
+            let (end_begin, end_end) = multi_value_range(end);
+            let result = self.scan_stored(&begin, if end_inclusive { &end_end } else { &end_begin }, false)?;
+            return decode_multi_value_keys(result);
+        }
+        self.scan_stored(begin, end, end_inclusive)
+    }
+
+    // 按保存的key范围读取，多值模式下key为编码后的key，沿NEXT_PAGE读取的叶节点提示为顺序访问
+    fn scan_stored(&self, begin: &[u8], end: &[u8], end_inclusive: bool) -> MiniBaseResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut result = Vec::new();
+        let mut next_page = self.root_node.scan(&self.mete_data, &self.data_file, begin, end, end_inclusive, &mut result)?;
+        while let Some(offset) = next_page {
+            let node = Node::load(&self.mete_data, &self.data_file, offset)?;
+            node.advise(AccessPattern::Sequential)?;
+            next_page = node.scan(&self.mete_data, &self.data_file, begin, end, end_inclusive, &mut result)?;
+            node.release(&self.mete_data, false)?;
+        }
+        if self.mete_data.ttl {
+            result.retain(|(_, value_data)| !self.extra_file.is_expired(value_data));
+        }
+        for (_, value) in result.iter_mut() {
+            *value = self.extra_file.decode_value(value)?;
+        }
+        Ok(result)
+    }
+}
+
+// 只读事务，存在期间持有读锁，写操作会等待事务结束，因此事务内的读取不会看到事务开始之后的写入
+// 隔离级别为可串行化：事务等价于在开始时刻一次性完成所有读取，代价是事务期间所有写操作被阻塞，事务应尽量短
+// 同一个线程在事务结束前执行写操作会死锁
+pub struct ReadTxn<'a> {
+    tree: RwLockReadGuard<'a, Tree>,
+}
+
+impl ReadTxn<'_> {
+    pub fn get_bytes(&self, key: &[u8]) -> MiniBaseResult<Option<Vec<u8>>> {
+        self.tree.get_bytes(key)
+    }
+
+    pub fn get_entry(&self, key: &[u8]) -> MiniBaseResult<Option<(Vec<u8>, Vec<u8>)>> {
+        self.tree.get_entry(key)
+    }
+
+    pub fn get_many(&self, keys: &[&[u8]]) -> MiniBaseResult<Vec<Option<Vec<u8>>>> {
+        self.tree.get_many(keys)
+    }
+
+    pub fn contains_key(&self, key: &[u8]) -> MiniBaseResult<bool> {
+        self.tree.contains_key(key)
+    }
+
+    pub fn scan_bytes(&self, begin: &[u8], end: &[u8]) -> MiniBaseResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.tree.scan_range(begin, end, false)
+    }
+
+    pub fn scan_inclusive_bytes(&self, begin: &[u8], end: &[u8]) -> MiniBaseResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.tree.scan_range(begin, end, true)
+    }
+
+    pub fn scan_prefix(&self, prefix: &[u8]) -> MiniBaseResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.tree.scan_prefix(prefix)
+    }
+
+    pub fn count_range(&self, begin: &[u8], end: &[u8]) -> MiniBaseResult<u64> {
+        self.tree.count_range(begin, end)
+    }
+
+    pub fn len(&self) -> MiniBaseResult<u64> {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> MiniBaseResult<bool> {
+        Ok(self.len()? == 0)
+    }
+}
+
+// 修改key已经存在的value
+type ModifyValue<'a> = dyn FnOnce(&mut Vec<u8>) + 'a;
+
+// key对应的读取修改写入操作，与HashMap的Entry类似，通过or_insert执行，只需要从根节点查找一次叶节点
+// 存在期间持有写锁，同一个线程在Entry结束前执行其它操作会死锁
+#[must_use = "entry does nothing until or_insert is called"]
+pub struct Entry<'a> {
+    tree: RwLockWriteGuard<'a, Tree>,
+    key: Vec<u8>,
+    modify: Option<Box<ModifyValue<'a>>>,
+}
+
+impl<'a> Entry<'a> {
+    // key存在时使用f修改value，在or_insert时执行
+    pub fn and_modify<F>(mut self, f: F) -> Entry<'a>
+        where F: FnOnce(&mut Vec<u8>) + 'a {
+        self.modify = Some(match self.modify.take() {
+            Some(modify) => Box::new(move |value: &mut Vec<u8>| {
+                modify(value);
+                f(value);
+            }),
+            None => Box::new(f),
+        });
+        self
+    }
+
+    // key不存在时写入default_value，存在时按and_modify修改，返回写入后的value
+    pub fn or_insert(mut self, default_value: &[u8]) -> MiniBaseResult<Vec<u8>> {
+        let key = self.key;
+        let record = |value: &Vec<u8>| vec![put_record(&key, value)];
+        match self.modify.take() {
+            // 没有修改时key已经存在不需要写入
+            None => self.tree.write_ahead(|tree| tree.get_or_insert(&key, default_value), record),
+            Some(modify) => {
+                let modify = Cell::new(Some(modify));
+                self.tree.write_ahead(|tree| tree.merge(&key, default_value, |current, default_value| match current {
+                    Some(value) => {
+                        let mut value = value.to_vec();
+                        if let Some(modify) = modify.take() {
+                            modify(&mut value);
+                        }
+                        value
+                    }
+                    None => default_value.to_vec(),
+                }), record)
+            }
+        }
+    }
+}
+
+// 依次读取叶节点，Iter和Keys共用
+struct LeafCursor {
+    // 下一个需要读取的页，第一次读取时是根节点，需要先找到最左侧（逆序时为最右侧）的叶节点
+    next_page: u64,
+    // 是否按key降序遍历，逆序时沿PREVIOUS_PAGE向前读取
+    reverse: bool,
+}
+
+impl LeafCursor {
+    fn new(tree: &Tree, reverse: bool) -> LeafCursor {
+        LeafCursor { next_page: tree.root_node.get_offset(), reverse }
+    }
+
+    // 读取下一个叶节点并通过read取出其中的数据，按遍历的顺序排列，已经读取完时返回None，出错后不再继续读取
+    fn next_leaf<T, F>(&mut self, tree: &Tree, read: F) -> Option<MiniBaseResult<Vec<T>>>
+        where F: FnOnce(&Node) -> Vec<T> {
+        if self.next_page == NULL_PAGE {
+            return None;
+        }
+        let (mete_data, data_file) = (&tree.mete_data, &tree.data_file);
+        let leaf = Node::load(mete_data, data_file, self.next_page).and_then(|node| if self.reverse {
+            node.rightmost_leaf(mete_data, data_file)
+        } else {
+            node.leftmost_leaf(mete_data, data_file)
+        }).and_then(|leaf| leaf.advise(AccessPattern::Sequential).map(|_| leaf));
+        let leaf = match leaf {
+            Ok(leaf) => leaf,
+            Err(error) => {
+                self.next_page = NULL_PAGE;
+                return Some(Err(error));
+            }
+        };
+        let mut items = read(&leaf);
+        if self.reverse {
+            items.reverse();
+            self.next_page = leaf.get_previous_page();
+        } else {
+            self.next_page = leaf.get_next_page();
+        }
+        if let Err(error) = leaf.release(mete_data, false) {
+            self.next_page = NULL_PAGE;
+            return Some(Err(error));
+        }
+        Some(Ok(items))
+    }
+}
+
+pub struct Iter<'a> {
+    tree: RwLockReadGuard<'a, Tree>,
+    // 当前叶节点中还未返回的数据
+    key_values: IntoIter<(Vec<u8>, Vec<u8>)>,
+    cursor: LeafCursor,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = MiniBaseResult<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((key, value_data)) = self.key_values.next() {
+                return Some(self.tree.decode_key(key).and_then(|key| Ok((key, self.tree.extra_file.decode_value(&value_data)?))));
+            }
+            let tree = &self.tree;
+            match self.cursor.next_leaf(tree, |leaf| tree.get_live_key_values(leaf))? {
+                Ok(key_values) => self.key_values = key_values.into_iter(),
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+// 流式读取一个value，value保存在额外数据文件中时每次读取一个chunk
+pub struct ValueReader<'a> {
+    tree: RwLockReadGuard<'a, Tree>,
+    // 已经读取但还未返回的value数据
+    buffer: Vec<u8>,
+    position: usize,
+    // 下一个chunk的偏移和还未读取的value长度，value保存在叶节点中时remaining为0
+    next_chunk: u64,
+    remaining: u32,
+}
+
+impl ValueReader<'_> {
+    fn read_next_chunk(&mut self) -> MiniBaseResult<()> {
+        let chunk = self.next_chunk;
+        self.next_chunk = self.tree.extra_file.read_chunk(chunk, self.remaining, &mut self.buffer)?;
+        self.position = 0;
+        self.remaining -= self.buffer.len() as u32;
+        if self.remaining == 0 && self.next_chunk != NULL_CHUNK {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("extra value chunk at offset {} has more chunks than expected", chunk))));
+        }
+        Ok(())
+    }
+}
+
+// 读取额外数据文件出错或数据损坏时返回io错误，错误信息为原始错误的信息
+impl Read for ValueReader<'_> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.buffer.len() && self.remaining > 0 {
+            self.read_next_chunk().map_err(|error| io::Error::other(error.to_string()))?;
+        }
+        let length = buffer.len().min(self.buffer.len() - self.position);
+        buffer[..length].copy_from_slice(&self.buffer[self.position..self.position + length]);
+        self.position += length;
+        Ok(length)
+    }
+}
+
+// 流式写入一个value，写入叶节点之前读取不到新的value
+pub struct ValueWriter<'a> {
+    tree: RwLockWriteGuard<'a, Tree>,
+    key: Vec<u8>,
+    // 写入额外数据文件之前为全部数据，之后为还未写入的最后一个chunk中的数据
+    buffer: Vec<u8>,
+    // 已经分配的chunk，最后一个为当前chunk，还未写入额外数据文件时为空
+    chunks: Vec<u64>,
+    size: u64,
+    finished: bool,
+}
+
+impl ValueWriter<'_> {
+    // 将value写入叶节点，之后不能再写入数据
+    pub fn finish(mut self) -> MiniBaseResult<()> {
+        self.finish_value()
+    }
+
+    // 写满的chunk在分配下一个chunk后写入，最后一个chunk在完成时写入
+    fn write_value(&mut self, data: &[u8]) -> MiniBaseResult<()> {
+        self.buffer.extend_from_slice(data);
+        self.size += data.len() as u64;
+        let tree = &mut *self.tree;
+        if self.chunks.is_empty() {
+            if tree.mete_data.has_wal() || self.size < tree.mete_data.value_threshold as u64 {
+                return Ok(());
+            }
+            self.chunks.push(tree.extra_file.allocate_chunk()?);
+        }
+        let capacity = tree.extra_file.get_chunk_capacity();
+        let mut start = 0;
+        while self.buffer.len() - start > capacity {
+            let chunk = *self.chunks.last().unwrap();
+            let next_chunk = tree.extra_file.allocate_chunk()?;
+            self.chunks.push(next_chunk);
+            tree.extra_file.write_chunk(chunk, next_chunk, &self.buffer[start..start + capacity])?;
+            start += capacity;
+        }
+        self.buffer.drain(..start);
+        Ok(())
+    }
+
+    fn finish_value(&mut self) -> MiniBaseResult<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        let (key, value) = (std::mem::take(&mut self.key), std::mem::take(&mut self.buffer));
+        let (Some(&first_chunk), Some(&chunk)) = (self.chunks.first(), self.chunks.last()) else {
+            return self.tree.write_ahead(|tree| tree.put_bytes(&key, &value), |_| vec![put_record(&key, &value)]);
+        };
+        // 没有启用预写日志，不需要记录
+        let value_data = self.tree.extra_file.encode_pointer(first_chunk, self.size as u32);
+        let result = self.tree.extra_file.write_chunk(chunk, NULL_CHUNK, &value)
+            .and_then(|_| self.tree.write_ahead(|tree| tree.put_value_data(&key, &value_data), |_| Vec::new()));
+        if result.is_err() {
+            self.abort();
+        }
+        result
+    }
+
+    // 写入失败时value没有写入叶节点，之后不再完成写入，释放已经分配的chunk
+    fn abort(&mut self) {
+        self.finished = true;
+        self.tree.extra_file.free_chunks(std::mem::take(&mut self.chunks));
+    }
+}
+
+impl Write for ValueWriter<'_> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.finished {
+            return Err(io::Error::other("value writer already finished"));
+        }
+        if self.size + data.len() as u64 > u32::MAX as u64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "value length exceeds u32::MAX"));
+        }
+        if let Err(error) = self.write_value(data) {
+            self.abort();
+            return Err(io::Error::other(error.to_string()));
+        }
+        Ok(data.len())
+    }
+
+    // 完成写入，之后写入数据返回错误
+    fn flush(&mut self) -> io::Result<()> {
+        self.finish_value().map_err(|error| io::Error::other(error.to_string()))
+    }
+}
+
+impl Drop for ValueWriter<'_> {
+    fn drop(&mut self) {
+        let _ = self.finish_value();
+    }
+}
+
+pub struct Keys<'a> {
+    tree: RwLockReadGuard<'a, Tree>,
+    // 当前叶节点中还未返回的key
+    keys: IntoIter<Vec<u8>>,
+    cursor: LeafCursor,
+}
+
+impl Iterator for Keys<'_> {
+    type Item = MiniBaseResult<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(key) = self.keys.next() {
+                return Some(self.tree.decode_key(key));
+            }
+            let tree = &self.tree;
+            let read = |leaf: &Node| match tree.mete_data.ttl {
+                true => tree.get_live_key_values(leaf).into_iter().map(|(key, _)| key).collect(),
+                false => leaf.get_keys(),
+            };
+            match self.cursor.next_leaf(tree, read)? {
+                Ok(keys) => self.keys = keys.into_iter(),
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+// 额外数据文件在第一次写入长度达到value_threshold的value时创建
+fn open_extra_file(mete_data: &MeteData) -> MiniBaseResult<ExtraFile> {
+    if mete_data.memory {
+        return ExtraFile::new(Storage::memory(mete_data.page_size), mete_data.page_size);
+    }
+    ExtraFile::open(&mete_data.get_extra_file_path()?, mete_data.read_only, mete_data.page_size, mete_data.endian)
+}
+
+fn put_record(key: &[u8], value: &[u8]) -> WalRecord {
+    WalRecord::Put { key: key.to_vec(), value: value.to_vec() }
+}
+
+// 多值模式下key的每个value保存为[key, 序号]编码后的key，序号按大端序编码，同一个key的value按写入顺序排列
+fn multi_value_key(key: &[u8], sequence: u64) -> Vec<u8> {
+    encode_composite_key(&[key, &sequence.to_be_bytes()])
+}
+
+// 保存key的所有value的编码后的key范围[begin, end)，begin为只有key一个分量的编码，以0x00 0x01结尾，将结尾改为0x02即为end
+fn multi_value_range(key: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let begin = encode_composite_key(&[key]);
+    let mut end = begin.clone();
+    *end.last_mut().unwrap() += 1;
+    (begin, end)
+}
+
+fn decode_multi_value_key(stored_key: &[u8]) -> MiniBaseResult<(Vec<u8>, u64)> {
+    let mut components = decode_composite_key(stored_key)?;
+    match components.as_slice() {
+        [_, sequence] if sequence.len() == 8 => {
+            let sequence = u64::from_be_bytes(sequence.as_slice().try_into().unwrap());
+            Ok((components.swap_remove(0), sequence))
+        }
+        _ => Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, "multi-value key invalid"))),
+    }
+}
+
+fn decode_multi_value_keys(key_values: Vec<(Vec<u8>, Vec<u8>)>) -> MiniBaseResult<Vec<(Vec<u8>, Vec<u8>)>> {
+    key_values.into_iter().map(|(stored_key, value)| Ok((decode_multi_value_key(&stored_key)?.0, value))).collect()
+}
+
+// 按字节序大于所有以prefix开头的key的最小值，即去掉末尾的0xFF后将最后一个字节加1，prefix全部为0xFF时不存在
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|byte| *byte != 0xFF)?;
+    let mut successor = Vec::from(&prefix[..=last]);
+    successor[last] += 1;
+    Some(successor)
+}
+
+fn into_strings(key_values: Vec<(Vec<u8>, Vec<u8>)>) -> MiniBaseResult<Vec<(String, String)>> {
+    let mut result = Vec::with_capacity(key_values.len());
+    for (key, value) in key_values {
+        result.push((String::from_utf8(key)?, String::from_utf8(value)?));
+    }
+    Ok(result)
+}
+
+impl Operate for Controller {
+    fn put(&self, key: &str, value: &str) -> MiniBaseResult<()> {
+        self.put_bytes(key.as_bytes(), value.as_bytes())
+    }
+
+    fn get(&self, key: &str) -> MiniBaseResult<Option<String>> {
+        match self.get_bytes(key.as_bytes())? {
+            None => Ok(None),
+            Some(value) => Ok(Some(String::from_utf8(value)?))
+        }
+    }
+
+    fn scan(&self, begin: &str, end: &str) -> MiniBaseResult<Vec<(String, String)>> {
+        into_strings(self.scan_bytes(begin.as_bytes(), end.as_bytes())?)
+    }
+
+    fn scan_inclusive(&self, begin: &str, end: &str) -> MiniBaseResult<Vec<(String, String)>> {
+        into_strings(self.scan_inclusive_bytes(begin.as_bytes(), end.as_bytes())?)
+    }
+
+    fn remove(&self, key: &str) -> MiniBaseResult<bool> {
+        self.remove_bytes(key.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::os::unix::fs::FileExt;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use crate::controller::{AutoVacuum, Controller, Operate, SyncMode, WriteOp};
+    use crate::node::{Node, NodeType, Stats};
+    use crate::page::{LeafPage, NULL_PAGE};
+    use crate::repair::VerificationErrorKind;
+    use crate::{create_memory_schema, create_schema, create_schema_with_comparator, create_schema_with_options, create_single_file_schema, drop_schema, error_kind, list_schemas, open_schema, open_schema_read_only, open_schema_with_options, AccessPattern, Comparator, CorruptionMode, CreateOptions, CustomComparator, ErrorKind, IoStats, MiniBaseResult, SchemaOptions, StructuralEvent};
+
+    const PAGE_SIZE: u32 = 512;
+
+    fn create_test_controller(dir_name: &str) -> Controller {
+        fs::create_dir_all(dir_name).unwrap();
+        create_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap().controller().unwrap()
+    }
+
+    fn reopen_test_controller(dir_name: &str) -> Controller {
+        open_schema(dir_name, "test").unwrap().controller().unwrap()
+    }
+
+    fn delete_test_dir(dir_name: &str) {
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+
+    #[test]
+    fn controller_remove() {
+        let dir_name = "controller_remove";
+        let controller = create_test_controller(dir_name);
+
+        controller.put("test", "今天真热").unwrap();
+        assert!(controller.contains_key("test".as_bytes()).unwrap());
+        assert!(controller.remove("test").unwrap());
+        assert_eq!(None, controller.get("test").unwrap());
+        assert!(!controller.contains_key("test".as_bytes()).unwrap());
+        assert!(!controller.contains_key("absent".as_bytes()).unwrap());
+        assert!(!controller.remove("test").unwrap());
+        assert!(!controller.remove("absent").unwrap());
+
+        controller.put("test", "今天真热啊").unwrap();
+        assert_eq!(Some("今天真热啊".to_string()), controller.get("test").unwrap());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_value_len() {
+        let dir_name = "controller_value_len";
+        let controller = create_test_controller(dir_name);
+        let large_value = "大".repeat(200);
+        for i in 0..300 {
+            controller.put(&format!("key{:03}", i), &"v".repeat(i)).unwrap();
+        }
+        controller.put("large", &large_value).unwrap();
+        controller.put("empty", "").unwrap();
+        assert!(!controller.read().root_node.is_leaf());
+        // 长度不小于value_threshold的value保存在额外数据文件中
+        for i in 0..300 {
+            assert_eq!(Some(i as u32), controller.value_len(format!("key{:03}", i).as_bytes()).unwrap());
+        }
+        assert_eq!(Some(large_value.len() as u32), controller.value_len("large".as_bytes()).unwrap());
+        assert_eq!(Some(0), controller.value_len("empty".as_bytes()).unwrap());
+        assert_eq!(None, controller.value_len("absent".as_bytes()).unwrap());
+        controller.remove("large").unwrap();
+        assert_eq!(None, controller.value_len("large".as_bytes()).unwrap());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_get_reader() {
+        let dir_name = "controller_get_reader";
+        let controller = create_test_controller(dir_name);
+        // 跨越多个chunk的value
+        let large_value: Vec<u8> = (0..5 * PAGE_SIZE + 77).map(|i| (i % 251) as u8).collect();
+        controller.put_bytes("large".as_bytes(), &large_value).unwrap();
+        controller.put("small", "small value").unwrap();
+        controller.put_bytes("empty".as_bytes(), &[]).unwrap();
+
+        let mut reader = controller.get_reader("large".as_bytes()).unwrap().unwrap();
+        let mut value = Vec::new();
+        let mut buffer = [0; 7];
+        loop {
+            let length = reader.read(&mut buffer).unwrap();
+            if length == 0 {
+                break;
+            }
+            assert!(length <= buffer.len());
+            value.extend_from_slice(&buffer[..length]);
+        }
+        assert_eq!(large_value, value);
+        drop(reader);
+        let mut value = String::new();
+        controller.get_reader("small".as_bytes()).unwrap().unwrap().read_to_string(&mut value).unwrap();
+        assert_eq!("small value", value);
+        let mut value = Vec::new();
+        controller.get_reader("empty".as_bytes()).unwrap().unwrap().read_to_end(&mut value).unwrap();
+        assert!(value.is_empty());
+        assert!(controller.get_reader("absent".as_bytes()).unwrap().is_none());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_put_writer() {
+        let dir_name = "controller_put_writer";
+        let controller = create_test_controller(dir_name);
+        controller.put("large", "old").unwrap();
+        let large_value: Vec<u8> = (0..3 * PAGE_SIZE + 100).map(|i| (i % 253) as u8).collect();
+        let mut writer = controller.put_writer("large".as_bytes()).unwrap();
+        for data in large_value.chunks(100) {
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap();
+        assert_eq!(Some(large_value.clone()), controller.get_bytes("large".as_bytes()).unwrap());
+        assert_eq!(Some(large_value.len() as u32), controller.value_len("large".as_bytes()).unwrap());
+
+        // 小于value_threshold的value保存在叶节点中，drop时完成写入
+        let mut writer = controller.put_writer("small".as_bytes()).unwrap();
+        writer.write_all(b"small ").unwrap();
+        writer.write_all(b"value").unwrap();
+        drop(writer);
+        assert_eq!(Some("small value".to_string()), controller.get("small").unwrap());
+        let mut writer = controller.put_writer("flushed".as_bytes()).unwrap();
+        writer.write_all(b"value").unwrap();
+        writer.flush().unwrap();
+        assert!(writer.write_all(b"more").is_err());
+        drop(writer);
+        assert_eq!(Some("value".to_string()), controller.get("flushed").unwrap());
+        let error = controller.put_writer(&[0; 65]).err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+
+        // 写入叶节点失败时value没有被引用，已经写入的chunk在flush之后复用
+        let extra_file_length = || fs::metadata(format!("{}/test.e", dir_name)).unwrap().len();
+        let mut writer = controller.put_writer("failed".as_bytes()).unwrap();
+        writer.write_all(&large_value).unwrap();
+        writer.tree.mete_data.read_only = true;
+        let error = writer.finish().err().unwrap();
+        assert_eq!(ErrorKind::ReadOnly, error_kind(error.as_ref()));
+        controller.write().mete_data.read_only = false;
+        assert_eq!(None, controller.get_bytes("failed".as_bytes()).unwrap());
+        controller.flush().unwrap();
+        let length = extra_file_length();
+        let mut writer = controller.put_writer("reused".as_bytes()).unwrap();
+        writer.write_all(&large_value).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(length, extra_file_length());
+        controller.verify().unwrap();
+        drop(controller);
+
+        let controller = reopen_test_controller(dir_name);
+        assert_eq!(Some(large_value.clone()), controller.get_bytes("large".as_bytes()).unwrap());
+        assert_eq!(Some(large_value), controller.get_bytes("reused".as_bytes()).unwrap());
+        assert_eq!(4, controller.len().unwrap());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_clear() {
+        let dir_name = "controller_clear";
+        let controller = create_test_controller(dir_name);
+        for i in 0..300 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i).repeat(i % 40 + 1)).unwrap();
+        }
+        for i in (0..300).step_by(2) {
+            controller.remove(&format!("key{:03}", i)).unwrap();
+        }
+        assert!(!controller.read().root_node.is_leaf());
+        controller.clear().unwrap();
+        assert_eq!(0, controller.len().unwrap());
+        assert_eq!(None, controller.get("key001").unwrap());
+        assert_eq!(PAGE_SIZE as u64, controller.read().data_file.len().unwrap());
+        assert_eq!(8, fs::metadata(format!("{}/test.e", dir_name)).unwrap().len());
+        controller.verify().unwrap();
+        controller.put("key", &"value".repeat(100)).unwrap();
+        drop(controller);
+
+        let controller = reopen_test_controller(dir_name);
+        let tree = controller.read();
+        assert_eq!((PAGE_SIZE, 64, 128), (tree.mete_data.page_size, tree.mete_data.key_max_length, tree.mete_data.value_threshold));
+        drop(tree);
+        assert_eq!(1, controller.len().unwrap());
+        assert_eq!(Some("value".repeat(100)), controller.get("key").unwrap());
+        let error = controller.put(&"k".repeat(65), "value").err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_compact_extra() {
+        let dir_name = "controller_compact_extra";
+        let controller = create_test_controller(dir_name);
+        let extra_file_length = || fs::metadata(format!("{}/test.e", dir_name)).unwrap().len();
+        // 每个value占用两个chunk
+        let value = |i: usize, round: usize| format!("{:03}-{}", i, round).repeat(120);
+        for i in 0..40 {
+            controller.put(&format!("key{:03}", i), &value(i, 0)).unwrap();
+        }
+        // 删除和覆盖的value在flush之后进入空闲链表，整理时移动到空闲位置
+        for i in (0..40).step_by(3) {
+            controller.remove(&format!("key{:03}", i)).unwrap();
+        }
+        for i in (1..40).step_by(3) {
+            controller.put(&format!("key{:03}", i), &value(i, 1)).unwrap();
+        }
+        controller.put("small", "value").unwrap();
+        let length = extra_file_length();
+
+        let released = controller.compact_extra().unwrap();
+        assert!(released > 0);
+        assert_eq!(length - released, extra_file_length());
+        // 剩余26个value，每个value两个chunk
+        assert_eq!(8 + 26 * 2 * PAGE_SIZE as u64, extra_file_length());
+        controller.verify().unwrap();
+        let check = |controller: &Controller| {
+            for i in 0..40 {
+                let expected = match i % 3 {
+                    0 => None,
+                    1 => Some(value(i, 1)),
+                    _ => Some(value(i, 0)),
+                };
+                assert_eq!(expected, controller.get(&format!("key{:03}", i)).unwrap());
+            }
+            assert_eq!(Some(String::from("value")), controller.get("small").unwrap());
+        };
+        check(&controller);
+        // 已经紧凑时不再移动
+        assert_eq!(0, controller.compact_extra().unwrap());
+        drop(controller);
+
+        let controller = reopen_test_controller(dir_name);
+        check(&controller);
+        // 整理后空闲链表为空，新的value写入文件末尾
+        controller.put("new", &value(0, 2)).unwrap();
+        assert_eq!(8 + 27 * 2 * PAGE_SIZE as u64, extra_file_length());
+        assert_eq!(Some(value(0, 2)), controller.get("new").unwrap());
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_get_required() {
+        let dir_name = "controller_get_required";
+        let controller = create_test_controller(dir_name);
+        controller.put("key", "value").unwrap();
+        assert_eq!("value", controller.get_required("key").unwrap());
+        assert_eq!("value".as_bytes(), controller.get_bytes_required(b"key").unwrap());
+        let error = controller.get_required("missing").err().unwrap();
+        assert_eq!(ErrorKind::NotFound, error_kind(error.as_ref()));
+        let error = controller.get_bytes_required(b"missing").err().unwrap();
+        assert_eq!(ErrorKind::NotFound, error_kind(error.as_ref()));
+
+        // key不存在时remove返回Ok(false)，删除后get_required同样返回NotFound
+        assert!(controller.remove("key").unwrap());
+        assert!(!controller.remove("key").unwrap());
+        let error = controller.get_required("key").err().unwrap();
+        assert_eq!(ErrorKind::NotFound, error_kind(error.as_ref()));
+        let lookup = |key: &str| -> MiniBaseResult<usize> { Ok(controller.get_required(key)?.len()) };
+        controller.put("other", "value").unwrap();
+        assert_eq!(5, lookup("other").unwrap());
+        assert!(lookup("key").is_err());
+        drop(controller);
+
+        // 读写失败时remove返回错误，而不是Ok(false)
+        let controller = open_schema_read_only(dir_name, "test").unwrap().controller().unwrap();
+        let error = controller.remove("other").err().unwrap();
+        assert_eq!(ErrorKind::ReadOnly, error_kind(error.as_ref()));
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_scan() {
+        let dir_name = "controller_scan";
+        let controller = create_test_controller(dir_name);
+
+        for key in ["m", "a", "n", "g", "z"] {
+            controller.put(key, &key.repeat(2)).unwrap();
+        }
+        controller.remove("g").unwrap();
+
+        let result = controller.scan("a", "n").unwrap();
+        assert_eq!(vec![("a".to_string(), "aa".to_string()), ("m".to_string(), "mm".to_string())], result);
+
+        let result = controller.scan_inclusive("a", "n").unwrap();
+        let keys: Vec<&str> = result.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(vec!["a", "m", "n"], keys);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_scan_prefix() {
+        let dir_name = "controller_scan_prefix";
+        let controller = create_test_controller(dir_name);
+        for i in 0..100 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        controller.put("kez", "after").unwrap();
+        controller.put_bytes(&[0xFF, 0xFF], "max".as_bytes()).unwrap();
+        controller.put_bytes(&[0xFF, 0xFF, 0x01], "max1".as_bytes()).unwrap();
+        controller.put_bytes(&[0xFE, 0xFF], "before".as_bytes()).unwrap();
+        controller.remove("key012").unwrap();
+
+        assert!(controller.scan_prefix("absent".as_bytes()).unwrap().is_empty());
+        let result = controller.scan_prefix("key042".as_bytes()).unwrap();
+        assert_eq!(vec![("key042".as_bytes().to_vec(), "value42".as_bytes().to_vec())], result);
+        let result = controller.scan_prefix("key01".as_bytes()).unwrap();
+        let keys: Vec<&[u8]> = result.iter().map(|(key, _)| &key[..]).collect();
+        let expected: Vec<String> = (10..20).filter(|i| *i != 12).map(|i| format!("key{:03}", i)).collect();
+        assert_eq!(expected.iter().map(|key| key.as_bytes()).collect::<Vec<&[u8]>>(), keys);
+        assert_eq!(100, controller.scan_prefix("ke".as_bytes()).unwrap().len());
+        assert_eq!(103, controller.scan_prefix(&[]).unwrap().len());
+
+        // 全部为0xFF的prefix没有后继，读取到最后一个key
+        let result = controller.scan_prefix(&[0xFF]).unwrap();
+        let keys: Vec<&[u8]> = result.iter().map(|(key, _)| &key[..]).collect();
+        assert_eq!(vec![&[0xFF, 0xFF][..], &[0xFF, 0xFF, 0x01][..]], keys);
+        assert_eq!(1, controller.scan_prefix(&[0xFE]).unwrap().len());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_count_range() {
+        let dir_name = "controller_count_range";
+        let controller = create_test_controller(dir_name);
+        assert_eq!(0, controller.len().unwrap());
+        assert!(controller.is_empty().unwrap());
+
+        for i in 0..100 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        for i in (0..100).step_by(10) {
+            controller.remove(&format!("key{:03}", i)).unwrap();
+        }
+        assert!(count_leaf_pages(&controller) > 2);
+
+        assert_eq!(90, controller.len().unwrap());
+        assert!(!controller.is_empty().unwrap());
+        assert_eq!(90, controller.count_range("".as_bytes(), "z".as_bytes()).unwrap());
+        for (begin, end) in [("key005", "key055"), ("key010", "key020"), ("key099", "key100"), ("key050", "key050"), ("a", "b")] {
+            let expected = controller.scan(begin, end).unwrap().len() as u64;
+            assert_eq!(expected, controller.count_range(begin.as_bytes(), end.as_bytes()).unwrap());
+        }
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_size_of_range() {
+        let dir_name = "controller_size_of_range";
+        let controller = create_test_controller(dir_name);
+        assert_eq!(0, controller.size_of_range(b"", b"z").unwrap());
+
+        for i in 0..200 {
+            controller.put(&format!("key{:03}", i), &format!("{:03}", i).repeat(15)).unwrap();
+        }
+        // 超过value_threshold的value保存在额外数据文件中
+        for i in (0..200).step_by(20) {
+            controller.put(&format!("key{:03}", i), &format!("{:03}", i).repeat(100)).unwrap();
+        }
+        for i in (5..200).step_by(10) {
+            controller.remove(&format!("key{:03}", i)).unwrap();
+        }
+        assert!(count_leaf_pages(&controller) > 2);
+
+        // 每个key value在叶节点中另外占用长度、偏移和排序表等18字节，value保存在额外数据文件中时另外占用30字节
+        for (begin, end) in [("", "z"), ("key005", "key155"), ("key010", "key020"), ("key100", "key101"), ("a", "b")] {
+            let key_values = controller.scan(begin, end).unwrap();
+            let total: u64 = key_values.iter().map(|(key, value)| (key.len() + value.len()) as u64).sum();
+            let size = controller.size_of_range(begin.as_bytes(), end.as_bytes()).unwrap();
+            assert!(size >= total && size <= total + 30 * key_values.len() as u64, "{} {} {}", begin, total, size);
+            assert!(size as f64 <= total as f64 * 1.5, "{} {} {}", begin, total, size);
+        }
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_empty_key_value() {
+        let dir_name = "controller_empty_key_value";
+        fs::create_dir_all(dir_name).unwrap();
+        // value_threshold为0时空value也写入额外数据文件
+        for (schema_name, value_threshold) in [("inline", 128), ("overflow", 0)] {
+            let controller = create_schema(dir_name, schema_name, PAGE_SIZE, 64, value_threshold).unwrap().controller().unwrap();
+            controller.put("", "x").unwrap();
+            controller.put("k", "").unwrap();
+            assert_eq!(Some("x".to_string()), controller.get("").unwrap());
+            assert_eq!(Some("".to_string()), controller.get("k").unwrap());
+            assert!(controller.contains_key("k".as_bytes()).unwrap());
+            assert_eq!(None, controller.get("absent").unwrap());
+
+            controller.put("", "").unwrap();
+            assert_eq!(Some("".to_string()), controller.get("").unwrap());
+            assert_eq!(vec![("".to_string(), "".to_string()), ("k".to_string(), "".to_string())], controller.scan("", "z").unwrap());
+            controller.flush().unwrap();
+            drop(controller);
+
+            let controller = open_schema(dir_name, schema_name).unwrap().controller().unwrap();
+            assert_eq!(Some("".to_string()), controller.get("").unwrap());
+            assert!(controller.remove("").unwrap());
+            assert_eq!(None, controller.get("").unwrap());
+            assert!(!controller.contains_key("".as_bytes()).unwrap());
+            assert_eq!(Some("".to_string()), controller.get("k").unwrap());
+        }
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_key_max_length() {
+        let dir_name = "controller_key_max_length";
+        fs::create_dir_all(dir_name).unwrap();
+        let controller = create_schema(dir_name, "test", PAGE_SIZE, 8, 128).unwrap().controller().unwrap();
+
+        let error = controller.put("123456789", "value").err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+        assert_eq!("mini base error: key length 9 exceeds max length 8", error.to_string());
+        assert_eq!(None, controller.get("123456789").unwrap());
+        controller.put("12345678", "value").unwrap();
+        assert_eq!(Some("value".to_string()), controller.get("12345678").unwrap());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_bytes() {
+        let dir_name = "controller_bytes";
+        let controller = create_test_controller(dir_name);
+
+        // 非UTF-8的数据
+        let key = [0xff, 0x00, 0xfe];
+        let value = [0xc3, 0x28, 0x00, 0xff];
+        controller.put_bytes(&key, &value).unwrap();
+        assert_eq!(Some(Vec::from(value)), controller.get_bytes(&key).unwrap());
+        assert!(controller.get(&String::from_utf8_lossy(&key)).unwrap().is_none());
+        assert_eq!(vec![(Vec::from(key), Vec::from(value))], controller.scan_bytes(&[0xff], &[0xff, 0xff]).unwrap());
+
+        assert!(controller.remove_bytes(&key).unwrap());
+        assert_eq!(None, controller.get_bytes(&key).unwrap());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_flush() {
+        let dir_name = "controller_flush";
+        let controller = create_test_controller(dir_name);
+
+        controller.put("test", "今天真热").unwrap();
+        controller.put("asd", "asd").unwrap();
+        controller.flush().unwrap();
+        drop(controller);
+
+        let controller = reopen_test_controller(dir_name);
+        assert_eq!(Some("今天真热".to_string()), controller.get("test").unwrap());
+        assert_eq!(Some("asd".to_string()), controller.get("asd").unwrap());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_close() {
+        let dir_name = "controller_close";
+        fs::create_dir_all(dir_name).unwrap();
+        let controller = create_schema(dir_name, "test", PAGE_SIZE, 64, 16).unwrap().controller().unwrap();
+        controller.put("test", "今天真热").unwrap();
+        controller.put("large", &"今天真热".repeat(10)).unwrap();
+        controller.close().unwrap();
+
+        let controller = reopen_test_controller(dir_name);
+        assert_eq!(Some("今天真热".to_string()), controller.get("test").unwrap());
+        assert_eq!(Some("今天真热".repeat(10)), controller.get("large").unwrap());
+        assert!(controller.remove("test").unwrap());
+        // 没有调用close和flush，drop时写入磁盘
+        drop(controller);
+
+        let controller = reopen_test_controller(dir_name);
+        assert_eq!(None, controller.get("test").unwrap());
+        assert_eq!(Some("今天真热".repeat(10)), controller.get("large").unwrap());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_root_cache() {
+        let dir_name = "controller_root_cache";
+        let controller = create_test_controller(dir_name);
+        controller.put("key000", "value0").unwrap();
+        assert_eq!(0, controller.read().root_node.get_offset());
+        // 根节点分裂后替换缓存的根节点，不需要重新打开
+        let mut i = 1;
+        while controller.read().root_node.get_offset() == 0 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+            i += 1;
+        }
+        let tree = controller.read();
+        assert_ne!(0, tree.root_node.get_offset());
+        assert_eq!(tree.mete_data.get_root_page_offset(), tree.root_node.get_offset());
+        drop(tree);
+        assert_eq!(2, controller.stats().unwrap().height);
+        controller.reset_io_stats();
+        for j in 0..i {
+            assert_eq!(Some(format!("value{}", j)), controller.get(&format!("key{:03}", j)).unwrap());
+        }
+        // 每次读取只从文件读取叶节点，根节点使用缓存
+        assert_eq!(0, controller.io_stats().inner_page_read_count);
+
+        // 根节点收缩或清空后同样替换缓存的根节点
+        controller.clear().unwrap();
+        let tree = controller.read();
+        assert_eq!(0, tree.root_node.get_offset());
+        assert_eq!(tree.mete_data.get_root_page_offset(), tree.root_node.get_offset());
+        drop(tree);
+        assert_eq!(None, controller.get("key000").unwrap());
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_reopen_after_split() {
+        let dir_name = "controller_reopen_after_split";
+        let controller = create_test_controller(dir_name);
+        for i in 0..300 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        // 根节点分裂后不再位于偏移0
+        let root_offset = controller.read().mete_data.get_root_page_offset();
+        assert_ne!(0, root_offset);
+        assert!(controller.stats().unwrap().height >= 3);
+        controller.close().unwrap();
+
+        let controller = reopen_test_controller(dir_name);
+        assert_eq!(root_offset, controller.read().root_node.get_offset());
+        for i in 0..300 {
+            assert_eq!(Some(format!("value{}", i)), controller.get(&format!("key{:03}", i)).unwrap());
+        }
+        // 删除大部分数据，根节点收缩后再次打开
+        for i in 0..290 {
+            assert!(controller.remove(&format!("key{:03}", i)).unwrap());
+        }
+        let root_offset = controller.read().mete_data.get_root_page_offset();
+        assert_eq!(root_offset, controller.read().root_node.get_offset());
+        controller.close().unwrap();
+
+        let controller = reopen_test_controller(dir_name);
+        assert_eq!(root_offset, controller.read().root_node.get_offset());
+        assert_eq!(10, controller.len().unwrap());
+        assert_eq!(Some("value295".to_string()), controller.get("key295").unwrap());
+        controller.put("key000", "value0").unwrap();
+        assert_eq!(Some("value0".to_string()), controller.get("key000").unwrap());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_write_ahead_log() {
+        let dir_name = "controller_write_ahead_log";
+        fs::create_dir_all(dir_name).unwrap();
+        let mut mete_data = create_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap();
+        mete_data.set_write_ahead_log(true);
+        let controller = mete_data.controller().unwrap();
+        for i in 0..100 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        controller.flush().unwrap();
+        let data_file_path = format!("{}/test.d", dir_name);
+        let checkpoint_length = fs::metadata(&data_file_path).unwrap().len();
+
+        // 检查点之后的写操作包括分裂、合并、释放和复用页，以及写入额外数据文件的value
+        for i in 100..300 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        for i in 0..50 {
+            assert!(controller.remove(&format!("key{:03}", i)).unwrap());
+        }
+        controller.put("key050", &"v".repeat(200)).unwrap();
+        // 模拟崩溃，不会flush，进程退出时锁会被释放
+        drop(controller.write().mete_data.lock_file.take());
+        std::mem::forget(controller);
+
+        // 崩溃时检查点之后追加的页和部分修改过的页没有写入磁盘
+        let data_file = fs::OpenOptions::new().write(true).open(&data_file_path).unwrap();
+        data_file.set_len(checkpoint_length).unwrap();
+        data_file.write_all_at(&vec![0; PAGE_SIZE as usize], 0).unwrap();
+        drop(data_file);
+
+        // 未启用预写日志时也会恢复，恢复后删除日志
+        let controller = reopen_test_controller(dir_name);
+        controller.verify().unwrap();
+        assert!(!fs::exists(format!("{}/test.wal", dir_name)).unwrap());
+        assert_eq!(250, controller.len().unwrap());
+        assert_eq!(None, controller.get("key049").unwrap());
+        assert_eq!(Some("v".repeat(200)), controller.get("key050").unwrap());
+        for i in 51..300 {
+            assert_eq!(Some(format!("value{}", i)), controller.get(&format!("key{:03}", i)).unwrap());
+        }
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_sync_mode() {
+        let dir_name = "controller_sync_mode";
+        let controller = create_test_controller(dir_name);
+        assert_eq!(SyncMode::Buffered, controller.get_sync_mode());
+        let error = controller.set_sync_mode(SyncMode::Periodic(Duration::ZERO)).err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+        // 创建时已经flush过一次，Buffered的写操作不会同步到磁盘
+        controller.reset_io_stats();
+        for i in 0..100 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        assert_eq!(0, controller.io_stats().sync_count);
+        controller.flush().unwrap();
+        assert_eq!(1, controller.io_stats().sync_count);
+
+        controller.set_sync_mode(SyncMode::EveryWrite).unwrap();
+        assert_eq!(SyncMode::EveryWrite, controller.get_sync_mode());
+        // 每个写操作之后都同步一次，根节点分裂后元数据文件中的根节点偏移也已经写入
+        for i in 100..300 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        for i in 0..100 {
+            assert!(controller.remove(&format!("key{:03}", i)).unwrap());
+        }
+        assert!(!controller.read().root_node.is_leaf());
+        assert_eq!(301, controller.io_stats().sync_count);
+        // 每个写操作之后缓存中修改过的页都已经写回，再次flush时没有需要写回的页
+        let bytes_written = controller.io_stats().bytes_written;
+        assert!(bytes_written > 0);
+        controller.flush().unwrap();
+        assert_eq!(bytes_written, controller.io_stats().bytes_written);
+        // 读操作不会同步
+        controller.reset_io_stats();
+        assert_eq!(Some("value100".to_string()), controller.get("key100").unwrap());
+        assert_eq!(0, controller.io_stats().sync_count);
+        drop(controller);
+
+        let controller = reopen_test_controller(dir_name);
+        assert_eq!(SyncMode::Buffered, controller.get_sync_mode());
+        controller.verify().unwrap();
+        assert_eq!(200, controller.len().unwrap());
+        for i in 100..300 {
+            assert_eq!(Some(format!("value{}", i)), controller.get(&format!("key{:03}", i)).unwrap());
+        }
+
+        // 后台线程定期flush，写操作本身不会同步
+        controller.reset_io_stats();
+        controller.set_sync_mode(SyncMode::Periodic(Duration::from_secs(60))).unwrap();
+        for i in 300..600 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        assert_eq!(0, controller.io_stats().sync_count);
+        controller.set_sync_mode(SyncMode::Periodic(Duration::from_millis(10))).unwrap();
+        let mut i = 0;
+        while controller.io_stats().sync_count == 0 {
+            assert!(i < 500, "periodic flush not run");
+            thread::sleep(Duration::from_millis(10));
+            i += 1;
+        }
+        // 切换到Buffered后停止后台线程，不再同步
+        controller.set_sync_mode(SyncMode::Buffered).unwrap();
+        let sync_count = controller.io_stats().sync_count;
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(sync_count, controller.io_stats().sync_count);
+        drop(controller);
+
+        let controller = reopen_test_controller(dir_name);
+        controller.verify().unwrap();
+        assert_eq!(500, controller.len().unwrap());
+        // drop时停止后台线程
+        controller.set_sync_mode(SyncMode::Periodic(Duration::from_secs(60))).unwrap();
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_auto_vacuum() {
+        let dir_name = "controller_auto_vacuum";
+        fs::create_dir_all(dir_name).unwrap();
+        // 写操作不主动整理，只有后台线程整理
+        let workload = |controller: &Controller| {
+            for round in 0..10 {
+                for i in 0..300 {
+                    controller.put(&format!("key{:03}", i), &format!("value{}", i).repeat(round % 3 + 1)).unwrap();
+                }
+                for i in (round % 2..300).step_by(2) {
+                    controller.remove(&format!("key{:03}", i)).unwrap();
+                }
+            }
+        };
+        let garbage_bound = |stats: &Stats| stats.leaf_page_count * PAGE_SIZE as usize / 5;
+
+        let mut mete_data = create_schema(dir_name, "manual", PAGE_SIZE, 64, 128).unwrap();
+        mete_data.set_garbage_ratio(1.0).unwrap();
+        let controller = mete_data.controller().unwrap();
+        workload(&controller);
+        let stats = controller.stats().unwrap();
+        assert!(stats.reclaimable_garbage_bytes > garbage_bound(&stats), "{:?}", stats);
+        drop(controller);
+
+        let mut mete_data = create_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap();
+        mete_data.set_garbage_ratio(1.0).unwrap();
+        let error = mete_data.set_auto_vacuum(Some(AutoVacuum { max_pages: 0, ..AutoVacuum::default() })).err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+        let auto_vacuum = AutoVacuum { interval: Duration::from_millis(5), garbage_ratio: 0.2, max_pages: 4 };
+        mete_data.set_auto_vacuum(Some(auto_vacuum)).unwrap();
+        let controller = mete_data.controller().unwrap();
+        workload(&controller);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut stats = controller.stats().unwrap();
+        while stats.reclaimable_garbage_bytes > garbage_bound(&stats) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+            stats = controller.stats().unwrap();
+        }
+        assert!(stats.reclaimable_garbage_bytes <= garbage_bound(&stats), "{:?}", stats);
+        controller.verify().unwrap();
+        assert_eq!(150, controller.len().unwrap());
+
+        // 停止后不再整理，可以按新的设置重新启动
+        controller.stop_auto_vacuum();
+        workload(&controller);
+        let garbage = controller.stats().unwrap().reclaimable_garbage_bytes;
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(garbage, controller.stats().unwrap().reclaimable_garbage_bytes);
+        let error = controller.start_auto_vacuum(AutoVacuum { garbage_ratio: 1.0, ..auto_vacuum }).err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+        controller.start_auto_vacuum(auto_vacuum).unwrap();
+        controller.close().unwrap();
+
+        let mut mete_data = open_schema_read_only(dir_name, "test").unwrap();
+        let error = mete_data.set_auto_vacuum(Some(auto_vacuum)).err().unwrap();
+        assert_eq!(ErrorKind::ReadOnly, error_kind(error.as_ref()));
+        let controller = mete_data.controller().unwrap();
+        let error = controller.start_auto_vacuum(auto_vacuum).err().unwrap();
+        assert_eq!(ErrorKind::ReadOnly, error_kind(error.as_ref()));
+        assert_eq!(150, controller.len().unwrap());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_read_txn() {
+        let dir_name = "controller_read_txn";
+        let controller = Arc::new(create_test_controller(dir_name));
+        for i in 0..100 {
+            controller.put(&format!("key{:03}", i), "old").unwrap();
+        }
+
+        let txn = controller.begin_read();
+        let writer = {
+            let controller = Arc::clone(&controller);
+            thread::spawn(move || {
+                // 写入会导致叶节点分裂
+                for i in 0..200 {
+                    controller.put(&format!("key{:03}", i), "new").unwrap();
+                }
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        // 事务期间写操作被阻塞，事务内的读取看到开始时的快照
+        assert!(!writer.is_finished());
+        assert_eq!(Some("old".as_bytes().to_vec()), txn.get_bytes("key050".as_bytes()).unwrap());
+        assert_eq!(100, txn.len().unwrap());
+        assert_eq!(100, txn.scan_bytes("key".as_bytes(), "kez".as_bytes()).unwrap().len());
+        assert_eq!(10, txn.count_range("key010".as_bytes(), "key020".as_bytes()).unwrap());
+        assert!(!txn.contains_key("key150".as_bytes()).unwrap());
+        drop(txn);
+
+        writer.join().unwrap();
+        let txn = controller.begin_read();
+        assert_eq!(200, txn.len().unwrap());
+        assert!(txn.scan_prefix("key1".as_bytes()).unwrap().iter().all(|(_, value)| value == "new".as_bytes()));
+        drop(txn);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_garbage_ratio() {
+        let dir_name = "controller_garbage_ratio";
+        fs::create_dir_all(dir_name).unwrap();
+        let mut mete_data = create_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap();
+        assert!(mete_data.set_garbage_ratio(1.5).is_err());
+        mete_data.set_garbage_ratio(0.3).unwrap();
+        let controller = mete_data.controller().unwrap();
+        for i in 0..3 {
+            controller.put(&format!("key{}", i), &"0".repeat(20)).unwrap();
+        }
+        // 覆盖和删除后垃圾数据不超过页容量的0.3
+        let limit = (PAGE_SIZE as f32 * 0.3) as usize;
+        for round in 0..10 {
+            controller.put("key0", &round.to_string().repeat(20)).unwrap();
+            assert!(controller.stats().unwrap().reclaimable_garbage_bytes <= limit);
+        }
+        assert!(controller.remove("key1").unwrap());
+        assert!(controller.stats().unwrap().reclaimable_garbage_bytes <= limit);
+        assert_eq!(Some("9".repeat(20)), controller.get("key0").unwrap());
+        assert_eq!(None, controller.get("key1").unwrap());
+        controller.close().unwrap();
+
+        // garbage_ratio保存在元数据文件中，为1时只在插入空间不足时整理
+        let mut mete_data = open_schema(dir_name, "test").unwrap();
+        assert_eq!(0.3, mete_data.get_garbage_ratio());
+        mete_data.set_garbage_ratio(1.0).unwrap();
+        let controller = mete_data.controller().unwrap();
+        for round in 0..10 {
+            controller.put("key2", &round.to_string().repeat(20)).unwrap();
+        }
+        assert!(controller.stats().unwrap().reclaimable_garbage_bytes > limit);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_u64_key() {
+        let dir_name = "controller_u64_key";
+        let controller = create_test_controller(dir_name);
+        // 小端序编码时256会排在2之前
+        for key in [256u64, 2, u64::MAX, 0, 1 << 40, 255, 65536] {
+            controller.put_u64(key, key.to_string().as_bytes()).unwrap();
+        }
+        for i in 1000..1200u64 {
+            controller.put_u64(i, "series".as_bytes()).unwrap();
+        }
+        assert_eq!(Some("256".as_bytes().to_vec()), controller.get_u64(256).unwrap());
+        assert_eq!(None, controller.get_u64(3).unwrap());
+
+        let keys: Vec<u64> = controller.scan_u64(0, 1000).unwrap().into_iter().map(|(key, _)| key).collect();
+        assert_eq!(vec![0, 2, 255, 256], keys);
+        let keys: Vec<u64> = controller.scan_u64(1100, u64::MAX).unwrap().into_iter().map(|(key, _)| key).collect();
+        assert_eq!((1100..1200).chain([65536, 1 << 40]).collect::<Vec<u64>>(), keys);
+        assert!(controller.remove_u64(u64::MAX).unwrap());
+        assert!(!controller.remove_u64(u64::MAX).unwrap());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_floor_ceiling() {
+        let dir_name = "controller_floor_ceiling";
+        let controller = create_test_controller(dir_name);
+        // 偶数key，分布在多个叶节点中
+        for i in (0..400).step_by(2) {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        assert!(count_leaf_pages(&controller) > 2);
+        let key = |i: usize| format!("key{:03}", i).into_bytes();
+        let entry = |i: usize| Some((key(i), format!("value{}", i).into_bytes()));
+
+        // key存在
+        assert_eq!(entry(100), controller.floor(&key(100)).unwrap());
+        assert_eq!(entry(100), controller.ceiling(&key(100)).unwrap());
+        assert_eq!(entry(102), controller.higher(&key(100)).unwrap());
+        assert_eq!(entry(98), controller.lower(&key(100)).unwrap());
+        // key不存在，位于中间
+        assert_eq!(entry(100), controller.floor(&key(101)).unwrap());
+        assert_eq!(entry(102), controller.ceiling(&key(101)).unwrap());
+        assert_eq!(entry(102), controller.higher(&key(101)).unwrap());
+        assert_eq!(entry(100), controller.lower(&key(101)).unwrap());
+        // key不存在，超出两端
+        assert_eq!(None, controller.floor("a".as_bytes()).unwrap());
+        assert_eq!(entry(0), controller.ceiling("a".as_bytes()).unwrap());
+        assert_eq!(None, controller.lower(&key(0)).unwrap());
+        assert_eq!(entry(398), controller.floor("z".as_bytes()).unwrap());
+        assert_eq!(None, controller.ceiling("z".as_bytes()).unwrap());
+        assert_eq!(None, controller.higher(&key(398)).unwrap());
+
+        // 已删除的key被跳过，跨越叶节点查找
+        for i in (100..300).step_by(2) {
+            controller.remove(&format!("key{:03}", i)).unwrap();
+        }
+        assert_eq!(entry(98), controller.floor(&key(250)).unwrap());
+        assert_eq!(entry(300), controller.ceiling(&key(150)).unwrap());
+        assert_eq!(entry(300), controller.higher(&key(98)).unwrap());
+        assert_eq!(entry(98), controller.lower(&key(300)).unwrap());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_bulk_load() {
+        let dir_name = "controller_bulk_load";
+        let controller = create_test_controller(dir_name);
+        let pairs = (0..10000).map(|i| (format!("key{:05}", i).into_bytes(), format!("value{}", i).into_bytes()));
+        assert_eq!(10000, controller.bulk_load(pairs.clone()).unwrap());
+        controller.verify().unwrap();
+        assert_eq!(10000, controller.len().unwrap());
+        assert_eq!(Some("value1234".to_string()), controller.get("key01234").unwrap());
+        assert_eq!(Some("value9999".to_string()), controller.get("key09999").unwrap());
+        assert_eq!(100, controller.scan("key05000", "key05100").unwrap().len());
+        let bulk_stats = controller.stats().unwrap();
+        // 只能用于空树
+        let error = controller.bulk_load(pairs.clone()).err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+        // 之后可以继续正常写入和删除
+        controller.put("key10000", "value10000").unwrap();
+        for i in 0..5000 {
+            assert!(controller.remove(&format!("key{:05}", i)).unwrap());
+        }
+        controller.verify().unwrap();
+        assert_eq!(5001, controller.len().unwrap());
+        drop(controller);
+        delete_test_dir(dir_name);
+
+        // 逐个写入相同的数据需要更多的页
+        let controller = create_test_controller(dir_name);
+        for (key, value) in pairs {
+            controller.put_bytes(&key, &value).unwrap();
+        }
+        let put_stats = controller.stats().unwrap();
+        assert!(bulk_stats.leaf_page_count < put_stats.leaf_page_count);
+        assert!(bulk_stats.leaf_page_count + bulk_stats.inner_page_count < put_stats.leaf_page_count + put_stats.inner_page_count);
+        drop(controller);
+        delete_test_dir(dir_name);
+
+        // key不是严格升序时返回错误，已经写入的数据被丢弃
+        let controller = create_test_controller(dir_name);
+        let pairs = (0..1000).chain([500]).map(|i| (format!("key{:05}", i).into_bytes(), Vec::new()));
+        let error = controller.bulk_load(pairs).err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+        assert_eq!(0, controller.len().unwrap());
+        controller.verify().unwrap();
+        let pairs = (0..10).map(|i| (format!("key{:05}", i).into_bytes(), Vec::new()));
+        assert_eq!(10, controller.bulk_load(pairs).unwrap());
+        assert_eq!(10, controller.len().unwrap());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_merge() {
+        let dir_name = "controller_merge";
+        let controller = create_test_controller(dir_name);
+        let add = |current: Option<&[u8]>, operand: &[u8]| {
+            let current = current.map_or(0, |value| u64::from_be_bytes(value.try_into().unwrap()));
+            let operand = u64::from_be_bytes(operand.try_into().unwrap());
+            (current + operand).to_be_bytes().to_vec()
+        };
+
+        // 计数器，key不存在时当前的value为None
+        for _ in 0..10 {
+            controller.merge("counter".as_bytes(), &1u64.to_be_bytes(), add).unwrap();
+        }
+        assert_eq!(15u64.to_be_bytes().to_vec(), controller.merge("counter".as_bytes(), &5u64.to_be_bytes(), add).unwrap());
+        assert_eq!(Some(15u64.to_be_bytes().to_vec()), controller.get_bytes("counter".as_bytes()).unwrap());
+        controller.remove("counter").unwrap();
+        assert_eq!(1u64.to_be_bytes().to_vec(), controller.merge("counter".as_bytes(), &1u64.to_be_bytes(), add).unwrap());
+
+        // value不断变长，超过value_threshold后写入额外数据文件，叶节点空间不足时分裂
+        let append = |current: Option<&[u8]>, operand: &[u8]| [current.unwrap_or_default(), operand].concat();
+        for i in 0..200 {
+            let key = format!("key{:03}", i % 50);
+            controller.merge(key.as_bytes(), "ab".as_bytes(), append).unwrap();
+        }
+        for i in 0..50 {
+            assert_eq!(Some("ab".repeat(4)), controller.get(&format!("key{:03}", i)).unwrap());
+        }
+        assert!(count_leaf_pages(&controller) > 1);
+        let long_value = controller.merge("key000".as_bytes(), "c".repeat(200).as_bytes(), append).unwrap();
+        assert_eq!("ab".repeat(4) + &"c".repeat(200), String::from_utf8(long_value).unwrap());
+        assert_eq!(Some("ab".repeat(4) + &"c".repeat(200)), controller.get("key000").unwrap());
+        assert!(controller.merge("a".repeat(65).as_bytes(), "ab".as_bytes(), append).is_err());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_debug_tree() {
+        let dir_name = "controller_debug_tree";
+        let controller = create_test_controller(dir_name);
+        controller.put("b", "1").unwrap();
+        controller.put("a", "1").unwrap();
+        controller.remove("b").unwrap();
+        assert_eq!("leaf offset=0 keys=2\n  \"a\"\n  \"b\" deleted\n", controller.debug_tree().unwrap());
+
+        for i in 0..100 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        let output = controller.debug_tree().unwrap();
+        let root_offset = controller.read().root_node.get_offset();
+        assert!(output.starts_with(&format!("inner offset={} keys=", root_offset)));
+        assert_eq!(count_leaf_pages(&controller), output.lines().filter(|line| line.trim_start().starts_with("leaf")).count());
+        assert!(output.contains("  leaf offset=0 keys="));
+        assert!(output.contains("    \"key099\"\n"));
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_verify() {
+        let dir_name = "controller_verify";
+        fs::create_dir_all(dir_name).unwrap();
+        let controller = create_schema(dir_name, "test", PAGE_SIZE, 64, 32).unwrap().controller().unwrap();
+        controller.verify().unwrap();
+        for i in 0..300 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i).repeat(i % 5 + 1)).unwrap();
+        }
+        for i in (0..300).step_by(4) {
+            controller.remove(&format!("key{:03}", i)).unwrap();
+        }
+        controller.verify().unwrap();
+
+        // 修改叶节点的链表，模拟数据损坏
+        {
+            let tree = controller.read();
+            let root = Node::load(&tree.mete_data, &tree.data_file, tree.root_node.get_offset()).unwrap();
+            let leaf = root.leftmost_leaf(&tree.mete_data, &tree.data_file).unwrap();
+            let mut leaf_page = LeafPage::from(&tree.data_file, leaf.get_offset(), PAGE_SIZE, true, Comparator::ByteLexicographic).unwrap();
+            let next_page = leaf_page.get_next_page();
+            leaf_page.update_next_page(NULL_PAGE);
+            let error = tree.verify().err().unwrap();
+            assert_eq!(ErrorKind::Corruption, error_kind(error.as_ref()));
+            assert!(error.to_string().contains("leaf links"), "{}", error);
+            leaf_page.update_next_page(next_page);
+            leaf.release(&tree.mete_data, false).unwrap();
+        }
+        controller.verify().unwrap();
+
+        // 截断额外数据文件，模拟value丢失
+        controller.flush().unwrap();
+        let extra_file = fs::OpenOptions::new().write(true).open(format!("{}/test.e", dir_name)).unwrap();
+        extra_file.set_len(10).unwrap();
+        let error = controller.verify().err().unwrap();
+        assert_eq!(ErrorKind::Corruption, error_kind(error.as_ref()));
+        assert!(error.to_string().starts_with("mini base error: page "), "{}", error);
+        assert!(error.to_string().contains("extra value"), "{}", error);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_verify_all() {
+        let dir_name = "controller_verify_all";
+        let controller = create_test_controller(dir_name);
+        assert!(controller.verify_all().unwrap().is_empty());
+        let large = |i: usize| i.is_multiple_of(50);
+        for i in 0..300 {
+            let value = if large(i) { "x".repeat(200) } else { format!("value{}", i) };
+            controller.put(&format!("key{:03}", i), &value).unwrap();
+        }
+        assert!(controller.verify_all().unwrap().is_empty());
+        let leaf_offset = {
+            let tree = controller.read();
+            let root = Node::load(&tree.mete_data, &tree.data_file, tree.root_node.get_offset()).unwrap();
+            let (leaf, _) = root.find_leaf(&tree.mete_data, &tree.data_file, "key120".as_bytes(), None).unwrap();
+            let offset = leaf.get_offset();
+            leaf.release(&tree.mete_data, false).unwrap();
+            offset
+        };
+        drop(controller);
+
+        // 同时损坏一个叶节点和额外数据文件
+        let data_file = fs::OpenOptions::new().read(true).write(true).open(format!("{}/test.d", dir_name)).unwrap();
+        let mut byte = [0u8; 1];
+        data_file.read_at(&mut byte, leaf_offset + PAGE_SIZE as u64 - 1).unwrap();
+        data_file.write_at(&[!byte[0]], leaf_offset + PAGE_SIZE as u64 - 1).unwrap();
+        drop(data_file);
+        fs::OpenOptions::new().write(true).open(format!("{}/test.e", dir_name)).unwrap().set_len(10).unwrap();
+
+        let controller = reopen_test_controller(dir_name);
+        let error = controller.verify().err().unwrap();
+        assert_eq!(ErrorKind::Corruption, error_kind(error.as_ref()));
+        let errors = controller.verify_all().unwrap();
+        let invalid_pages: Vec<_> = errors.iter().filter(|error| error.kind == VerificationErrorKind::InvalidPage).collect();
+        assert_eq!(1, invalid_pages.len());
+        assert_eq!(leaf_offset, invalid_pages[0].page_offset);
+        assert!(invalid_pages[0].to_string().starts_with(&format!("page {}: ", leaf_offset)), "{}", invalid_pages[0]);
+        let (begin, end) = (invalid_pages[0].begin.clone().unwrap(), invalid_pages[0].end.clone().unwrap());
+        let lost = |key: &str| begin.as_slice() <= key.as_bytes() && key.as_bytes() < end.as_slice();
+        assert!(lost("key120"));
+
+        // 与无法读取的叶节点相邻的链接不做检查，其余叶节点中每个保存在额外数据文件中的value都记录为问题
+        let extra_values: Vec<_> = errors.iter().filter(|error| error.kind == VerificationErrorKind::ExtraValue).collect();
+        assert_eq!(errors.len(), invalid_pages.len() + extra_values.len());
+        let expected = (0..300).filter(|i| large(*i) && !lost(&format!("key{:03}", i))).count();
+        assert!(expected > 0);
+        assert_eq!(expected, extra_values.len());
+        for error in extra_values {
+            assert_ne!(leaf_offset, error.page_offset);
+            assert!(error.message.starts_with("key key"), "{}", error);
+            let key = &error.message.as_bytes()[4..10];
+            assert!(error.begin.as_ref().is_none_or(|begin| begin.as_slice() <= key));
+            assert!(error.end.as_ref().is_none_or(|end| key < end.as_slice()));
+        }
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_iter() {
+        let dir_name = "controller_iter";
+        let controller = create_test_controller(dir_name);
+        assert_eq!(0, controller.iter().count());
+
+        for i in (0..100).rev() {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        for i in (0..100).step_by(3) {
+            controller.remove(&format!("key{:03}", i)).unwrap();
+        }
+
+        let key_values: Vec<(Vec<u8>, Vec<u8>)> = controller.iter().collect::<Result<_, _>>().unwrap();
+        let expect: Vec<(Vec<u8>, Vec<u8>)> = (0..100).filter(|i| i % 3 != 0)
+            .map(|i| (format!("key{:03}", i).into_bytes(), format!("value{}", i).into_bytes()))
+            .collect();
+        assert_eq!(expect, key_values);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_iter_rev() {
+        let dir_name = "controller_iter_rev";
+        let controller = create_test_controller(dir_name);
+        assert_eq!(0, controller.iter_rev().count());
+
+        for i in 0..100 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        controller.remove("key098").unwrap();
+
+        let largest: Vec<Vec<u8>> = controller.iter_rev().take(3).map(|key_value| key_value.unwrap().0).collect();
+        assert_eq!(vec![b"key099".to_vec(), b"key097".to_vec(), b"key096".to_vec()], largest);
+
+        let mut key_values: Vec<(Vec<u8>, Vec<u8>)> = controller.iter_rev().collect::<Result<_, _>>().unwrap();
+        key_values.reverse();
+        let expect: Vec<(Vec<u8>, Vec<u8>)> = controller.iter().collect::<Result<_, _>>().unwrap();
+        assert_eq!(99, key_values.len());
+        assert_eq!(expect, key_values);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_keys() {
+        let dir_name = "controller_keys";
+        let controller = create_test_controller(dir_name);
+        assert_eq!(0, controller.keys().count());
+
+        for i in 0..100 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i).repeat(50)).unwrap();
+        }
+        for i in (0..100).step_by(3) {
+            controller.remove(&format!("key{:03}", i)).unwrap();
+        }
+        let expect: Vec<Vec<u8>> = controller.iter().map(|key_value| key_value.unwrap().0).collect();
+        assert_eq!(66, expect.len());
+        controller.flush().unwrap();
+
+        // value都保存在额外数据文件中，截断后iter无法读取value，keys不读取额外数据文件
+        let extra_file = fs::OpenOptions::new().write(true).open(format!("{}/test.e", dir_name)).unwrap();
+        extra_file.set_len(0).unwrap();
+        assert!(controller.iter().next().unwrap().is_err());
+        let keys: Vec<Vec<u8>> = controller.keys().collect::<Result<_, _>>().unwrap();
+        assert_eq!(expect, keys);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_export_import() {
+        let dir_name = "controller_export_import";
+        let controller = create_test_controller(dir_name);
+        for i in 0..1000 {
+            controller.put(&format!("key{:04}", i), &format!("value{}", i).repeat(i % 30 + 1)).unwrap();
+        }
+        for i in (0..1000).step_by(7) {
+            assert!(controller.remove(&format!("key{:04}", i)).unwrap());
+        }
+        controller.put_bytes(b"", b"").unwrap();
+        let expect: Vec<(Vec<u8>, Vec<u8>)> = controller.iter().collect::<Result<_, _>>().unwrap();
+        let mut data = Vec::new();
+        assert_eq!(expect.len(), controller.export(&mut data).unwrap());
+
+        // 导入页大小不同的schema
+        let imported = create_schema(dir_name, "imported", PAGE_SIZE * 2, 64, 256).unwrap().controller().unwrap();
+        assert_eq!(expect.len(), imported.import(&mut data.as_slice()).unwrap());
+        imported.verify().unwrap();
+        assert_eq!(expect, imported.iter().collect::<Result<Vec<_>, _>>().unwrap());
+        // 只能导入空树
+        let error = imported.import(&mut data.as_slice()).err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+
+        // 数据不完整时丢弃已经导入的数据
+        let truncated = create_schema(dir_name, "truncated", PAGE_SIZE, 64, 128).unwrap().controller().unwrap();
+        let error = truncated.import(&mut &data[..data.len() - 1]).err().unwrap();
+        assert_eq!(ErrorKind::Corruption, error_kind(error.as_ref()));
+        assert_eq!(0, truncated.len().unwrap());
+        truncated.verify().unwrap();
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_put_batch() {
+        let dir_name = "controller_put_batch";
+        let controller = create_test_controller(dir_name);
+        controller.put("key010", "old").unwrap();
+        controller.put("key020", "old").unwrap();
+        controller.remove("key020").unwrap();
+
+        let keys: Vec<String> = (0..100).rev().map(|i| format!("key{:03}", i)).collect();
+        let values: Vec<String> = (0..100).rev().map(|i| format!("value{}", i)).collect();
+        let pairs: Vec<(&[u8], &[u8])> = keys.iter().zip(values.iter())
+            .map(|(key, value)| (key.as_bytes(), value.as_bytes()))
+            .collect();
+        assert_eq!((99, 1), controller.put_batch(&pairs).unwrap());
+
+        for i in 0..100 {
+            assert_eq!(Some(format!("value{}", i)), controller.get(&format!("key{:03}", i)).unwrap());
+            assert!(controller.contains_key(format!("key{:03}", i).as_bytes()).unwrap());
+        }
+        assert_eq!(100, controller.iter().count());
+        assert!(controller.put_batch(&[("a".repeat(65).as_bytes(), "value".as_bytes())]).is_err());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_get_many() {
+        let dir_name = "controller_get_many";
+        let controller = create_test_controller(dir_name);
+        for i in 0..100 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        controller.remove("key050").unwrap();
+        assert!(count_leaf_pages(&controller) > 2);
+
+        // 乱序、重复以及不存在的key，结果与输入的顺序一致
+        let keys = ["key099", "key000", "absent", "key050", "key042", "key000", "key100"];
+        let keys: Vec<&[u8]> = keys.iter().map(|key| key.as_bytes()).collect();
+        let values = controller.get_many(&keys).unwrap();
+        assert_eq!(vec![
+            Some("value99".as_bytes().to_vec()),
+            Some("value0".as_bytes().to_vec()),
+            None,
+            None,
+            Some("value42".as_bytes().to_vec()),
+            Some("value0".as_bytes().to_vec()),
+            None,
+        ], values);
+
+        let keys: Vec<String> = (0..100).rev().map(|i| format!("key{:03}", i)).collect();
+        let keys: Vec<&[u8]> = keys.iter().map(|key| key.as_bytes()).collect();
+        for (key, value) in keys.iter().zip(controller.get_many(&keys).unwrap()) {
+            assert_eq!(controller.get_bytes(key).unwrap(), value);
+        }
+        assert!(controller.get_many(&[]).unwrap().is_empty());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_concurrent_get() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Controller>();
+
+        let dir_name = "controller_concurrent_get";
+        let controller = create_test_controller(dir_name);
+        for i in 0..200 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+
+        // 多个线程同时持有读锁读取
+        let controller = Arc::new(controller);
+        let handles: Vec<_> = (0..4).map(|t| {
+            let controller = Arc::clone(&controller);
+            thread::spawn(move || {
+                for i in (0..200).map(|i| (i + t * 50) % 200) {
+                    assert_eq!(Some(format!("value{}", i)), controller.get(&format!("key{:03}", i)).unwrap());
+                }
+                assert_eq!(200, controller.iter().count());
+            })
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_concurrent_put_get() {
+        let dir_name = "controller_concurrent_put_get";
+        let controller = Arc::new(create_test_controller(dir_name));
+
+        // 每个线程写入不相交的key范围，写入后立即读取，同时读取其它线程已经写入的数据
+        let handles: Vec<_> = (0..4).map(|t| {
+            let controller = Arc::clone(&controller);
+            thread::spawn(move || {
+                for i in 0..200 {
+                    let key = format!("key{}_{:03}", t, i);
+                    controller.put(&key, &format!("value{}", i)).unwrap();
+                    assert_eq!(Some(format!("value{}", i)), controller.get(&key).unwrap());
+                    if i % 3 == 0 {
+                        assert!(controller.remove(&key).unwrap());
+                    }
+                    let other = format!("key{}_{:03}", (t + 1) % 4, i / 2);
+                    if let Some(value) = controller.get(&other).unwrap() {
+                        assert_eq!(format!("value{}", i / 2), value);
+                    }
+                }
+            })
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        for t in 0..4 {
+            for i in 0..200 {
+                let expected = if i % 3 == 0 { None } else { Some(format!("value{}", i)) };
+                assert_eq!(expected, controller.get(&format!("key{}_{:03}", t, i)).unwrap());
+            }
+        }
+        assert_eq!(4 * 133, controller.len().unwrap());
+        let keys: Vec<Vec<u8>> = controller.iter().map(|result| result.unwrap().0).collect();
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_split() {
+        let dir_name = "controller_split";
+        let controller = create_test_controller(dir_name);
+
+        // 倒序插入，新数据总是位于有序列表的头部
+        for i in (0..100).rev() {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        // 根节点和至少三个叶节点
+        assert!(controller.read().data_file.len().unwrap() >= 4 * PAGE_SIZE as u64);
+        assert_ne!(0, controller.read().mete_data.get_root_page_offset());
+
+        for i in 0..100 {
+            assert_eq!(Some(format!("value{}", i)), controller.get(&format!("key{:03}", i)).unwrap());
+        }
+        let result = controller.scan("key000", "key100").unwrap();
+        let keys: Vec<String> = result.into_iter().map(|(key, _)| key).collect();
+        let expect: Vec<String> = (0..100).map(|i| format!("key{:03}", i)).collect();
+        assert_eq!(expect, keys);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_get_after_split() {
+        let dir_name = "controller_get_after_split";
+        let controller = create_test_controller(dir_name);
+        for i in 0..100 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        // 根节点已经分裂为内部节点，key位于根节点之外的叶节点
+        let leaf_offset = {
+            let tree = controller.read();
+            assert!(!tree.root_node.is_leaf());
+            let root = Node::load(&tree.mete_data, &tree.data_file, tree.root_node.get_offset()).unwrap();
+            let (leaf, _) = root.find_leaf(&tree.mete_data, &tree.data_file, "key077".as_bytes(), None).unwrap();
+            let offset = leaf.get_offset();
+            leaf.release(&tree.mete_data, false).unwrap();
+            offset
+        };
+        assert_ne!(controller.read().root_node.get_offset(), leaf_offset);
+        assert_eq!(Some("value77".to_string()), controller.get("key077").unwrap());
+        assert_eq!(None, controller.get("key0771").unwrap());
+
+        // value不是合法的utf8时返回错误
+        controller.put_bytes("key077".as_bytes(), &[0xFF, 0xFE]).unwrap();
+        let error = controller.get("key077").err().unwrap();
+        assert_eq!(ErrorKind::Other, error_kind(error.as_ref()));
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_comparator() {
+        let dir_name = "controller_comparator";
+        fs::create_dir_all(dir_name).unwrap();
+        let mete_data = create_schema_with_comparator(dir_name, "test", PAGE_SIZE, 64, 128, Comparator::U64BigEndian).unwrap();
+        let controller = mete_data.controller().unwrap();
+
+        // 去掉开头的0后长度不同，按字节比较时[1, 0]小于[255]，按数值比较时256大于255
+        let encode = |i: u64| {
+            let bytes = i.to_be_bytes();
+            let start = bytes.iter().position(|byte| *byte != 0).unwrap_or(7);
+            Vec::from(&bytes[start..])
+        };
+        for i in (0..400u64).map(|i| i * 7 % 400) {
+            controller.put_bytes(&encode(i), format!("value{}", i).as_bytes()).unwrap();
+        }
+        let keys: Vec<Vec<u8>> = controller.iter().map(|key_value| key_value.unwrap().0).collect();
+        let expect: Vec<Vec<u8>> = (0..400).map(encode).collect();
+        assert_eq!(expect, keys);
+        let result = controller.scan_bytes(&encode(250), &encode(260)).unwrap();
+        assert_eq!(10, result.len());
+        assert_eq!(Some("value8".as_bytes().to_vec()), controller.get_bytes(&8u64.to_be_bytes()).unwrap());
+        drop(controller);
+
+        let mete_data = open_schema(dir_name, "test").unwrap();
+        assert_eq!(Comparator::U64BigEndian, mete_data.get_comparator());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_case_insensitive() {
+        let dir_name = "controller_case_insensitive";
+        fs::create_dir_all(dir_name).unwrap();
+        let mete_data = create_schema_with_comparator(dir_name, "test", PAGE_SIZE, 64, 128, Comparator::CaseInsensitiveAscii).unwrap();
+        let controller = mete_data.controller().unwrap();
+
+        controller.put("Banana", "1").unwrap();
+        controller.put("apple", "2").unwrap();
+        controller.put("cherry", "3").unwrap();
+        assert_eq!(Some("1".to_string()), controller.get("BANANA").unwrap());
+        controller.put("APPLE", "4").unwrap();
+        let result = controller.scan_inclusive("a", "C").unwrap();
+        assert_eq!(vec![("apple".to_string(), "4".to_string()), ("Banana".to_string(), "1".to_string())], result);
+        // 覆盖时保留第一次写入的key
+        let entry = controller.get_entry("APPLE".as_bytes()).unwrap();
+        assert_eq!(Some(("apple".as_bytes().to_vec(), "4".as_bytes().to_vec())), entry);
+        assert_eq!(None, controller.get_entry("durian".as_bytes()).unwrap());
+        controller.remove("Cherry").unwrap();
+        assert_eq!(None, controller.get_entry("cherry".as_bytes()).unwrap());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_custom_comparator() {
+        let dir_name = "controller_custom_comparator";
+        fs::create_dir_all(dir_name).unwrap();
+        let reverse = || CustomComparator::new("reverse", Box::new(|a: &[u8], b: &[u8]| b.cmp(a)));
+        let mete_data = create_schema_with_comparator(dir_name, "test", PAGE_SIZE, 64, 128, Comparator::Custom(reverse())).unwrap();
+        let controller = mete_data.controller().unwrap();
+        for i in (0..300).map(|i| i * 7 % 300) {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        // 按比较函数的顺序排列，范围的begin在end之前
+        let keys: Vec<_> = controller.keys().map(|key| String::from_utf8(key.unwrap()).unwrap()).collect();
+        let expect: Vec<_> = (0..300).rev().map(|i| format!("key{:03}", i)).collect();
+        assert_eq!(expect, keys);
+        let result = controller.scan("key200", "key190").unwrap();
+        assert_eq!(10, result.len());
+        assert_eq!("key200", result[0].0);
+        // 前缀读取按比较函数的顺序返回所有以prefix开头的key
+        let result = controller.scan_prefix(b"key1").unwrap();
+        let expect: Vec<_> = (100..200).rev().map(|i| (format!("key{:03}", i).into_bytes(), format!("value{}", i).into_bytes())).collect();
+        assert_eq!(expect, result);
+        assert_eq!(300, controller.scan_prefix(b"").unwrap().len());
+        assert_eq!(10, controller.begin_read().scan_prefix(b"key29").unwrap().len());
+        assert!(controller.scan_prefix(b"other").unwrap().is_empty());
+        assert_eq!(Some("value100".to_string()), controller.get("key100").unwrap());
+        controller.verify().unwrap();
+        drop(controller);
+
+        // 重新打开时需要提供名称相同的比较函数
+        let error = open_schema(dir_name, "test").unwrap().controller().err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+        let mut mete_data = open_schema(dir_name, "test").unwrap();
+        let other = CustomComparator::new("other", Box::new(|a: &[u8], b: &[u8]| a.cmp(b)));
+        let error = mete_data.set_custom_comparator(other).err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+        mete_data.set_custom_comparator(reverse()).unwrap();
+        assert_eq!(Comparator::Custom(reverse()), mete_data.get_comparator());
+        let controller = mete_data.controller().unwrap();
+        controller.verify().unwrap();
+        assert_eq!(Some(("key299".as_bytes().to_vec(), "value299".as_bytes().to_vec())), controller.iter().next().map(Result::unwrap));
+        drop(controller);
+
+        // 关闭后比较函数及其捕获的数据随之释放
+        let captured = Arc::new(Mutex::new(0));
+        let mut mete_data = open_schema(dir_name, "test").unwrap();
+        let counter = captured.clone();
+        mete_data.set_custom_comparator(CustomComparator::new("reverse", Box::new(move |a: &[u8], b: &[u8]| {
+            *counter.lock().unwrap() += 1;
+            b.cmp(a)
+        }))).unwrap();
+        let controller = mete_data.controller().unwrap();
+        assert_eq!(Some("value7".to_string()), controller.get("key007").unwrap());
+        assert!(*captured.lock().unwrap() > 0);
+        assert!(Arc::strong_count(&captured) > 1);
+        drop(controller);
+        assert_eq!(1, Arc::strong_count(&captured));
+
+        // 使用内置排序方式的schema不能设置比较函数
+        let mut mete_data = create_schema(dir_name, "builtin", PAGE_SIZE, 64, 128).unwrap();
+        let error = mete_data.set_custom_comparator(reverse()).err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+
+        delete_test_dir(dir_name)
+    }
+
+    // 沿叶节点之间的链表统计叶节点数量
+    fn count_leaf_pages(controller: &Controller) -> usize {
+        let tree = controller.read();
+        let (mete_data, data_file) = (&tree.mete_data, &tree.data_file);
+        let root = Node::load(mete_data, data_file, tree.root_node.get_offset()).unwrap();
+        let mut leaf = root.leftmost_leaf(mete_data, data_file).unwrap();
+        let mut count = 1;
+        while leaf.get_next_page() != NULL_PAGE {
+            leaf = Node::from(mete_data, data_file, leaf.get_next_page(), NodeType::Leaf).unwrap();
+            count += 1;
+        }
+        count
+    }
+
+    #[test]
+    fn controller_shrink_to_fit() {
+        let dir_name = "controller_shrink_to_fit";
+        let controller = create_test_controller(dir_name);
+        assert_eq!(0, controller.shrink_to_fit().unwrap());
+        for i in 0..2000 {
+            controller.put(&format!("key{:04}", i), &format!("value{}", i)).unwrap();
+        }
+        let file_length = controller.read().data_file.len().unwrap();
+
+        // 保留少量分散的key，剩余的页分布在文件各处，需要移动到文件前部
+        for i in (0..2000).filter(|i| i % 100 != 0) {
+            controller.remove(&format!("key{:04}", i)).unwrap();
+        }
+        assert_eq!(file_length, controller.read().data_file.len().unwrap());
+        let released = controller.shrink_to_fit().unwrap();
+        let shrunk_length = controller.read().data_file.len().unwrap();
+        assert!(released > 0);
+        assert_eq!(file_length - released, shrunk_length);
+        assert_eq!(NULL_PAGE, controller.read().mete_data.get_free_page_head());
+        controller.verify().unwrap();
+        let keys: Vec<_> = controller.iter().map(|result| String::from_utf8(result.unwrap().0).unwrap()).collect();
+        let expect: Vec<_> = (0..2000).step_by(100).map(|i| format!("key{:04}", i)).collect();
+        assert_eq!(expect, keys);
+        assert_eq!(0, controller.shrink_to_fit().unwrap());
+        drop(controller);
+
+        // 重新打开后数据完整，写入时从文件末尾分配新页
+        let controller = reopen_test_controller(dir_name);
+        assert_eq!(shrunk_length, controller.read().data_file.len().unwrap());
+        controller.verify().unwrap();
+        assert_eq!(Some("value1000".to_string()), controller.get("key1000").unwrap());
+        for i in 0..2000 {
+            controller.remove(&format!("key{:04}", i)).unwrap();
+        }
+        controller.shrink_to_fit().unwrap();
+        assert_eq!(PAGE_SIZE as u64, controller.read().data_file.len().unwrap());
+        controller.put("key", "value").unwrap();
+        assert_eq!(Some("value".to_string()), controller.get("key").unwrap());
+        controller.verify().unwrap();
+        controller.remove("key").unwrap();
+        drop(controller);
+
+        // 启用预写日志时同样可以移动和截断，内存模式下截断内存中的文件
+        let mut mete_data = open_schema(dir_name, "test").unwrap();
+        mete_data.set_write_ahead_log(true);
+        for controller in [mete_data.controller().unwrap(), create_memory_schema(PAGE_SIZE, 64, 128).unwrap().controller().unwrap()] {
+            for i in 0..1000 {
+                controller.put(&format!("key{:04}", i), &format!("value{}", i)).unwrap();
+            }
+            for i in (0..1000).filter(|i| i % 50 != 0) {
+                controller.remove(&format!("key{:04}", i)).unwrap();
+            }
+            assert!(controller.shrink_to_fit().unwrap() > 0);
+            controller.verify().unwrap();
+            assert_eq!(20, controller.len().unwrap());
+        }
+        let controller = reopen_test_controller(dir_name);
+        controller.verify().unwrap();
+        assert_eq!(20, controller.len().unwrap());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_remove_merge() {
+        let dir_name = "controller_remove_merge";
+        let controller = create_test_controller(dir_name);
+
+        for i in 0..300 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        let leaf_pages = count_leaf_pages(&controller);
+        let file_length = controller.read().data_file.len().unwrap();
+        assert!(leaf_pages > 10);
+        assert_eq!(NULL_PAGE, controller.read().mete_data.get_free_page_head());
+
+        // 删除大部分数据，叶节点合并，空出的页进入空闲链表
+        for i in (0..300).filter(|i| i % 30 != 0) {
+            assert!(controller.remove(&format!("key{:03}", i)).unwrap());
+        }
+        assert!(count_leaf_pages(&controller) < leaf_pages / 4);
+        assert_ne!(NULL_PAGE, controller.read().mete_data.get_free_page_head());
+        let result = controller.scan("key000", "key300").unwrap();
+        let keys: Vec<String> = result.into_iter().map(|(key, _)| key).collect();
+        let expect: Vec<String> = (0..300).filter(|i| i % 30 == 0).map(|i| format!("key{:03}", i)).collect();
+        assert_eq!(expect, keys);
+        let keys: Vec<Vec<u8>> = controller.iter_rev().map(|key_value| key_value.unwrap().0).collect();
+        let expect: Vec<Vec<u8>> = expect.into_iter().rev().map(String::into_bytes).collect();
+        assert_eq!(expect, keys);
+
+        // 删除全部数据后根节点退化为叶节点
+        for i in (0..300).filter(|i| i % 30 == 0) {
+            assert!(controller.remove(&format!("key{:03}", i)).unwrap());
+        }
+        assert_eq!(1, count_leaf_pages(&controller));
+        assert!(controller.read().root_node.get_only_child().is_none());
+        assert_eq!(None, controller.iter().next().map(|key_value| key_value.unwrap()));
+
+        // 重新写入时复用空闲页
+        for i in 0..300 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        for i in 0..300 {
+            assert_eq!(Some(format!("value{}", i)), controller.get(&format!("key{:03}", i)).unwrap());
+        }
+        assert!(controller.read().data_file.len().unwrap() <= file_length + 2 * PAGE_SIZE as u64);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_get_or_insert() {
+        let dir_name = "controller_get_or_insert";
+        let controller = create_test_controller(dir_name);
+
+        assert_eq!("0".as_bytes(), controller.get_or_insert("counter".as_bytes(), "0".as_bytes()).unwrap());
+        controller.put("counter", "1").unwrap();
+        assert_eq!("1".as_bytes(), controller.get_or_insert("counter".as_bytes(), "0".as_bytes()).unwrap());
+        controller.remove("counter").unwrap();
+        assert_eq!("2".as_bytes(), controller.get_or_insert("counter".as_bytes(), "2".as_bytes()).unwrap());
+
+        // 插入过程中叶节点和根节点分裂
+        for i in 0..200 {
+            let key = format!("key{:03}", i);
+            assert_eq!(key.as_bytes(), controller.get_or_insert(key.as_bytes(), key.as_bytes()).unwrap());
+        }
+        for i in 0..200 {
+            let key = format!("key{:03}", i);
+            assert_eq!(key.as_bytes(), controller.get_or_insert(key.as_bytes(), "other".as_bytes()).unwrap());
+        }
+        assert_ne!(0, controller.read().mete_data.get_root_page_offset());
+        assert_eq!(201, controller.iter().count());
+
+        // key已经存在时写入额外数据文件的default_value被释放，flush之后复用，文件不再增长
+        let large = "d".repeat(300);
+        controller.get_or_insert("counter".as_bytes(), large.as_bytes()).unwrap();
+        controller.flush().unwrap();
+        let extra_length = fs::metadata(format!("{}/test.e", dir_name)).unwrap().len();
+        for _ in 0..5 {
+            assert_eq!("2".as_bytes(), controller.get_or_insert("counter".as_bytes(), large.as_bytes()).unwrap());
+            controller.flush().unwrap();
+        }
+        assert_eq!(extra_length, fs::metadata(format!("{}/test.e", dir_name)).unwrap().len());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_insert() {
+        let dir_name = "controller_insert";
+        let controller = create_test_controller(dir_name);
+
+        assert!(controller.insert(b"key", b"first").unwrap());
+        // key存在时不修改，value相同时也返回false
+        assert!(!controller.insert(b"key", b"second").unwrap());
+        assert!(!controller.insert(b"key", b"first").unwrap());
+        assert_eq!(Some("first".to_string()), controller.get("key").unwrap());
+        // 已删除的key视为不存在
+        assert!(controller.remove("key").unwrap());
+        assert!(controller.insert(b"key", b"third").unwrap());
+        assert_eq!(Some("third".to_string()), controller.get("key").unwrap());
+        assert_eq!(1, controller.len().unwrap());
+
+        // 插入过程中叶节点和根节点分裂
+        for i in 0..200 {
+            assert!(controller.insert(format!("key{:03}", i).as_bytes(), b"value").unwrap());
+        }
+        for i in 0..200 {
+            assert!(!controller.insert(format!("key{:03}", i).as_bytes(), b"other").unwrap());
+        }
+        assert!(!controller.read().root_node.is_leaf());
+        assert_eq!(201, controller.len().unwrap());
+        assert!(controller.scan("key000", "key200").unwrap().iter().all(|(_, value)| value == "value"));
+
+        // 没有写入的较长value占用的chunk在flush之后释放，之后可以复用
+        let large_value = "v".repeat(300);
+        assert!(controller.insert(b"large", large_value.as_bytes()).unwrap());
+        assert!(!controller.insert(b"large", b"other".repeat(100).as_slice()).unwrap());
+        controller.flush().unwrap();
+        let extra_length = fs::metadata(format!("{}/test.e", dir_name)).unwrap().len();
+        for _ in 0..10 {
+            assert!(!controller.insert(b"large", b"other".repeat(100).as_slice()).unwrap());
+            controller.flush().unwrap();
+        }
+        assert_eq!(extra_length, fs::metadata(format!("{}/test.e", dir_name)).unwrap().len());
+        assert_eq!(Some(large_value), controller.get("large").unwrap());
+        controller.verify().unwrap();
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_split_fill_factor() {
+        let dir_name = "controller_split_fill_factor";
+        fs::create_dir_all(dir_name).unwrap();
+        let mut mete_data = create_schema(dir_name, "invalid", PAGE_SIZE, 64, 128).unwrap();
+        for split_fill_factor in [0.0, 1.0, f32::NAN] {
+            let error = mete_data.set_split_fill_factor(split_fill_factor).err().unwrap();
+            assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+        }
+
+        // key递增写入，比例越大叶节点越少
+        let leaf_page_count = |schema_name: &str, split_fill_factor: f32, rightmost_split: bool| {
+            let mut mete_data = create_schema(dir_name, schema_name, PAGE_SIZE, 64, 128).unwrap();
+            mete_data.set_split_fill_factor(split_fill_factor).unwrap();
+            mete_data.set_rightmost_split(rightmost_split);
+            let controller = mete_data.controller().unwrap();
+            for i in 0..2000 {
+                controller.put(&format!("key{:05}", i), &format!("value{}", i)).unwrap();
+            }
+            controller.verify().unwrap();
+            assert_eq!(2000, controller.len().unwrap());
+            controller.stats().unwrap().leaf_page_count
+        };
+        let balanced = leaf_page_count("balanced", 0.5, false);
+        let filled = leaf_page_count("filled", 0.9, false);
+        let rightmost = leaf_page_count("rightmost", 0.5, true);
+        assert!(filled * 10 < balanced * 6, "{} {}", filled, balanced);
+        assert!(rightmost < filled, "{} {}", rightmost, filled);
+
+        // 随机写入和覆盖时结果不变
+        let mut mete_data = create_schema(dir_name, "random", PAGE_SIZE, 64, 128).unwrap();
+        mete_data.set_split_fill_factor(0.9).unwrap();
+        mete_data.set_rightmost_split(true);
+        let controller = mete_data.controller().unwrap();
+        for i in 0..1000 {
+            let key = format!("key{:05}", (i * 7919) % 1000);
+            controller.put(&key, &format!("value{}", i)).unwrap();
+        }
+        controller.verify().unwrap();
+        assert_eq!(1000, controller.len().unwrap());
+        assert_eq!(Some("value1".to_string()), controller.get("key00919").unwrap());
+
+        // 比例较小时写入大小差别较大的value，分裂后右侧不能超过一页
+        for (schema_name, split_fill_factor) in [("small", 0.3), ("tiny", 0.1)] {
+            let mut mete_data = create_schema(dir_name, schema_name, PAGE_SIZE, 64, 128).unwrap();
+            mete_data.set_split_fill_factor(split_fill_factor).unwrap();
+            let controller = mete_data.controller().unwrap();
+            for i in 0..500 {
+                let value = if i % 5 == 0 { "v".repeat(120) } else { format!("{:02}", i % 100) };
+                controller.put_bytes(format!("key{:05}", (i * 7919) % 500).as_bytes(), value.as_bytes()).unwrap();
+            }
+            controller.verify().unwrap();
+            assert_eq!(500, controller.len().unwrap());
+        }
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_stats() {
+        let dir_name = "controller_stats";
+        let controller = create_test_controller(dir_name);
+
+        let stats = controller.stats().unwrap();
+        assert_eq!((0, 1, 0, 1), (stats.live_key_count, stats.leaf_page_count, stats.inner_page_count, stats.height));
+
+        for i in 0..300 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        controller.remove("key000").unwrap();
+        controller.put("key001", "value").unwrap();
+        let stats = controller.stats().unwrap();
+        assert_eq!(299, stats.live_key_count);
+        assert_eq!(1, stats.tombstone_count);
+        assert_eq!(count_leaf_pages(&controller), stats.leaf_page_count);
+        assert!(stats.inner_page_count >= 1);
+        assert!(stats.height >= 2);
+        assert!(stats.reclaimable_garbage_bytes > 0);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_composite_key() {
+        let dir_name = "controller_composite_key";
+        let controller = create_test_controller(dir_name);
+        let keys: [&[&[u8]]; 5] = [&[b"a", b"bb"], &[b"ab", b"c"], &[b"a", b"b"], &[b"a", b"b\0"], &[b"a"]];
+        for (i, components) in keys.iter().enumerate() {
+            controller.put_composite(components, format!("value{}", i).as_bytes()).unwrap();
+        }
+        assert_eq!(Some("value0".as_bytes().to_vec()), controller.get_composite(&[b"a", b"bb"]).unwrap());
+        assert_eq!(None, controller.get_composite(&[b"abb"]).unwrap());
+
+        // 分量是另一个分量的前缀时排在前面，前缀只匹配完整的分量
+        let expected: Vec<(Vec<Vec<u8>>, Vec<u8>)> = [(&keys[4], 4), (&keys[2], 2), (&keys[3], 3), (&keys[0], 0)].iter()
+            .map(|(components, i)| (components.iter().map(|component| component.to_vec()).collect(), format!("value{}", i).into_bytes()))
+            .collect();
+        assert_eq!(expected, controller.scan_composite_prefix(&[b"a"]).unwrap());
+        assert_eq!(5, controller.scan_composite_prefix(&[]).unwrap().len());
+        assert_eq!(1, controller.scan_composite_prefix(&[b"a", b"b"]).unwrap().len());
+
+        assert!(controller.remove_composite(&[b"a", b"b"]).unwrap());
+        assert!(!controller.remove_composite(&[b"a", b"b"]).unwrap());
+        assert_eq!(3, controller.scan_composite_prefix(&[b"a"]).unwrap().len());
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn controller_typed() {
+        use serde::{Deserialize, Serialize};
+        use crate::decode_typed;
+
+        #[derive(Debug, PartialEq, PartialOrd, Clone, Serialize, Deserialize)]
+        struct UserKey {
+            tenant: String,
+            id: i64,
+        }
+
+        #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+        struct User {
+            name: String,
+            age: u8,
+            emails: Vec<String>,
+            score: Option<f64>,
+        }
+
+        let dir_name = "controller_typed";
+        let controller = create_test_controller(dir_name);
+        let mut keys = Vec::new();
+        for tenant in ["b", "a\0", "a", "ab"] {
+            for id in [-100, 7, -1, 0, 300] {
+                keys.push(UserKey { tenant: tenant.to_string(), id });
+            }
+        }
+        let user = |key: &UserKey| User {
+            name: format!("{}{}", key.tenant, key.id),
+            age: key.id.unsigned_abs() as u8,
+            emails: vec![format!("{}@example.com", key.id)],
+            score: (key.id > 0).then_some(key.id as f64 / 2.0),
+        };
+        for key in &keys {
+            controller.put_typed(key, &user(key)).unwrap();
+        }
+        for key in &keys {
+            assert_eq!(Some(user(key)), controller.get_typed(key).unwrap());
+        }
+        let missing = UserKey { tenant: "a".to_string(), id: 1 };
+        assert_eq!(None, controller.get_typed::<_, User>(&missing).unwrap());
+
+        // 按字节序遍历的key与按字段逐个比较的顺序一致
+        let mut ordered = keys.clone();
+        ordered.sort_by(|left, right| left.partial_cmp(right).unwrap());
+        let stored: Vec<UserKey> = controller.keys().map(|key| decode_typed(&key.unwrap()).unwrap()).collect();
+        assert_eq!(ordered, stored);
+
+        // value的类型与写入时不同时返回错误
+        let error = controller.get_typed::<_, u64>(&keys[0]).unwrap_err();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+
+        assert!(controller.remove_typed(&keys[0]).unwrap());
+        assert!(!controller.remove_typed(&keys[0]).unwrap());
+        assert_eq!(None, controller.get_typed::<_, User>(&keys[0]).unwrap());
+        drop(controller);
+
+        let controller = reopen_test_controller(dir_name);
+        assert_eq!(Some(user(&keys[1])), controller.get_typed(&keys[1]).unwrap());
+        assert_eq!(keys.len() - 1, controller.keys().count());
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_corruption_mode() {
+        let dir_name = "controller_corruption_mode";
+        let controller = create_test_controller(dir_name);
+        for i in 0..300 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        let leaf_offset = {
+            let tree = controller.read();
+            let root = Node::load(&tree.mete_data, &tree.data_file, tree.root_node.get_offset()).unwrap();
+            let (leaf, _) = root.find_leaf(&tree.mete_data, &tree.data_file, "key150".as_bytes(), None).unwrap();
+            let offset = leaf.get_offset();
+            leaf.release(&tree.mete_data, false).unwrap();
+            offset
+        };
+        assert_ne!(controller.read().root_node.get_offset(), leaf_offset);
+        drop(controller);
+
+        // 修改叶节点中的一个字节，校验和不再匹配
+        let data_file = fs::OpenOptions::new().read(true).write(true).open(format!("{}/test.d", dir_name)).unwrap();
+        let mut byte = [0u8; 1];
+        data_file.read_at(&mut byte, leaf_offset + PAGE_SIZE as u64 - 1).unwrap();
+        data_file.write_at(&[!byte[0]], leaf_offset + PAGE_SIZE as u64 - 1).unwrap();
+        data_file.sync_all().unwrap();
+        drop(data_file);
+
+        let controller = open_schema(dir_name, "test").unwrap().controller().unwrap();
+        let error = controller.get("key150").err().unwrap();
+        assert_eq!(ErrorKind::Corruption, error_kind(error.as_ref()));
+        assert!(controller.lost_ranges().is_empty());
+        drop(controller);
+
+        let options = SchemaOptions { corruption_mode: CorruptionMode::Repair, ..SchemaOptions::default() };
+        let error = open_schema_with_options(dir_name, "test", SchemaOptions { read_only: true, ..options }).err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+
+        // 宽松模式下损坏的叶节点视为空，其余叶节点可以读取，不能写入
+        let mete_data = open_schema_with_options(dir_name, "test", SchemaOptions { corruption_mode: CorruptionMode::Lenient, ..options }).unwrap();
+        assert!(mete_data.is_read_only());
+        let controller = mete_data.controller().unwrap();
+        let lost_ranges = controller.lost_ranges();
+        assert_eq!(1, lost_ranges.len());
+        assert_eq!(leaf_offset, lost_ranges[0].page_offset);
+        let (begin, end) = (lost_ranges[0].begin.clone().unwrap(), lost_ranges[0].end.clone().unwrap());
+        let lost = |key: &str| begin.as_slice() <= key.as_bytes() && key.as_bytes() < end.as_slice();
+        assert!(lost("key150"));
+        let lost_count = (0..300).filter(|i| lost(&format!("key{:03}", i))).count();
+        assert_eq!(None, controller.get("key150").unwrap());
+        assert_eq!(Some("value0".to_string()), controller.get("key000").unwrap());
+        assert_eq!(Some("value299".to_string()), controller.get("key299").unwrap());
+        let keys: Vec<_> = controller.iter().map(|result| result.unwrap().0).collect();
+        assert_eq!(300 - lost_count, keys.len());
+        assert_eq!((300 - lost_count) as u64, controller.len().unwrap());
+        let error = controller.put("key150", "value150").err().unwrap();
+        assert_eq!(ErrorKind::ReadOnly, error_kind(error.as_ref()));
+        drop(controller);
+
+        // 修复模式下损坏的叶节点重新初始化，其余数据可以读写，之后可以正常打开
+        let controller = open_schema_with_options(dir_name, "test", options).unwrap().controller().unwrap();
+        assert_eq!(lost_ranges, controller.lost_ranges());
+        controller.verify().unwrap();
+        for i in 0..300 {
+            let key = format!("key{:03}", i);
+            let expected = if lost(&key) { None } else { Some(format!("value{}", i)) };
+            assert_eq!(expected, controller.get(&key).unwrap());
+        }
+        controller.put("key150", "value150").unwrap();
+        drop(controller);
+        let controller = open_schema(dir_name, "test").unwrap().controller().unwrap();
+        controller.verify().unwrap();
+        assert_eq!(Some("value150".to_string()), controller.get("key150").unwrap());
+        assert_eq!((300 - lost_count + 1) as u64, controller.len().unwrap());
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_memory_schema() {
+        let error = create_memory_schema(100, 64, 128).err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+        let mut mete_data = create_memory_schema(PAGE_SIZE, 64, 128).unwrap();
+        assert!(mete_data.is_memory());
+        // 内存模式下没有日志文件
+        mete_data.set_write_ahead_log(true);
+        let controller = mete_data.controller().unwrap();
+        for i in 0..500 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i).repeat(i % 40 + 1)).unwrap();
+        }
+        for i in (0..500).step_by(3) {
+            assert!(controller.remove(&format!("key{:03}", i)).unwrap());
+        }
+        controller.verify().unwrap();
+        assert!(!controller.read().root_node.is_leaf());
+        assert!(controller.read().data_file.len().unwrap() > 64 * 1024);
+        for i in 0..500 {
+            let expected = if i % 3 == 0 { None } else { Some(format!("value{}", i).repeat(i % 40 + 1)) };
+            assert_eq!(expected, controller.get(&format!("key{:03}", i)).unwrap());
+        }
+        assert_eq!(333, controller.len().unwrap());
+        assert_eq!(10, controller.scan("key100", "key115").unwrap().len());
+        controller.flush().unwrap();
+        assert_eq!(Some("value1".repeat(2)), controller.get("key001").unwrap());
+    }
+
+    #[test]
+    fn controller_single_file_schema() {
+        let dir_name = "controller_single_file_schema";
+        fs::create_dir_all(dir_name).unwrap();
+        let mete_data = create_single_file_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap();
+        assert!(mete_data.is_single_file());
+        assert_eq!(format!("{}/test.m", dir_name), mete_data.get_data_file_path().unwrap());
+        assert_eq!(format!("{}/test.m", dir_name), mete_data.get_extra_file_path().unwrap());
+        let error = open_schema_read_only(dir_name, "test").unwrap().controller().err().unwrap();
+        assert_eq!(ErrorKind::NotFound, error_kind(error.as_ref()));
+        let controller = mete_data.controller().unwrap();
+        let value = |i: usize| format!("value{}", i).repeat(i % 40 + 1);
+        // 大value写入额外数据区域，数据区域增长时额外数据区域被移动到文件末尾
+        for i in 0..500 {
+            controller.put(&format!("key{:03}", i), &value(i)).unwrap();
+        }
+        for i in (0..500).step_by(3) {
+            assert!(controller.remove(&format!("key{:03}", i)).unwrap());
+        }
+        controller.verify().unwrap();
+        assert!(!controller.read().root_node.is_leaf());
+        drop(controller);
+        let mut file_names: Vec<_> = fs::read_dir(dir_name).unwrap().map(|entry| entry.unwrap().file_name().into_string().unwrap()).collect();
+        file_names.sort();
+        assert_eq!(vec!["test.lock", "test.m"], file_names);
+
+        // 复制元数据文件即可得到完整的schema
+        fs::copy(format!("{}/test.m", dir_name), format!("{}/copy.m", dir_name)).unwrap();
+        for (schema_name, read_only) in [("test", false), ("copy", true), ("copy", false)] {
+            let mete_data = if read_only { open_schema_read_only(dir_name, schema_name) } else { open_schema(dir_name, schema_name) }.unwrap();
+            assert!(mete_data.is_single_file());
+            let controller = mete_data.controller().unwrap();
+            controller.verify().unwrap();
+            assert_eq!(333, controller.len().unwrap());
+            for i in 0..500 {
+                let expected = if i % 3 == 0 { None } else { Some(value(i)) };
+                assert_eq!(expected, controller.get(&format!("key{:03}", i)).unwrap());
+            }
+            if !read_only {
+                controller.put("key000", &"new".repeat(100)).unwrap();
+                assert!(controller.remove("key001").unwrap());
+            }
+        }
+        let controller = open_schema(dir_name, "copy").unwrap().controller().unwrap();
+        assert_eq!(Some("new".repeat(100)), controller.get("key000").unwrap());
+        assert_eq!(None, controller.get("key001").unwrap());
+        drop(controller);
+        assert_eq!(vec!["copy", "test"], list_schemas(dir_name).unwrap());
+        drop_schema(dir_name, "copy").unwrap();
+        drop_schema(dir_name, "test").unwrap();
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_multi_value() {
+        let dir_name = "controller_multi_value";
+        fs::create_dir_all(dir_name).unwrap();
+        let mete_data = create_schema_with_options(dir_name, "test", PAGE_SIZE, 64, 128, CreateOptions { multi_value: true, ..CreateOptions::default() }).unwrap();
+        assert!(mete_data.is_multi_value());
+        let controller = mete_data.controller().unwrap();
+        let large_value = "large".repeat(100);
+        for value in ["v2", "v1", large_value.as_str()] {
+            controller.put("key", value).unwrap();
+        }
+        controller.put("key\0", "zero").unwrap();
+        controller.put("kez", "other").unwrap();
+        // key_max_length限制的是编码后的key，包括8字节序号和转义，超过时返回InvalidArgument而不是PageFull
+        controller.put(&"k".repeat(44), "longest").unwrap();
+        for key in ["k".repeat(45), format!("{}\0", "k".repeat(43)), "\0".repeat(64)] {
+            let error = controller.put(&key, "value").err().unwrap();
+            assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+        }
+        assert!(controller.remove_bytes("k".repeat(44).as_bytes()).unwrap());
+        // 同一个key的value按写入顺序排列，get返回最早写入的value
+        let values = vec![b"v2".to_vec(), b"v1".to_vec(), large_value.clone().into_bytes()];
+        assert_eq!(values, controller.get_all(b"key").unwrap());
+        assert_eq!(Some("v2".to_string()), controller.get("key").unwrap());
+        assert!(controller.contains_key(b"key").unwrap());
+        assert!(controller.get_all(b"ke").unwrap().is_empty());
+        assert_eq!(None, controller.get("ke").unwrap());
+        assert_eq!(5, controller.len().unwrap());
+
+        // scan和iter中每个value一项
+        let keys = |key_values: Vec<(Vec<u8>, Vec<u8>)>| key_values.into_iter().map(|(key, _)| key).collect::<Vec<_>>();
+        let all_keys = vec![b"key".to_vec(), b"key".to_vec(), b"key".to_vec(), b"key\0".to_vec(), b"kez".to_vec()];
+        assert_eq!(all_keys, keys(controller.iter().collect::<Result<Vec<_>, _>>().unwrap()));
+        assert_eq!(all_keys[..3], keys(controller.scan_bytes(b"key", b"key\0").unwrap()));
+        assert_eq!(all_keys[..4], keys(controller.scan_inclusive_bytes(b"key", b"key\0").unwrap()));
+        assert_eq!(all_keys[..4], keys(controller.scan_prefix(b"key").unwrap()));
+        assert_eq!(values, controller.scan_bytes(b"key", b"kez").unwrap()[..3].iter().map(|(_, value)| value.clone()).collect::<Vec<_>>());
+
+        // 删除指定的value，再写入的value排在已有value之后
+        controller.put("key", "v1").unwrap();
+        assert_eq!(2, controller.remove_value(b"key", b"v1").unwrap());
+        assert_eq!(0, controller.remove_value(b"key", b"v1").unwrap());
+        controller.put("key", "v3").unwrap();
+        assert_eq!(vec![b"v2".to_vec(), large_value.clone().into_bytes(), b"v3".to_vec()], controller.get_all(b"key").unwrap());
+
+        // 只支持单值的操作
+        let error = controller.floor(b"key").err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+        let error = controller.put_batch(&[(b"key", b"v4")]).err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+        controller.flush().unwrap();
+        drop(controller);
+
+        let controller = reopen_test_controller(dir_name);
+        assert_eq!(vec![b"v2".to_vec(), large_value.into_bytes(), b"v3".to_vec()], controller.get_all(b"key").unwrap());
+        assert!(controller.remove_bytes(b"key").unwrap());
+        assert!(controller.get_all(b"key").unwrap().is_empty());
+        assert_eq!(vec![b"zero".to_vec()], controller.get_all(b"key\0").unwrap());
+        controller.verify().unwrap();
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_entry() {
+        let dir_name = "controller_entry";
+        let controller = create_test_controller(dir_name);
+        // key不存在时写入默认值，不执行修改
+        assert_eq!("1".as_bytes(), controller.entry("counter".as_bytes()).and_modify(|_| panic!("key absent")).or_insert("1".as_bytes()).unwrap());
+        assert_eq!("1".as_bytes(), controller.entry("counter".as_bytes()).or_insert("0".as_bytes()).unwrap());
+        // key存在时依次执行所有修改
+        let increment = |value: &mut Vec<u8>| {
+            let count: u32 = String::from_utf8(value.clone()).unwrap().parse().unwrap();
+            *value = (count + 1).to_string().into_bytes();
+        };
+        let value = controller.entry("counter".as_bytes()).and_modify(increment).and_modify(increment).or_insert("0".as_bytes()).unwrap();
+        assert_eq!("3".as_bytes(), value);
+        assert_eq!(Some("3".to_string()), controller.get("counter").unwrap());
+
+        // 多个线程同时修改时不会丢失更新
+        let controller = Arc::new(controller);
+        let handles: Vec<_> = (0..4).map(|_| {
+            let controller = controller.clone();
+            thread::spawn(move || for _ in 0..50 {
+                controller.entry("counter".as_bytes()).and_modify(increment).or_insert("0".as_bytes()).unwrap();
+            })
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(Some("203".to_string()), controller.get("counter").unwrap());
+        let error = controller.entry(&[b'k'; 65]).or_insert("0".as_bytes()).err().unwrap();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_scan_from() {
+        let dir_name = "controller_scan_from";
+        let controller = create_test_controller(dir_name);
+        for i in 0..300 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        for i in (0..300).step_by(7) {
+            controller.remove(&format!("key{:03}", i)).unwrap();
+        }
+        let expected: Vec<_> = controller.iter().collect::<Result<_, _>>().unwrap();
+
+        // 每页读取limit个key，直到返回的key为None
+        let mut result = Vec::new();
+        let (mut page, mut last_key) = controller.scan_from(None, 40).unwrap();
+        let mut page_count = 1;
+        loop {
+            assert!(page.len() <= 40);
+            result.append(&mut page);
+            match last_key {
+                Some(key) => (page, last_key) = controller.scan_from(Some(&key), 40).unwrap(),
+                None => break,
+            }
+            page_count += 1;
+        }
+        assert_eq!(expected.len().div_ceil(40), page_count);
+        assert_eq!(expected, result);
+
+        // last_key不存在时从下一个key开始，limit恰好等于剩余数量时没有下一页
+        let (page, last_key) = controller.scan_from(Some("key000".as_bytes()), 3).unwrap();
+        assert_eq!(vec!["key001", "key002", "key003"], page.iter().map(|(key, _)| String::from_utf8(key.clone()).unwrap()).collect::<Vec<_>>());
+        assert_eq!(Some("key003".as_bytes().to_vec()), last_key);
+        assert_eq!((vec![("key299".as_bytes().to_vec(), "value299".as_bytes().to_vec())], None), controller.scan_from(Some("key298".as_bytes()), 1).unwrap());
+        assert_eq!((Vec::new(), None), controller.scan_from(Some("key299".as_bytes()), 10).unwrap());
+        assert!(controller.scan_from(None, 0).is_err());
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_scan_with_deadline() {
+        let dir_name = "controller_scan_with_deadline";
+        let controller = create_test_controller(dir_name);
+        for i in 0..500 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        let expected = controller.scan_bytes("key010".as_bytes(), "key490".as_bytes()).unwrap();
+        let (result, resume_key) = controller.scan_with_deadline("key010".as_bytes(), "key490".as_bytes(), Instant::now() + Duration::from_secs(60)).unwrap();
+        assert_eq!((&expected, None), (&result, resume_key));
+
+        // deadline已经过去时只读取几个叶节点，从返回的key继续读取可以得到剩余的数据
+        let (mut result, mut resume_key) = controller.scan_with_deadline("key010".as_bytes(), "key490".as_bytes(), Instant::now()).unwrap();
+        assert!(result.len() < expected.len());
+        let mut chunk_count = 1;
+        while let Some(begin) = resume_key {
+            let (mut chunk, next_key) = controller.scan_with_deadline(&begin, "key490".as_bytes(), Instant::now()).unwrap();
+            result.append(&mut chunk);
+            resume_key = next_key;
+            chunk_count += 1;
+        }
+        assert!(chunk_count > 1);
+        assert_eq!(expected, result);
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_read_only() {
+        let dir_name = "controller_read_only";
+        fs::create_dir_all(dir_name).unwrap();
+        drop(create_schema(dir_name, "empty", PAGE_SIZE, 64, 128).unwrap());
+        let error = open_schema_read_only(dir_name, "empty").unwrap().controller().err().unwrap();
+        assert_eq!(ErrorKind::NotFound, error_kind(error.as_ref()));
+        assert!(!PathBuf::from(format!("{}/empty.d", dir_name)).exists());
+
+        let controller = create_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap().controller().unwrap();
+        for i in 0..200 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        controller.put("large", &"large".repeat(100)).unwrap();
+        drop(controller);
+        let mete_data_before = fs::read(format!("{}/test.m", dir_name)).unwrap();
+        let data_before = fs::read(format!("{}/test.d", dir_name)).unwrap();
+
+        let mut mete_data = open_schema_read_only(dir_name, "test").unwrap();
+        assert!(mete_data.is_read_only());
+        let error = mete_data.set_garbage_ratio(0.2).err().unwrap();
+        assert_eq!(ErrorKind::ReadOnly, error_kind(error.as_ref()));
+        let controller = mete_data.controller().unwrap();
+        assert_eq!(Some("value100".to_string()), controller.get("key100").unwrap());
+        assert_eq!(Some("large".repeat(100)), controller.get("large").unwrap());
+        assert_eq!(2, controller.scan("key010", "key012").unwrap().len());
+        assert_eq!(201, controller.len().unwrap());
+        controller.verify().unwrap();
+
+        // 写操作在修改页之前返回ReadOnly错误
+        for error in [
+            controller.put("key", "value").err().unwrap(),
+            controller.remove("key100").err().unwrap(),
+            controller.put_batch(&[("key".as_bytes(), "value".as_bytes())]).err().unwrap(),
+            controller.set_value_threshold(0).err().unwrap(),
+        ] {
+            assert_eq!(ErrorKind::ReadOnly, error_kind(error.as_ref()));
+        }
+        assert_eq!(Some("value100".to_string()), controller.get("key100").unwrap());
+        controller.flush().unwrap();
+        drop(controller);
+        assert_eq!(mete_data_before, fs::read(format!("{}/test.m", dir_name)).unwrap());
+        assert_eq!(data_before, fs::read(format!("{}/test.d", dir_name)).unwrap());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_set_value_threshold() {
+        let dir_name = "controller_set_value_threshold";
+        fs::create_dir_all(dir_name).unwrap();
+        let controller = create_schema(dir_name, "test", PAGE_SIZE, 64, u32::MAX).unwrap().controller().unwrap();
+        let extra_path = format!("{}/test.e", dir_name);
+        for i in 0..100 {
+            let value = if i % 10 == 0 { format!("{:0>200}", i) } else { format!("value{}", i) };
+            controller.put(&format!("key{:03}", i), &value).unwrap();
+        }
+        // 所有value都保存在叶节点中时不会创建额外数据文件
+        assert!(!fs::exists(&extra_path).unwrap());
+
+        // 降低value_threshold后较长的value迁移到额外数据文件
+        assert_eq!(10, controller.set_value_threshold(100).unwrap());
+        assert!(fs::metadata(&extra_path).unwrap().len() > 8);
+        assert_eq!(0, controller.set_value_threshold(100).unwrap());
+        for i in 0..100 {
+            let value = if i % 10 == 0 { format!("{:0>200}", i) } else { format!("value{}", i) };
+            assert_eq!(Some(value), controller.get(&format!("key{:03}", i)).unwrap());
+        }
+        controller.verify().unwrap();
+        drop(controller);
+
+        // 重新打开后使用新的value_threshold，提高value_threshold后迁移回叶节点
+        let mete_data = open_schema(dir_name, "test").unwrap();
+        assert_eq!(100, mete_data.get_value_threshold());
+        let controller = mete_data.controller().unwrap();
+        controller.put("large", &"large".repeat(30)).unwrap();
+        assert_eq!(11, controller.set_value_threshold(u32::MAX).unwrap());
+        assert_eq!(Some("large".repeat(30)), controller.get("large").unwrap());
+        assert_eq!(Some(format!("{:0>200}", 50)), controller.get("key050").unwrap());
+        controller.verify().unwrap();
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_compare_and_set() {
+        let dir_name = "controller_compare_and_set";
+        let controller = create_test_controller(dir_name);
+        // expected为None时只在key不存在时写入
+        assert!(controller.compare_and_set("key".as_bytes(), None, "value1".as_bytes()).unwrap());
+        assert!(!controller.compare_and_set("key".as_bytes(), None, "value2".as_bytes()).unwrap());
+        // expected与当前value不一致时不修改
+        assert!(!controller.compare_and_set("key".as_bytes(), Some("other".as_bytes()), "value2".as_bytes()).unwrap());
+        assert_eq!(Some("value1".to_string()), controller.get("key").unwrap());
+        assert!(controller.compare_and_set("key".as_bytes(), Some("value1".as_bytes()), "value2".as_bytes()).unwrap());
+        assert_eq!(Some("value2".to_string()), controller.get("key").unwrap());
+        assert!(controller.remove("key").unwrap());
+        assert!(!controller.compare_and_set("key".as_bytes(), Some("value2".as_bytes()), "value3".as_bytes()).unwrap());
+        assert_eq!(None, controller.get("key").unwrap());
+
+        // 多个线程同时从同一个value开始compare_and_set，只有一个能成功
+        controller.put("counter", "0").unwrap();
+        let swapped = thread::scope(|scope| {
+            let handles: Vec<_> = (0..8).map(|i| {
+                let controller = &controller;
+                scope.spawn(move || controller.compare_and_set("counter".as_bytes(), Some("0".as_bytes()), format!("{}", i + 1).as_bytes()).unwrap())
+            }).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).filter(|swapped| *swapped).count()
+        });
+        assert_eq!(1, swapped);
+        assert_ne!(Some("0".to_string()), controller.get("counter").unwrap());
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_io_stats() {
+        let dir_name = "controller_io_stats";
+        fs::create_dir_all(dir_name).unwrap();
+        let controller = create_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap().controller().unwrap();
+        for i in 0..300 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        let stats = controller.io_stats();
+        assert!(stats.split_count > 0);
+        assert!(stats.mapped_page_count > stats.split_count);
+        assert_eq!(0, stats.merge_count);
+        controller.flush().unwrap();
+        assert!(controller.io_stats().bytes_written >= PAGE_SIZE as u64);
+
+        // 重置后重复读取，节点都在页缓存中，不需要重新读取页
+        controller.reset_io_stats();
+        assert_eq!(IoStats::default(), controller.io_stats());
+        for _ in 0..10 {
+            assert_eq!(Some("value150".to_string()), controller.get("key150").unwrap());
+        }
+        let stats = controller.io_stats();
+        assert_eq!((0, 0, 0), (stats.leaf_page_read_count, stats.inner_page_read_count, stats.bytes_read));
+
+        for i in 0..300 {
+            controller.remove(&format!("key{:03}", i)).unwrap();
+        }
+        assert!(controller.io_stats().merge_count > 0);
+        drop(controller);
+
+        // 重新打开后从文件读取页
+        let controller = open_schema(dir_name, "test").unwrap().controller().unwrap();
+        assert_eq!(None, controller.get("key150").unwrap());
+        let stats = controller.io_stats();
+        assert!(stats.leaf_page_read_count > 0);
+        assert_eq!((stats.leaf_page_read_count + stats.inner_page_read_count) * PAGE_SIZE as u64, stats.bytes_read);
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_structural_change() {
+        let dir_name = "controller_structural_change";
+        let controller = create_test_controller(dir_name);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorder = events.clone();
+        controller.on_structural_change(Box::new(move |event| recorder.lock().unwrap().push(event)));
+        for i in 0..300 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        let recorded: Vec<StructuralEvent> = events.lock().unwrap().drain(..).collect();
+        let split_count = recorded.iter().filter(|event| matches!(event, StructuralEvent::Split { .. })).count();
+        assert_eq!(controller.io_stats().split_count as usize, split_count);
+        // 第一次分裂的是根叶节点，之后创建新的根节点
+        let StructuralEvent::Split { parent, left: 0, right, separator } = &recorded[0] else {
+            panic!("unexpected event {:?}", recorded[0]);
+        };
+        assert_eq!(StructuralEvent::RootChange { old: 0, new: *parent }, recorded[1]);
+        assert!(separator.starts_with(b"key"));
+        assert_ne!(0, *right);
+        // 内部节点也分裂，根节点再次改变
+        let root_changes = recorded.iter().filter(|event| matches!(event, StructuralEvent::RootChange { .. })).count();
+        assert!(root_changes >= 2);
+        let root_offset = controller.read().root_node.get_offset();
+        assert!(matches!(recorded.iter().rev().find(|event| matches!(event, StructuralEvent::RootChange { .. })), Some(StructuralEvent::RootChange { new, .. }) if *new == root_offset));
+
+        for i in 0..290 {
+            controller.remove(&format!("key{:03}", i)).unwrap();
+        }
+        let recorded: Vec<StructuralEvent> = events.lock().unwrap().drain(..).collect();
+        let merge_count = recorded.iter().filter(|event| matches!(event, StructuralEvent::Merge { .. })).count();
+        assert!(merge_count > 0);
+        assert_eq!(controller.io_stats().merge_count as usize, merge_count);
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_bloom_filter() {
+        let dir_name = "controller_bloom_filter";
+        fs::create_dir_all(dir_name).unwrap();
+        let mete_data = create_schema_with_options(dir_name, "bloom", PAGE_SIZE, 64, 128, CreateOptions { bloom_filter: true, ..CreateOptions::default() }).unwrap();
+        assert!(mete_data.has_bloom_filter());
+        let bloom = mete_data.controller().unwrap();
+        let plain = create_schema(dir_name, "plain", PAGE_SIZE, 64, 128).unwrap().controller().unwrap();
+        for controller in [&bloom, &plain] {
+            for i in 0..300 {
+                controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+            }
+            controller.remove("key100").unwrap();
+            controller.reset_io_stats();
+            for i in 0..300 {
+                assert_eq!(None, controller.get(&format!("absent{:03}", i)).unwrap());
+                assert!(!controller.contains_key(format!("key{:03}", i + 300).as_bytes()).unwrap());
+            }
+            assert_eq!(None, controller.get("key100").unwrap());
+            assert_eq!(Some(String::from("value299")), controller.get("key299").unwrap());
+        }
+        // 大部分不存在的key不需要在叶节点中二分查找
+        assert!(bloom.io_stats().bloom_filter_skip_count > 500, "{:?}", bloom.io_stats());
+        assert_eq!(0, plain.io_stats().bloom_filter_skip_count);
+        drop(bloom);
+        drop(plain);
+
+        let mete_data = open_schema(dir_name, "bloom").unwrap();
+        assert!(mete_data.has_bloom_filter());
+        let controller = mete_data.controller().unwrap();
+        controller.verify().unwrap();
+        for i in (0..300).filter(|i| *i != 100) {
+            assert_eq!(Some(format!("value{}", i)), controller.get(&format!("key{:03}", i)).unwrap());
+        }
+        assert_eq!(None, controller.get("absent").unwrap());
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_access_pattern() {
+        let dir_name = "controller_access_pattern";
+        let controller = create_test_controller(dir_name);
+        for i in 0..300 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        drop(controller);
+
+        for access_pattern in [AccessPattern::Random, AccessPattern::Sequential, AccessPattern::Normal] {
+            for read_only in [false, true] {
+                let options = SchemaOptions { read_only, access_pattern, ..SchemaOptions::default() };
+                let controller = open_schema_with_options(dir_name, "test", options).unwrap().controller().unwrap();
+                assert!(controller.stats().unwrap().leaf_page_count > 10);
+                for i in (0..300).step_by(7) {
+                    assert_eq!(Some(format!("value{}", i)), controller.get(&format!("key{:03}", i)).unwrap());
+                }
+                // 范围读取和遍历将读取的叶节点提示为顺序访问
+                assert_eq!(300, controller.scan("key000", "key300").unwrap().len());
+                assert_eq!(300, controller.iter().count());
+                assert_eq!(300, controller.iter_rev().count());
+                let (key_values, next_key) = controller.scan_from(Some(b"key100"), 50).unwrap();
+                assert_eq!((50, Some(b"key150".to_vec())), (key_values.len(), next_key));
+                if !read_only {
+                    controller.put("key300", "value300").unwrap();
+                    assert!(controller.remove("key300").unwrap());
+                }
+                drop(controller);
+            }
+        }
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_write_batch() {
+        let dir_name = "controller_write_batch";
+        fs::create_dir_all(dir_name).unwrap();
+        let put = |key: &str, value: &str| WriteOp::Put { key: key.as_bytes().to_vec(), value: value.as_bytes().to_vec() };
+        let delete = |key: &str| WriteOp::Delete { key: key.as_bytes().to_vec() };
+        // value都保存在叶节点中，超过页大小的value在写入时返回PageFull
+        for write_ahead_log in [true, false] {
+            let schema_name = if write_ahead_log { "wal" } else { "plain" };
+            let mut mete_data = create_schema(dir_name, schema_name, PAGE_SIZE, 64, u32::MAX).unwrap();
+            mete_data.set_write_ahead_log(write_ahead_log);
+            let controller = mete_data.controller().unwrap();
+            for i in 0..100 {
+                controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+            }
+            let expected: Vec<_> = controller.iter().map(|key_value| key_value.unwrap()).collect();
+            let height = controller.stats().unwrap().height;
+
+            // key不合法时不修改任何数据
+            let error = controller.write_batch(vec![put("key100", "value100"), delete("key000"), put(&"k".repeat(65), "value")]).unwrap_err();
+            assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+            assert_eq!(expected, controller.iter().map(|key_value| key_value.unwrap()).collect::<Vec<_>>());
+
+            let mut ops: Vec<_> = (100..400).map(|i| put(&format!("key{:03}", i), &format!("value{}", i))).collect();
+            ops.extend((0..50).map(|i| delete(&format!("key{:03}", i))));
+            ops.push(put("large", &"v".repeat(PAGE_SIZE as usize)));
+            ops.push(put("last", "value"));
+            let error = controller.write_batch(ops).unwrap_err();
+            assert_eq!(ErrorKind::PageFull, error_kind(error.as_ref()));
+            if write_ahead_log {
+                // 出错前的写入、删除和分裂全部回滚
+                assert_eq!(expected, controller.iter().map(|key_value| key_value.unwrap()).collect::<Vec<_>>());
+                assert_eq!(height, controller.stats().unwrap().height);
+                assert_eq!(100, controller.len().unwrap());
+                controller.verify().unwrap();
+            } else {
+                assert_eq!(None, controller.get("key000").unwrap());
+            }
+
+            controller.write_batch(vec![put("key000", "new"), delete("key001"), put("key500", "value500"), delete("key500"), put("key501", "value501"), delete("absent")]).unwrap();
+            assert_eq!(Some(String::from("new")), controller.get("key000").unwrap());
+            assert_eq!(None, controller.get("key001").unwrap());
+            assert_eq!(None, controller.get("key500").unwrap());
+            assert_eq!(Some(String::from("value501")), controller.get("key501").unwrap());
+            controller.write_batch(Vec::new()).unwrap();
+            drop(controller);
+
+            let controller = open_schema(dir_name, schema_name).unwrap().controller().unwrap();
+            controller.verify().unwrap();
+            assert_eq!(Some(String::from("new")), controller.get("key000").unwrap());
+            assert_eq!(Some(String::from("value501")), controller.get("key501").unwrap());
+            if write_ahead_log {
+                assert_eq!(100, controller.len().unwrap());
+                assert_eq!(None, controller.get("key100").unwrap());
+            }
+            drop(controller);
+        }
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_ttl() {
+        let dir_name = "controller_ttl";
+        fs::create_dir_all(dir_name).unwrap();
+        let mete_data = create_schema_with_options(dir_name, "ttl", PAGE_SIZE, 64, 128, CreateOptions { ttl: true, ..CreateOptions::default() }).unwrap();
+        assert!(mete_data.has_ttl());
+        let controller = mete_data.controller().unwrap();
+        let large_value = "large value".repeat(30);
+        for i in 0..100 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        // 偶数key在100ms后过期，key050的value保存在额外数据文件中
+        for i in (0..100).step_by(2) {
+            controller.put_with_ttl(format!("key{:03}", i).as_bytes(), format!("short{}", i).as_bytes(), Duration::from_millis(100)).unwrap();
+        }
+        controller.put_with_ttl(b"key050", large_value.as_bytes(), Duration::from_millis(100)).unwrap();
+        controller.put_with_ttl(b"key099", b"long", Duration::from_secs(3600)).unwrap();
+        assert_eq!(Some(String::from("short10")), controller.get("key010").unwrap());
+        assert_eq!(Some(large_value.clone().into_bytes()), controller.get_bytes(b"key050").unwrap());
+        assert_eq!(Some(6), controller.value_len(b"key001").unwrap());
+        assert_eq!(100, controller.iter().count());
+
+        thread::sleep(Duration::from_millis(150));
+        assert_eq!(None, controller.get("key010").unwrap());
+        assert_eq!(None, controller.get_bytes(b"key050").unwrap());
+        assert_eq!(None, controller.value_len(b"key010").unwrap());
+        assert!(controller.get_reader(b"key050").unwrap().is_none());
+        assert!(!controller.contains_key(b"key010").unwrap());
+        assert_eq!(vec![None, Some(b"value11".to_vec())], controller.get_many(&[b"key010", b"key011"]).unwrap());
+        assert_eq!(Some(String::from("long")), controller.get("key099").unwrap());
+        // 遍历和范围读取跳过过期的key，len在清除之前仍然包括过期的key
+        let keys: Vec<_> = controller.iter().map(|key_value| key_value.unwrap().0).collect();
+        assert_eq!((0..100).filter(|i| i % 2 == 1 || *i == 99).count(), keys.len());
+        assert!(keys.iter().all(|key| key == b"key099" || key[5] % 2 == 1));
+        assert_eq!(50, controller.keys().count());
+        assert_eq!(vec![(String::from("key011"), String::from("value11"))], controller.scan("key010", "key012").unwrap());
+        assert_eq!(Some((b"key011".to_vec(), b"value11".to_vec())), controller.ceiling(b"key010").unwrap());
+        assert_eq!(Some((b"key009".to_vec(), b"value9".to_vec())), controller.floor(b"key010").unwrap());
+        assert_eq!(100, controller.len().unwrap());
+        // 过期的key可以重新写入
+        assert!(!controller.remove("key012").unwrap());
+        assert!(controller.insert(b"key020", b"again").unwrap());
+        assert_eq!(b"merged".to_vec(), controller.merge(b"key022", b"merged", |current, operand| [current.unwrap_or_default(), operand].concat()).unwrap());
+
+        assert_eq!(47, controller.purge_expired().unwrap());
+        assert_eq!(0, controller.purge_expired().unwrap());
+        assert_eq!(52, controller.len().unwrap());
+        controller.verify().unwrap();
+        drop(controller);
+
+        let mete_data = open_schema(dir_name, "ttl").unwrap();
+        assert!(mete_data.has_ttl());
+        let controller = mete_data.controller().unwrap();
+        assert_eq!(Some(String::from("long")), controller.get("key099").unwrap());
+        assert_eq!(Some(String::from("again")), controller.get("key020").unwrap());
+        assert_eq!(52, controller.iter().count());
+        drop(controller);
+
+        // 不支持过期时间的schema返回InvalidArgument
+        let controller = create_schema(dir_name, "plain", PAGE_SIZE, 64, 128).unwrap().controller().unwrap();
+        let error = controller.put_with_ttl(b"key", b"value", Duration::from_secs(1)).unwrap_err();
+        assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+        assert_eq!(0, controller.purge_expired().unwrap());
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_value_threshold() {
+        let dir_name = "controller_value_threshold";
+        fs::create_dir_all(dir_name).unwrap();
+        let large_value = "large value".repeat(100);
+
+        // value_threshold为0时所有value都写入额外数据文件，value可以超过页大小
+        for (schema_name, value_threshold) in [("always", 0), ("never", u32::MAX)] {
+            let controller = create_schema(dir_name, schema_name, PAGE_SIZE, 64, value_threshold).unwrap().controller().unwrap();
+            for i in 0..100 {
+                controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+            }
+            controller.put("empty", "").unwrap();
+            assert!(controller.remove("key050").unwrap());
+            for i in (0..100).filter(|i| *i != 50) {
+                assert_eq!(Some(format!("value{}", i)), controller.get(&format!("key{:03}", i)).unwrap());
+            }
+            assert_eq!(None, controller.get("key050").unwrap());
+            assert_eq!(Some(String::new()), controller.get("empty").unwrap());
+            let result = controller.scan("key010", "key012").unwrap();
+            assert_eq!(vec![("key010".to_string(), "value10".to_string()), ("key011".to_string(), "value11".to_string())], result);
+            assert_eq!(Some(("empty".as_bytes().to_vec(), Vec::new())), controller.iter().next().map(|key_value| key_value.unwrap()));
+
+            let extra_path = format!("{}/{}.e", dir_name, schema_name);
+            // 额外数据文件开头为8字节的空闲块链表头，没有value写入额外数据文件时不会创建
+            if value_threshold == 0 {
+                assert!(fs::metadata(&extra_path).unwrap().len() > 8);
+                controller.put("large", &large_value).unwrap();
+                assert_eq!(Some(large_value.clone()), controller.get("large").unwrap());
+            } else {
+                assert!(!fs::exists(&extra_path).unwrap());
+                let error = controller.put("large", &large_value).err().unwrap();
+                assert_eq!(ErrorKind::PageFull, error_kind(error.as_ref()));
+            }
+        }
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_lazy_extra_file() {
+        let dir_name = "controller_lazy_extra_file";
+        let controller = create_test_controller(dir_name);
+        let extra_path = format!("{}/test.e", dir_name);
+        for i in 0..100 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        assert!(controller.remove("key050").unwrap());
+        controller.flush().unwrap();
+        controller.verify().unwrap();
+        assert!(!fs::exists(&extra_path).unwrap());
+        drop(controller);
+
+        // 额外数据文件不存在时也可以只读打开
+        let controller = open_schema_read_only(dir_name, "test").unwrap().controller().unwrap();
+        assert_eq!(99, controller.len().unwrap());
+        controller.verify().unwrap();
+        drop(controller);
+
+        // 第一次写入较长的value时创建
+        let controller = reopen_test_controller(dir_name);
+        assert!(!fs::exists(&extra_path).unwrap());
+        controller.put("large", &"v".repeat(200)).unwrap();
+        assert!(fs::exists(&extra_path).unwrap());
+        drop(controller);
+        let controller = reopen_test_controller(dir_name);
+        assert_eq!(Some("v".repeat(200)), controller.get("large").unwrap());
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_overflow_chunks() {
+        let dir_name = "controller_overflow_chunks";
+        fs::create_dir_all(dir_name).unwrap();
+        let controller = create_schema(dir_name, "test", PAGE_SIZE, 64, 64).unwrap().controller().unwrap();
+        let extra_path = format!("{}/test.e", dir_name);
+        // value超过多个页大小时写入多个块组成的链表
+        let value: Vec<u8> = (0..PAGE_SIZE as usize * 5 + 17).map(|i| (i * 31 % 251) as u8).collect();
+        controller.put_bytes("large".as_bytes(), &value).unwrap();
+        controller.put_bytes("small".as_bytes(), "small".as_bytes()).unwrap();
+        assert_eq!(Some(value.clone()), controller.get_bytes("large".as_bytes()).unwrap());
+        controller.flush().unwrap();
+        let extra_length = fs::metadata(&extra_path).unwrap().len();
+        assert!(extra_length > 8 + PAGE_SIZE as u64 * 5);
+
+        // 删除后flush释放块，再次写入时复用释放的块
+        assert!(controller.remove_bytes("large".as_bytes()).unwrap());
+        assert_eq!(None, controller.get_bytes("large".as_bytes()).unwrap());
+        controller.flush().unwrap();
+        let value: Vec<u8> = value.iter().rev().copied().collect();
+        controller.put_bytes("other".as_bytes(), &value).unwrap();
+        assert_eq!(extra_length, fs::metadata(&extra_path).unwrap().len());
+        assert_eq!(Some(value.clone()), controller.get_bytes("other".as_bytes()).unwrap());
+
+        // 覆盖时释放原来的块，flush之后下一次覆盖复用，文件不再增长
+        let overwrite = |round: usize| {
+            let value: Vec<u8> = value.iter().map(|byte| byte.wrapping_add(round as u8)).collect();
+            match round % 4 {
+                0 => controller.put_bytes("other".as_bytes(), &value).unwrap(),
+                1 => { controller.merge("other".as_bytes(), &value, |_, operand| operand.to_vec()).unwrap(); }
+                2 => assert!(controller.compare_and_set("other".as_bytes(), controller.get_bytes("other".as_bytes()).unwrap().as_deref(), &value).unwrap()),
+                _ => assert_eq!((0, 1), controller.put_batch(&[("other".as_bytes(), &value)]).unwrap()),
+            }
+            controller.flush().unwrap();
+            value
+        };
+        overwrite(0);
+        let overwritten_length = fs::metadata(&extra_path).unwrap().len();
+        for round in 1..9 {
+            let value = overwrite(round);
+            assert_eq!(overwritten_length, fs::metadata(&extra_path).unwrap().len());
+            assert_eq!(Some(value), controller.get_bytes("other".as_bytes()).unwrap());
+        }
+        let value = overwrite(9);
+        controller.verify().unwrap();
+        drop(controller);
+
+        let controller = open_schema(dir_name, "test").unwrap().controller().unwrap();
+        assert_eq!(Some(value), controller.get_bytes("other".as_bytes()).unwrap());
+        assert_eq!(Some("small".as_bytes().to_vec()), controller.get_bytes("small".as_bytes()).unwrap());
+        drop(controller);
+
+        delete_test_dir(dir_name)
+    }
+
+    #[test]
+    fn controller_page_cache() {
+        let dir_name = "controller_page_cache";
+        fs::create_dir_all(dir_name).unwrap();
+        let mut mete_data = create_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap();
+        mete_data.set_page_cache_capacity(4).unwrap();
+        let controller = mete_data.controller().unwrap();
+
+        for i in 0..300 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        for i in (0..300).step_by(3) {
+            controller.remove(&format!("key{:03}", i)).unwrap();
+        }
+        for i in 0..300 {
+            let expect = if i % 3 == 0 { None } else { Some(format!("value{}", i)) };
+            assert_eq!(expect, controller.get(&format!("key{:03}", i)).unwrap());
+        }
+        assert_eq!(200, controller.iter().count());
+        assert!(controller.stats().unwrap().cached_page_count <= 4);
+        controller.write().mete_data.set_page_cache_capacity(0).unwrap();
+        assert_eq!(0, controller.stats().unwrap().cached_page_count);
+        assert_eq!(Some("value1".to_string()), controller.get("key001").unwrap());
+        controller.flush().unwrap();
+
+        delete_test_dir(dir_name)
+    }
+}
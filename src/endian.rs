@@ -0,0 +1,57 @@
+use crate::{ErrorKind, MiniBaseError, MiniBaseResult};
+
+// 数据页和额外数据文件中整数的字节序，创建schema时指定并保存在元数据文件中，与运行的平台无关
+// 元数据页和单文件的区域表总是小端序，打开时先读取其中记录的字节序
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum Endian {
+    #[default]
+    Little,
+    Big,
+}
+
+impl Endian {
+    pub(crate) fn read_u32(self, data: &[u8]) -> u32 {
+        let data = data.try_into().unwrap();
+        match self {
+            Endian::Little => u32::from_le_bytes(data),
+            Endian::Big => u32::from_be_bytes(data),
+        }
+    }
+
+    pub(crate) fn read_u64(self, data: &[u8]) -> u64 {
+        let data = data.try_into().unwrap();
+        match self {
+            Endian::Little => u64::from_le_bytes(data),
+            Endian::Big => u64::from_be_bytes(data),
+        }
+    }
+
+    pub(crate) fn u32_bytes(self, value: u32) -> [u8; 4] {
+        match self {
+            Endian::Little => value.to_le_bytes(),
+            Endian::Big => value.to_be_bytes(),
+        }
+    }
+
+    pub(crate) fn u64_bytes(self, value: u64) -> [u8; 8] {
+        match self {
+            Endian::Little => value.to_le_bytes(),
+            Endian::Big => value.to_be_bytes(),
+        }
+    }
+
+    pub(crate) fn to_tag(self) -> u32 {
+        match self {
+            Endian::Little => 0,
+            Endian::Big => 1,
+        }
+    }
+
+    pub(crate) fn from_tag(tag: u32) -> MiniBaseResult<Endian> {
+        match tag {
+            0 => Ok(Endian::Little),
+            1 => Ok(Endian::Big),
+            _ => Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("endian tag {} invalid", tag)))),
+        }
+    }
+}
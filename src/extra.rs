@@ -0,0 +1,529 @@
+use std::collections::HashSet;
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::{ErrorKind, MiniBaseError, MiniBaseResult};
+use crate::endian::Endian;
+use crate::storage::Storage;
+
+// 叶节点中value数据的第一个字节，表示value的保存方式
+const INLINE_VALUE: u8 = 0;
+const OVERFLOW_VALUE: u8 = 1;
+// 设置了过期时间的value，之后是u64的过期时间（自UNIX_EPOCH起的毫秒数），再之后是INLINE_VALUE或OVERFLOW_VALUE开头的value数据
+const EXPIRING_VALUE: u8 = 2;
+const EXPIRY_LENGTH: usize = 8;
+
+// chunk偏移的空值，表示链表结束
+pub(crate) const NULL_CHUNK: u64 = u64::MAX;
+// 文件头，保存空闲链表头部的chunk偏移
+const FREE_CHUNK_HEAD: u64 = 0;
+const HEADER_LENGTH: u64 = 8;
+// chunk开头保存下一个chunk的偏移，之后是value的数据
+const CHUNK_HEADER_LENGTH: usize = 8;
+
+// 第一个chunk移动了的value在compact参数中的位置，以及指向新位置的value数据
+pub(crate) type MovedValues = Vec<(usize, Vec<u8>)>;
+
+// 叶节点中value数据的解析结果
+pub(crate) enum StoredValue {
+    Inline(Vec<u8>),
+    // 第一个chunk的偏移和value长度
+    Chunks(u64, u32),
+}
+
+// 额外数据文件，保存长度达到value_threshold的value
+// 文件由固定大小的chunk组成，每个value保存在一个chunk链表中，value的长度保存在叶节点中，可以超过一个chunk
+// 删除的value占用的chunk放入空闲链表，分配chunk时优先复用空闲链表中的chunk
+// 只有小value的schema不需要额外数据文件，文件在第一次写入value时才创建，之前不存在
+pub(crate) struct ExtraFile {
+    file: OnceLock<Storage>,
+    // 文件不存在时创建文件的路径，内存模式下文件已经存在，为None
+    path: Option<String>,
+    chunk_size: u32,
+    // chunk头和叶节点中value指针的字节序，与数据文件相同
+    endian: Endian,
+    // 已经删除但还不能复用的chunk，启用预写日志时恢复可能回滚到删除之前，因此只有flush之后才放入空闲链表
+    freed_chunks: Vec<u64>,
+}
+
+impl ExtraFile {
+    pub(crate) fn new(file: Storage, chunk_size: u32) -> MiniBaseResult<ExtraFile> {
+        init_header(&file)?;
+        let endian = file.endian();
+        Ok(ExtraFile { file: OnceLock::from(file), path: None, chunk_size, endian, freed_chunks: Vec::new() })
+    }
+
+    // 打开path处的额外数据文件，文件不存在时在第一次写入value时创建
+    pub(crate) fn open(path: &str, read_only: bool, chunk_size: u32, endian: Endian) -> MiniBaseResult<ExtraFile> {
+        if !Path::new(path).exists() {
+            return Ok(ExtraFile { file: OnceLock::new(), path: Some(path.to_string()), chunk_size, endian, freed_chunks: Vec::new() });
+        }
+        let file = if read_only {
+            OpenOptions::new().read(true).open(path)?
+        } else {
+            OpenOptions::new().read(true).write(true).open(path)?
+        };
+        ExtraFile::new(Storage::from(file).with_endian(endian), chunk_size)
+    }
+
+    // 读取时文件不存在说明叶节点中的value数据指向了不存在的chunk
+    fn existing_file(&self) -> MiniBaseResult<&Storage> {
+        self.file.get().ok_or_else(|| Box::from(MiniBaseError::new(ErrorKind::Corruption, "extra value references missing extra file")))
+    }
+
+    // 写入时文件不存在则创建，写操作持有写锁，不会同时创建
+    fn writable_file(&self) -> MiniBaseResult<&Storage> {
+        if let Some(file) = self.file.get() {
+            return Ok(file);
+        }
+        let path = self.path.as_ref().unwrap();
+        let file = Storage::from(OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?).with_endian(self.endian);
+        init_header(&file)?;
+        Ok(self.file.get_or_init(|| file))
+    }
+
+    // 将value编码为写入叶节点的数据，长度达到value_threshold的value写入额外数据文件，叶节点中只保存第一个chunk的u64偏移和value的u32长度
+    // value_threshold为0时所有value都写入额外数据文件，为u32::MAX时所有value都保存在叶节点中
+    pub(crate) fn encode_value(&self, value: &[u8], value_threshold: u32) -> MiniBaseResult<Vec<u8>> {
+        let mut data = Vec::new();
+        if value.len() as u64 >= value_threshold as u64 {
+            let offset = self.write_chunks(value)?;
+            return Ok(self.encode_pointer(offset, value.len() as u32));
+        }
+        data.push(INLINE_VALUE);
+        data.extend_from_slice(value);
+        Ok(data)
+    }
+
+    // 保存在额外数据文件中的value在叶节点中的数据，offset为第一个chunk的偏移
+    pub(crate) fn encode_pointer(&self, offset: u64, size: u32) -> Vec<u8> {
+        let mut data = vec![OVERFLOW_VALUE];
+        data.extend_from_slice(&self.endian.u64_bytes(offset));
+        data.extend_from_slice(&self.endian.u32_bytes(size));
+        data
+    }
+
+    // 读取叶节点中保存的value数据对应的value
+    pub(crate) fn decode_value(&self, data: &[u8]) -> MiniBaseResult<Vec<u8>> {
+        let data = self.strip_expiry(data)?;
+        match self.decode_pointer(data)? {
+            None => Ok(Vec::from(&data[1..])),
+            Some((offset, size)) => {
+                let mut value = Vec::with_capacity(size as usize);
+                self.walk_chunks(offset, size, |_, data| value.extend_from_slice(data))?;
+                Ok(value)
+            }
+        }
+    }
+
+    // 保存在叶节点中的value直接返回，保存在额外数据文件中时返回第一个chunk的偏移和value长度，由调用方通过read_chunk逐个读取
+    pub(crate) fn stored_value(&self, data: &[u8]) -> MiniBaseResult<StoredValue> {
+        let data = self.strip_expiry(data)?;
+        match self.decode_pointer(data)? {
+            None => Ok(StoredValue::Inline(Vec::from(&data[1..]))),
+            Some((offset, size)) => Ok(StoredValue::Chunks(offset, size)),
+        }
+    }
+
+    // 叶节点中保存的value数据对应的value长度，不读取额外数据文件
+    pub(crate) fn value_length(&self, data: &[u8]) -> MiniBaseResult<u32> {
+        let data = self.strip_expiry(data)?;
+        match self.decode_pointer(data)? {
+            None => Ok((data.len() - 1) as u32),
+            Some((_, size)) => Ok(size),
+        }
+    }
+
+    // 检查叶节点中保存的value数据是否合法，指向额外数据文件时检查chunk链表位于文件内且长度一致
+    pub(crate) fn verify_value(&self, data: &[u8]) -> MiniBaseResult<()> {
+        match self.decode_pointer(data)? {
+            None => Ok(()),
+            Some((offset, size)) => self.walk_chunks(offset, size, |_, _| {}),
+        }
+    }
+
+    // value_threshold修改后，value数据的保存方式与新的value_threshold不一致时需要重新编码
+    pub(crate) fn needs_reencode(&self, data: &[u8], value_threshold: u32) -> MiniBaseResult<bool> {
+        let data = self.strip_expiry(data)?;
+        match self.decode_pointer(data)? {
+            None => Ok((data.len() - 1) as u64 >= value_threshold as u64),
+            Some((_, size)) => Ok((size as u64) < value_threshold as u64),
+        }
+    }
+
+    // 按新的value_threshold重新编码value数据，保留原有的过期时间，原来的value数据需要调用方通过free_value释放
+    pub(crate) fn reencode_value(&self, data: &[u8], value_threshold: u32) -> MiniBaseResult<Vec<u8>> {
+        let value = self.decode_value(data)?;
+        let new_data = self.encode_value(&value, value_threshold)?;
+        Ok(self.with_expiry(self.get_expiry(data)?, new_data))
+    }
+
+    // 在value数据前加上过期时间，expiry为None时不修改
+    pub(crate) fn with_expiry(&self, expiry: Option<u64>, data: Vec<u8>) -> Vec<u8> {
+        let Some(expiry) = expiry else {
+            return data;
+        };
+        let mut expiring_data = vec![EXPIRING_VALUE];
+        expiring_data.extend_from_slice(&self.endian.u64_bytes(expiry));
+        expiring_data.extend(data);
+        expiring_data
+    }
+
+    // value数据中的过期时间，没有设置过期时间时返回None
+    pub(crate) fn get_expiry(&self, data: &[u8]) -> MiniBaseResult<Option<u64>> {
+        match data.split_first() {
+            Some((&EXPIRING_VALUE, expiring_data)) if expiring_data.len() > EXPIRY_LENGTH => Ok(Some(self.endian.read_u64(&expiring_data[..EXPIRY_LENGTH]))),
+            Some((&EXPIRING_VALUE, _)) => Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, "value data invalid"))),
+            _ => Ok(None),
+        }
+    }
+
+    // value是否已经过期，过期时间不合法时返回false，由读取value时返回错误
+    pub(crate) fn is_expired(&self, data: &[u8]) -> bool {
+        self.get_expiry(data).ok().flatten().is_some_and(|expiry| expiry <= now_millis())
+    }
+
+    // 去掉value数据开头的过期时间
+    fn strip_expiry<'d>(&self, data: &'d [u8]) -> MiniBaseResult<&'d [u8]> {
+        match self.get_expiry(data)? {
+            Some(_) => Ok(&data[1 + EXPIRY_LENGTH..]),
+            None => Ok(data),
+        }
+    }
+
+    // 删除value，value占用的chunk在flush之后才会复用
+    pub(crate) fn free_value(&mut self, data: &[u8]) -> MiniBaseResult<()> {
+        if let Some((offset, size)) = self.decode_pointer(data)? {
+            let mut chunks = Vec::new();
+            self.walk_chunks(offset, size, |chunk, _| chunks.push(chunk))?;
+            self.freed_chunks.append(&mut chunks);
+        }
+        Ok(())
+    }
+
+    // 释放已经分配但还没有被叶节点引用的chunk，例如流式写入value失败时，同样在flush之后才会复用
+    pub(crate) fn free_chunks(&mut self, mut chunks: Vec<u64>) {
+        self.freed_chunks.append(&mut chunks);
+    }
+
+    // value数据是否指向额外数据文件
+    pub(crate) fn is_extra_value(&self, data: &[u8]) -> MiniBaseResult<bool> {
+        Ok(self.decode_pointer(data)?.is_some())
+    }
+
+    // 将文件末尾仍然被引用的chunk移动到前部的空闲位置，values为叶节点中所有指向额外数据文件的value数据
+    // 返回整理后的文件长度，以及第一个chunk移动了的value在values中的位置和新的value数据，文件已经足够紧凑时返回None
+    // 移动期间不修改被引用的chunk中的数据，只复制到空闲的chunk，叶节点中的指针更新并写入磁盘之后才能通过truncate截断文件
+    pub(crate) fn compact(&self, values: &[Vec<u8>]) -> MiniBaseResult<Option<(u64, MovedValues)>> {
+        let Some(file) = self.file.get() else {
+            return Ok(None);
+        };
+        let mut chains = Vec::with_capacity(values.len());
+        for data in values {
+            let Some((offset, size)) = self.decode_pointer(data)? else {
+                return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, "value data not stored in extra file")));
+            };
+            let mut chunks = Vec::new();
+            self.walk_chunks(offset, size, |chunk, _| chunks.push(chunk))?;
+            chains.push((size, chunks));
+        }
+        let chunk_size = self.chunk_size as u64;
+        let live_count: usize = chains.iter().map(|(_, chunks)| chunks.len()).sum();
+        let compacted_length = HEADER_LENGTH + live_count as u64 * chunk_size;
+        if file.len()? <= compacted_length {
+            return Ok(None);
+        }
+        let live: HashSet<u64> = chains.iter().flat_map(|(_, chunks)| chunks.iter().copied()).collect();
+        let mut holes = (HEADER_LENGTH..compacted_length).step_by(chunk_size as usize).filter(|chunk| !live.contains(chunk));
+        // 空闲链表保存在空闲的chunk中，写入前先清空，中途崩溃时空闲的chunk只是不再复用，下次整理时回收
+        file.write_all_at(&self.endian.u64_bytes(NULL_CHUNK), FREE_CHUNK_HEAD)?;
+        file.sync_all()?;
+        let mut moved = Vec::new();
+        let mut buffer = vec![0; self.chunk_size as usize];
+        for (index, (size, chunks)) in chains.iter().enumerate() {
+            if chunks.iter().all(|chunk| *chunk < compacted_length) {
+                continue;
+            }
+            let new_chunks: Vec<u64> = chunks.iter()
+                .map(|chunk| if *chunk < compacted_length { *chunk } else { holes.next().unwrap() })
+                .collect();
+            // 从最后一个chunk开始写入，任何时候从第一个chunk开始的链表都是完整的
+            for position in (0..chunks.len()).rev() {
+                file.read_exact_at(&mut buffer, chunks[position])?;
+                let next_chunk = new_chunks.get(position + 1).copied().unwrap_or(NULL_CHUNK);
+                buffer[..CHUNK_HEADER_LENGTH].copy_from_slice(&self.endian.u64_bytes(next_chunk));
+                file.write_all_at(&buffer, new_chunks[position])?;
+            }
+            if new_chunks[0] != chunks[0] {
+                let pointer = self.encode_pointer(new_chunks[0], *size);
+                moved.push((index, self.with_expiry(self.get_expiry(&values[index])?, pointer)));
+            }
+        }
+        file.sync_all()?;
+        Ok(Some((compacted_length, moved)))
+    }
+
+    // 截断compact整理后的文件，返回释放的字节数
+    pub(crate) fn truncate(&mut self, length: u64) -> MiniBaseResult<u64> {
+        let file = self.existing_file()?;
+        let file_length = file.len()?;
+        file.set_len(length)?;
+        file.sync_all()?;
+        Ok(file_length - length)
+    }
+
+    // 解析叶节点中的value数据，保存在额外数据文件中时返回第一个chunk的偏移和value长度
+    fn decode_pointer(&self, data: &[u8]) -> MiniBaseResult<Option<(u64, u32)>> {
+        match self.strip_expiry(data)?.split_first() {
+            Some((&INLINE_VALUE, _)) => Ok(None),
+            Some((&OVERFLOW_VALUE, pointer)) if pointer.len() == 12 => {
+                let offset = self.endian.read_u64(&pointer[..8]);
+                let size = self.endian.read_u32(&pointer[8..]);
+                Ok(Some((offset, size)))
+            }
+            _ => Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, "value data invalid"))),
+        }
+    }
+
+    // 每个chunk能够保存的value数据长度
+    pub(crate) fn get_chunk_capacity(&self) -> usize {
+        self.chunk_size as usize - CHUNK_HEADER_LENGTH
+    }
+
+    // 保存size长度的value需要的chunk数量，空value也占用一个chunk
+    fn get_chunk_count(&self, size: u32) -> usize {
+        (size as usize).div_ceil(self.get_chunk_capacity()).max(1)
+    }
+
+    // 将value写入新分配的chunk链表，返回第一个chunk的偏移
+    fn write_chunks(&self, value: &[u8]) -> MiniBaseResult<u64> {
+        let chunk_count = self.get_chunk_count(value.len() as u32);
+        let mut chunks = Vec::with_capacity(chunk_count);
+        for _ in 0..chunk_count {
+            chunks.push(self.allocate_chunk()?);
+        }
+        let mut data = value.chunks(self.get_chunk_capacity());
+        for (index, chunk) in chunks.iter().enumerate() {
+            let next_chunk = chunks.get(index + 1).copied().unwrap_or(NULL_CHUNK);
+            self.write_chunk(*chunk, next_chunk, data.next().unwrap_or(&[]))?;
+        }
+        Ok(chunks[0])
+    }
+
+    // 写入已经分配的chunk，data的长度不超过chunk的容量
+    pub(crate) fn write_chunk(&self, chunk: u64, next_chunk: u64, data: &[u8]) -> MiniBaseResult<()> {
+        // 写入完整的chunk，保证在文件末尾追加的chunk之后文件长度仍然对齐
+        let mut buffer = vec![0; self.chunk_size as usize];
+        buffer[..CHUNK_HEADER_LENGTH].copy_from_slice(&self.endian.u64_bytes(next_chunk));
+        buffer[CHUNK_HEADER_LENGTH..CHUNK_HEADER_LENGTH + data.len()].copy_from_slice(data);
+        self.writable_file()?.write_all_at(&buffer, chunk)
+    }
+
+    // 分配chunk，优先复用空闲链表中的chunk，空闲链表为空时在文件末尾追加
+    pub(crate) fn allocate_chunk(&self) -> MiniBaseResult<u64> {
+        let file = self.writable_file()?;
+        let free_chunk_head = self.read_u64(FREE_CHUNK_HEAD)?;
+        if free_chunk_head != NULL_CHUNK {
+            let next_free_chunk = self.read_u64(free_chunk_head)?;
+            file.write_all_at(&self.endian.u64_bytes(next_free_chunk), FREE_CHUNK_HEAD)?;
+            return Ok(free_chunk_head);
+        }
+        let file_length = file.len()?;
+        file.set_len(file_length + self.chunk_size as u64)?;
+        Ok(file_length)
+    }
+
+    // 依次访问value的chunk链表中的每个chunk及其中的value数据，检查chunk位于文件内且链表长度与value长度一致
+    fn walk_chunks<F>(&self, offset: u64, size: u32, mut visit: F) -> MiniBaseResult<()>
+        where F: FnMut(u64, &[u8]) {
+        let mut remaining = size;
+        let mut chunk = offset;
+        let mut buffer = Vec::with_capacity(self.chunk_size as usize);
+        for _ in 0..self.get_chunk_count(size) {
+            let next_chunk = self.read_chunk(chunk, remaining, &mut buffer)?;
+            visit(chunk, &buffer);
+            remaining -= buffer.len() as u32;
+            chunk = next_chunk;
+        }
+        if chunk != NULL_CHUNK {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("extra value at offset {} size {} has more chunks than expected", offset, size))));
+        }
+        Ok(())
+    }
+
+    // 将chunk中的value数据读取到buffer，remaining为value还未读取的长度，返回下一个chunk的偏移
+    pub(crate) fn read_chunk(&self, chunk: u64, remaining: u32, buffer: &mut Vec<u8>) -> MiniBaseResult<u64> {
+        let file = self.existing_file()?;
+        let file_length = file.len()?;
+        if chunk < HEADER_LENGTH || chunk.checked_add(self.chunk_size as u64).is_none_or(|end| end > file_length) {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("extra value chunk at offset {} exceeds file length {}", chunk, file_length))));
+        }
+        buffer.resize(self.chunk_size as usize, 0);
+        file.read_exact_at(buffer, chunk)?;
+        let next_chunk = self.endian.read_u64(&buffer[..CHUNK_HEADER_LENGTH]);
+        let length = (remaining as usize).min(self.get_chunk_capacity());
+        buffer.copy_within(CHUNK_HEADER_LENGTH..CHUNK_HEADER_LENGTH + length, 0);
+        buffer.truncate(length);
+        Ok(next_chunk)
+    }
+
+    fn read_u64(&self, offset: u64) -> MiniBaseResult<u64> {
+        let mut data = [0; 8];
+        self.existing_file()?.read_exact_at(&mut data, offset)?;
+        Ok(self.endian.read_u64(&data))
+    }
+
+    pub(crate) fn flush(&self) -> MiniBaseResult<()> {
+        if let Some(file) = self.file.get() {
+            file.sync_all()?;
+        }
+        Ok(())
+    }
+
+    // 删除所有value，文件只保留空的空闲链表，文件还不存在时不需要创建
+    pub(crate) fn clear(&mut self) -> MiniBaseResult<()> {
+        self.freed_chunks.clear();
+        if let Some(file) = self.file.get() {
+            file.set_len(0)?;
+            init_header(file)?;
+            file.sync_all()?;
+        }
+        Ok(())
+    }
+
+    // 删除value的操作被回滚，value占用的chunk仍然被引用，不能放入空闲链表
+    pub(crate) fn discard_freed_chunks(&mut self) {
+        self.freed_chunks.clear();
+    }
+
+    // 数据文件中已经不再引用删除的value后，将其占用的chunk放入空闲链表
+    pub(crate) fn release_freed_chunks(&mut self) -> MiniBaseResult<()> {
+        if self.freed_chunks.is_empty() {
+            return Ok(());
+        }
+        let mut free_chunk_head = self.read_u64(FREE_CHUNK_HEAD)?;
+        // 已经读取到空闲链表头部，文件一定存在
+        let file = self.file.get().unwrap();
+        for chunk in self.freed_chunks.drain(..) {
+            file.write_all_at(&self.endian.u64_bytes(free_chunk_head), chunk)?;
+            free_chunk_head = chunk;
+        }
+        file.write_all_at(&self.endian.u64_bytes(free_chunk_head), FREE_CHUNK_HEAD)?;
+        file.sync_all()?;
+        Ok(())
+    }
+}
+
+// 当前时间，自UNIX_EPOCH起的毫秒数，用于计算和比较value的过期时间
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_millis() as u64)
+}
+
+// 新文件开头写入空的空闲链表
+fn init_header(file: &Storage) -> MiniBaseResult<()> {
+    if file.len()? == 0 {
+        file.write_all_at(&file.endian().u64_bytes(NULL_CHUNK), FREE_CHUNK_HEAD)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::fs::OpenOptions;
+    use crate::extra::{now_millis, ExtraFile};
+    use crate::storage::Storage;
+
+    const CHUNK_SIZE: u32 = 64;
+
+    #[test]
+    fn extra_file_encode_decode() {
+        let file_name = "extra_file_encode_decode";
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(file_name).unwrap();
+        let extra_file = ExtraFile::new(Storage::from(file), CHUNK_SIZE).unwrap();
+
+        // 长度小于value_threshold的value保存在叶节点中
+        let data = extra_file.encode_value("short".as_bytes(), 8).unwrap();
+        assert_eq!("\0short".as_bytes(), &data[..]);
+        assert_eq!("short".as_bytes(), &extra_file.decode_value(&data).unwrap()[..]);
+
+        // 1000字节的value需要18个chunk
+        let long_value = "long value".repeat(100);
+        let data = extra_file.encode_value(long_value.as_bytes(), 8).unwrap();
+        assert_eq!(13, data.len());
+        assert_eq!(long_value.as_bytes(), &extra_file.decode_value(&data).unwrap()[..]);
+        let empty = extra_file.encode_value(&[], 0).unwrap();
+        assert_eq!(13, empty.len());
+        assert!(extra_file.decode_value(&empty).unwrap().is_empty());
+        assert_eq!(8 + 19 * CHUNK_SIZE as u64, fs::metadata(file_name).unwrap().len());
+
+        assert!(extra_file.decode_value(&[]).is_err());
+        assert!(extra_file.decode_value(&[1, 0, 0]).is_err());
+
+        fs::remove_file(file_name).unwrap()
+    }
+
+    #[test]
+    fn extra_file_expiry() {
+        let file_name = "extra_file_expiry";
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(file_name).unwrap();
+        let extra_file = ExtraFile::new(Storage::from(file), CHUNK_SIZE).unwrap();
+
+        let data = extra_file.encode_value("short".as_bytes(), 8).unwrap();
+        assert_eq!(None, extra_file.get_expiry(&data).unwrap());
+        assert!(!extra_file.is_expired(&data));
+        let expiring = extra_file.with_expiry(Some(now_millis() + 60_000), data.clone());
+        assert_eq!(data.len() + 9, expiring.len());
+        assert!(!extra_file.is_expired(&expiring));
+        assert_eq!("short".as_bytes(), &extra_file.decode_value(&expiring).unwrap()[..]);
+        assert_eq!(5, extra_file.value_length(&expiring).unwrap());
+        let expired = extra_file.with_expiry(Some(now_millis() - 1), data);
+        assert!(extra_file.is_expired(&expired));
+
+        // 重新编码到额外数据文件后保留过期时间
+        let reencoded = extra_file.reencode_value(&expired, 0).unwrap();
+        assert!(extra_file.is_expired(&reencoded));
+        assert!(extra_file.is_extra_value(&reencoded).unwrap());
+        assert_eq!("short".as_bytes(), &extra_file.decode_value(&reencoded).unwrap()[..]);
+
+        assert!(extra_file.decode_value(&[2, 0, 0]).is_err());
+        assert!(extra_file.decode_value(&expired[..9]).is_err());
+
+        fs::remove_file(file_name).unwrap()
+    }
+
+    #[test]
+    fn extra_file_chunk_chain() {
+        let file_name = "extra_file_chunk_chain";
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(file_name).unwrap();
+        let mut extra_file = ExtraFile::new(Storage::from(file), CHUNK_SIZE).unwrap();
+
+        // 长度为chunk大小数倍的value，每个字节都不同
+        let value: Vec<u8> = (0..CHUNK_SIZE * 10).map(|i| (i * 7 % 251) as u8).collect();
+        let data = extra_file.encode_value(&value, 0).unwrap();
+        assert_eq!(value, extra_file.decode_value(&data).unwrap());
+        extra_file.verify_value(&data).unwrap();
+        let file_length = fs::metadata(file_name).unwrap().len();
+
+        // 删除的chunk在release之后复用，文件长度不变
+        extra_file.free_value(&data).unwrap();
+        let other = extra_file.encode_value(&value[..100], 0).unwrap();
+        assert!(fs::metadata(file_name).unwrap().len() > file_length);
+        extra_file.release_freed_chunks().unwrap();
+        let file_length = fs::metadata(file_name).unwrap().len();
+        let data = extra_file.encode_value(&value, 0).unwrap();
+        assert_eq!(file_length, fs::metadata(file_name).unwrap().len());
+        assert_eq!(value, extra_file.decode_value(&data).unwrap());
+        assert_eq!(&value[..100], &extra_file.decode_value(&other).unwrap()[..]);
+
+        // value长度与链表长度不一致
+        let mut invalid = data.clone();
+        invalid[9..].copy_from_slice(&(CHUNK_SIZE * 20).to_le_bytes());
+        assert!(extra_file.verify_value(&invalid).is_err());
+        invalid[9..].copy_from_slice(&1u32.to_le_bytes());
+        assert!(extra_file.verify_value(&invalid).is_err());
+
+        fs::remove_file(file_name).unwrap()
+    }
+}
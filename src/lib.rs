@@ -1,56 +1,559 @@
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt::{Display, Formatter};
-use std::fs::{File, OpenOptions};
+use std::fs;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io;
 use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, PoisonError};
 use regex::Regex;
-use crate::controller::Controller;
-use crate::page::{Page, Pager};
+use crate::cache::PageCache;
+use crate::node::{IoCounters, StructuralHook};
+use crate::page::{FreePage, NULL_PAGE, Page, Pager};
+use crate::storage::{init_single_file, SingleFile, Storage};
+use crate::wal::{Wal, WalRecord};
 
+mod cache;
+mod checksum;
+mod composite;
+mod comparator;
 mod page;
 mod node;
 mod controller;
+mod extra;
+mod wal;
+mod repair;
+mod storage;
+mod endian;
+mod backup;
+#[cfg(feature = "serde")]
+mod typed;
+
+pub use crate::comparator::{CompareFn, Comparator, CustomComparator};
+pub use crate::composite::{decode_composite_key, encode_composite_key, CompositeKey};
+pub use crate::endian::Endian;
+pub use crate::page::AccessPattern;
+pub use crate::controller::{AutoVacuum, Controller, Entry, Iter, Keys, Operate, ReadTxn, ScanChunk, SyncMode, ValueReader, ValueWriter, WriteOp};
+pub use crate::node::{IoStats, Stats, StructuralEvent};
+#[cfg(feature = "serde")]
+pub use crate::typed::{decode_typed, encode_typed};
+pub use crate::repair::{CorruptionMode, LostRange, VerificationError, VerificationErrorKind};
+
+// 错误的分类，调用方可以根据分类处理错误，而不需要匹配错误信息
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ErrorKind {
+    // schema、文件、key等不存在
+    NotFound,
+    // schema、文件等已经存在
+    AlreadyExists,
+    // 页空间不足以保存数据
+    PageFull,
+    // 文件中的数据损坏
+    Corruption,
+    // 参数不合法
+    InvalidArgument,
+    // 读写文件失败
+    Io,
+    // 以只读方式打开的schema不能写入
+    ReadOnly,
+    // schema已经被其它进程以写方式打开
+    Locked,
+    // 其它错误，例如value不是合法的utf8
+    Other,
+}
 
 #[derive(Debug)]
-struct MiniBaseError(
-    &'static str
-);
+pub struct MiniBaseError {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl MiniBaseError {
+    pub(crate) fn new(kind: ErrorKind, message: impl Into<String>) -> MiniBaseError {
+        MiniBaseError { kind, message: message.into() }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
 
 impl Display for MiniBaseError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "mini base error: {}", self.0)
+        write!(f, "mini base error: {}", self.message)
     }
 }
 
 impl Error for MiniBaseError {}
 
+// 获取接口返回错误的分类，文件读写的错误属于Io
+pub fn error_kind(error: &(dyn Error + 'static)) -> ErrorKind {
+    if let Some(error) = error.downcast_ref::<MiniBaseError>() {
+        error.kind()
+    } else if error.is::<std::io::Error>() {
+        ErrorKind::Io
+    } else {
+        ErrorKind::Other
+    }
+}
+
 type MiniBaseResult<T> = Result<T, Box<dyn Error>>;
 
 pub struct MeteData {
     page_size: u32,
     key_max_length: u32,
     value_threshold: u32,
+    comparator: Comparator,
+    garbage_ratio: f32,
+    // 同一个key是否可以保存多个value，创建时确定，保存在元数据文件中
+    multi_value: bool,
+    // 数据页和额外数据文件中整数的字节序，创建时确定，保存在元数据文件中
+    endian: Endian,
+    // 叶节点是否启用布隆过滤器，创建时确定，保存在元数据文件中
+    bloom_filter: bool,
+    // 是否支持为key设置过期时间，创建时确定，保存在元数据文件中
+    ttl: bool,
+    // 读取页时是否校验checksum，仅在运行时生效，不会持久化
+    verify_checksum: bool,
+    // 映射页后提示操作系统的访问方式，仅在运行时生效
+    access_pattern: AccessPattern,
+    // 叶节点分裂时左侧占用的空间比例，以及顺序写入时是否只将新key分裂到右侧，仅在运行时生效
+    split_fill_factor: f32,
+    rightmost_split: bool,
+    // 已经映射的节点的缓存，仅在运行时生效
+    page_cache: Mutex<PageCache>,
+    // 是否以只读方式打开，只读时文件以只读方式打开，页使用只读映射，写操作返回ReadOnly错误
+    read_only: bool,
+    // 是否只保存在内存中，内存模式下所有数据保存在匿名映射中，不会创建任何文件，drop之后数据丢失
+    memory: bool,
+    // 是否为单文件模式，创建时确定，元数据、数据和额外数据保存在元数据文件的不同区域中
+    single_file: bool,
+    // 打开的元数据文件的路径，单文件模式下不记录路径，复制到其它位置后仍然可以打开，仅在运行时生效
+    mete_file_path: String,
+    // 打开时对损坏的叶节点的处理方式，仅在运行时生效
+    corruption_mode: CorruptionMode,
+    // 宽松模式下打开时发现的损坏的叶节点，以及其在叶节点链表中的前后节点
+    skipped_leaves: HashMap<u64, (u64, u64)>,
+    // 是否使用预写日志，仅在运行时生效，打开时存在未清空的日志总会先恢复
+    write_ahead_log: bool,
+    // 预写日志，写操作期间加载的页都需要记录原始数据，因此由互斥锁保护
+    wal: Option<Mutex<Wal>>,
+    // 创建controller时启动的后台整理线程的设置，仅在运行时生效
+    auto_vacuum: Option<AutoVacuum>,
+    // 打开之后读写数据文件的统计，仅在运行时生效
+    io_counters: IoCounters,
+    // 页分裂、合并和根节点改变时调用的回调，仅在运行时生效
+    structural_hook: Option<Box<StructuralHook>>,
     mete_page: Page,
+    // 以写方式打开时持有排它锁的锁文件，在其它字段之后drop，释放锁之前所有映射已经解除
+    lock_file: Option<File>,
 }
 
 impl MeteData {
-    fn get_root_page_offset(&self) -> u32 {
-        self.mete_page.read_u32(0)
+    // 文件路径之后的字段，相对于get_extension_position的偏移
+    const FREE_PAGE_HEAD: usize = 0;
+    const PAGE_SIZE: usize = 8;
+    const KEY_MAX_LENGTH: usize = 12;
+    const VALUE_THRESHOLD: usize = 16;
+    const COMPARATOR: usize = 20;
+    const GARBAGE_RATIO: usize = 24;
+    const MULTI_VALUE: usize = 28;
+    const COMPARATOR_NAME_HASH: usize = 32;
+    const SINGLE_FILE: usize = 36;
+    const ENDIAN: usize = 40;
+    const BLOOM_FILTER: usize = 44;
+    const TTL: usize = 48;
+    const EXTENSION_LENGTH: usize = 52;
+    // 叶节点的垃圾数据超过容量的一半时整理
+    const DEFAULT_GARBAGE_RATIO: f32 = 0.5;
+    const DEFAULT_PAGE_CACHE_CAPACITY: usize = 128;
+    const DEFAULT_SPLIT_FILL_FACTOR: f32 = 0.5;
+    // 叶节点的页头占37字节，最小的页还需要保存至少一个较短的key value
+    const MIN_PAGE_SIZE: u32 = 64;
+
+    fn from(mete_page: Page) -> MiniBaseResult<MeteData> {
+        let extension_position = get_extension_position(&mete_page);
+        let page_size = mete_page.read_u32(extension_position + Self::PAGE_SIZE);
+        let key_max_length = mete_page.read_u32(extension_position + Self::KEY_MAX_LENGTH);
+        let value_threshold = mete_page.read_u32(extension_position + Self::VALUE_THRESHOLD);
+        let comparator_name_hash = mete_page.read_u32(extension_position + Self::COMPARATOR_NAME_HASH);
+        let comparator = Comparator::from_tag(mete_page.read_u32(extension_position + Self::COMPARATOR), comparator_name_hash)?;
+        let garbage_ratio = f32::from_bits(mete_page.read_u32(extension_position + Self::GARBAGE_RATIO));
+        let multi_value = mete_page.read_u32(extension_position + Self::MULTI_VALUE) != 0;
+        let single_file = mete_page.read_u32(extension_position + Self::SINGLE_FILE) != 0;
+        let endian = Endian::from_tag(mete_page.read_u32(extension_position + Self::ENDIAN))?;
+        let bloom_filter = mete_page.read_u32(extension_position + Self::BLOOM_FILTER) != 0;
+        let ttl = mete_page.read_u32(extension_position + Self::TTL) != 0;
+        let page_cache = Mutex::new(PageCache::new(Self::DEFAULT_PAGE_CACHE_CAPACITY));
+        Ok(MeteData { page_size, key_max_length, value_threshold, comparator, garbage_ratio, multi_value, endian, bloom_filter, ttl, verify_checksum: true, access_pattern: AccessPattern::Normal, split_fill_factor: Self::DEFAULT_SPLIT_FILL_FACTOR, rightmost_split: false, page_cache, read_only: false, memory: false, single_file, mete_file_path: String::new(), corruption_mode: CorruptionMode::Strict, skipped_leaves: HashMap::new(), write_ahead_log: false, wal: None, auto_vacuum: None, io_counters: IoCounters::default(), structural_hook: None, mete_page, lock_file: None })
+    }
+
+    pub fn get_page_size(&self) -> u32 {
+        self.page_size
+    }
+    pub fn get_key_max_length(&self) -> u32 {
+        self.key_max_length
+    }
+    pub fn get_value_threshold(&self) -> u32 {
+        self.value_threshold
+    }
+    pub fn get_comparator(&self) -> Comparator {
+        self.comparator.clone()
+    }
+    pub fn get_garbage_ratio(&self) -> f32 {
+        self.garbage_ratio
+    }
+
+    // 删除或覆盖后，叶节点中可回收的空间超过页容量的garbage_ratio时立即整理页，保存在元数据文件中
+    // 为1时只在插入空间不足时整理，值越小页越紧凑，读取时需要访问的页越少，但写操作的开销越大
+    pub fn set_garbage_ratio(&mut self, garbage_ratio: f32) -> MiniBaseResult<()> {
+        self.check_writable()?;
+        if !(0.0..=1.0).contains(&garbage_ratio) {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, format!("garbage_ratio {} invalid, must be in [0, 1]", garbage_ratio))));
+        }
+        let position = get_extension_position(&self.mete_page) + Self::GARBAGE_RATIO;
+        self.mete_page.write_u32(position, garbage_ratio.to_bits());
+        self.garbage_ratio = garbage_ratio;
+        Ok(())
+    }
+
+    pub fn is_multi_value(&self) -> bool {
+        self.multi_value
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn is_memory(&self) -> bool {
+        self.memory
+    }
+
+    pub fn is_single_file(&self) -> bool {
+        self.single_file
+    }
+
+    pub fn get_endian(&self) -> Endian {
+        self.endian
+    }
+
+    pub fn has_bloom_filter(&self) -> bool {
+        self.bloom_filter
+    }
+
+    pub fn has_ttl(&self) -> bool {
+        self.ttl
+    }
+
+    pub(crate) fn check_writable(&self) -> MiniBaseResult<()> {
+        if self.read_only {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::ReadOnly, "schema opened read-only")));
+        }
+        Ok(())
+    }
+
+    // 只修改元数据，已经写入的value由Controller::set_value_threshold迁移
+    fn set_value_threshold(&mut self, value_threshold: u32) {
+        let position = get_extension_position(&self.mete_page) + Self::VALUE_THRESHOLD;
+        self.mete_page.write_u32(position, value_threshold);
+        self.value_threshold = value_threshold;
+    }
+
+    fn get_root_page_offset(&self) -> u64 {
+        self.mete_page.read_u64(0)
+    }
+    fn set_root_page_offset(&mut self, offset: u64) {
+        self.mete_page.write_u64(0, offset)
+    }
+    fn get_free_page_head(&self) -> u64 {
+        self.mete_page.read_u64(get_extension_position(&self.mete_page) + Self::FREE_PAGE_HEAD)
+    }
+    fn set_free_page_head(&mut self, offset: u64) {
+        let position = get_extension_position(&self.mete_page) + Self::FREE_PAGE_HEAD;
+        self.mete_page.write_u64(position, offset)
+    }
+
+    // 分配新的页，优先复用空闲链表中的页，空闲链表为空时在数据文件末尾追加
+    pub(crate) fn allocate_page(&mut self, data_file: &Storage) -> MiniBaseResult<u64> {
+        let free_page_head = self.get_free_page_head();
+        if free_page_head != NULL_PAGE {
+            // 复用的页会被新的节点覆盖
+            self.journal_page(data_file, free_page_head)?;
+            let free_page = FreePage::from(data_file, free_page_head, self.page_size)?;
+            self.io_counters.record_mapped_page();
+            self.set_free_page_head(free_page.get_next_free_page());
+            self.page_cache_mut().invalidate(free_page_head);
+            return Ok(free_page_head);
+        }
+        let file_length = data_file.len()?;
+        data_file.set_len(file_length + self.page_size as u64)?;
+        Ok(file_length)
+    }
+
+    // 释放页，将页放入空闲链表的头部
+    pub(crate) fn free_page(&mut self, data_file: &Storage, offset: u64) -> MiniBaseResult<()> {
+        self.page_cache_mut().invalidate(offset);
+        self.journal_page(data_file, offset)?;
+        FreePage::new(data_file, offset, self.page_size, self.get_free_page_head())?;
+        self.io_counters.record_mapped_page();
+        self.set_free_page_head(offset);
+        Ok(())
+    }
+    // 单文件模式下数据文件和额外数据文件都是元数据文件，通过区域表中的偏移区分
+    fn get_data_file_path(&self) -> MiniBaseResult<String> {
+        if self.single_file {
+            return Ok(self.mete_file_path.clone());
+        }
+        let data_file_path_size = self.mete_page.read_u32(8);
+        let data = self.mete_page.read_bytes(8 + 4, data_file_path_size as usize);
+        String::from_utf8(Vec::from(data)).map_err(|_| Box::from(MiniBaseError::new(ErrorKind::Corruption, "data file path not valid utf8")))
+    }
+    fn get_extra_file_path(&self) -> MiniBaseResult<String> {
+        if self.single_file {
+            return Ok(self.mete_file_path.clone());
+        }
+        let data_file_path_size = self.mete_page.read_u32(8);
+        let extra_file_path_size = self.mete_page.read_u32((8 + 4 + data_file_path_size) as usize);
+        let data = self.mete_page.read_bytes((8 + 4 + data_file_path_size + 4) as usize, extra_file_path_size as usize);
+        String::from_utf8(Vec::from(data)).map_err(|_| Box::from(MiniBaseError::new(ErrorKind::Corruption, "extra file path not valid utf8")))
+    }
+
+    fn flush(&mut self) -> MiniBaseResult<()> {
+        self.page_cache_mut().flush()?;
+        self.mete_page.flush()
+    }
+
+    // 单文件模式下数据文件路径为元数据文件路径，日志路径与多文件模式相同
+    fn get_wal_file_path(&self) -> MiniBaseResult<String> {
+        let data_file_path = self.get_data_file_path()?;
+        let prefix = data_file_path.strip_suffix(".d").or_else(|| data_file_path.strip_suffix(".m")).unwrap_or(&data_file_path);
+        Ok(format!("{}.wal", prefix))
+    }
+
+    // 打开单文件中的数据区域和额外数据区域，区域表位于元数据页之后
+    pub(crate) fn open_single_file(&self) -> MiniBaseResult<(Storage, Storage)> {
+        let path = self.get_data_file_path()?;
+        let file = if self.read_only {
+            OpenOptions::new().read(true).open(path)?
+        } else {
+            OpenOptions::new().read(true).write(true).open(path)?
+        };
+        let (data_file, extra_file) = SingleFile::open(file, self.mete_page.get_mmap().len() as u64)?;
+        Ok((data_file.with_endian(self.endian), extra_file.with_endian(self.endian)))
     }
-    fn get_data_file_path(&self) -> String {
-        let data_file_path_size = self.mete_page.read_u32(4);
-        let data = self.mete_page.read_bytes(4, data_file_path_size as usize);
-        String::from_utf8(Vec::from(data)).unwrap()
+
+    // 打开预写日志，未启用预写日志但存在上次留下的日志时也需要打开，用于恢复
+    // 只读时不打开日志，也不恢复，上次检查点之后没有写入磁盘的修改可能只有一部分可见
+    // 内存模式下没有需要恢复的数据，不使用日志
+    pub(crate) fn open_wal(&mut self) -> MiniBaseResult<()> {
+        if self.memory {
+            return Ok(());
+        }
+        let wal_file_path = self.get_wal_file_path()?;
+        if self.read_only {
+            return Ok(());
+        }
+        if self.write_ahead_log || PathBuf::from(wal_file_path.as_str()).exists() {
+            self.wal = Some(Mutex::new(Wal::open(wal_file_path.as_str())?));
+        }
+        Ok(())
     }
-    fn get_extra_file_path(&self) -> String {
-        let data_file_path_size = self.mete_page.read_u32(4);
-        let extra_file_path_size = self.mete_page.read_u32((4 + 4 + data_file_path_size) as usize);
-        let data = self.mete_page.read_bytes((4 + 4 + data_file_path_size + 4) as usize, extra_file_path_size as usize);
-        String::from_utf8(Vec::from(data)).unwrap()
+
+    // 将数据文件和元数据回滚到日志中的检查点，返回检查点之后需要重放的写操作
+    pub(crate) fn recover_wal(&mut self, data_file: &Storage) -> MiniBaseResult<Vec<WalRecord>> {
+        let wal = match self.wal.as_mut() {
+            Some(wal) => wal.get_mut().unwrap_or_else(PoisonError::into_inner),
+            None => return Ok(Vec::new()),
+        };
+        let mut records = wal.read_records()?.into_iter();
+        // 检查点记录写入前还没有修改任何页
+        let (data_length, mete_data) = match records.next() {
+            Some(WalRecord::Checkpoint { data_length, mete_data }) => (data_length, mete_data),
+            _ => {
+                wal.checkpoint()?;
+                return Ok(Vec::new());
+            }
+        };
+        if mete_data.len() != self.mete_page.get_mmap().len() {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("wal mete data length {} not match mete file length {}", mete_data.len(), self.mete_page.get_mmap().len()))));
+        }
+        let mut journaled_pages = HashSet::new();
+        let mut operations = Vec::new();
+        for record in records {
+            match record {
+                WalRecord::Checkpoint { .. } => return Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, "wal contains more than one checkpoint"))),
+                WalRecord::Page { offset, data } => {
+                    data_file.write_all_at(&data, offset)?;
+                    journaled_pages.insert(offset);
+                }
+                operation => operations.push(operation),
+            }
+        }
+        // 检查点之后追加的页会在重放时重新分配
+        data_file.set_len(data_length)?;
+        data_file.sync_all()?;
+        self.mete_page.write_bytes(0, &mete_data);
+        self.mete_page.flush()?;
+        wal.resume(data_length, journaled_pages)?;
+        Ok(operations)
+    }
+
+    // 所有修改已经写入磁盘，清空预写日志，未启用预写日志时删除恢复用的日志
+    pub(crate) fn checkpoint_wal(&mut self) -> MiniBaseResult<()> {
+        if let Some(wal) = self.wal.as_mut() {
+            wal.get_mut().unwrap_or_else(PoisonError::into_inner).checkpoint()?;
+            if !self.write_ahead_log {
+                self.wal = None;
+                fs::remove_file(self.get_wal_file_path()?)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn has_wal(&self) -> bool {
+        self.wal.is_some()
+    }
+
+    // 写操作开始前记录检查点，之后写操作加载的页在修改前都会记录原始数据
+    pub(crate) fn begin_wal(&mut self, data_file: &Storage) -> MiniBaseResult<()> {
+        if let Some(wal) = self.wal.as_mut() {
+            let mete_data = self.mete_page.get_mmap().to_vec();
+            wal.get_mut().unwrap_or_else(PoisonError::into_inner).begin(data_file, &mete_data)?;
+        }
+        Ok(())
+    }
+
+    // 写操作结束，records为需要重放的写操作，写入并fsync之后写操作才算完成
+    pub(crate) fn end_wal(&mut self, records: &[WalRecord]) -> MiniBaseResult<()> {
+        match self.wal.as_mut() {
+            Some(wal) => wal.get_mut().unwrap_or_else(PoisonError::into_inner).end(records),
+            None => Ok(()),
+        }
+    }
+
+    // 页被修改前记录原始数据，只在写操作期间生效
+    pub(crate) fn journal_page(&self, data_file: &Storage, offset: u64) -> MiniBaseResult<()> {
+        match self.wal.as_ref() {
+            Some(wal) => wal.lock().unwrap_or_else(PoisonError::into_inner).journal_page(data_file, offset, self.page_size),
+            None => Ok(()),
+        }
+    }
+
+    // 启用后每个写操作都会先写入预写日志并fsync，进程或系统崩溃后打开时通过日志恢复，写操作的开销更大
+    // 需要在controller之前设置
+    pub fn set_write_ahead_log(&mut self, write_ahead_log: bool) {
+        self.write_ahead_log = write_ahead_log;
+    }
+
+    // 关闭checksum校验可以减少读取页的开销，但无法发现损坏的页
+    pub fn set_verify_checksum(&mut self, verify_checksum: bool) {
+        self.verify_checksum = verify_checksum;
+    }
+
+    // 映射数据页后通过madvise提示操作系统的访问方式，点查为主时使用Random，范围读取为主时使用Sequential
+    // 范围读取和遍历总是将读取的叶节点提示为Sequential，需要在controller之前设置
+    pub fn set_access_pattern(&mut self, access_pattern: AccessPattern) {
+        self.access_pattern = access_pattern;
+    }
+
+    // 叶节点分裂时左侧占用的空间比例，范围为(0, 1)，默认0.5均分
+    // key递增写入时分裂后左侧的叶节点不会再写入，使用较大的比例可以减少叶节点数量，随机写入时会导致更频繁的分裂
+    // 比例较小时按比例分裂后右侧可能放不下，此时分裂点右移到右侧能够容纳的位置
+    // 需要在controller之前设置
+    pub fn set_split_fill_factor(&mut self, split_fill_factor: f32) -> MiniBaseResult<()> {
+        if !(split_fill_factor > 0.0 && split_fill_factor < 1.0) {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, format!("split_fill_factor {} invalid, must be in (0, 1)", split_fill_factor))));
+        }
+        self.split_fill_factor = split_fill_factor;
+        Ok(())
+    }
+
+    // 启用后向最右侧的叶节点追加大于所有key的新key时，原有的key全部保留在左侧，新key单独放入右侧，适合key严格递增的写入
+    pub fn set_rightmost_split(&mut self, rightmost_split: bool) {
+        self.rightmost_split = rightmost_split;
+    }
+
+    // 使用自定义比较函数创建的schema，每次打开后都需要在controller之前提供同样的比较函数
+    // 名称与创建时不同，或者schema使用内置的排序方式时返回InvalidArgument错误
+    pub fn set_custom_comparator(&mut self, comparator: CustomComparator) -> MiniBaseResult<()> {
+        match &self.comparator {
+            Comparator::Custom(custom) if *custom == comparator => {
+                self.comparator = Comparator::Custom(comparator);
+                Ok(())
+            }
+            Comparator::Custom(_) => Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, "custom comparator name not match the one used to create the schema"))),
+            _ => Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, format!("schema uses built-in comparator {:?}", self.comparator)))),
+        }
+    }
+
+    // 创建controller时启动后台整理线程，None表示不启动，之后可以通过Controller::start_auto_vacuum和stop_auto_vacuum修改
+    // 需要在controller之前设置，只读打开时返回ReadOnly错误
+    pub fn set_auto_vacuum(&mut self, auto_vacuum: Option<AutoVacuum>) -> MiniBaseResult<()> {
+        if let Some(auto_vacuum) = auto_vacuum {
+            auto_vacuum.check()?;
+            self.check_writable()?;
+        }
+        self.auto_vacuum = auto_vacuum;
+        Ok(())
+    }
+
+    // 缓存的节点数量，为0时不缓存，缓存中的节点再次读取时不会重新校验checksum
+    pub fn set_page_cache_capacity(&mut self, capacity: usize) -> MiniBaseResult<()> {
+        self.page_cache_mut().set_capacity(capacity)
+    }
+
+    // 页缓存只在取出和放回节点时加锁，多个读操作可以同时使用
+    // 持有锁时出现panic不会破坏缓存本身的状态，因此忽略锁中毒
+    pub(crate) fn page_cache(&self) -> MutexGuard<'_, PageCache> {
+        self.page_cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn io_stats(&self) -> IoStats {
+        self.io_counters.snapshot(self.page_size, self.page_cache().get_flushed_page_count())
+    }
+
+    pub(crate) fn reset_io_stats(&self) {
+        self.io_counters.reset();
+        self.page_cache().reset_flushed_page_count();
+    }
+
+    // 没有注册回调时不创建事件
+    pub(crate) fn notify_structural_change<F>(&self, event: F)
+        where F: FnOnce() -> StructuralEvent {
+        if let Some(hook) = &self.structural_hook {
+            hook(event());
+        }
+    }
+
+    fn page_cache_mut(&mut self) -> &mut PageCache {
+        self.page_cache.get_mut().unwrap_or_else(PoisonError::into_inner)
     }
 
     pub fn controller(self) -> MiniBaseResult<Controller> {
-        let data_file_path = self.get_data_file_path();
-        if !PathBuf::from(data_file_path).exists() {
+        if let Comparator::Custom(custom) = &self.comparator {
+            if !custom.is_resolved() {
+                return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, "schema uses a custom comparator, call set_custom_comparator before controller")));
+            }
+        }
+        if self.memory {
+            return Controller::new(self);
+        }
+        let data_file_path = self.get_data_file_path()?;
+        // 单文件模式下数据区域在创建controller时才分配第一个页
+        let data_file_exists = if self.single_file {
+            self.open_single_file()?.0.len()? != 0
+        } else {
+            PathBuf::from(data_file_path).exists()
+        };
+        if !data_file_exists {
+            // 只读时不能创建数据文件
+            if self.read_only {
+                return Err(Box::from(MiniBaseError::new(ErrorKind::NotFound, "data_file not exist, schema opened read-only")));
+            }
             Controller::new(self)
         } else {
             Controller::from(self)
@@ -58,13 +561,22 @@ impl MeteData {
     }
 }
 
-pub fn create_schema(data_dir: &str, schema_name: &str, page_size: u32, key_max_length: u32, value_threshold: u32) -> MiniBaseResult<MeteData> {
+// 扩展字段位于数据文件路径和额外数据文件路径之后
+fn get_extension_position(mete_page: &Page) -> usize {
+    let data_file_path_size = mete_page.read_u32(8);
+    let extra_file_path_size = mete_page.read_u32((8 + 4 + data_file_path_size) as usize);
+    (8 + 4 + data_file_path_size + 4 + extra_file_path_size) as usize
+}
+
+// 校验schema_name并返回元数据文件、数据文件和额外数据文件的路径
+fn get_schema_file_paths(data_dir: &str, schema_name: &str) -> MiniBaseResult<(String, String, String)> {
     if !PathBuf::from(data_dir).exists() {
-        return Err(Box::from(MiniBaseError("data_dir not exist")));
+        return Err(Box::from(MiniBaseError::new(ErrorKind::NotFound, format!("data_dir {} not exist", data_dir))));
     }
-    let schema_name_regex = Regex::new(r"[a-z]|[0-9]+?").unwrap();
+    // schema_name会拼接到文件路径中，只允许小写字母、数字和下划线，避免路径穿越
+    let schema_name_regex = Regex::new(r"^[a-z0-9_]+$").unwrap();
     if !schema_name_regex.is_match(schema_name) {
-        return Err(Box::from(MiniBaseError("schema_name invalid")));
+        return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, format!("schema_name {} invalid", schema_name))));
     }
     let format_data_dir = if !data_dir.ends_with('/') {
         String::from(data_dir) + "/"
@@ -74,28 +586,612 @@ pub fn create_schema(data_dir: &str, schema_name: &str, page_size: u32, key_max_
     let mete_file_path = format_data_dir.clone() + schema_name + ".m";
     let data_file_path = format_data_dir.clone() + schema_name + ".d";
     let extra_file_path = format_data_dir.clone() + schema_name + ".e";
+    Ok((mete_file_path, data_file_path, extra_file_path))
+}
+
+pub fn create_schema(data_dir: &str, schema_name: &str, page_size: u32, key_max_length: u32, value_threshold: u32) -> MiniBaseResult<MeteData> {
+    create_schema_with_comparator(data_dir, schema_name, page_size, key_max_length, value_threshold, Comparator::ByteLexicographic)
+}
+
+// 创建使用指定key排序方式的schema，排序方式保存在元数据文件中，之后不能修改
+pub fn create_schema_with_comparator(data_dir: &str, schema_name: &str, page_size: u32, key_max_length: u32, value_threshold: u32, comparator: Comparator) -> MiniBaseResult<MeteData> {
+    create_schema_with_options(data_dir, schema_name, page_size, key_max_length, value_threshold, CreateOptions { comparator, ..CreateOptions::default() })
+}
+
+// 创建单文件模式的schema，元数据、数据和额外数据保存在同一个.m文件中，复制这一个文件即可备份schema
+// 预写日志和锁文件仍然是单独的文件，数据区域缩小时文件不会变短
+pub fn create_single_file_schema(data_dir: &str, schema_name: &str, page_size: u32, key_max_length: u32, value_threshold: u32) -> MiniBaseResult<MeteData> {
+    create_schema_files(data_dir, schema_name, page_size, key_max_length, value_threshold, CreateOptions::default(), true)
+}
+
+fn create_schema_files(data_dir: &str, schema_name: &str, page_size: u32, key_max_length: u32, value_threshold: u32, options: CreateOptions, single_file: bool) -> MiniBaseResult<MeteData> {
+    let (mete_file_path, data_file_path, extra_file_path) = get_schema_file_paths(data_dir, schema_name)?;
+    check_page_size(page_size)?;
     if PathBuf::from(mete_file_path.as_str()).exists() {
-        return Err(Box::from(MiniBaseError("mete_file already exist")));
+        return Err(Box::from(MiniBaseError::new(ErrorKind::AlreadyExists, "mete_file already exist")));
     }
     if PathBuf::from(data_file_path.as_str()).exists() {
-        return Err(Box::from(MiniBaseError("data_file already exist")));
+        return Err(Box::from(MiniBaseError::new(ErrorKind::AlreadyExists, "data_file already exist")));
     }
     if PathBuf::from(extra_file_path.as_str()).exists() {
-        return Err(Box::from(MiniBaseError("extra_data_file already exist")));
+        return Err(Box::from(MiniBaseError::new(ErrorKind::AlreadyExists, "extra_data_file already exist")));
     }
-    let mete_file = OpenOptions::new().read(true).write(true).create(true).open(mete_file_path.as_str())?;
-    let mete_page = init_mete_file(&mete_file, data_file_path.as_str(), extra_file_path.as_str())?;
-    Ok(MeteData { page_size, key_max_length, value_threshold, mete_page })
+    // data_dir没有写权限时创建或写入文件失败，返回Io错误
+    let lock_file = lock_schema(mete_file_path.as_str())?;
+    let mete_file = Storage::from(OpenOptions::new().read(true).write(true).create(true).truncate(false).open(mete_file_path.as_str())?);
+    let result = if single_file {
+        init_single_file_mete(&mete_file, page_size, key_max_length, value_threshold, &options)
+    } else {
+        init_mete_file(&mete_file, data_file_path.as_str(), extra_file_path.as_str(), page_size, key_max_length, value_threshold, &options)
+    };
+    match result {
+        Ok(mete_page) => {
+            let mut mete_data = MeteData::from(mete_page)?;
+            // 元数据文件中只保存了自定义比较函数名称的哈希
+            mete_data.comparator = options.comparator;
+            mete_data.mete_file_path = mete_file_path;
+            mete_data.lock_file = Some(lock_file);
+            Ok(mete_data)
+        }
+        Err(error) => {
+            // 删除写入不完整的元数据文件，之后可以重新创建
+            let _ = fs::remove_file(mete_file_path.as_str());
+            Err(error)
+        }
+    }
+}
+
+// 创建schema的选项，都保存在元数据文件中，之后不能修改
+#[derive(Debug, PartialEq, Clone)]
+pub struct CreateOptions {
+    // 同一个key可以保存多个value，put追加value而不是覆盖，通过Controller::get_all读取key的所有value
+    // 同一个key的value按写入顺序排列，key按字节序排列，不能使用其它排序方式
+    // 页中保存的是加上序号编码后的key，key_max_length限制的是编码后的长度，最长为(key长度+8)*2+4，key中没有0x00时为key长度+20
+    pub multi_value: bool,
+    // 数据页和额外数据文件中整数的字节序，在任何平台上打开都按记录的字节序读取
+    pub endian: Endian,
+    // 每个叶节点在页尾预留1/16的空间，查找不存在的key时大多不需要二分查找
+    // 过滤器在插入时更新，整理页时重建，删除key后不会清除，只对内置的排序方式生效
+    pub bloom_filter: bool,
+    // 通过put_with_ttl写入的key在过期后读取不到，占用的空间通过purge_expired回收
+    // 只有设置了过期时间的value在叶节点中多保存8字节的过期时间，不支持多值模式
+    pub ttl: bool,
+    pub comparator: Comparator,
 }
 
-fn init_mete_file(mete_file: &File, data_file_path: &str, extra_file_path: &str) -> MiniBaseResult<Page> {
-    let file_length = 4 + data_file_path.len() + 4 + extra_file_path.len() + 4;
-    mete_file.set_len(file_length as u64).unwrap();
+impl Default for CreateOptions {
+    fn default() -> CreateOptions {
+        CreateOptions { multi_value: false, endian: Endian::Little, bloom_filter: false, ttl: false, comparator: Comparator::ByteLexicographic }
+    }
+}
+
+impl CreateOptions {
+    fn check(&self) -> MiniBaseResult<()> {
+        if self.multi_value && self.ttl {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, "multi_value schema not support ttl")));
+        }
+        if self.multi_value && self.comparator != Comparator::ByteLexicographic {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, "multi_value schema only support byte lexicographic comparator")));
+        }
+        Ok(())
+    }
+}
+
+// 按options创建schema，不同的选项可以同时启用，例如同时支持过期时间和布隆过滤器
+pub fn create_schema_with_options(data_dir: &str, schema_name: &str, page_size: u32, key_max_length: u32, value_threshold: u32, options: CreateOptions) -> MiniBaseResult<MeteData> {
+    options.check()?;
+    create_schema_files(data_dir, schema_name, page_size, key_max_length, value_threshold, options, false)
+}
+
+// 打开已经存在的schema，page_size、key_max_length和value_threshold从元数据文件中读取
+// schema已经被其它进程以写方式打开时返回Locked错误，锁在MeteData或controller drop时释放
+pub fn open_schema(data_dir: &str, schema_name: &str) -> MiniBaseResult<MeteData> {
+    let (mete_file_path, _, _) = get_schema_file_paths(data_dir, schema_name)?;
+    if !PathBuf::from(mete_file_path.as_str()).exists() {
+        return Err(Box::from(MiniBaseError::new(ErrorKind::NotFound, "mete_file not exist")));
+    }
+    let lock_file = lock_schema(mete_file_path.as_str())?;
+    let mete_file = Storage::from(OpenOptions::new().read(true).write(true).open(mete_file_path.as_str())?);
+    let mete_page = Page::new(&mete_file, 0, get_mete_length(&mete_file)?)?;
+    let mut mete_data = MeteData::from(mete_page)?;
+    mete_data.mete_file_path = mete_file_path;
+    mete_data.lock_file = Some(lock_file);
+    Ok(mete_data)
+}
+
+// 锁文件与元数据文件位于同一目录，创建后不会删除，只有drop_schema会删除
+// 使用操作系统的建议锁，进程退出时自动释放，同一进程中重复打开同样会失败
+fn lock_schema(mete_file_path: &str) -> MiniBaseResult<File> {
+    let lock_file_path = format!("{}.lock", mete_file_path.strip_suffix(".m").unwrap());
+    let lock_file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(lock_file_path)?;
+    match lock_file.try_lock() {
+        Ok(()) => Ok(lock_file),
+        Err(TryLockError::WouldBlock) => Err(Box::from(MiniBaseError::new(ErrorKind::Locked, "schema locked by another process"))),
+        Err(TryLockError::Error(error)) => Err(Box::from(error)),
+    }
+}
+
+// 打开schema的选项
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct SchemaOptions {
+    pub read_only: bool,
+    pub verify_checksum: bool,
+    pub corruption_mode: CorruptionMode,
+    pub access_pattern: AccessPattern,
+}
+
+impl Default for SchemaOptions {
+    fn default() -> SchemaOptions {
+        SchemaOptions { read_only: false, verify_checksum: true, corruption_mode: CorruptionMode::Strict, access_pattern: AccessPattern::Normal }
+    }
+}
+
+// 按options打开已经存在的schema，Lenient总是只读打开，Repair需要修改数据文件，不能只读打开
+// Lenient和Repair在创建controller时检查所有页，丢失的数据范围通过Controller::lost_ranges获取
+pub fn open_schema_with_options(data_dir: &str, schema_name: &str, options: SchemaOptions) -> MiniBaseResult<MeteData> {
+    let read_only = options.read_only || options.corruption_mode == CorruptionMode::Lenient;
+    if read_only && options.corruption_mode == CorruptionMode::Repair {
+        return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, "repair mode can not open read-only")));
+    }
+    let mut mete_data = if read_only {
+        open_schema_read_only(data_dir, schema_name)?
+    } else {
+        open_schema(data_dir, schema_name)?
+    };
+    mete_data.set_verify_checksum(options.verify_checksum);
+    mete_data.set_access_pattern(options.access_pattern);
+    mete_data.corruption_mode = options.corruption_mode;
+    Ok(mete_data)
+}
+
+// 以只读方式打开已经存在的schema，所有文件都以只读方式打开，可以用于只读的文件系统或检查正在使用的schema
+// 通过controller的写操作返回ReadOnly错误，不获取锁，其它进程同时写入时读取到的数据可能不一致
+pub fn open_schema_read_only(data_dir: &str, schema_name: &str) -> MiniBaseResult<MeteData> {
+    let (mete_file_path, _, _) = get_schema_file_paths(data_dir, schema_name)?;
+    if !PathBuf::from(mete_file_path.as_str()).exists() {
+        return Err(Box::from(MiniBaseError::new(ErrorKind::NotFound, "mete_file not exist")));
+    }
+    let mete_file = Storage::from(OpenOptions::new().read(true).open(mete_file_path.as_str())?);
+    let mete_page = Page::new_read_only(&mete_file, 0, get_mete_length(&mete_file)?)?;
+    let mut mete_data = MeteData::from(mete_page)?;
+    mete_data.mete_file_path = mete_file_path;
+    mete_data.read_only = true;
+    Ok(mete_data)
+}
+
+// 创建只保存在内存中的schema，不需要data_dir，元数据、数据和额外数据都保存在匿名映射中
+// 不会创建任何文件，controller drop之后数据丢失，适用于测试和临时数据，预写日志不会生效
+pub fn create_memory_schema(page_size: u32, key_max_length: u32, value_threshold: u32) -> MiniBaseResult<MeteData> {
+    check_page_size(page_size)?;
+    let mete_file = Storage::memory(page_size);
+    let mete_page = init_mete_file(&mete_file, "", "", page_size, key_max_length, value_threshold, &CreateOptions::default())?;
+    let mut mete_data = MeteData::from(mete_page)?;
+    mete_data.memory = true;
+    Ok(mete_data)
+}
+
+// 页按page_size对齐映射，page_size为2的幂时页不会跨越操作系统的内存页
+fn check_page_size(page_size: u32) -> MiniBaseResult<()> {
+    if !page_size.is_power_of_two() || page_size < MeteData::MIN_PAGE_SIZE {
+        return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, format!("page_size {} invalid, must be a power of two and at least {}", page_size, MeteData::MIN_PAGE_SIZE))));
+    }
+    Ok(())
+}
+
+// 删除schema的元数据文件、数据文件、额外数据文件、预写日志和锁文件，除元数据文件外都可能还未创建
+// schema正在以写方式打开时返回Locked错误
+pub fn drop_schema(data_dir: &str, schema_name: &str) -> MiniBaseResult<()> {
+    let (mete_file_path, data_file_path, extra_file_path) = get_schema_file_paths(data_dir, schema_name)?;
+    if !PathBuf::from(mete_file_path.as_str()).exists() {
+        return Err(Box::from(MiniBaseError::new(ErrorKind::NotFound, "mete_file not exist")));
+    }
+    let _lock_file = lock_schema(mete_file_path.as_str())?;
+    let wal_file_path = format!("{}.wal", data_file_path.strip_suffix(".d").unwrap());
+    for file_path in [data_file_path, extra_file_path, wal_file_path] {
+        match fs::remove_file(file_path) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(Box::from(error)),
+            _ => {}
+        }
+    }
+    // 最后删除元数据文件，删除中途失败时可以再次调用
+    fs::remove_file(mete_file_path.as_str())?;
+    fs::remove_file(format!("{}.lock", mete_file_path.strip_suffix(".m").unwrap()))?;
+    Ok(())
+}
+
+// 列出data_dir中所有的schema，即所有元数据文件去掉.m后缀的名称，按名称排序
+pub fn list_schemas(data_dir: &str) -> MiniBaseResult<Vec<String>> {
+    if !PathBuf::from(data_dir).exists() {
+        return Err(Box::from(MiniBaseError::new(ErrorKind::NotFound, format!("data_dir {} not exist", data_dir))));
+    }
+    let mut schema_names = Vec::new();
+    for entry in fs::read_dir(data_dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let file_name = entry.file_name();
+        if let Some(schema_name) = file_name.to_str().and_then(|file_name| file_name.strip_suffix(".m")) {
+            if get_schema_file_paths(data_dir, schema_name).is_ok() {
+                schema_names.push(String::from(schema_name));
+            }
+        }
+    }
+    schema_names.sort();
+    Ok(schema_names)
+}
+
+// 元数据页的长度由其中的两个路径的长度确定，单文件模式下元数据页之后还有区域表和数据
+fn get_mete_length(mete_file: &Storage) -> MiniBaseResult<u32> {
+    let file_length = mete_file.len()?;
+    let corruption = || Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("mete file length {} too short", file_length)));
+    let mut size = [0; 4];
+    mete_file.read_exact_at(&mut size, 8).map_err(|_| corruption())?;
+    let extra_file_path_size_position = 8 + 4 + u32::from_le_bytes(size) as u64;
+    mete_file.read_exact_at(&mut size, extra_file_path_size_position).map_err(|_| corruption())?;
+    let mete_length = extra_file_path_size_position + 4 + u32::from_le_bytes(size) as u64 + MeteData::EXTENSION_LENGTH as u64;
+    if mete_length > file_length {
+        return Err(corruption());
+    }
+    Ok(mete_length as u32)
+}
+
+// 单文件模式下不记录数据文件和额外数据文件的路径，区域的位置保存在元数据页之后的区域表中
+fn init_single_file_mete(mete_file: &Storage, page_size: u32, key_max_length: u32, value_threshold: u32, options: &CreateOptions) -> MiniBaseResult<Page> {
+    let mut page = init_mete_file(mete_file, "", "", page_size, key_max_length, value_threshold, options)?;
+    let extension_position = get_extension_position(&page);
+    page.write_u32(extension_position + MeteData::SINGLE_FILE, 1);
+    page.flush()?;
+    init_single_file(mete_file, page.get_mmap().len() as u64, page_size)?;
+    Ok(page)
+}
+
+fn init_mete_file(mete_file: &Storage, data_file_path: &str, extra_file_path: &str, page_size: u32, key_max_length: u32, value_threshold: u32, options: &CreateOptions) -> MiniBaseResult<Page> {
+    let extension_position = 8 + 4 + data_file_path.len() + 4 + extra_file_path.len();
+    let file_length = extension_position + MeteData::EXTENSION_LENGTH;
+    mete_file.set_len(file_length as u64)?;
     let mut page = Page::new(mete_file, 0, file_length as u32)?;
-    page.write_u32(0, 0 as u32);
-    page.write_u32(4, data_file_path.len() as u32);
-    page.write_bytes(4 + 4, data_file_path.as_bytes());
-    page.write_u32(4 + 4 + data_file_path.len(), extra_file_path.len() as u32);
-    page.write_bytes(4 + 4 + data_file_path.len() + 4, extra_file_path.as_bytes());
+    page.write_u64(0, 0);
+    page.write_u32(8, data_file_path.len() as u32);
+    page.write_bytes(8 + 4, data_file_path.as_bytes());
+    page.write_u32(8 + 4 + data_file_path.len(), extra_file_path.len() as u32);
+    page.write_bytes(8 + 4 + data_file_path.len() + 4, extra_file_path.as_bytes());
+    page.write_u64(extension_position + MeteData::FREE_PAGE_HEAD, NULL_PAGE);
+    page.write_u32(extension_position + MeteData::PAGE_SIZE, page_size);
+    page.write_u32(extension_position + MeteData::KEY_MAX_LENGTH, key_max_length);
+    page.write_u32(extension_position + MeteData::VALUE_THRESHOLD, value_threshold);
+    page.write_u32(extension_position + MeteData::COMPARATOR, options.comparator.to_tag());
+    page.write_u32(extension_position + MeteData::GARBAGE_RATIO, MeteData::DEFAULT_GARBAGE_RATIO.to_bits());
+    page.write_u32(extension_position + MeteData::MULTI_VALUE, options.multi_value as u32);
+    page.write_u32(extension_position + MeteData::COMPARATOR_NAME_HASH, options.comparator.get_name_hash());
+    page.write_u32(extension_position + MeteData::SINGLE_FILE, 0);
+    page.write_u32(extension_position + MeteData::ENDIAN, options.endian.to_tag());
+    page.write_u32(extension_position + MeteData::BLOOM_FILTER, options.bloom_filter as u32);
+    page.write_u32(extension_position + MeteData::TTL, options.ttl as u32);
     Ok(page)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::fs::OpenOptions;
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use crate::{create_schema, create_schema_with_options, drop_schema, error_kind, list_schemas, open_schema, open_schema_read_only, CreateOptions, Endian, ErrorKind, Operate};
+    use crate::Comparator;
+    use crate::page::{LeafPage, NULL_PAGE, Pager};
+    use crate::storage::Storage;
+
+    const PAGE_SIZE: u32 = 512;
+
+    #[test]
+    fn mete_data_allocate_free_page() {
+        let dir_name = "mete_data_allocate_free_page";
+        fs::create_dir_all(dir_name).unwrap();
+        let mut mete_data = create_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap();
+        let data_file = Storage::from(OpenOptions::new().read(true).write(true).create(true).truncate(false)
+            .open(mete_data.get_data_file_path().unwrap()).unwrap());
+        let page_size = PAGE_SIZE as u64;
+
+        assert_eq!(0, mete_data.allocate_page(&data_file).unwrap());
+        assert_eq!(page_size, mete_data.allocate_page(&data_file).unwrap());
+        assert_eq!(2 * page_size, mete_data.allocate_page(&data_file).unwrap());
+
+        mete_data.free_page(&data_file, page_size).unwrap();
+        mete_data.free_page(&data_file, 0).unwrap();
+        assert_eq!(0, mete_data.get_free_page_head());
+
+        // 后释放的页先被复用，空闲链表耗尽后再追加新页
+        assert_eq!(0, mete_data.allocate_page(&data_file).unwrap());
+        assert_eq!(page_size, mete_data.allocate_page(&data_file).unwrap());
+        assert_eq!(NULL_PAGE, mete_data.get_free_page_head());
+        assert_eq!(3 * page_size, mete_data.allocate_page(&data_file).unwrap());
+
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+
+    #[test]
+    fn mete_data_page_offset_beyond_4gib() {
+        let dir_name = "mete_data_page_offset_beyond_4gib";
+        fs::create_dir_all(dir_name).unwrap();
+        let mut mete_data = create_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap();
+        let data_file = Storage::from(OpenOptions::new().read(true).write(true).create(true).truncate(false)
+            .open(mete_data.get_data_file_path().unwrap()).unwrap());
+        // 稀疏文件，不会实际占用4GiB的磁盘空间
+        let offset = 1u64 << 32;
+        data_file.set_len(offset).unwrap();
+
+        assert_eq!(offset, mete_data.allocate_page(&data_file).unwrap());
+        let mut leaf_page = LeafPage::new(&data_file, offset, PAGE_SIZE, Comparator::ByteLexicographic).unwrap();
+        leaf_page.update_next_page(offset + PAGE_SIZE as u64);
+        let leaf_page = LeafPage::from(&data_file, offset, PAGE_SIZE, true, Comparator::ByteLexicographic).unwrap();
+        assert_eq!(offset + PAGE_SIZE as u64, leaf_page.get_next_page());
+
+        mete_data.set_root_page_offset(offset);
+        mete_data.free_page(&data_file, offset).unwrap();
+        mete_data.flush().unwrap();
+        drop(mete_data);
+        let mete_data = open_schema(dir_name, "test").unwrap();
+        assert_eq!(offset, mete_data.get_root_page_offset());
+        assert_eq!(offset, mete_data.get_free_page_head());
+
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+
+    #[test]
+    fn open_schema_settings() {
+        let dir_name = "open_schema_settings";
+        fs::create_dir_all(dir_name).unwrap();
+        let error = open_schema(dir_name, "test").err().unwrap();
+        assert_eq!(ErrorKind::NotFound, error_kind(error.as_ref()));
+        drop(create_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap());
+
+        let mete_data = open_schema(dir_name, "test").unwrap();
+        assert_eq!(PAGE_SIZE, mete_data.page_size);
+        assert_eq!(64, mete_data.key_max_length);
+        assert_eq!(128, mete_data.value_threshold);
+
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+
+    #[test]
+    fn mete_data_settings_persist() {
+        let dir_name = "mete_data_settings_persist";
+        fs::create_dir_all(dir_name).unwrap();
+        let mete_data = create_schema(dir_name, "test", 1024, 32, 256).unwrap();
+        let controller = mete_data.controller().unwrap();
+        controller.put_bytes("test".as_bytes(), "test".as_bytes()).unwrap();
+        drop(controller);
+
+        let mete_data = open_schema(dir_name, "test").unwrap();
+        assert_eq!(1024, mete_data.get_page_size());
+        assert_eq!(32, mete_data.get_key_max_length());
+        assert_eq!(256, mete_data.get_value_threshold());
+
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+
+    #[test]
+    fn mete_data_file_path_invalid_utf8() {
+        let dir_name = "mete_data_file_path_invalid_utf8";
+        fs::create_dir_all(dir_name).unwrap();
+        let mut mete_data = create_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap();
+        mete_data.mete_page.write_u8(8 + 4, 0xff);
+
+        assert!(mete_data.get_data_file_path().is_err());
+        assert!(mete_data.controller().is_err());
+
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+
+    #[test]
+    fn schema_name_validation() {
+        let dir_name = "schema_name_validation";
+        fs::create_dir_all(dir_name).unwrap();
+        assert!(create_schema(dir_name, "valid_name1", PAGE_SIZE, 64, 128).is_ok());
+        for schema_name in ["", "Bad", "a/b", "../etc", "a.b", "Foo Bar!"] {
+            let error = create_schema(dir_name, schema_name, PAGE_SIZE, 64, 128).err().unwrap();
+            assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+            assert!(open_schema(dir_name, schema_name).is_err());
+        }
+
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+
+    #[test]
+    fn page_size_validation() {
+        let dir_name = "page_size_validation";
+        fs::create_dir_all(dir_name).unwrap();
+        for page_size in [16, 100, 0] {
+            let error = create_schema(dir_name, "test", page_size, 8, 16).err().unwrap();
+            assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+            assert!(error.to_string().contains("page_size"));
+        }
+        // 校验失败时不会创建元数据文件
+        assert!(!PathBuf::from(format!("{}/test.m", dir_name)).exists());
+        assert_eq!(4096, create_schema(dir_name, "test", 4096, 64, 128).unwrap().get_page_size());
+
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+
+    #[test]
+    fn drop_schema_remove_files() {
+        let dir_name = "drop_schema_remove_files";
+        fs::create_dir_all(dir_name).unwrap();
+        let error = drop_schema(dir_name, "test").err().unwrap();
+        assert_eq!(ErrorKind::NotFound, error_kind(error.as_ref()));
+
+        // 还没有写入数据的schema只有元数据文件和锁文件
+        drop(create_schema(dir_name, "empty", PAGE_SIZE, 64, 128).unwrap());
+        drop_schema(dir_name, "empty").unwrap();
+        assert!(!PathBuf::from(format!("{}/empty.m", dir_name)).exists());
+        assert!(!PathBuf::from(format!("{}/empty.lock", dir_name)).exists());
+
+        let controller = create_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap().controller().unwrap();
+        controller.put_bytes("test".as_bytes(), "test".as_bytes()).unwrap();
+        drop(controller);
+        fs::write(format!("{}/test.e", dir_name), "extra").unwrap();
+        drop_schema(dir_name, "test").unwrap();
+        for suffix in ["m", "d", "e", "lock"] {
+            assert!(!PathBuf::from(format!("{}/test.{}", dir_name, suffix)).exists());
+        }
+        assert!(open_schema(dir_name, "test").is_err());
+
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+
+    #[test]
+    fn schema_endian() {
+        let dir_name = "schema_endian";
+        fs::create_dir_all(dir_name).unwrap();
+        let mete_data = create_schema_with_options(dir_name, "test", PAGE_SIZE, 64, 128, CreateOptions { endian: Endian::Big, ..CreateOptions::default() }).unwrap();
+        assert_eq!(Endian::Big, mete_data.get_endian());
+        let controller = mete_data.controller().unwrap();
+        controller.put("small", "value").unwrap();
+        controller.put("large", &"large".repeat(100)).unwrap();
+        drop(controller);
+
+        // 数据文件中的整数按记录的字节序写入，按小端序读取页时校验失败
+        let data_file_path = format!("{}/test.d", dir_name);
+        let data_file = || Storage::from(OpenOptions::new().read(true).write(true).open(&data_file_path).unwrap());
+        assert!(LeafPage::from(&data_file(), 0, PAGE_SIZE, true, Comparator::ByteLexicographic).is_err());
+        let leaf_page = LeafPage::from(&data_file().with_endian(Endian::Big), 0, PAGE_SIZE, true, Comparator::ByteLexicographic).unwrap();
+        assert_eq!(NULL_PAGE, leaf_page.get_next_page());
+
+        let mete_data = open_schema(dir_name, "test").unwrap();
+        assert_eq!(Endian::Big, mete_data.get_endian());
+        let controller = mete_data.controller().unwrap();
+        assert_eq!(Some("value".to_string()), controller.get("small").unwrap());
+        assert_eq!(Some("large".repeat(100)), controller.get("large").unwrap());
+        for i in 0..300 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i).repeat(i % 40 + 1)).unwrap();
+        }
+        controller.verify().unwrap();
+        drop(controller);
+        let controller = open_schema_read_only(dir_name, "test").unwrap().controller().unwrap();
+        assert_eq!(302, controller.len().unwrap());
+        for i in 0..300 {
+            assert_eq!(Some(format!("value{}", i).repeat(i % 40 + 1)), controller.get(&format!("key{:03}", i)).unwrap());
+        }
+        drop(controller);
+        // 默认使用小端序
+        assert_eq!(Endian::Little, create_schema(dir_name, "little", PAGE_SIZE, 64, 128).unwrap().get_endian());
+
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+
+    #[test]
+    fn schema_create_options() {
+        let dir_name = "schema_create_options";
+        fs::create_dir_all(dir_name).unwrap();
+        // 同时启用过期时间、布隆过滤器和大端序
+        let options = CreateOptions { endian: Endian::Big, bloom_filter: true, ttl: true, ..CreateOptions::default() };
+        let controller = create_schema_with_options(dir_name, "test", PAGE_SIZE, 64, 128, options).unwrap().controller().unwrap();
+        for i in 0..100 {
+            controller.put(&format!("key{:03}", i), &format!("value{}", i)).unwrap();
+        }
+        controller.put_with_ttl(b"key000", b"expired", Duration::ZERO).unwrap();
+        controller.reset_io_stats();
+        assert_eq!(None, controller.get("key000").unwrap());
+        assert_eq!(None, controller.get("absent").unwrap());
+        assert!(controller.io_stats().bloom_filter_skip_count > 0);
+        drop(controller);
+
+        let mete_data = open_schema(dir_name, "test").unwrap();
+        assert!(mete_data.has_ttl());
+        assert!(mete_data.has_bloom_filter());
+        assert!(!mete_data.is_multi_value());
+        assert_eq!(Endian::Big, mete_data.get_endian());
+        let controller = mete_data.controller().unwrap();
+        assert_eq!(1, controller.purge_expired().unwrap());
+        assert_eq!(99, controller.len().unwrap());
+        controller.verify().unwrap();
+        drop(controller);
+
+        // 多值模式不支持过期时间和其它排序方式，检查失败时不会创建文件
+        let options = [
+            CreateOptions { multi_value: true, ttl: true, ..CreateOptions::default() },
+            CreateOptions { multi_value: true, comparator: Comparator::CaseInsensitiveAscii, ..CreateOptions::default() },
+        ];
+        for options in options {
+            let error = create_schema_with_options(dir_name, "invalid", PAGE_SIZE, 64, 128, options).err().unwrap();
+            assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+            assert!(!PathBuf::from(format!("{}/invalid.m", dir_name)).exists());
+        }
+        assert_eq!(CreateOptions::default(), CreateOptions { multi_value: false, endian: Endian::Little, bloom_filter: false, ttl: false, comparator: Comparator::ByteLexicographic });
+
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn schema_lock() {
+        let dir_name = "schema_lock";
+        fs::create_dir_all(dir_name).unwrap();
+        let controller = create_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap().controller().unwrap();
+        controller.put_bytes("key".as_bytes(), "value".as_bytes()).unwrap();
+        controller.flush().unwrap();
+
+        // 锁属于打开的文件，同一进程中第二次以写方式打开同样失败
+        let error = open_schema(dir_name, "test").err().unwrap();
+        assert_eq!(ErrorKind::Locked, error_kind(error.as_ref()));
+        assert!(error.to_string().contains("schema locked by another process"), "{}", error);
+        let error = drop_schema(dir_name, "test").err().unwrap();
+        assert_eq!(ErrorKind::Locked, error_kind(error.as_ref()));
+        // 只读打开不需要锁
+        let reader = open_schema_read_only(dir_name, "test").unwrap().controller().unwrap();
+        assert_eq!(Some("value".as_bytes().to_vec()), reader.get_bytes("key".as_bytes()).unwrap());
+        drop(reader);
+
+        // controller drop之后释放锁
+        drop(controller);
+        let mete_data = open_schema(dir_name, "test").unwrap();
+        assert_eq!(ErrorKind::Locked, error_kind(open_schema(dir_name, "test").err().unwrap().as_ref()));
+        drop(mete_data);
+        open_schema(dir_name, "test").unwrap().controller().unwrap().close().unwrap();
+        drop_schema(dir_name, "test").unwrap();
+
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+
+    #[test]
+    fn list_schemas_in_dir() {
+        let dir_name = "list_schemas_in_dir";
+        fs::create_dir_all(dir_name).unwrap();
+        assert!(list_schemas(dir_name).unwrap().is_empty());
+
+        for schema_name in ["orders", "users", "audit_log"] {
+            drop(create_schema(dir_name, schema_name, PAGE_SIZE, 64, 128).unwrap());
+        }
+        let controller = open_schema(dir_name, "users").unwrap().controller().unwrap();
+        controller.put_bytes("test".as_bytes(), "test".as_bytes()).unwrap();
+        drop(controller);
+        fs::write(format!("{}/notes.txt", dir_name), "notes").unwrap();
+        fs::create_dir_all(format!("{}/nested.m", dir_name)).unwrap();
+        assert_eq!(vec!["audit_log", "orders", "users"], list_schemas(dir_name).unwrap());
+
+        drop_schema(dir_name, "orders").unwrap();
+        assert_eq!(vec!["audit_log", "users"], list_schemas(dir_name).unwrap());
+
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn create_schema_read_only_dir() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir_name = "create_schema_read_only_dir";
+        fs::create_dir_all(dir_name).unwrap();
+        fs::set_permissions(dir_name, fs::Permissions::from_mode(0o555)).unwrap();
+        // root用户不受目录权限限制，无法构造写入失败的情况
+        let writable = fs::File::create(format!("{}/probe", dir_name)).is_ok();
+        if !writable {
+            let error = create_schema(dir_name, "test", PAGE_SIZE, 64, 128).err().unwrap();
+            assert_eq!(ErrorKind::Io, error_kind(error.as_ref()));
+            assert!(list_schemas(dir_name).unwrap().is_empty());
+        }
+
+        fs::set_permissions(dir_name, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+}
@@ -1,6 +1,19 @@
-use std::fs::File;
-use crate::MiniBaseResult;
-use crate::page::{InnerPage, LeafPage};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use crate::{error_kind, ErrorKind, MeteData, MiniBaseError, MiniBaseResult};
+use crate::page::{AccessPattern, InnerPage, InsertResult, LeafPage, NULL_PAGE, Page, Pager, Rebalance};
+use crate::repair::{LeafScan, LostRange, VerificationErrorKind, VerifiedLeaf, VerifyScan};
+use crate::storage::Storage;
+
+// 在错误信息前加上出错的位置，例如出错的key
+pub(crate) fn with_message_prefix(prefix: &str, error: Box<dyn Error>) -> Box<dyn Error> {
+    match error.downcast_ref::<MiniBaseError>() {
+        Some(error) => Box::from(MiniBaseError::new(error.kind(), format!("{}: {}", prefix, error.message()))),
+        None => error,
+    }
+}
 
 #[derive(PartialEq, Copy, Clone)]
 pub(crate) enum NodeType {
@@ -8,44 +21,960 @@ pub(crate) enum NodeType {
     Inner,
 }
 
+// 节点分裂的结果，separator为右侧节点中最小的key
+pub(crate) struct Split {
+    pub(crate) separator: Vec<u8>,
+    pub(crate) right_offset: u64,
+}
+
+// 树中所有页的统计信息，叶节点的统计为所有叶节点之和
+#[derive(Debug, PartialEq, Default)]
+pub struct Stats {
+    pub live_key_count: usize,
+    pub tombstone_count: usize,
+    pub free_space: usize,
+    pub reclaimable_garbage_bytes: usize,
+    pub leaf_page_count: usize,
+    pub inner_page_count: usize,
+    // 从根节点到叶节点的层数，只有根节点时为1
+    pub height: usize,
+    // 页缓存中的节点数量
+    pub cached_page_count: usize,
+}
+
+// 打开之后读写数据文件的统计，可以用于判断页缓存是否有效以及负载是否受限于缺页
+#[derive(Debug, PartialEq, Default, Copy, Clone)]
+pub struct IoStats {
+    // 映射数据文件中页的次数，包括新分配的页和只读取页头的映射
+    pub mapped_page_count: u64,
+    // 从文件读取的叶节点和内部节点数量，从页缓存中取出的节点不计入
+    pub leaf_page_read_count: u64,
+    pub inner_page_read_count: u64,
+    pub bytes_read: u64,
+    // 修改后写回文件的页的字节数
+    pub bytes_written: u64,
+    pub split_count: u64,
+    pub merge_count: u64,
+    // 叶节点的布隆过滤器判断key不存在，不需要在页中二分查找的次数
+    pub bloom_filter_skip_count: u64,
+    // flush时将数据文件同步到磁盘的次数，包括SyncMode触发的flush
+    pub sync_count: u64,
+}
+
+// 树结构变化的事件，偏移都是数据文件中页的偏移
+#[derive(Debug, PartialEq, Clone)]
+pub enum StructuralEvent {
+    // 子节点left分裂出right，separator为right中最小的key，写入父节点parent，parent随后分裂时separator可能位于parent分裂出的节点中
+    Split { parent: u64, left: u64, right: u64, separator: Vec<u8> },
+    // 父节点parent中相邻的两个子节点合并到survivor，freed被释放
+    Merge { parent: u64, survivor: u64, freed: u64 },
+    // 根节点分裂、收缩或被清空后根节点改变
+    RootChange { old: u64, new: u64 },
+}
+
+// 树结构变化时调用的回调，在写操作持有写锁期间调用，不能再调用同一个controller的方法
+pub(crate) type StructuralHook = dyn Fn(StructuralEvent) + Send + Sync;
+
+// IoStats对应的计数器，读操作只持有读锁，因此使用原子变量
+#[derive(Default)]
+pub(crate) struct IoCounters {
+    mapped_page_count: AtomicU64,
+    leaf_page_read_count: AtomicU64,
+    inner_page_read_count: AtomicU64,
+    split_count: AtomicU64,
+    merge_count: AtomicU64,
+    bloom_filter_skip_count: AtomicU64,
+    sync_count: AtomicU64,
+}
+
+impl IoCounters {
+    pub(crate) fn record_mapped_page(&self) {
+        self.mapped_page_count.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+
+    fn record_page_read(&self, node_type: NodeType) {
+        let counter = match node_type {
+            NodeType::Leaf => &self.leaf_page_read_count,
+            NodeType::Inner => &self.inner_page_read_count,
+        };
+        counter.fetch_add(1, AtomicOrdering::Relaxed);
+        self.record_mapped_page();
+    }
+
+    fn record_split(&self) {
+        self.split_count.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+
+    fn record_merge(&self) {
+        self.merge_count.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+
+    fn record_bloom_filter_skip(&self) {
+        self.bloom_filter_skip_count.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+
+    pub(crate) fn record_sync(&self) {
+        self.sync_count.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+
+    // 读取和写入的字节数由读取和写回的页数量乘以页大小得到
+    pub(crate) fn snapshot(&self, page_size: u32, flushed_page_count: u64) -> IoStats {
+        let leaf_page_read_count = self.leaf_page_read_count.load(AtomicOrdering::Relaxed);
+        let inner_page_read_count = self.inner_page_read_count.load(AtomicOrdering::Relaxed);
+        IoStats {
+            mapped_page_count: self.mapped_page_count.load(AtomicOrdering::Relaxed),
+            leaf_page_read_count,
+            inner_page_read_count,
+            bytes_read: (leaf_page_read_count + inner_page_read_count) * page_size as u64,
+            bytes_written: flushed_page_count * page_size as u64,
+            split_count: self.split_count.load(AtomicOrdering::Relaxed),
+            merge_count: self.merge_count.load(AtomicOrdering::Relaxed),
+            bloom_filter_skip_count: self.bloom_filter_skip_count.load(AtomicOrdering::Relaxed),
+            sync_count: self.sync_count.load(AtomicOrdering::Relaxed),
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        for counter in [&self.mapped_page_count, &self.leaf_page_read_count, &self.inner_page_read_count, &self.split_count, &self.merge_count, &self.bloom_filter_skip_count, &self.sync_count] {
+            counter.store(0, AtomicOrdering::Relaxed);
+        }
+    }
+}
+
+// 根据key当前的value数据计算新的value数据，key不存在或已删除时参数为None
+pub(crate) type MergeValue<'a> = dyn FnMut(Option<&[u8]>) -> MiniBaseResult<Vec<u8>> + 'a;
+
 pub(crate) struct Node {
+    offset: u64,
     leaf_page: Option<LeafPage>,
     inner_page: Option<InnerPage>,
     node_type: NodeType,
 }
 
 impl Node {
-    pub(crate) fn new(file: &File, offset: u32, page_size: u32, node_type: NodeType) -> MiniBaseResult<Node> {
+    pub(crate) fn new(mete_data: &MeteData, file: &Storage, offset: u64, node_type: NodeType) -> MiniBaseResult<Node> {
+        let (page_size, comparator) = (mete_data.page_size, mete_data.comparator.clone());
+        mete_data.io_counters.record_mapped_page();
         match node_type {
             NodeType::Leaf => {
-                let page = LeafPage::new(file, offset, page_size)?;
-                Ok(Node { leaf_page: Some(page), inner_page: None, node_type: NodeType::Leaf })
+                let page = LeafPage::new(file, offset, page_size, comparator)?.with_bloom_filter(mete_data.bloom_filter);
+                Ok(Node { offset, leaf_page: Some(page), inner_page: None, node_type: NodeType::Leaf })
             }
             NodeType::Inner => {
-                let page = InnerPage::new(file, offset, page_size)?;
-                Ok(Node { leaf_page: None, inner_page: Some(page), node_type: NodeType::Inner })
+                let page = InnerPage::new(file, offset, page_size, comparator)?;
+                Ok(Node { offset, leaf_page: None, inner_page: Some(page), node_type: NodeType::Inner })
             }
         }
     }
 
-    pub(crate) fn from(file: &File, offset: u32, page_size: u32, node_type: NodeType) -> MiniBaseResult<Node> {
+    pub(crate) fn from(mete_data: &MeteData, file: &Storage, offset: u64, node_type: NodeType) -> MiniBaseResult<Node> {
+        let (page_size, comparator) = (mete_data.page_size, mete_data.comparator.clone());
+        mete_data.io_counters.record_page_read(node_type);
         match node_type {
             NodeType::Leaf => {
-                let page = LeafPage::from(file, offset, page_size)?;
-                Ok(Node { leaf_page: Some(page), inner_page: None, node_type: NodeType::Leaf })
+                let page = if mete_data.read_only {
+                    LeafPage::from_read_only(file, offset, page_size, mete_data.verify_checksum, comparator)?
+                } else {
+                    LeafPage::from(file, offset, page_size, mete_data.verify_checksum, comparator)?
+                };
+                Ok(Node { offset, leaf_page: Some(page), inner_page: None, node_type: NodeType::Leaf })
             }
             NodeType::Inner => {
-                let page = InnerPage::from(file, offset, page_size)?;
-                Ok(Node { leaf_page: None, inner_page: Some(page), node_type: NodeType::Inner })
+                let page = if mete_data.read_only {
+                    InnerPage::from_read_only(file, offset, page_size, mete_data.verify_checksum, comparator)?
+                } else {
+                    InnerPage::from(file, offset, page_size, mete_data.verify_checksum, comparator)?
+                };
+                Ok(Node { offset, leaf_page: None, inner_page: Some(page), node_type: NodeType::Inner })
             }
         }
     }
 
+    // 根据页头判断节点类型并读取节点，优先从缓存中取出，使用结束后需要通过release放回缓存
+    pub(crate) fn load(mete_data: &MeteData, file: &Storage, offset: u64) -> MiniBaseResult<Node> {
+        // 宽松模式下损坏的叶节点视为只保存在内存中的空叶节点，保留在叶节点链表中的位置
+        if let Some((previous_page, next_page)) = mete_data.skipped_leaves.get(&offset) {
+            let mut leaf_page = LeafPage::new_anonymous(mete_data.page_size, mete_data.comparator.clone())?;
+            leaf_page.update_previous_page(*previous_page);
+            leaf_page.update_next_page(*next_page);
+            return Ok(Node { offset, leaf_page: Some(leaf_page), inner_page: None, node_type: NodeType::Leaf });
+        }
+        // 写操作加载的页可能被修改，修改前记录原始数据
+        mete_data.journal_page(file, offset)?;
+        if let Some(node) = mete_data.page_cache().take(offset) {
+            return Ok(node);
+        }
+        // 只读取页头，只读打开时数据文件也只能只读映射
+        let page = Page::new_read_only(file, offset, mete_data.page_size)?;
+        mete_data.io_counters.record_mapped_page();
+        let node_type = if LeafPage::is_leaf_header(page.read_u8(0)) {
+            NodeType::Leaf
+        } else {
+            NodeType::Inner
+        };
+        let node = Node::from(mete_data, file, offset, node_type)?;
+        // 缓存中的节点在映射时已经提示过
+        if mete_data.access_pattern != AccessPattern::Normal {
+            node.advise(mete_data.access_pattern)?;
+        }
+        Ok(node)
+    }
+
+    // 提示操作系统之后访问节点所在页的方式
+    pub(crate) fn advise(&self, access_pattern: AccessPattern) -> MiniBaseResult<()> {
+        match self.get_type() {
+            NodeType::Leaf => self.leaf_page.as_ref().unwrap().advise(access_pattern),
+            NodeType::Inner => self.inner_page.as_ref().unwrap().advise(access_pattern),
+        }
+    }
+
+    // 根节点分裂后创建新的根节点，left_offset为原根节点
+    pub(crate) fn new_root(mete_data: &MeteData, file: &Storage, offset: u64, left_offset: u64, split: &Split) -> MiniBaseResult<Node> {
+        let mut inner_page = InnerPage::new(file, offset, mete_data.page_size, mete_data.comparator.clone())?;
+        mete_data.io_counters.record_mapped_page();
+        inner_page.update_last_pointer(left_offset);
+        inner_page.insert_separator(&split.separator, left_offset, split.right_offset);
+        Ok(Node { offset, leaf_page: None, inner_page: Some(inner_page), node_type: NodeType::Inner })
+    }
+
+    // 将使用结束的节点放回缓存，dirty表示使用期间是否修改了页
+    pub(crate) fn release(self, mete_data: &MeteData, dirty: bool) -> MiniBaseResult<()> {
+        mete_data.page_cache().release(self, dirty)
+    }
+
+    pub(crate) fn get_offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub(crate) fn flush(&mut self) -> MiniBaseResult<()> {
+        match self.get_type() {
+            NodeType::Leaf => self.leaf_page.as_mut().unwrap().flush(),
+            NodeType::Inner => self.inner_page.as_mut().unwrap().flush(),
+        }
+    }
+
     fn get_type(&self) -> NodeType {
         self.node_type
     }
 
-    pub(crate) fn put(&self, key: &str, value: &str) -> MiniBaseResult<()> {
-        todo!()
+    pub(crate) fn is_leaf(&self) -> bool {
+        self.node_type == NodeType::Leaf
+    }
+
+    // 读取key所在的子节点
+    fn load_child(&self, mete_data: &MeteData, file: &Storage, key: &[u8]) -> MiniBaseResult<Node> {
+        let inner_page = self.inner_page.as_ref().unwrap();
+        Node::load(mete_data, file, inner_page.find_child(key))
+    }
+
+    // 查找key所在的叶节点，同时返回叶节点中数据的上界，upper_bound为当前节点的上界
+    pub(crate) fn find_leaf(self, mete_data: &MeteData, file: &Storage, key: &[u8], upper_bound: Option<Vec<u8>>) -> MiniBaseResult<(Node, Option<Vec<u8>>)> {
+        match self.get_type() {
+            NodeType::Leaf => Ok((self, upper_bound)),
+            NodeType::Inner => {
+                let inner_page = self.inner_page.as_ref().unwrap();
+                let (child, child_upper_bound) = inner_page.find_child_with_upper_bound(key);
+                let child = Node::load(mete_data, file, child)?;
+                self.release(mete_data, false)?;
+                child.find_leaf(mete_data, file, key, child_upper_bound.or(upper_bound))
+            }
+        }
+    }
+
+    // 向叶节点插入数据，同时返回被覆盖的value，节点空间不足时返回NeedsSplit且不修改页，覆盖后垃圾数据超过garbage_ratio时整理页
+    pub(crate) fn insert_key_value(&mut self, key: &[u8], value: &[u8], garbage_ratio: f32) -> (InsertResult, Option<Vec<u8>>) {
+        let leaf_page = self.leaf_page.as_mut().unwrap();
+        let replaced = leaf_page.get_value(key).map(Vec::from);
+        let result = leaf_page.insert_key_value(key, value);
+        if result == InsertResult::Overwritten {
+            leaf_page.compact_if_garbage_exceeds(garbage_ratio);
+        }
+        (result, replaced)
     }
-}
\ No newline at end of file
+
+    // 检查叶节点的页数据，可回收的空间超过garbage_ratio时整理，返回是否整理，页数据不正确时不整理
+    pub(crate) fn vacuum(&mut self, verify_checksum: bool, garbage_ratio: f32) -> bool {
+        let leaf_page = self.leaf_page.as_mut().unwrap();
+        leaf_page.verify(verify_checksum).is_ok() && leaf_page.compact_if_garbage_exceeds(garbage_ratio)
+    }
+
+    // 叶节点中最小的key，包括已删除的key，空的叶节点返回None
+    pub(crate) fn get_first_key(&self) -> Option<Vec<u8>> {
+        self.leaf_page.as_ref().unwrap().get_keys_with_deleted().into_iter().next().map(|(key, _)| key)
+    }
+
+    // 查找节点下最左侧的叶节点
+    pub(crate) fn leftmost_leaf(self, mete_data: &MeteData, file: &Storage) -> MiniBaseResult<Node> {
+        match self.get_type() {
+            NodeType::Leaf => Ok(self),
+            NodeType::Inner => {
+                let child = Node::load(mete_data, file, self.inner_page.as_ref().unwrap().get_first_child())?;
+                self.release(mete_data, false)?;
+                child.leftmost_leaf(mete_data, file)
+            }
+        }
+    }
+
+    // 查找节点下最右侧的叶节点
+    pub(crate) fn rightmost_leaf(self, mete_data: &MeteData, file: &Storage) -> MiniBaseResult<Node> {
+        match self.get_type() {
+            NodeType::Leaf => Ok(self),
+            NodeType::Inner => {
+                let child = Node::load(mete_data, file, self.inner_page.as_ref().unwrap().get_last_pointer())?;
+                self.release(mete_data, false)?;
+                child.rightmost_leaf(mete_data, file)
+            }
+        }
+    }
+
+    // 叶节点中所有未删除的key value
+    pub(crate) fn get_key_values(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.leaf_page.as_ref().unwrap().get_key_values()
+    }
+
+    pub(crate) fn get_keys(&self) -> Vec<Vec<u8>> {
+        self.leaf_page.as_ref().unwrap().get_keys()
+    }
+
+    // 叶节点中与key最近的未删除的key value，参数与LeafPage::get_nearest相同
+    pub(crate) fn get_nearest(&self, key: Option<&[u8]>, inclusive: bool, forward: bool) -> Option<(Vec<u8>, Vec<u8>)> {
+        self.leaf_page.as_ref().unwrap().get_nearest(key, inclusive, forward)
+            .map(|(key, value)| (Vec::from(key), Vec::from(value)))
+    }
+
+    // 叶节点的上一个叶节点
+    pub(crate) fn get_previous_page(&self) -> u64 {
+        self.leaf_page.as_ref().unwrap().get_previous_page()
+    }
+
+    // 叶节点的下一个叶节点
+    pub(crate) fn get_next_page(&self) -> u64 {
+        self.leaf_page.as_ref().unwrap().get_next_page()
+    }
+
+    // 查找叶节点前先检查布隆过滤器，key一定不存在时不需要二分查找
+    fn may_contain(&self, mete_data: &MeteData, key: &[u8]) -> bool {
+        if self.leaf_page.as_ref().unwrap().may_contain(key) {
+            return true;
+        }
+        mete_data.io_counters.record_bloom_filter_skip();
+        false
+    }
+
+    pub(crate) fn get(&self, mete_data: &MeteData, file: &Storage, key: &[u8]) -> MiniBaseResult<Option<Vec<u8>>> {
+        match self.get_type() {
+            NodeType::Leaf => {
+                if !self.may_contain(mete_data, key) {
+                    return Ok(None);
+                }
+                let leaf_page = self.leaf_page.as_ref().unwrap();
+                Ok(leaf_page.get_value(key).map(Vec::from))
+            }
+            NodeType::Inner => {
+                let child = self.load_child(mete_data, file, key)?;
+                let value = child.get(mete_data, file, key);
+                child.release(mete_data, false)?;
+                value
+            }
+        }
+    }
+
+    // 在叶节点中找到key的value数据后直接交给read读取，不复制value数据
+    pub(crate) fn read_value<T>(&self, mete_data: &MeteData, file: &Storage, key: &[u8], read: &dyn Fn(&[u8]) -> MiniBaseResult<T>) -> MiniBaseResult<Option<T>> {
+        match self.get_type() {
+            NodeType::Leaf if !self.may_contain(mete_data, key) => Ok(None),
+            NodeType::Leaf => self.leaf_page.as_ref().unwrap().get_value(key).map(read).transpose(),
+            NodeType::Inner => {
+                let child = self.load_child(mete_data, file, key)?;
+                let value = child.read_value(mete_data, file, key, read);
+                child.release(mete_data, false)?;
+                value
+            }
+        }
+    }
+
+    // 读取保存的key和value数据
+    pub(crate) fn get_entry(&self, mete_data: &MeteData, file: &Storage, key: &[u8]) -> MiniBaseResult<Option<(Vec<u8>, Vec<u8>)>> {
+        match self.get_type() {
+            NodeType::Leaf => {
+                if !self.may_contain(mete_data, key) {
+                    return Ok(None);
+                }
+                let leaf_page = self.leaf_page.as_ref().unwrap();
+                Ok(leaf_page.get_entry(key).map(|(key, value)| (Vec::from(key), Vec::from(value))))
+            }
+            NodeType::Inner => {
+                let child = self.load_child(mete_data, file, key)?;
+                let entry = child.get_entry(mete_data, file, key);
+                child.release(mete_data, false)?;
+                entry
+            }
+        }
+    }
+
+    // 判断key是否存在且未删除
+    pub(crate) fn contains_key(&self, mete_data: &MeteData, file: &Storage, key: &[u8]) -> MiniBaseResult<bool> {
+        match self.get_type() {
+            NodeType::Leaf => Ok(self.may_contain(mete_data, key) && self.leaf_page.as_ref().unwrap().has_live_key(key)),
+            NodeType::Inner => {
+                let child = self.load_child(mete_data, file, key)?;
+                let exist = child.contains_key(mete_data, file, key);
+                child.release(mete_data, false)?;
+                exist
+            }
+        }
+    }
+
+    // 写入key value，返回被覆盖的value，节点分裂时同时返回分裂结果，由调用方将分隔key写入父节点
+    pub(crate) fn put(&mut self, mete_data: &mut MeteData, file: &Storage, key: &[u8], value: &[u8]) -> MiniBaseResult<(Option<Vec<u8>>, Option<Split>)> {
+        match self.get_type() {
+            NodeType::Leaf => {
+                let leaf_page = self.leaf_page.as_mut().unwrap();
+                let replaced = leaf_page.get_value(key).map(Vec::from);
+                match leaf_page.insert_key_value(key, value) {
+                    InsertResult::Inserted => Ok((replaced, None)),
+                    InsertResult::Overwritten => {
+                        leaf_page.compact_if_garbage_exceeds(mete_data.garbage_ratio);
+                        Ok((replaced, None))
+                    }
+                    InsertResult::NeedsSplit => Ok((replaced, Some(self.split_leaf(mete_data, file, key, value)?))),
+                }
+            }
+            NodeType::Inner => {
+                let mut child = self.load_child(mete_data, file, key)?;
+                let child_offset = child.offset;
+                let result = child.put(mete_data, file, key, value);
+                child.release(mete_data, true)?;
+                match result? {
+                    (replaced, None) => Ok((replaced, None)),
+                    (replaced, Some(split)) => Ok((replaced, self.insert_split(mete_data, file, child_offset, split)?)),
+                }
+            }
+        }
+    }
+
+    // key存在时返回已有的value，否则写入default_value并返回None，节点分裂时同时返回分裂结果
+    pub(crate) fn get_or_insert(&mut self, mete_data: &mut MeteData, file: &Storage, key: &[u8], default_value: &[u8]) -> MiniBaseResult<(Option<Vec<u8>>, Option<Split>)> {
+        match self.get_type() {
+            NodeType::Leaf => {
+                let leaf_page = self.leaf_page.as_mut().unwrap();
+                match leaf_page.get_or_insert(key, default_value) {
+                    Some((value, false)) => Ok((Some(value), None)),
+                    Some((_, true)) => Ok((None, None)),
+                    None => Ok((None, Some(self.split_leaf(mete_data, file, key, default_value)?))),
+                }
+            }
+            NodeType::Inner => {
+                let mut child = self.load_child(mete_data, file, key)?;
+                let child_offset = child.offset;
+                let result = child.get_or_insert(mete_data, file, key, default_value);
+                child.release(mete_data, true)?;
+                match result? {
+                    (value, None) => Ok((value, None)),
+                    (value, Some(split)) => Ok((value, self.insert_split(mete_data, file, child_offset, split)?)),
+                }
+            }
+        }
+    }
+
+    // 读取key当前的value，由merge根据当前的value计算新的value并写入，返回被覆盖的value，节点分裂时同时返回分裂结果
+    pub(crate) fn merge(&mut self, mete_data: &mut MeteData, file: &Storage, key: &[u8], merge: &mut MergeValue) -> MiniBaseResult<(Option<Vec<u8>>, Option<Split>)> {
+        match self.get_type() {
+            NodeType::Leaf => {
+                let leaf_page = self.leaf_page.as_mut().unwrap();
+                let replaced = leaf_page.get_value(key).map(Vec::from);
+                let value = merge(replaced.as_deref())?;
+                match leaf_page.insert_key_value(key, &value) {
+                    InsertResult::Inserted => Ok((replaced, None)),
+                    InsertResult::Overwritten => {
+                        leaf_page.compact_if_garbage_exceeds(mete_data.garbage_ratio);
+                        Ok((replaced, None))
+                    }
+                    InsertResult::NeedsSplit => {
+                        let split = self.split_leaf(mete_data, file, key, &value)?;
+                        Ok((replaced, Some(split)))
+                    }
+                }
+            }
+            NodeType::Inner => {
+                let mut child = self.load_child(mete_data, file, key)?;
+                let child_offset = child.offset;
+                let result = child.merge(mete_data, file, key, merge);
+                child.release(mete_data, true)?;
+                match result? {
+                    (replaced, None) => Ok((replaced, None)),
+                    (replaced, Some(split)) => Ok((replaced, self.insert_split(mete_data, file, child_offset, split)?)),
+                }
+            }
+        }
+    }
+
+    // 叶节点空间不足时分裂，新数据与原有数据一起均分到当前节点和新分配的右侧节点
+    fn split_leaf(&mut self, mete_data: &mut MeteData, file: &Storage, key: &[u8], value: &[u8]) -> MiniBaseResult<Split> {
+        let (page_size, comparator) = (mete_data.page_size, mete_data.comparator.clone());
+        let leaf_page = self.leaf_page.as_mut().unwrap();
+        let right_offset = mete_data.allocate_page(file)?;
+        let mut right_page = LeafPage::new(file, right_offset, page_size, comparator.clone())?.with_bloom_filter(mete_data.bloom_filter);
+        mete_data.io_counters.record_mapped_page();
+        // 只在最右侧的叶节点追加key时使用rightmost分裂，顺序写入时左侧的叶节点不会再写入
+        let rightmost = mete_data.rightmost_split && leaf_page.get_next_page() == NULL_PAGE;
+        let separator = match leaf_page.split(&mut right_page, key, value, mete_data.split_fill_factor, rightmost) {
+            Ok(separator) => separator,
+            Err(error) => {
+                mete_data.free_page(file, right_offset)?;
+                return Err(error);
+            }
+        };
+        // 维护叶节点之间的双向链表
+        let next_page = leaf_page.get_next_page();
+        if next_page != NULL_PAGE {
+            mete_data.journal_page(file, next_page)?;
+            LeafPage::from(file, next_page, page_size, mete_data.verify_checksum, comparator)?.update_previous_page(right_offset);
+            mete_data.io_counters.record_page_read(NodeType::Leaf);
+        }
+        right_page.update_previous_page(self.offset);
+        right_page.update_next_page(next_page);
+        leaf_page.update_next_page(right_offset);
+        mete_data.io_counters.record_split();
+        Ok(Split { separator, right_offset })
+    }
+
+    // 子节点分裂后将分隔key写入当前节点，空间不足时当前节点也需要分裂
+    fn insert_split(&mut self, mete_data: &mut MeteData, file: &Storage, child_offset: u64, split: Split) -> MiniBaseResult<Option<Split>> {
+        let inner_page = self.inner_page.as_mut().unwrap();
+        if inner_page.insert_separator(&split.separator, child_offset, split.right_offset) {
+            self.notify_split(mete_data, child_offset, &split);
+            return Ok(None);
+        }
+        let right_offset = mete_data.allocate_page(file)?;
+        let mut right_page = InnerPage::new(file, right_offset, mete_data.page_size, mete_data.comparator.clone())?;
+        mete_data.io_counters.record_mapped_page();
+        let separator = match inner_page.split(&mut right_page, &split.separator, child_offset, split.right_offset) {
+            Ok(separator) => separator,
+            Err(error) => {
+                mete_data.free_page(file, right_offset)?;
+                return Err(error);
+            }
+        };
+        mete_data.io_counters.record_split();
+        self.notify_split(mete_data, child_offset, &split);
+        Ok(Some(Split { separator, right_offset }))
+    }
+
+    fn notify_split(&self, mete_data: &MeteData, child_offset: u64, split: &Split) {
+        mete_data.notify_structural_change(|| StructuralEvent::Split { parent: self.offset, left: child_offset, right: split.right_offset, separator: split.separator.clone() });
+    }
+
+    // 范围读取，返回下一个需要继续读取的叶节点偏移
+    pub(crate) fn scan(&self, mete_data: &MeteData, file: &Storage, begin: &[u8], end: &[u8], end_inclusive: bool, result: &mut Vec<(Vec<u8>, Vec<u8>)>) -> MiniBaseResult<Option<u64>> {
+        self.walk_range(mete_data, file, begin, &mut |leaf_page| Ok(leaf_page.scan(begin, end, end_inclusive, result)))
+    }
+
+    // 统计范围内未删除的key数量，返回下一个需要继续统计的叶节点偏移
+    pub(crate) fn count(&self, mete_data: &MeteData, file: &Storage, begin: &[u8], end: &[u8], end_inclusive: bool, count: &mut u64) -> MiniBaseResult<Option<u64>> {
+        self.walk_range(mete_data, file, begin, &mut |leaf_page| Ok(leaf_page.count(begin, end, end_inclusive, count)))
+    }
+
+    // 与count相同，统计[begin, end)内的key value占用的空间
+    pub(crate) fn measure(&self, mete_data: &MeteData, file: &Storage, begin: &[u8], end: &[u8], extra_size: &dyn Fn(&[u8]) -> MiniBaseResult<u64>, size: &mut u64) -> MiniBaseResult<Option<u64>> {
+        self.walk_range(mete_data, file, begin, &mut |leaf_page| leaf_page.measure(begin, end, extra_size, size))
+    }
+
+    // 找到begin所在的叶节点并调用visit，visit返回后续的页是否可能还有范围内的数据，返回下一个需要继续访问的叶节点偏移
+    fn walk_range(&self, mete_data: &MeteData, file: &Storage, begin: &[u8], visit: &mut dyn FnMut(&LeafPage) -> MiniBaseResult<bool>) -> MiniBaseResult<Option<u64>> {
+        match self.get_type() {
+            NodeType::Leaf => {
+                let leaf_page = self.leaf_page.as_ref().unwrap();
+                let has_more = visit(leaf_page)?;
+                let next_page = leaf_page.get_next_page();
+                if has_more && next_page != NULL_PAGE {
+                    Ok(Some(next_page))
+                } else {
+                    Ok(None)
+                }
+            }
+            NodeType::Inner => {
+                let child = self.load_child(mete_data, file, begin)?;
+                let next_page = child.walk_range(mete_data, file, begin, visit);
+                child.release(mete_data, false)?;
+                next_page
+            }
+        }
+    }
+
+    // 叶节点中未删除的key数量
+    pub(crate) fn get_live_key_count(&self) -> usize {
+        self.leaf_page.as_ref().unwrap().get_live_key_count()
+    }
+
+    // 删除key，返回删除的value数据，key不存在时返回None，删除后子节点数据过少时与相邻节点合并或重新分配
+    pub(crate) fn remove(&mut self, mete_data: &mut MeteData, file: &Storage, key: &[u8]) -> MiniBaseResult<Option<Vec<u8>>> {
+        match self.get_type() {
+            NodeType::Leaf => {
+                let leaf_page = self.leaf_page.as_mut().unwrap();
+                let value = leaf_page.get_value(key).map(Vec::from);
+                if value.is_some() {
+                    leaf_page.delete_value(key);
+                    leaf_page.compact_if_garbage_exceeds(mete_data.garbage_ratio);
+                }
+                Ok(value)
+            }
+            NodeType::Inner => {
+                let index = self.inner_page.as_ref().unwrap().find_child_index(key);
+                let mut child = self.load_child(mete_data, file, key)?;
+                let removed = child.remove(mete_data, file, key);
+                let underflow = child.is_underflow();
+                child.release(mete_data, true)?;
+                let removed = removed?;
+                if removed.is_some() && underflow {
+                    self.rebalance_child(mete_data, file, index)?;
+                }
+                Ok(removed)
+            }
+        }
+    }
+
+    fn is_underflow(&self) -> bool {
+        match self.get_type() {
+            NodeType::Leaf => self.leaf_page.as_ref().unwrap().is_underflow(),
+            NodeType::Inner => self.inner_page.as_ref().unwrap().is_underflow(),
+        }
+    }
+
+    // 内部节点只有一个子节点时返回该子节点，此时子节点可以代替当前节点
+    pub(crate) fn get_only_child(&self) -> Option<u64> {
+        match self.get_type() {
+            NodeType::Leaf => None,
+            NodeType::Inner => {
+                let inner_page = self.inner_page.as_ref().unwrap();
+                if inner_page.get_separators().is_empty() {
+                    Some(inner_page.get_last_pointer())
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    // 位于index的子节点数据过少，与相邻的兄弟节点合并或重新分配，并更新当前节点中的分隔key
+    // 优先与右侧的兄弟节点合并，LAST_POINTER与左侧的兄弟节点合并
+    fn rebalance_child(&mut self, mete_data: &mut MeteData, file: &Storage, index: usize) -> MiniBaseResult<()> {
+        let inner_page = self.inner_page.as_ref().unwrap();
+        let mut separators = inner_page.get_separators();
+        let mut last_pointer = inner_page.get_last_pointer();
+        if separators.is_empty() {
+            return Ok(());
+        }
+        let separator_index = index.min(separators.len() - 1);
+        let left_offset = separators[separator_index].1;
+        let right_offset = separators.get(separator_index + 1).map_or(last_pointer, |separator| separator.1);
+        let max_separator_length = inner_page.get_max_separator_length(separator_index);
+        let mut left = Node::load(mete_data, file, left_offset)?;
+        let mut right = Node::load(mete_data, file, right_offset)?;
+        let rebalance = match left.get_type() {
+            NodeType::Leaf => {
+                let right_page = right.leaf_page.as_mut().unwrap();
+                left.leaf_page.as_mut().unwrap().rebalance(right_page, max_separator_length)
+            }
+            NodeType::Inner => {
+                let right_page = right.inner_page.as_mut().unwrap();
+                left.inner_page.as_mut().unwrap().rebalance(right_page, &separators[separator_index].0, max_separator_length)
+            }
+        };
+        match rebalance {
+            Rebalance::Unchanged => {
+                left.release(mete_data, false)?;
+                right.release(mete_data, false)?;
+                return Ok(());
+            }
+            Rebalance::Redistributed(separator) => {
+                separators[separator_index].0 = separator;
+                left.release(mete_data, true)?;
+                right.release(mete_data, true)?;
+            }
+            Rebalance::Merged => {
+                if left.get_type() == NodeType::Leaf {
+                    // 维护叶节点之间的双向链表
+                    let next_page = right.get_next_page();
+                    if next_page != NULL_PAGE {
+                        mete_data.journal_page(file, next_page)?;
+                        LeafPage::from(file, next_page, mete_data.page_size, mete_data.verify_checksum, mete_data.comparator.clone())?.update_previous_page(left_offset);
+                        mete_data.io_counters.record_page_read(NodeType::Leaf);
+                    }
+                    left.leaf_page.as_mut().unwrap().update_next_page(next_page);
+                }
+                left.release(mete_data, true)?;
+                drop(right);
+                mete_data.free_page(file, right_offset)?;
+                mete_data.io_counters.record_merge();
+                mete_data.notify_structural_change(|| StructuralEvent::Merge { parent: self.offset, survivor: left_offset, freed: right_offset });
+                // 删除两个节点之间的分隔key，原来指向右侧节点的指针改为指向左侧节点
+                separators.remove(separator_index);
+                match separators.get_mut(separator_index) {
+                    Some(separator) => separator.1 = left_offset,
+                    None => last_pointer = left_offset,
+                }
+            }
+        }
+        self.inner_page.as_mut().unwrap().rewrite(&separators, last_pointer);
+        Ok(())
+    }
+
+    // 检查当前节点下所有页的数据，以及所有key位于[lower_bound, upper_bound)内，按顺序收集叶节点偏移、层数和数据的范围
+    // 无法读取的子节点记录为问题并跳过其中的子树
+    pub(crate) fn verify(&self, mete_data: &MeteData, file: &Storage, depth: usize, lower_bound: Option<&[u8]>, upper_bound: Option<&[u8]>, scan: &mut VerifyScan) -> MiniBaseResult<()> {
+        let comparator = mete_data.comparator.clone();
+        let check_bound = |key: &[u8], scan: &mut VerifyScan| -> MiniBaseResult<()> {
+            let below_lower = lower_bound.is_some_and(|lower_bound| comparator.compare(key, lower_bound) == Ordering::Less);
+            let above_upper = upper_bound.is_some_and(|upper_bound| comparator.compare(key, upper_bound) != Ordering::Less);
+            if below_lower || above_upper {
+                scan.report(self.offset, VerificationErrorKind::KeyOutOfBounds, format!("key {} out of separator bounds", key.escape_ascii()), lower_bound, upper_bound)?;
+            }
+            Ok(())
+        };
+        match self.get_type() {
+            NodeType::Leaf => {
+                let leaf_page = self.leaf_page.as_ref().unwrap();
+                if let Err(error) = leaf_page.verify(mete_data.verify_checksum) {
+                    scan.report_error(self.offset, VerificationErrorKind::InvalidPage, error, lower_bound, upper_bound)?;
+                }
+                for (key, _) in leaf_page.get_keys_with_deleted() {
+                    check_bound(&key, scan)?;
+                }
+                scan.leaves.push(Some(VerifiedLeaf { offset: self.offset, depth, begin: lower_bound.map(<[u8]>::to_vec), end: upper_bound.map(<[u8]>::to_vec) }));
+            }
+            NodeType::Inner => {
+                let inner_page = self.inner_page.as_ref().unwrap();
+                if let Err(error) = inner_page.verify(mete_data.verify_checksum) {
+                    scan.report_error(self.offset, VerificationErrorKind::InvalidPage, error, lower_bound, upper_bound)?;
+                }
+                let separators = inner_page.get_separators();
+                for (key, _) in &separators {
+                    check_bound(key, scan)?;
+                }
+                // 每个子节点的下界为左侧的分隔key，上界为对应的分隔key，LAST_POINTER的上界为当前节点的上界
+                let mut children: Vec<_> = separators.iter().map(|(key, child)| (*child, Some(key.as_slice()))).collect();
+                children.push((inner_page.get_last_pointer(), upper_bound));
+                let mut child_lower_bound = lower_bound;
+                for (child, child_upper_bound) in children {
+                    match Node::load(mete_data, file, child) {
+                        Ok(child) => {
+                            let result = child.verify(mete_data, file, depth + 1, child_lower_bound, child_upper_bound, scan);
+                            child.release(mete_data, false)?;
+                            result?;
+                        }
+                        Err(error) => {
+                            scan.report_error(child, VerificationErrorKind::InvalidPage, error, child_lower_bound, child_upper_bound)?;
+                            scan.leaves.push(None);
+                        }
+                    }
+                    child_lower_bound = child_upper_bound;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // 收集当前节点下所有页的父节点偏移以及是否为叶节点，parent为当前节点的父节点，根节点为NULL_PAGE
+    pub(crate) fn collect_pages(&self, mete_data: &MeteData, file: &Storage, parent: u64, pages: &mut HashMap<u64, (u64, bool)>) -> MiniBaseResult<()> {
+        pages.insert(self.offset, (parent, self.is_leaf()));
+        if let Some(inner_page) = self.inner_page.as_ref() {
+            for child in inner_page.get_children() {
+                let child = Node::load(mete_data, file, child)?;
+                let result = child.collect_pages(mete_data, file, self.offset, pages);
+                child.release(mete_data, false)?;
+                result?;
+            }
+        }
+        Ok(())
+    }
+
+    // 沿第一个能够读取的子节点向下查找叶节点所在的层数，depth为当前节点的层数
+    pub(crate) fn find_leaf_depth(&self, mete_data: &MeteData, file: &Storage, depth: usize) -> MiniBaseResult<usize> {
+        if self.is_leaf() {
+            return Ok(depth);
+        }
+        for child in self.inner_page.as_ref().unwrap().get_children() {
+            if let Ok(child) = Node::load(mete_data, file, child) {
+                let result = child.find_leaf_depth(mete_data, file, depth + 1);
+                child.release(mete_data, false)?;
+                return result;
+            }
+        }
+        Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("page {}: no readable child", self.offset))))
+    }
+
+    // 按key的顺序收集当前节点下的所有叶节点，位于叶节点层但无法读取的页记录为损坏的叶节点
+    // lower_bound和upper_bound为当前节点中数据的范围，内部节点无法读取时返回错误
+    pub(crate) fn collect_leaves(&self, mete_data: &MeteData, file: &Storage, depth: usize, lower_bound: Option<&[u8]>, upper_bound: Option<&[u8]>, scan: &mut LeafScan) -> MiniBaseResult<()> {
+        let inner_page = match self.get_type() {
+            NodeType::Leaf => {
+                scan.leaves.push(self.offset);
+                return Ok(());
+            }
+            NodeType::Inner => self.inner_page.as_ref().unwrap(),
+        };
+        let separators = inner_page.get_separators();
+        let mut children: Vec<_> = separators.iter().map(|(key, child)| (*child, Some(key.as_slice()))).collect();
+        children.push((inner_page.get_last_pointer(), upper_bound));
+        let mut child_lower_bound = lower_bound;
+        for (child, child_upper_bound) in children {
+            match Node::load(mete_data, file, child) {
+                Ok(node) => {
+                    let result = if node.is_leaf() == (depth + 1 == scan.leaf_depth) {
+                        node.collect_leaves(mete_data, file, depth + 1, child_lower_bound, child_upper_bound, scan)
+                    } else {
+                        Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("page {}: node at depth {} not match leaf depth {}", child, depth + 1, scan.leaf_depth))))
+                    };
+                    node.release(mete_data, false)?;
+                    result?;
+                }
+                Err(error) if depth + 1 == scan.leaf_depth && error_kind(error.as_ref()) == ErrorKind::Corruption => {
+                    scan.leaves.push(child);
+                    scan.lost_ranges.push(LostRange { page_offset: child, begin: child_lower_bound.map(Vec::from), end: child_upper_bound.map(Vec::from) });
+                }
+                Err(error) => return Err(error),
+            }
+            child_lower_bound = child_upper_bound;
+        }
+        Ok(())
+    }
+
+    // 按层级缩进输出当前节点下所有节点的结构，内部节点输出分隔key和子节点偏移，叶节点输出所有key，已删除的key标记为deleted
+    pub(crate) fn debug_tree(&self, mete_data: &MeteData, file: &Storage, depth: usize, output: &mut String) -> MiniBaseResult<()> {
+        let indent = "  ".repeat(depth);
+        match self.get_type() {
+            NodeType::Leaf => {
+                let keys = self.leaf_page.as_ref().unwrap().get_keys_with_deleted();
+                output.push_str(&format!("{}leaf offset={} keys={}\n", indent, self.offset, keys.len()));
+                for (key, deleted) in keys {
+                    let deleted = if deleted { " deleted" } else { "" };
+                    output.push_str(&format!("{}  \"{}\"{}\n", indent, key.escape_ascii(), deleted));
+                }
+            }
+            NodeType::Inner => {
+                let inner_page = self.inner_page.as_ref().unwrap();
+                let separators = inner_page.get_separators();
+                output.push_str(&format!("{}inner offset={} keys={}\n", indent, self.offset, separators.len()));
+                for (key, child) in separators {
+                    output.push_str(&format!("{}  < \"{}\" -> {}\n", indent, key.escape_ascii(), child));
+                }
+                output.push_str(&format!("{}  last -> {}\n", indent, inner_page.get_last_pointer()));
+                for child in inner_page.get_children() {
+                    let child = Node::load(mete_data, file, child)?;
+                    let result = child.debug_tree(mete_data, file, depth + 1, output);
+                    child.release(mete_data, false)?;
+                    result?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // 遍历当前节点下的所有页并累加统计信息，depth为当前节点的层数
+    pub(crate) fn collect_stats(&self, mete_data: &MeteData, file: &Storage, depth: usize, stats: &mut Stats) -> MiniBaseResult<()> {
+        stats.height = stats.height.max(depth);
+        match self.get_type() {
+            NodeType::Leaf => {
+                let page_stats = self.leaf_page.as_ref().unwrap().stats();
+                stats.live_key_count += page_stats.live_key_count;
+                stats.tombstone_count += page_stats.tombstone_count;
+                stats.free_space += page_stats.free_space;
+                stats.reclaimable_garbage_bytes += page_stats.reclaimable_garbage_bytes;
+                stats.leaf_page_count += 1;
+            }
+            NodeType::Inner => {
+                stats.inner_page_count += 1;
+                for child in self.inner_page.as_ref().unwrap().get_children() {
+                    let child = Node::load(mete_data, file, child)?;
+                    let result = child.collect_stats(mete_data, file, depth + 1, stats);
+                    child.release(mete_data, false)?;
+                    result?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::create_schema;
+    use crate::node::{Node, NodeType, Split};
+    use crate::storage::Storage;
+
+    const PAGE_SIZE: u32 = 512;
+
+    #[test]
+    fn node_get_two_levels() {
+        let dir_name = "node_get_two_levels";
+        fs::create_dir_all(dir_name).unwrap();
+        let mete_data = create_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap();
+        let data_file = Storage::from(fs::OpenOptions::new().read(true).write(true).create(true).truncate(false)
+            .open(format!("{}/test.d", dir_name)).unwrap());
+        data_file.set_len(3 * PAGE_SIZE as u64).unwrap();
+
+        // 内部根节点以"m"分隔两个叶节点
+        let page_size = PAGE_SIZE as u64;
+        for (offset, keys) in [(0, ["a", "c"]), (page_size, ["m", "x"])] {
+            let mut leaf = Node::new(&mete_data, &data_file, offset, NodeType::Leaf).unwrap();
+            for key in keys {
+                leaf.leaf_page.as_mut().unwrap().insert_key_value(key.as_bytes(), format!("value_{}", key).as_bytes());
+            }
+            leaf.flush().unwrap();
+        }
+        let split = Split { separator: Vec::from("m"), right_offset: page_size };
+        let root = Node::new_root(&mete_data, &data_file, 2 * page_size, 0, &split).unwrap();
+        assert!(root.get_type() == NodeType::Inner);
+
+        for key in ["a", "c", "m", "x"] {
+            assert_eq!(Some(format!("value_{}", key).into_bytes()), root.get(&mete_data, &data_file, key.as_bytes()).unwrap());
+        }
+        // 不存在的key在两侧叶节点中都找不到
+        for key in ["b", "l", "n", "z"] {
+            assert_eq!(None, root.get(&mete_data, &data_file, key.as_bytes()).unwrap());
+        }
+        drop(root);
+        drop(mete_data);
+
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+
+    #[test]
+    fn node_put_split_two_levels() {
+        let dir_name = "node_put_split_two_levels";
+        fs::create_dir_all(dir_name).unwrap();
+        let mut mete_data = create_schema(dir_name, "test", PAGE_SIZE, 64, 128).unwrap();
+        let data_file = Storage::from(fs::OpenOptions::new().read(true).write(true).create(true).truncate(false)
+            .open(format!("{}/test.d", dir_name)).unwrap());
+        data_file.set_len(PAGE_SIZE as u64).unwrap();
+
+        // 与controller相同，根节点分裂后创建新的内部根节点
+        let mut root = Node::new(&mete_data, &data_file, 0, NodeType::Leaf).unwrap();
+        let mut root_split_count = 0;
+        let value = [b'v'; 100];
+        for i in 0..300 {
+            let key = format!("key{:04}", i);
+            if let (_, Some(split)) = root.put(&mut mete_data, &data_file, key.as_bytes(), &value).unwrap() {
+                let root_offset = mete_data.allocate_page(&data_file).unwrap();
+                root = Node::new_root(&mete_data, &data_file, root_offset, root.get_offset(), &split).unwrap();
+                root_split_count += 1;
+            }
+        }
+        // 叶节点和内部节点都发生了分裂，根节点之下还有一层内部节点
+        assert!(root_split_count >= 2);
+        assert!(mete_data.io_stats().split_count > root_split_count);
+        let child = root.load_child(&mete_data, &data_file, b"key0000").unwrap();
+        assert!(child.get_type() == NodeType::Inner);
+        child.release(&mete_data, false).unwrap();
+        for i in 0..300 {
+            assert_eq!(Some(Vec::from(value)), root.get(&mete_data, &data_file, format!("key{:04}", i).as_bytes()).unwrap());
+        }
+        assert_eq!(None, root.get(&mete_data, &data_file, b"key0300").unwrap());
+        drop(root);
+        drop(mete_data);
+
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+}
@@ -1,17 +1,152 @@
+use std::cmp::Ordering;
 use std::fs::File;
-use memmap2::{Mmap, MmapMut, MmapOptions};
-use crate::{MiniBaseError, MiniBaseResult};
+use std::ops::{Deref, DerefMut};
+use memmap2::{Advice, Mmap, MmapMut, MmapOptions};
+use crate::{ErrorKind, MiniBaseError, MiniBaseResult};
+use crate::checksum::{crc32, crc32_patch, crc32_update};
+use crate::comparator::Comparator;
+use crate::endian::Endian;
+use crate::storage::{Backend, MemoryMap, Storage};
+
+// 页偏移的空值，表示不存在对应的页
+pub(crate) const NULL_PAGE: u64 = u64::MAX;
+
+// 页的访问方式，映射页之后通过madvise提示操作系统，只影响预读，不影响读写的结果
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum AccessPattern {
+    // 不做提示，使用操作系统默认的预读
+    #[default]
+    Normal,
+    // 以范围读取和遍历为主，预读更多后续的数据
+    Sequential,
+    // 以点查为主，减少预读
+    Random,
+}
+
+// 页的映射，以只读方式打开schema时使用只读映射，文件也以只读方式打开，内存模式下映射内存文件中的段
+enum Mapping {
+    Writable(MmapMut),
+    ReadOnly(Mmap),
+    Memory(MemoryMap),
+}
+
+// 页的映射及页中整数的字节序
+pub(crate) struct PageMap {
+    mapping: Mapping,
+    endian: Endian,
+}
+
+impl PageMap {
+    fn new(mapping: Mapping, endian: Endian) -> PageMap {
+        PageMap { mapping, endian }
+    }
+
+    pub(crate) fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    // 只读映射没有需要写回的修改
+    fn flush(&self) -> MiniBaseResult<()> {
+        if let Mapping::Writable(mmap) = &self.mapping {
+            mmap.flush()?;
+        }
+        Ok(())
+    }
+
+    // 内存模式下的映射不对应文件，没有预读，不需要提示
+    fn advise(&self, access_pattern: AccessPattern) -> MiniBaseResult<()> {
+        let advice = match access_pattern {
+            AccessPattern::Normal => Advice::Normal,
+            AccessPattern::Sequential => Advice::Sequential,
+            AccessPattern::Random => Advice::Random,
+        };
+        match &self.mapping {
+            Mapping::Writable(mmap) => mmap.advise(advice)?,
+            Mapping::ReadOnly(mmap) => mmap.advise(advice)?,
+            Mapping::Memory(_) => {}
+        }
+        Ok(())
+    }
+}
+
+impl Deref for PageMap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.mapping {
+            Mapping::Writable(mmap) => mmap,
+            Mapping::ReadOnly(mmap) => mmap,
+            Mapping::Memory(mmap) => mmap,
+        }
+    }
+}
+
+// 只读打开时所有写操作在修改页之前已经返回ReadOnly错误，修改只读映射说明存在遗漏的写操作
+impl DerefMut for PageMap {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match &mut self.mapping {
+            Mapping::Writable(mmap) => mmap,
+            Mapping::ReadOnly(_) => panic!("page mapped read-only cannot be modified"),
+            Mapping::Memory(mmap) => mmap,
+        }
+    }
+}
+
+// 映射超出文件末尾的区域在访问时会触发SIGBUS，需要提前检查
+fn check_mapping(file: &File, offset: u64, length: u32) -> MiniBaseResult<MmapOptions> {
+    let file_length = file.metadata()?.len();
+    if offset + length as u64 > file_length {
+        return Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("mapping exceeds file length, offset {} length {} file length {}", offset, length, file_length))));
+    }
+    let mut options = MmapOptions::new();
+    options.offset(offset).len(length as usize);
+    Ok(options)
+}
+
+fn create_mmap(storage: &Storage, offset: u64, length: u32) -> MiniBaseResult<PageMap> {
+    let (file, offset) = match &storage.backend {
+        Backend::File(file) => (file, offset),
+        Backend::Memory(memory) => return Ok(PageMap::new(Mapping::Memory(memory.map(offset, length)?), storage.endian())),
+        Backend::Region(file, region) => file.map_position(*region, offset, length)?,
+    };
+    let options = check_mapping(file, offset, length)?;
+    let mmap = unsafe { options.map_mut(file)? };
+    Ok(PageMap::new(Mapping::Writable(mmap), storage.endian()))
+}
+
+// 不对应任何文件的映射，只保存在内存中，不会写入文件，使用默认的字节序
+fn create_anonymous_mmap(length: u32) -> MiniBaseResult<PageMap> {
+    let mmap = MmapOptions::new().len(length as usize).map_anon()?;
+    Ok(PageMap::new(Mapping::Writable(mmap), Endian::default()))
+}
 
-fn create_mmap(file: &File, offset: u32, length: u32) -> MiniBaseResult<(Mmap, MmapMut)> {
-    let mmap = unsafe { MmapOptions::new().offset(offset as u64).len(length as usize).map(file)? };
-    let mmap_mut = unsafe { MmapOptions::new().offset(offset as u64).len(length as usize).map_mut(file)? };
-    Ok((mmap, mmap_mut))
+// 内存文件不会以只读方式打开
+fn create_read_only_mmap(storage: &Storage, offset: u64, length: u32) -> MiniBaseResult<PageMap> {
+    let (file, offset) = match &storage.backend {
+        Backend::File(file) => (file, offset),
+        Backend::Memory(memory) => return Ok(PageMap::new(Mapping::Memory(memory.map(offset, length)?), storage.endian())),
+        Backend::Region(file, region) => file.map_position(*region, offset, length)?,
+    };
+    let options = check_mapping(file, offset, length)?;
+    let mmap = unsafe { options.map(file)? };
+    Ok(PageMap::new(Mapping::ReadOnly(mmap), storage.endian()))
 }
 
 pub(crate) trait Pager {
-    fn get_mmap(&self) -> &Mmap;
+    // 读写使用同一个映射，只读映射不能通过get_mmap_mut修改
+    fn get_mmap(&self) -> &PageMap;
+
+    fn get_mmap_mut(&mut self) -> &mut PageMap;
+
+    // 提示操作系统之后访问页的方式
+    fn advise(&self, access_pattern: AccessPattern) -> MiniBaseResult<()> {
+        self.get_mmap().advise(access_pattern)
+    }
 
-    fn get_mmap_mut(&mut self) -> &mut MmapMut;
+    // 将映射内存中的修改同步写入文件
+    fn flush(&mut self) -> MiniBaseResult<()> {
+        self.get_mmap().flush()
+    }
 
     fn read_u8(&self, offset: usize) -> u8 {
         let mmap = self.get_mmap();
@@ -19,23 +154,44 @@ pub(crate) trait Pager {
         data[0]
     }
 
+    // 带边界检查的读取，用于读取从页中数据得到的偏移，避免数据损坏时越界
+    fn try_read_u8(&self, offset: usize) -> MiniBaseResult<u8> {
+        Ok(self.try_read_bytes(offset, 1)?[0])
+    }
+
     fn write_u8(&mut self, offset: usize, value: u8) {
-        let mmap_mut = self.get_mmap_mut();
-        mmap_mut[offset] = value;
+        self.write_bytes(offset, &[value])
     }
 
     fn read_u32(&self, offset: usize) -> u32 {
         let mmap = self.get_mmap();
-        let data = &mmap[offset..offset + 4];
-        u32::from_le_bytes(data.try_into().unwrap())
+        mmap.endian().read_u32(&mmap[offset..offset + 4])
+    }
+
+    fn try_read_u32(&self, offset: usize) -> MiniBaseResult<u32> {
+        let data = self.try_read_bytes(offset, 4)?;
+        Ok(self.get_mmap().endian().read_u32(data))
     }
 
     fn write_u32(&mut self, offset: usize, value: u32) {
-        let mmap_mut = self.get_mmap_mut();
-        let data = value.to_le_bytes();
-        for i in 0..data.len() {
-            mmap_mut[offset + i] = data[i];
-        }
+        let data = self.get_mmap().endian().u32_bytes(value);
+        self.write_bytes(offset, &data)
+    }
+
+    // 页之间的引用使用u64偏移，支持超过4GiB的文件
+    fn read_u64(&self, offset: usize) -> u64 {
+        let mmap = self.get_mmap();
+        mmap.endian().read_u64(&mmap[offset..offset + 8])
+    }
+
+    fn try_read_u64(&self, offset: usize) -> MiniBaseResult<u64> {
+        let data = self.try_read_bytes(offset, 8)?;
+        Ok(self.get_mmap().endian().read_u64(data))
+    }
+
+    fn write_u64(&mut self, offset: usize, value: u64) {
+        let data = self.get_mmap().endian().u64_bytes(value);
+        self.write_bytes(offset, &data)
     }
 
     fn read_bytes(&self, offset: usize, length: usize) -> &[u8] {
@@ -43,10 +199,35 @@ pub(crate) trait Pager {
         &mmap[offset..offset + length]
     }
 
+    fn try_read_bytes(&self, offset: usize, length: usize) -> MiniBaseResult<&[u8]> {
+        let mmap = self.get_mmap();
+        match offset.checked_add(length) {
+            Some(end) if end <= mmap.len() => Ok(&mmap[offset..end]),
+            _ => Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("read out of page bounds, offset {} length {} page length {}", offset, length, mmap.len())))),
+        }
+    }
+
+    // 页中校验和字段的偏移，校验和覆盖除该字段外的整个页，没有校验和的页返回None
+    fn checksum_offset(&self) -> Option<usize> {
+        None
+    }
+
+    // 有校验和的页根据修改的字节增量更新校验和，不需要重新计算整个页
+    // 写入的范围包含校验和字段时不更新，由调用方写入校验和或调用update_checksum重新计算
     fn write_bytes(&mut self, offset: usize, value: &[u8]) {
+        let checksum_offset = self.checksum_offset();
         let mmap_mut = self.get_mmap_mut();
-        for i in 0..value.len() {
-            mmap_mut[offset + i] = value[i];
+        let end = offset + value.len();
+        match checksum_offset {
+            Some(checksum_offset) if end <= checksum_offset || offset >= checksum_offset + 4 => {
+                let endian = mmap_mut.endian();
+                let trailing = mmap_mut.len() - end - if end <= checksum_offset { 4 } else { 0 };
+                let checksum = endian.read_u32(&mmap_mut[checksum_offset..checksum_offset + 4]);
+                let checksum = crc32_patch(checksum, &mmap_mut[offset..end], value, trailing);
+                mmap_mut[offset..end].copy_from_slice(value);
+                mmap_mut[checksum_offset..checksum_offset + 4].copy_from_slice(&endian.u32_bytes(checksum));
+            }
+            _ => mmap_mut[offset..end].copy_from_slice(value),
         }
     }
 }
@@ -54,9 +235,11 @@ pub(crate) trait Pager {
 trait DataPager: Pager {
     const HEADER: usize = 0;
     const CAPACITY: usize = 1;
+    #[allow(dead_code)]
     const PARENT: usize = 5;
     const DATA_HEAD_OFFSET: usize = 9;
     const DATA_TAIL_OFFSET: usize = 13;
+    const CHECKSUM: usize = 17;
 
     fn get_header(&self) -> u8 {
         self.read_u8(Self::HEADER)
@@ -90,21 +273,102 @@ trait DataPager: Pager {
         self.write_u32(Self::DATA_TAIL_OFFSET, value)
     }
 
+    fn get_checksum(&self) -> u32 {
+        self.read_u32(Self::CHECKSUM)
+    }
+
+    // 计算除校验和字段外整个页的crc32
+    fn compute_checksum(&self) -> u32 {
+        let mmap = self.get_mmap();
+        let crc = crc32(&mmap[..Self::CHECKSUM]);
+        crc32_update(crc, &mmap[Self::CHECKSUM + 4..])
+    }
+
+    // 重新计算整个页的校验和，用于初始化页，之后的修改通过write_bytes增量更新
+    fn update_checksum(&mut self) {
+        let checksum = self.compute_checksum();
+        self.write_u32(Self::CHECKSUM, checksum)
+    }
+
     fn get_free_space(&self) -> u32 {
         (self.get_data_tail_offset() - self.get_data_head_offset()) as u32
     }
 
     fn get_sorted_table_offset(&self) -> usize;
 
+    fn get_comparator(&self) -> &Comparator;
+
+    // 从头部分配空间
+    fn allocate_space_head(&mut self, size: usize) -> Option<usize> {
+        let data_head_offset = self.get_data_head_offset();
+        let data_tail_offset = self.get_data_tail_offset();
+        let new_data_head_offset = data_head_offset + size;
+        if new_data_head_offset > data_tail_offset {
+            return None;
+        }
+        self.update_data_head_offset(new_data_head_offset as u32);
+        Some(data_head_offset)
+    }
+
+    // 从尾部分配空间
+    fn allocate_space_tail(&mut self, size: usize) -> Option<usize> {
+        let data_head_offset = self.get_data_head_offset();
+        let data_tail_offset = self.get_data_tail_offset();
+        // size可能大于整个页，需要检查溢出
+        let new_data_tail_offset = match data_tail_offset.checked_sub(size) {
+            Some(offset) if offset >= data_head_offset => offset,
+            _ => return None,
+        };
+        self.update_data_tail_offset(new_data_tail_offset as u32);
+        Some(new_data_tail_offset)
+    }
+
+    // 在有序列表的index位置插入key_offset，之后的元素依次后移
+    fn insert_sorted_table(&mut self, index: usize, sorted_table_length: usize, key_offset: usize) {
+        let new_key_index_offset = self.allocate_space_head(4).unwrap();
+        if index == sorted_table_length {
+            // 有序列表为空或新数据位于末尾，直接插入
+            self.write_u32(new_key_index_offset, key_offset as u32);
+        } else {
+            // 需要移动数据，保证顺序
+            let move_offset = self.get_sorted_table_offset() + 4 * index;
+            let bytes_to_move = self.read_bytes(move_offset, (sorted_table_length - index) * 4);
+            let vec = Vec::from(bytes_to_move);
+            self.write_bytes(move_offset + 4, &vec);
+            self.write_u32(move_offset, key_offset as u32);
+        }
+    }
+
+    fn get_key(&self, key_offset: usize) -> &[u8] {
+        let key_size = self.read_u32(key_offset);
+        self.read_bytes(key_offset + 4, key_size as usize)
+    }
+
+    // 读取数据区[DATA_TAIL_OFFSET, 页尾)中的数据，偏移位于页头或有序列表中、长度超出页的剩余空间时返回Corruption
+    // 调用方需要先检查页头中的DATA_TAIL_OFFSET
+    fn try_read_data(&self, offset: usize, length: usize) -> MiniBaseResult<&[u8]> {
+        let data_tail_offset = self.get_data_tail_offset();
+        if offset < data_tail_offset {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("offset {} before data region at {}", offset, data_tail_offset))));
+        }
+        self.try_read_bytes(offset, length)
+    }
+
+    // 与get_key相同，key_offset和key的长度超出数据区时返回Corruption
+    fn try_get_key(&self, key_offset: usize) -> MiniBaseResult<&[u8]> {
+        let key_size = self.get_mmap().endian().read_u32(self.try_read_data(key_offset, 4)?);
+        self.try_read_bytes(key_offset + 4, key_size as usize)
+    }
+
     // 获取叶数据的有序列表，返回key_offset的列表，按key的自然序排列
     fn get_sorted_table(&self) -> Vec<usize> {
         let data_head_offset = self.get_data_head_offset();
         let sorted_table_length = (data_head_offset - self.get_sorted_table_offset()) / 4;
         let sorted_table_data = self.read_bytes(self.get_sorted_table_offset(), sorted_table_length * 4);
+        let endian = self.get_mmap().endian();
         let mut result = Vec::new();
         for i in 0..sorted_table_length {
-            let key_offset_data = &sorted_table_data[(i * 4)..(i * 4 + 4)];
-            let key_offset = u32::from_le_bytes(key_offset_data.try_into().unwrap());
+            let key_offset = endian.read_u32(&sorted_table_data[(i * 4)..(i * 4 + 4)]);
             result.push(key_offset as usize)
         }
         result
@@ -116,9 +380,7 @@ trait DataPager: Pager {
             return (false, 0);
         }
         let key_position = sorted_table.binary_search_by(|key_offset| {
-            let key_size = self.read_u32(*key_offset);
-            let key_data = self.read_bytes(*key_offset + 4, key_size as usize);
-            key_data.cmp(key)
+            self.get_comparator().compare(self.get_key(*key_offset), key)
         });
         match key_position {
             Ok(p) => (true, p),
@@ -128,39 +390,97 @@ trait DataPager: Pager {
 }
 
 pub(crate) struct Page {
-    mmap: Mmap,
-    mmap_mut: MmapMut,
+    mmap: PageMap,
 }
 
 impl Pager for Page {
-    fn get_mmap(&self) -> &Mmap {
+    fn get_mmap(&self) -> &PageMap {
         &self.mmap
     }
 
-    fn get_mmap_mut(&mut self) -> &mut MmapMut {
-        &mut self.mmap_mut
+    fn get_mmap_mut(&mut self) -> &mut PageMap {
+        &mut self.mmap
     }
 }
 
 impl Page {
-    pub(crate) fn new(file: &File, offset: u32, length: u32) -> MiniBaseResult<Page> {
-        let (mmap, mmap_mut) = create_mmap(file, offset, length)?;
-        Ok(Page { mmap, mmap_mut })
+    pub(crate) fn new(file: &Storage, offset: u64, length: u32) -> MiniBaseResult<Page> {
+        let mmap = create_mmap(file, offset, length)?;
+        Ok(Page { mmap })
     }
+
+    pub(crate) fn new_read_only(file: &Storage, offset: u64, length: u32) -> MiniBaseResult<Page> {
+        let mmap = create_read_only_mmap(file, offset, length)?;
+        Ok(Page { mmap })
+    }
+}
+
+// 向叶节点插入数据的结果
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub(crate) enum InsertResult {
+    // key不存在或已删除，插入了新的数据
+    Inserted,
+    // key已存在，value被覆盖
+    Overwritten,
+    // 节点空间不足，需要分裂
+    NeedsSplit,
+}
+
+// 删除数据后与相邻节点合并或重新分配的结果
+#[derive(Debug, PartialEq)]
+pub(crate) enum Rebalance {
+    // 右侧节点的数据全部移入左侧节点，右侧节点可以释放
+    Merged,
+    // 两个节点的数据重新均分，返回新的分隔key
+    Redistributed(Vec<u8>),
+    // 无法合并且新的分隔key过长，父节点无法容纳，两个节点保持不变
+    Unchanged,
+}
+
+// 按占用空间均分时左侧的数量和左侧占用的空间，左右两侧都至少有一个元素
+fn balanced_split_point(required_spaces: &[usize]) -> (usize, usize) {
+    let total_space: usize = required_spaces.iter().sum();
+    fill_split_point(required_spaces, total_space / 2)
+}
+
+// 左侧占用的空间不超过left_limit时尽量多地放入左侧，返回左侧的数量和左侧占用的空间，左右两侧都至少有一个元素
+fn fill_split_point(required_spaces: &[usize], left_limit: usize) -> (usize, usize) {
+    let mut middle = 1;
+    let mut left_space = required_spaces[0];
+    while middle < required_spaces.len() - 1 && left_space + required_spaces[middle] <= left_limit {
+        left_space += required_spaces[middle];
+        middle += 1;
+    }
+    (middle, left_space)
+}
+
+// 叶节点的空间使用情况
+#[derive(Debug, PartialEq, Default)]
+pub(crate) struct PageStats {
+    pub(crate) live_key_count: usize,
+    pub(crate) tombstone_count: usize,
+    pub(crate) free_space: usize,
+    // 已删除的key value和被覆盖的value占用的空间，整理页后可以回收
+    pub(crate) reclaimable_garbage_bytes: usize,
+    pub(crate) data_tail_offset: usize,
 }
 
 pub(crate) struct LeafPage {
-    mmap: Mmap,
-    mmap_mut: MmapMut,
+    mmap: PageMap,
+    comparator: Comparator,
 }
 
 impl Pager for LeafPage {
-    fn get_mmap(&self) -> &Mmap {
+    fn get_mmap(&self) -> &PageMap {
         &self.mmap
     }
 
-    fn get_mmap_mut(&mut self) -> &mut MmapMut {
-        &mut self.mmap_mut
+    fn get_mmap_mut(&mut self) -> &mut PageMap {
+        &mut self.mmap
+    }
+
+    fn checksum_offset(&self) -> Option<usize> {
+        Some(Self::CHECKSUM)
     }
 }
 
@@ -168,6 +488,18 @@ impl DataPager for LeafPage {
     fn get_sorted_table_offset(&self) -> usize {
         LeafPage::SORTED_TABLE
     }
+
+    fn get_comparator(&self) -> &Comparator {
+        &self.comparator
+    }
+}
+
+// 页的长度需要大于有序列表的起始位置，否则页头之后没有可用空间，计算偏移时会越界
+fn check_page_length(length: u32, sorted_table_offset: usize) -> MiniBaseResult<()> {
+    if length as usize <= sorted_table_offset {
+        return Err(Box::from(MiniBaseError::new(ErrorKind::InvalidArgument, format!("page_size too small for header, page_size {} header length {}", length, sorted_table_offset))));
+    }
+    Ok(())
 }
 
 fn common_init<T>(data_pager: &mut T, length: usize, header: u8)
@@ -176,75 +508,535 @@ fn common_init<T>(data_pager: &mut T, length: usize, header: u8)
     data_pager.update_data_head_offset(data_pager.get_sorted_table_offset() as u32);
     data_pager.update_data_tail_offset(length as u32);
     data_pager.update_header(header);
+    // 初始化前页中可能是任意数据，校验和需要重新计算，之后的写入增量更新
+    data_pager.update_checksum();
 }
 
-fn valid_common_data<T>(data_pager: &T, length: u32, expect_header: u8) -> Option<MiniBaseError>
+fn valid_common_data<T>(data_pager: &T, length: u32, expect_header: u8, verify_checksum: bool) -> Option<MiniBaseError>
     where T: DataPager {
     let header = data_pager.get_header();
     if header != expect_header {
-        return Some(MiniBaseError("header invalid"));
+        return Some(MiniBaseError::new(ErrorKind::Corruption, format!("header {:#010b} invalid, expect {:#010b}", header, expect_header)));
     }
     let capacity = data_pager.get_capacity();
     if capacity != length {
-        return Some(MiniBaseError("capacity invalid"));
+        return Some(MiniBaseError::new(ErrorKind::Corruption, format!("capacity {} invalid, expect {}", capacity, length)));
     }
     let data_head_offset = data_pager.get_data_head_offset();
     if data_head_offset < data_pager.get_sorted_table_offset() {
-        return Some(MiniBaseError("data_head_offset invalid"));
+        return Some(MiniBaseError::new(ErrorKind::Corruption, format!("data_head_offset {} before sorted table", data_head_offset)));
     }
     let data_tail_offset = data_pager.get_data_tail_offset();
     if data_tail_offset < data_head_offset || data_tail_offset > length as usize {
-        return Some(MiniBaseError("data_tail_offset invalid"));
+        return Some(MiniBaseError::new(ErrorKind::Corruption, format!("data_tail_offset {} out of range [{}, {}]", data_tail_offset, data_head_offset, length)));
+    }
+    if verify_checksum && data_pager.get_checksum() != data_pager.compute_checksum() {
+        return Some(MiniBaseError::new(ErrorKind::Corruption, "page checksum mismatch"));
     }
     None
 }
 
+// 检查有序列表中的key严格递增，调用方需要先检查页头和key的偏移
+fn verify_sorted_keys<T>(data_pager: &T) -> MiniBaseResult<()>
+    where T: DataPager {
+    let sorted_table = data_pager.get_sorted_table();
+    for (index, pair) in sorted_table.windows(2).enumerate() {
+        if data_pager.get_comparator().compare(data_pager.get_key(pair[0]), data_pager.get_key(pair[1])) != Ordering::Less {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("keys not strictly sorted at index {}", index + 1))));
+        }
+    }
+    Ok(())
+}
+
 impl LeafPage {
-    const PREVIOUS_PAGE: usize = 17;
-    const NEXT_PAGE: usize = 21;
-    const SORTED_TABLE: usize = 25;
+    const PREVIOUS_PAGE: usize = 21;
+    const NEXT_PAGE: usize = 29;
+    const SORTED_TABLE: usize = 37;
     pub(crate) const HEADER: u8 = 0b1000_0000;
+    // 页头中的标记位，表示页尾预留了布隆过滤器的空间
+    const BLOOM_FILTER: u8 = 0b0001_0000;
+    // 布隆过滤器占页的1/16，每个key设置3位
+    const BLOOM_FILTER_RATIO: usize = 16;
+    const BLOOM_FILTER_HASH_COUNT: u32 = 3;
+
+    pub(crate) fn new(file: &Storage, offset: u64, length: u32, comparator: Comparator) -> MiniBaseResult<LeafPage> {
+        check_page_length(length, Self::SORTED_TABLE)?;
+        let mmap = create_mmap(file, offset, length)?;
+        let mut page = LeafPage { mmap, comparator };
+        common_init(&mut page, length as usize, Self::HEADER);
+        page.update_previous_page(NULL_PAGE);
+        page.update_next_page(NULL_PAGE);
+        Ok(page)
+    }
 
-    pub(crate) fn new(file: &File, offset: u32, length: u32) -> MiniBaseResult<LeafPage> {
-        let (mmap, mmap_mut) = create_mmap(file, offset, length)?;
-        let mut page = LeafPage { mmap, mmap_mut };
+    // 只保存在内存中的空叶节点，用于代替损坏的叶节点
+    pub(crate) fn new_anonymous(length: u32, comparator: Comparator) -> MiniBaseResult<LeafPage> {
+        check_page_length(length, Self::SORTED_TABLE)?;
+        let mut page = LeafPage { mmap: create_anonymous_mmap(length)?, comparator };
         common_init(&mut page, length as usize, Self::HEADER);
+        page.update_previous_page(NULL_PAGE);
+        page.update_next_page(NULL_PAGE);
+        Ok(page)
+    }
+
+    pub(crate) fn from(file: &Storage, offset: u64, length: u32, verify_checksum: bool, comparator: Comparator) -> MiniBaseResult<LeafPage> {
+        LeafPage::from_mmap(create_mmap(file, offset, length)?, verify_checksum, comparator)
+    }
+
+    // 以只读方式打开schema时使用只读映射读取页
+    pub(crate) fn from_read_only(file: &Storage, offset: u64, length: u32, verify_checksum: bool, comparator: Comparator) -> MiniBaseResult<LeafPage> {
+        LeafPage::from_mmap(create_read_only_mmap(file, offset, length)?, verify_checksum, comparator)
+    }
+
+    fn from_mmap(mmap: PageMap, verify_checksum: bool, comparator: Comparator) -> MiniBaseResult<LeafPage> {
+        let length = mmap.len() as u32;
+        check_page_length(length, Self::SORTED_TABLE)?;
+        let page = LeafPage { mmap, comparator };
+        page.valid_header(verify_checksum)?;
+        page.valid_records()?;
         Ok(page)
     }
 
-    pub(crate) fn from(file: &File, offset: u32, length: u32) -> MiniBaseResult<LeafPage> {
-        let (mmap, mmap_mut) = create_mmap(file, offset, length)?;
-        let page = LeafPage { mmap, mmap_mut };
-        let error = valid_common_data(&page, length, Self::HEADER);
-        match error {
-            None => Ok(page),
-            Some(error) => Err(Box::from(error))
+    // 检查页中的数据是否完整，包括页头、所有key value的偏移以及key的顺序
+    pub(crate) fn verify(&self, verify_checksum: bool) -> MiniBaseResult<()> {
+        self.valid_header(verify_checksum)?;
+        self.valid_records()?;
+        verify_sorted_keys(self)
+    }
+
+    // 启用布隆过滤器时数据区不能覆盖页尾的过滤器
+    fn valid_header(&self, verify_checksum: bool) -> MiniBaseResult<()> {
+        let length = self.mmap.len() as u32;
+        let expect_header = Self::HEADER | (self.get_header() & Self::BLOOM_FILTER);
+        if let Some(error) = valid_common_data(self, length, expect_header, verify_checksum) {
+            return Err(Box::from(error));
+        }
+        let bloom_filter_offset = length as usize - self.get_bloom_filter_length();
+        if self.get_data_tail_offset() > bloom_filter_offset {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("data_tail_offset {} overlaps bloom filter at {}", self.get_data_tail_offset(), bloom_filter_offset))));
+        }
+        Ok(())
+    }
+
+    // 页头是否为叶节点，包括启用了布隆过滤器的叶节点
+    pub(crate) fn is_leaf_header(header: u8) -> bool {
+        header & !Self::BLOOM_FILTER == Self::HEADER
+    }
+
+    // 在新建的空页中启用布隆过滤器，查找不存在的key时可以不进行二分查找
+    pub(crate) fn with_bloom_filter(mut self, enabled: bool) -> LeafPage {
+        if enabled {
+            self.update_header(Self::HEADER | Self::BLOOM_FILTER);
+            self.reset();
+        }
+        self
+    }
+
+    pub(crate) fn has_bloom_filter(&self) -> bool {
+        self.get_header() & Self::BLOOM_FILTER != 0
+    }
+
+    fn get_bloom_filter_length(&self) -> usize {
+        if self.has_bloom_filter() {
+            self.mmap.len() / Self::BLOOM_FILTER_RATIO
+        } else {
+            0
+        }
+    }
+
+    // key在过滤器中的位，自定义的排序方式无法确定相等的key，不使用过滤器
+    fn get_bloom_filter_bits(&self, key: &[u8]) -> Option<impl Iterator<Item = usize>> {
+        let bit_count = (self.get_bloom_filter_length() * 8) as u32;
+        if bit_count == 0 {
+            return None;
+        }
+        let key = self.comparator.normalize(key)?;
+        // 由两个哈希组合出多个哈希
+        let hash = crc32(&key);
+        let step = crc32_update(hash, &key) | 1;
+        Some((0..Self::BLOOM_FILTER_HASH_COUNT).map(move |i| (hash.wrapping_add(i.wrapping_mul(step)) % bit_count) as usize))
+    }
+
+    // 返回false时key一定不在页中，删除key后过滤器不会更新，返回true时key也可能不存在
+    pub(crate) fn may_contain(&self, key: &[u8]) -> bool {
+        let Some(mut bits) = self.get_bloom_filter_bits(key) else {
+            return true;
+        };
+        let bloom_filter_offset = self.mmap.len() - self.get_bloom_filter_length();
+        let filter = self.read_bytes(bloom_filter_offset, self.get_bloom_filter_length());
+        bits.all(|bit| filter[bit / 8] & (1 << (bit % 8)) != 0)
+    }
+
+    fn add_to_bloom_filter(&mut self, key: &[u8]) {
+        let Some(bits) = self.get_bloom_filter_bits(key) else {
+            return;
+        };
+        let bloom_filter_offset = self.mmap.len() - self.get_bloom_filter_length();
+        for bit in bits {
+            let position = bloom_filter_offset + bit / 8;
+            self.write_u8(position, self.read_u8(position) | (1 << (bit % 8)));
+        }
+    }
+
+    // 检查有序列表中所有key value的偏移和长度都位于页内，之后的读取可以不再检查边界
+    fn valid_records(&self) -> MiniBaseResult<()> {
+        for key_offset in self.get_sorted_table() {
+            self.try_get_value_by_key_offset(key_offset)?;
+        }
+        Ok(())
+    }
+
+    // 向叶节点插入数据，如果key已经关联量数据，value会被覆盖，节点空间不足时返回NeedsSplit
+    // 空间不足但回收垃圾数据后足够时，会先整理页再插入
+    // key和value都可以为空，空key排在最前面，空value与已删除的key通过删除标记区分
+    pub(crate) fn insert_key_value(&mut self, key: &[u8], value: &[u8]) -> InsertResult {
+        let mut result = self.try_insert_key_value(key, value);
+        if result == InsertResult::NeedsSplit && (self.get_free_space() as usize) + self.get_garbage_space() >= self.get_required_space(key, value) {
+            self.compact();
+            result = self.try_insert_key_value(key, value);
+        }
+        result
+    }
+
+    fn try_insert_key_value(&mut self, key: &[u8], value: &[u8]) -> InsertResult {
+        let sorted_table = &self.get_sorted_table()[..];
+        let (exist, index) = self.binary_search(key, sorted_table);
+        if exist {
+            // 已删除的key重新写入视为新插入
+            let (deleted, _) = self.get_value_by_key_offset(sorted_table[index]);
+            match (self.override_value(sorted_table, index, key, value), deleted) {
+                (false, _) => InsertResult::NeedsSplit,
+                (true, true) => InsertResult::Inserted,
+                (true, false) => InsertResult::Overwritten,
+            }
+        } else if self.insert_value(sorted_table, index, key, value) {
+            InsertResult::Inserted
+        } else {
+            InsertResult::NeedsSplit
         }
     }
 
-    // 向叶节点插入数据，返回是否成功，如果key已经关联量数据，value会被覆盖，节点空间不足时会失败
-    pub(crate) fn insert_key_value(&mut self, key: &[u8], value: &[u8]) -> bool {
+    // key存在时返回已有的value和false，否则插入default_value并返回default_value和true，节点空间不足时返回None
+    // 存在性检查和插入使用同一次二分查找的结果，已删除的key视为不存在，插入后清除删除标记
+    pub(crate) fn get_or_insert(&mut self, key: &[u8], default_value: &[u8]) -> Option<(Vec<u8>, bool)> {
         let sorted_table = &self.get_sorted_table()[..];
         let (exist, index) = self.binary_search(key, sorted_table);
-        return if exist {
-            self.override_value(sorted_table, index, key, value)
+        let ok = if exist {
+            let (deleted, value) = self.get_value_by_key_offset(sorted_table[index]);
+            if !deleted {
+                return Some((Vec::from(value), false));
+            }
+            self.override_value(sorted_table, index, key, default_value)
         } else {
-            self.insert_value(sorted_table, index, key, value)
+            self.insert_value(sorted_table, index, key, default_value)
         };
+        if ok {
+            return Some((Vec::from(default_value), true));
+        }
+        // 空间不足时尝试整理页后再插入
+        match self.insert_key_value(key, default_value) {
+            InsertResult::NeedsSplit => None,
+            InsertResult::Inserted | InsertResult::Overwritten => Some((Vec::from(default_value), true)),
+        }
+    }
+
+    // 整理页，只保留未删除的key value，回收已删除和被覆盖的数据占用的空间
+    pub(crate) fn compact(&mut self) {
+        let key_values = self.get_key_values();
+        self.rewrite(&key_values);
+    }
+
+    // 可回收的空间超过页容量的garbage_ratio时整理页，返回是否整理，garbage_ratio为1时不会整理
+    pub(crate) fn compact_if_garbage_exceeds(&mut self, garbage_ratio: f32) -> bool {
+        if garbage_ratio >= 1.0 || self.get_garbage_space() as f64 <= self.get_capacity() as f64 * garbage_ratio as f64 {
+            return false;
+        }
+        self.compact();
+        true
+    }
+
+    // 清空页后按顺序写入key value，调用方需要保证空间足够
+    fn rewrite(&mut self, key_values: &[(Vec<u8>, Vec<u8>)]) {
+        self.reset();
+        for (key, value) in key_values {
+            self.try_insert_key_value(key, value);
+        }
+    }
+
+    // 可回收的空间，即已使用的空间减去未删除的key value实际需要的空间
+    fn get_garbage_space(&self) -> usize {
+        let used_space = self.get_usable_space() - self.get_free_space() as usize;
+        used_space - self.get_live_space()
+    }
+
+    // 除页头和布隆过滤器外可以用于保存数据的空间
+    fn get_usable_space(&self) -> usize {
+        self.get_capacity() as usize - Self::SORTED_TABLE - self.get_bloom_filter_length()
+    }
+
+    // 未删除的key value实际需要的空间
+    fn get_live_space(&self) -> usize {
+        let mut live_space = 0;
+        for key_offset in self.get_sorted_table() {
+            let (deleted, value) = self.get_value_by_key_offset(key_offset);
+            if !deleted {
+                live_space += self.get_required_space(self.get_key(key_offset), value);
+            }
+        }
+        live_space
+    }
+
+    pub(crate) fn stats(&self) -> PageStats {
+        let sorted_table = self.get_sorted_table();
+        let tombstone_count = sorted_table.iter()
+            .filter(|key_offset| self.get_value_by_key_offset(**key_offset).0)
+            .count();
+        PageStats {
+            live_key_count: sorted_table.len() - tombstone_count,
+            tombstone_count,
+            free_space: self.get_free_space() as usize,
+            reclaimable_garbage_bytes: self.get_garbage_space(),
+            data_tail_offset: self.get_data_tail_offset(),
+        }
+    }
+
+    // 未删除的数据低于可用空间的1/4时，需要与相邻节点合并或重新分配
+    pub(crate) fn is_underflow(&self) -> bool {
+        self.get_live_space() * 4 < self.get_usable_space()
+    }
+
+    // 与右侧相邻的节点合并，合并后超出一页时两个节点重新均分数据
+    // max_separator_length为父节点能够容纳的新分隔key的最大长度
+    pub(crate) fn rebalance(&mut self, right: &mut LeafPage, max_separator_length: usize) -> Rebalance {
+        let mut key_values = self.get_key_values();
+        key_values.extend(right.get_key_values());
+        let required_spaces: Vec<usize> = key_values.iter()
+            .map(|(key, value)| self.get_required_space(key, value))
+            .collect();
+        let total_space: usize = required_spaces.iter().sum();
+        let usable_space = self.get_usable_space();
+        if total_space <= usable_space {
+            self.rewrite(&key_values);
+            return Rebalance::Merged;
+        }
+        let (middle, left_space) = balanced_split_point(&required_spaces);
+        if left_space > usable_space || total_space - left_space > usable_space || key_values[middle].0.len() > max_separator_length {
+            return Rebalance::Unchanged;
+        }
+        self.rewrite(&key_values[..middle]);
+        right.rewrite(&key_values[middle..]);
+        Rebalance::Redistributed(key_values.swap_remove(middle).0)
     }
 
     pub(crate) fn get_value(&self, key: &[u8]) -> Option<&[u8]> {
+        self.get_entry(key).map(|(_, value)| value)
+    }
+
+    // 返回保存的key和value，比较方式不区分大小写等情况下保存的key与查找的key可能不同
+    pub(crate) fn get_entry(&self, key: &[u8]) -> Option<(&[u8], &[u8])> {
         let sorted_table = &self.get_sorted_table()[..];
         let (exist, index) = self.binary_search(key, sorted_table);
         if !exist {
             return None;
         }
-        match self.get_value_by_key_offset(*sorted_table.get(index).unwrap()) {
-            (false, value) => Some(value),
+        let key_offset = sorted_table[index];
+        match self.get_value_by_key_offset(key_offset) {
+            (false, value) => Some((self.get_key(key_offset), value)),
             (true, _) => None,
         }
     }
 
+    // forward为true时查找大于key（inclusive时大于等于）的最小的未删除的key value，为false时查找小于key（inclusive时小于等于）的最大的
+    // key为None时从页的第一个（forward为false时最后一个）key开始查找，用于沿叶节点之间的链表继续查找
+    pub(crate) fn get_nearest(&self, key: Option<&[u8]>, inclusive: bool, forward: bool) -> Option<(&[u8], &[u8])> {
+        let sorted_table = &self.get_sorted_table()[..];
+        let (exist, index) = match key {
+            Some(key) => self.binary_search(key, sorted_table),
+            None if forward => (false, 0),
+            None => (false, sorted_table.len()),
+        };
+        // binary_search返回的index在key不存在时是插入位置，即第一个大于key的位置
+        let mut positions: Box<dyn Iterator<Item = usize>> = match (forward, exist && inclusive, exist) {
+            (true, false, true) => Box::new(index + 1..sorted_table.len()),
+            (true, _, _) => Box::new(index..sorted_table.len()),
+            (false, true, _) => Box::new((0..index + 1).rev()),
+            (false, false, _) => Box::new((0..index).rev()),
+        };
+        positions.find_map(|position| match self.get_value_by_key_offset(sorted_table[position]) {
+            (false, value) => Some((self.get_key(sorted_table[position]), value)),
+            (true, _) => None,
+        })
+    }
+
+    // 判断key是否存在且未删除，只读取删除标记，不读取value
+    pub(crate) fn has_live_key(&self, key: &[u8]) -> bool {
+        let sorted_table = &self.get_sorted_table()[..];
+        let (exist, index) = self.binary_search(key, sorted_table);
+        if !exist {
+            return false;
+        }
+        !self.is_deleted(sorted_table[index])
+    }
+
+    // 只读取删除标记，不读取value
+    fn is_deleted(&self, key_offset: usize) -> bool {
+        let key_size = self.read_u32(key_offset) as usize;
+        self.read_u8(self.get_value_deleted_position(key_offset, key_size)) == 1
+    }
+
+    pub(crate) fn get_previous_page(&self) -> u64 {
+        self.read_u64(Self::PREVIOUS_PAGE)
+    }
+
+    pub(crate) fn update_previous_page(&mut self, value: u64) {
+        self.write_u64(Self::PREVIOUS_PAGE, value)
+    }
+
+    pub(crate) fn get_next_page(&self) -> u64 {
+        self.read_u64(Self::NEXT_PAGE)
+    }
+
+    pub(crate) fn update_next_page(&mut self, value: u64) {
+        self.write_u64(Self::NEXT_PAGE, value)
+    }
+
+    // 获取所有未删除的key value，按key的自然序排列
+    pub(crate) fn get_key_values(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut result = Vec::new();
+        for key_offset in self.get_sorted_table() {
+            let (deleted, value) = self.get_value_by_key_offset(key_offset);
+            if !deleted {
+                result.push((Vec::from(self.get_key(key_offset)), Vec::from(value)));
+            }
+        }
+        result
+    }
+
+    // 获取所有未删除的key，不读取value
+    pub(crate) fn get_keys(&self) -> Vec<Vec<u8>> {
+        self.get_sorted_table().into_iter()
+            .filter(|key_offset| !self.is_deleted(*key_offset))
+            .map(|key_offset| Vec::from(self.get_key(key_offset)))
+            .collect()
+    }
+
+    // 获取所有key及其删除标记，包括已删除的key，按key的自然序排列
+    pub(crate) fn get_keys_with_deleted(&self) -> Vec<(Vec<u8>, bool)> {
+        self.get_sorted_table().into_iter()
+            .map(|key_offset| (Vec::from(self.get_key(key_offset)), self.is_deleted(key_offset)))
+            .collect()
+    }
+
+    // 分裂叶节点，新数据与原有数据合并后按占用空间均分，后半部分写入right，返回right中最小的key作为分隔key
+    // 空间不足以完成分裂时返回错误，此时当前页不会被修改
+    // fill_factor为左侧占用的空间相对于总空间的比例，rightmost为true且新key大于页中所有key时，新key单独放入右侧
+    pub(crate) fn split(&mut self, right: &mut LeafPage, key: &[u8], value: &[u8], fill_factor: f32, rightmost: bool) -> MiniBaseResult<Vec<u8>> {
+        let mut key_values = self.get_key_values();
+        let appended = match key_values.binary_search_by(|(k, _)| self.comparator.compare(k, key)) {
+            Ok(index) => {
+                key_values[index].1 = Vec::from(value);
+                false
+            }
+            Err(index) => {
+                key_values.insert(index, (Vec::from(key), Vec::from(value)));
+                index == key_values.len() - 1
+            }
+        };
+        if key_values.len() < 2 {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::PageFull, "key value too large for page")));
+        }
+        let required_spaces: Vec<usize> = key_values.iter()
+            .map(|(key, value)| self.get_required_space(key, value))
+            .collect();
+        let total_space: usize = required_spaces.iter().sum();
+        let usable_space = self.get_usable_space();
+        // 左侧不能超过一页
+        let (mut middle, mut left_space) = if rightmost && appended {
+            (key_values.len() - 1, total_space - required_spaces[key_values.len() - 1])
+        } else {
+            fill_split_point(&required_spaces, ((total_space as f64 * fill_factor as f64) as usize).min(usable_space))
+        };
+        // 比例较小时右侧可能超过一页，分裂点右移直到右侧能够容纳，此时左侧仍然超过一页说明无法分裂
+        while total_space - left_space > usable_space && middle < key_values.len() - 1 {
+            left_space += required_spaces[middle];
+            middle += 1;
+        }
+        if left_space > usable_space || total_space - left_space > usable_space {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::PageFull, "key value too large for page")));
+        }
+        self.rewrite(&key_values[..middle]);
+        right.rewrite(&key_values[middle..]);
+        Ok(key_values.swap_remove(middle).0)
+    }
+
+    // 清空页中的数据，保留前后页的链接，启用布隆过滤器时同时清空过滤器
+    fn reset(&mut self) {
+        let capacity = self.get_capacity() as usize;
+        let header = self.get_header();
+        common_init(self, capacity, header);
+        let bloom_filter_length = self.get_bloom_filter_length();
+        if bloom_filter_length > 0 {
+            self.write_bytes(capacity - bloom_filter_length, &vec![0; bloom_filter_length]);
+            self.update_data_tail_offset((capacity - bloom_filter_length) as u32);
+        }
+    }
+
+    // 按顺序读取从begin开始到end为止未删除的key value，返回后续的页是否可能还有范围内的数据
+    pub(crate) fn scan(&self, begin: &[u8], end: &[u8], end_inclusive: bool, result: &mut Vec<(Vec<u8>, Vec<u8>)>) -> bool {
+        let (key_offsets, has_more) = self.get_range_key_offsets(begin, end, end_inclusive);
+        for key_offset in key_offsets {
+            let (_, value) = self.get_value_by_key_offset(key_offset);
+            result.push((Vec::from(self.get_key(key_offset)), Vec::from(value)));
+        }
+        has_more
+    }
+
+    // 统计从begin开始到end为止未删除的key数量，不读取value，返回后续的页是否可能还有范围内的数据
+    pub(crate) fn count(&self, begin: &[u8], end: &[u8], end_inclusive: bool, count: &mut u64) -> bool {
+        let (key_offsets, has_more) = self.get_range_key_offsets(begin, end, end_inclusive);
+        *count += key_offsets.len() as u64;
+        has_more
+    }
+
+    // 统计从begin开始到end之前未删除的key value在页中占用的空间，包括长度、偏移和排序表中的位置，不包括页头和垃圾数据
+    // extra_size返回value数据在页外另外占用的空间，返回后续的页是否可能还有范围内的数据
+    pub(crate) fn measure(&self, begin: &[u8], end: &[u8], extra_size: &dyn Fn(&[u8]) -> MiniBaseResult<u64>, size: &mut u64) -> MiniBaseResult<bool> {
+        let (key_offsets, has_more) = self.get_range_key_offsets(begin, end, false);
+        for key_offset in key_offsets {
+            let (_, value) = self.get_value_by_key_offset(key_offset);
+            *size += self.get_required_space(self.get_key(key_offset), value) as u64 + extra_size(value)?;
+        }
+        Ok(has_more)
+    }
+
+    // 按顺序返回从begin开始到end为止未删除的key的偏移，以及后续的页是否可能还有范围内的数据
+    fn get_range_key_offsets(&self, begin: &[u8], end: &[u8], end_inclusive: bool) -> (Vec<usize>, bool) {
+        let sorted_table = &self.get_sorted_table()[..];
+        let (_, index) = self.binary_search(begin, sorted_table);
+        let mut key_offsets = Vec::new();
+        for key_offset in &sorted_table[index..] {
+            let in_range = match self.comparator.compare(self.get_key(*key_offset), end) {
+                Ordering::Less => true,
+                Ordering::Equal => end_inclusive,
+                Ordering::Greater => false,
+            };
+            if !in_range {
+                return (key_offsets, false);
+            }
+            if !self.is_deleted(*key_offset) {
+                key_offsets.push(*key_offset);
+            }
+        }
+        (key_offsets, true)
+    }
+
+    // 未删除的key数量
+    pub(crate) fn get_live_key_count(&self) -> usize {
+        self.get_sorted_table().into_iter()
+            .filter(|key_offset| !self.is_deleted(*key_offset))
+            .count()
+    }
+
     // 删除key value，返回是否成功，key不存在或已删除时失败
     pub(crate) fn delete_value(&mut self, key: &[u8]) -> bool {
         let sorted_table = &self.get_sorted_table()[..];
@@ -252,11 +1044,26 @@ impl LeafPage {
         if !exist {
             return false;
         }
-        let key_offset = sorted_table.get(index).unwrap();
-        self.update_value_delete(*key_offset, true);
+        let key_offset = *sorted_table.get(index).unwrap();
+        let (deleted, _) = self.get_value_by_key_offset(key_offset);
+        if deleted {
+            return false;
+        }
+        self.update_value_delete(key_offset, true);
         true
     }
 
+    // 与get_value_by_key_offset相同，key和value的偏移及长度超出数据区时返回Corruption，不会panic或读取到其它数据
+    fn try_get_value_by_key_offset(&self, key_offset: usize) -> MiniBaseResult<(bool, &[u8])> {
+        // key之后的字段位于key之后，只需要检查是否超出页尾
+        let key_size = self.try_get_key(key_offset)?.len();
+        let deleted = self.try_read_u8(self.get_value_deleted_position(key_offset, key_size))? == 1;
+        let value_offset = self.try_read_u32(self.get_value_offset_position(key_offset, key_size))? as usize;
+        let value_size = self.get_mmap().endian().read_u32(self.try_read_data(value_offset, 4)?);
+        Ok((deleted, self.try_read_bytes(value_offset + 4, value_size as usize)?))
+    }
+
+    // 加载页时已经通过try_get_value_by_key_offset检查了所有偏移和长度，这里不再检查
     fn get_value_by_key_offset(&self, key_offset: usize) -> (bool, &[u8]) {
         let key_size = self.read_u32(key_offset);
         let deleted = self.read_u8(self.get_value_deleted_position(key_offset, key_size as usize)) == 1;
@@ -267,34 +1074,30 @@ impl LeafPage {
     }
 
     // 覆盖叶节点指定位置的数据，返回是否成功，节点空间不足时会失败
+    // 先分配空间再修改，失败时不修改页，已删除的key仍然保持删除标记
     fn override_value(&mut self, sorted_table: &[usize], index: usize, key: &[u8], value: &[u8]) -> bool {
         let key_offset = *sorted_table.get(index).unwrap();
         let (deleted, old_value) = self.get_value_by_key_offset(key_offset);
-        if old_value == value {
-            if deleted {
-                self.update_value_delete(key_offset, false);
-            }
-            return true;
-        }
-        // 判断叶空间是否足够
-        if self.get_free_space() < (value.len() + 4) as u32 {
-            return false;
+        if old_value != value {
+            let Some(new_value_offset) = self.allocate_space_tail(self.get_value_required_space(value)) else {
+                return false;
+            };
+            // 写入新的value
+            self.write_u32(new_value_offset, value.len() as u32);
+            self.write_bytes(new_value_offset + 4, value);
+            // 更新key指向的value地址
+            self.write_u32(self.get_value_offset_position(key_offset, key.len()), new_value_offset as u32);
         }
+        // 新的value写入后才清除删除标记
         if deleted {
             self.update_value_delete(key_offset, false);
         }
-        let new_value_offset = self.allocate_space_tail(value.len() + 4).unwrap();
-        // 写入新的value
-        self.write_u32(new_value_offset, value.len() as u32);
-        self.write_bytes(new_value_offset + 4, value);
-        // 更新key指向的value地址
-        self.write_u32(self.get_value_offset_position(key_offset, key.len()), new_value_offset as u32);
         true
     }
 
     // 向叶插入数据，需要移动数据保证有序列表元素的顺序，节点空间不足时会失败
     fn insert_value(&mut self, sorted_table: &[usize], index: usize, key: &[u8], value: &[u8]) -> bool {
-        let required_space = (4 + key.len() + 4 + 4 + value.len() + 4) as u32;
+        let required_space = self.get_required_space(key, value) as u32;
         // 判断叶空间是否足够
         let free_space = self.get_free_space();
         if free_space < required_space {
@@ -310,20 +1113,11 @@ impl LeafPage {
         // 写入key
         self.write_u32(new_key_offset, key.len() as u32);
         self.write_bytes(new_key_offset + 4, key);
+        self.write_u8(self.get_value_deleted_position(new_key_offset, key.len()), 0);
         self.write_u32(self.get_value_offset_position(new_key_offset, key.len()), new_value_offset as u32);
         // 更新有序列表
-        let new_key_index_offset = self.allocate_space_head(4).unwrap();
-        if sorted_table.is_empty() || index == sorted_table.len() - 1 {
-            // 叶数据为空或新数据位于末尾，直接插入
-            self.write_u32(new_key_index_offset, new_key_offset as u32);
-        } else {
-            // 需要移动数据，保证顺序
-            let move_offset = Self::SORTED_TABLE + 4 * index;
-            let bytes_to_move = self.read_bytes(move_offset, (sorted_table.len() - index) * 4);
-            let vec = Vec::from(bytes_to_move);
-            self.write_bytes(move_offset + 4, &vec);
-            self.write_u32(move_offset, new_key_offset as u32);
-        }
+        self.insert_sorted_table(index, sorted_table.len(), new_key_offset);
+        self.add_to_bloom_filter(key);
         true
     }
 
@@ -333,6 +1127,11 @@ impl LeafPage {
         self.write_u8(self.get_value_deleted_position(key_offset, key_size as usize), deleted)
     }
 
+    // 插入一组key value需要的空间，包括key、value以及有序列表中的key_offset
+    fn get_required_space(&self, key: &[u8], value: &[u8]) -> usize {
+        self.get_key_required_space(key) + self.get_value_required_space(value) + 4
+    }
+
     fn get_value_required_space(&self, value: &[u8]) -> usize {
         4 + value.len()
     }
@@ -349,43 +1148,66 @@ impl LeafPage {
         key_offset + 4 + key_size
     }
 
-    // 从头部分配空间
-    fn allocate_space_head(&mut self, size: usize) -> Option<usize> {
-        let data_head_offset = self.get_data_head_offset();
-        let data_tail_offset = self.get_data_tail_offset();
-        let new_data_head_offset = data_head_offset + size;
-        if new_data_head_offset > data_tail_offset {
-            return None;
-        }
-        self.update_data_head_offset(new_data_head_offset as u32);
-        Some(data_head_offset)
+}
+
+// 空闲页，通过NEXT_FREE_PAGE串联成空闲链表
+pub(crate) struct FreePage {
+    mmap: PageMap,
+}
+
+impl Pager for FreePage {
+    fn get_mmap(&self) -> &PageMap {
+        &self.mmap
     }
 
-    // 从尾部分配空间
-    fn allocate_space_tail(&mut self, size: usize) -> Option<usize> {
-        let data_head_offset = self.get_data_head_offset();
-        let data_tail_offset = self.get_data_tail_offset();
-        let new_data_tail_offset = data_tail_offset - size;
-        if new_data_tail_offset < data_head_offset {
-            return None;
+    fn get_mmap_mut(&mut self) -> &mut PageMap {
+        &mut self.mmap
+    }
+}
+
+impl FreePage {
+    const HEADER_OFFSET: usize = 0;
+    const NEXT_FREE_PAGE: usize = 1;
+    pub(crate) const HEADER: u8 = 0b1000_0010;
+
+    pub(crate) fn new(file: &Storage, offset: u64, length: u32, next_free_page: u64) -> MiniBaseResult<FreePage> {
+        let mmap = create_mmap(file, offset, length)?;
+        let mut page = FreePage { mmap };
+        page.write_u8(Self::HEADER_OFFSET, Self::HEADER);
+        page.write_u64(Self::NEXT_FREE_PAGE, next_free_page);
+        Ok(page)
+    }
+
+    pub(crate) fn from(file: &Storage, offset: u64, length: u32) -> MiniBaseResult<FreePage> {
+        let mmap = create_mmap(file, offset, length)?;
+        let page = FreePage { mmap };
+        if page.read_u8(Self::HEADER_OFFSET) != Self::HEADER {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, "free page header invalid")));
         }
-        self.update_data_tail_offset(new_data_tail_offset as u32);
-        Some(new_data_tail_offset)
+        Ok(page)
+    }
+
+    pub(crate) fn get_next_free_page(&self) -> u64 {
+        self.read_u64(Self::NEXT_FREE_PAGE)
     }
 }
 
 pub(crate) struct InnerPage {
-    mmap: Mmap,
-    mmap_mut: MmapMut,
+    mmap: PageMap,
+    comparator: Comparator,
 }
 
 impl Pager for InnerPage {
-    fn get_mmap(&self) -> &Mmap {
+    fn get_mmap(&self) -> &PageMap {
         &self.mmap
     }
 
-    fn get_mmap_mut(&mut self) -> &mut MmapMut {
-        &mut self.mmap_mut
+    fn get_mmap_mut(&mut self) -> &mut PageMap {
+        &mut self.mmap
+    }
+
+    fn checksum_offset(&self) -> Option<usize> {
+        Some(Self::CHECKSUM)
     }
 }
 
@@ -393,165 +1215,888 @@ impl DataPager for InnerPage {
     fn get_sorted_table_offset(&self) -> usize {
         InnerPage::SORTED_TABLE
     }
+
+    fn get_comparator(&self) -> &Comparator {
+        &self.comparator
+    }
 }
 
 impl InnerPage {
-    const LAST_POINTER: usize = 17;
-    const SORTED_TABLE: usize = 21;
+    const LAST_POINTER: usize = 21;
+    const SORTED_TABLE: usize = 29;
     pub(crate) const HEADER: u8 = 0b1000_0001;
 
-    pub(crate) fn new(file: &File, offset: u32, length: u32) -> MiniBaseResult<InnerPage> {
-        let file_metadata = file.metadata()?;
-        let file_length = file_metadata.len();
-        if file_length < (offset + length) as u64 {
-            file.set_len((offset + length) as u64)?;
+    pub(crate) fn new(file: &Storage, offset: u64, length: u32, comparator: Comparator) -> MiniBaseResult<InnerPage> {
+        check_page_length(length, Self::SORTED_TABLE)?;
+        let file_length = file.len()?;
+        if file_length < offset + length as u64 {
+            file.set_len(offset + length as u64)?;
         }
-        let (mmap, mmap_mut) = create_mmap(file, offset, length)?;
-        let mut page = InnerPage { mmap, mmap_mut };
+        let mmap = create_mmap(file, offset, length)?;
+        let mut page = InnerPage { mmap, comparator };
         common_init(&mut page, length as usize, Self::HEADER);
         Ok(page)
     }
 
-    pub(crate) fn from(file: &File, offset: u32, length: u32) -> MiniBaseResult<InnerPage> {
-        let (mmap, mmap_mut) = create_mmap(file, offset, length)?;
-        let page = InnerPage { mmap, mmap_mut };
-        let error = valid_common_data(&page, length, Self::HEADER);
-        match error {
-            None => Ok(page),
-            Some(error) => Err(Box::from(error))
+    pub(crate) fn from(file: &Storage, offset: u64, length: u32, verify_checksum: bool, comparator: Comparator) -> MiniBaseResult<InnerPage> {
+        InnerPage::from_mmap(create_mmap(file, offset, length)?, verify_checksum, comparator)
+    }
+
+    // 以只读方式打开schema时使用只读映射读取页
+    pub(crate) fn from_read_only(file: &Storage, offset: u64, length: u32, verify_checksum: bool, comparator: Comparator) -> MiniBaseResult<InnerPage> {
+        InnerPage::from_mmap(create_read_only_mmap(file, offset, length)?, verify_checksum, comparator)
+    }
+
+    fn from_mmap(mmap: PageMap, verify_checksum: bool, comparator: Comparator) -> MiniBaseResult<InnerPage> {
+        let length = mmap.len() as u32;
+        check_page_length(length, Self::SORTED_TABLE)?;
+        let page = InnerPage { mmap, comparator };
+        if let Some(error) = valid_common_data(&page, length, Self::HEADER, verify_checksum) {
+            return Err(Box::from(error));
+        }
+        page.valid_records()?;
+        Ok(page)
+    }
+
+    pub(crate) fn verify(&self, verify_checksum: bool) -> MiniBaseResult<()> {
+        if let Some(error) = valid_common_data(self, self.mmap.len() as u32, Self::HEADER, verify_checksum) {
+            return Err(Box::from(error));
+        }
+        self.valid_records()?;
+        verify_sorted_keys(self)
+    }
+
+    // 检查有序列表中所有分隔key和子节点的偏移都位于页内
+    fn valid_records(&self) -> MiniBaseResult<()> {
+        for key_offset in self.get_sorted_table() {
+            let key_size = self.try_get_key(key_offset)?.len();
+            self.try_read_u64(key_offset + 4 + key_size)?;
         }
+        Ok(())
+    }
+
+    pub(crate) fn get_last_pointer(&self) -> u64 {
+        self.read_u64(Self::LAST_POINTER)
+    }
+
+    pub(crate) fn update_last_pointer(&mut self, value: u64) {
+        self.write_u64(Self::LAST_POINTER, value)
+    }
+
+    // 查找key所在的子节点，分隔key对应的子节点保存小于分隔key的数据，大于等于最大分隔key的数据位于LAST_POINTER
+    pub(crate) fn find_child(&self, key: &[u8]) -> u64 {
+        let sorted_table = &self.get_sorted_table()[..];
+        let (exist, index) = self.binary_search(key, sorted_table);
+        let index = if exist { index + 1 } else { index };
+        match sorted_table.get(index) {
+            Some(key_offset) => self.get_child(*key_offset),
+            None => self.get_last_pointer(),
+        }
+    }
+
+    // 查找key所在的子节点，同时返回子节点中数据的上界，即子节点右侧的分隔key，LAST_POINTER没有上界
+    pub(crate) fn find_child_with_upper_bound(&self, key: &[u8]) -> (u64, Option<Vec<u8>>) {
+        let sorted_table = &self.get_sorted_table()[..];
+        let (exist, index) = self.binary_search(key, sorted_table);
+        let index = if exist { index + 1 } else { index };
+        match sorted_table.get(index) {
+            Some(key_offset) => (self.get_child(*key_offset), Some(Vec::from(self.get_key(*key_offset)))),
+            None => (self.get_last_pointer(), None),
+        }
+    }
+
+    // 所有子节点，按key的自然序排列，最后一个是LAST_POINTER
+    pub(crate) fn get_children(&self) -> Vec<u64> {
+        let mut children: Vec<u64> = self.get_sorted_table().into_iter()
+            .map(|key_offset| self.get_child(key_offset))
+            .collect();
+        children.push(self.get_last_pointer());
+        children
+    }
+
+    // 最左侧的子节点，保存最小的数据
+    pub(crate) fn get_first_child(&self) -> u64 {
+        match self.get_sorted_table().first() {
+            Some(key_offset) => self.get_child(*key_offset),
+            None => self.get_last_pointer(),
+        }
+    }
+
+    // 子节点分裂后插入分隔key，left_child保存小于key的数据，right_child保存大于等于key的数据，节点空间不足时会失败
+    pub(crate) fn insert_separator(&mut self, key: &[u8], left_child: u64, right_child: u64) -> bool {
+        let sorted_table = &self.get_sorted_table()[..];
+        if (self.get_free_space() as usize) < self.get_required_space(key) {
+            return false;
+        }
+        let (_, index) = self.binary_search(key, sorted_table);
+        self.write_separator(index, sorted_table.len(), key, left_child);
+        // 原来指向left_child的指针改为指向right_child
+        match sorted_table.get(index) {
+            Some(key_offset) => self.update_child(*key_offset, right_child),
+            None => self.write_u64(Self::LAST_POINTER, right_child),
+        }
+        true
+    }
+
+    // 子节点的页被移动后，将指向old_child的指针改为指向new_child，没有找到old_child时返回false
+    pub(crate) fn replace_child(&mut self, old_child: u64, new_child: u64) -> bool {
+        let key_offset = self.get_sorted_table().into_iter().find(|key_offset| self.get_child(*key_offset) == old_child);
+        match key_offset {
+            Some(key_offset) => self.update_child(key_offset, new_child),
+            None if self.get_last_pointer() == old_child => self.write_u64(Self::LAST_POINTER, new_child),
+            None => return false,
+        }
+        true
+    }
+
+    // 获取所有分隔key及其对应的子节点，按key的自然序排列
+    pub(crate) fn get_separators(&self) -> Vec<(Vec<u8>, u64)> {
+        self.get_sorted_table().into_iter()
+            .map(|key_offset| (Vec::from(self.get_key(key_offset)), self.get_child(key_offset)))
+            .collect()
+    }
+
+    // 分裂内部节点，新分隔key与原有分隔key合并后，中间的key上推到父节点并作为返回值，其后的分隔key写入right
+    // 空间不足以完成分裂时返回错误，此时当前页不会被修改
+    pub(crate) fn split(&mut self, right: &mut InnerPage, key: &[u8], left_child: u64, right_child: u64) -> MiniBaseResult<Vec<u8>> {
+        let mut separators = self.get_separators();
+        let mut last_pointer = self.get_last_pointer();
+        let index = match separators.binary_search_by(|(k, _)| self.comparator.compare(k, key)) {
+            Ok(index) | Err(index) => index
+        };
+        separators.insert(index, (Vec::from(key), left_child));
+        match separators.get_mut(index + 1) {
+            Some(separator) => separator.1 = right_child,
+            None => last_pointer = right_child,
+        }
+        let middle = separators.len() / 2;
+        let usable_space = self.get_usable_space();
+        let left_space: usize = separators[..middle].iter().map(|(key, _)| self.get_required_space(key)).sum();
+        let right_space: usize = separators[middle + 1..].iter().map(|(key, _)| self.get_required_space(key)).sum();
+        if left_space > usable_space || right_space > usable_space {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::PageFull, "separator key too large for page")));
+        }
+        let (middle_key, middle_child) = separators[middle].clone();
+        self.rewrite(&separators[..middle], middle_child);
+        right.rewrite(&separators[middle + 1..], last_pointer);
+        Ok(middle_key)
+    }
+
+    // 清空页后写入分隔key和LAST_POINTER，调用方需要保证空间足够
+    pub(crate) fn rewrite(&mut self, separators: &[(Vec<u8>, u64)], last_pointer: u64) {
+        let capacity = self.get_capacity();
+        common_init(self, capacity as usize, Self::HEADER);
+        self.write_u64(Self::LAST_POINTER, last_pointer);
+        for (index, (key, child)) in separators.iter().enumerate() {
+            self.write_separator(index, index, key, *child);
+        }
+    }
+
+    // 除页头外可以用于保存数据的空间
+    fn get_usable_space(&self) -> usize {
+        self.get_capacity() as usize - Self::SORTED_TABLE
+    }
+
+    fn get_live_space(&self) -> usize {
+        self.get_sorted_table().into_iter()
+            .map(|key_offset| self.get_required_space(self.get_key(key_offset)))
+            .sum()
+    }
+
+    // 分隔key占用的空间低于可用空间的1/4时，需要与相邻节点合并或重新分配
+    pub(crate) fn is_underflow(&self) -> bool {
+        self.get_live_space() * 4 < self.get_usable_space()
+    }
+
+    // 替换index位置的分隔key时，新的分隔key能够使用的最大长度
+    pub(crate) fn get_max_separator_length(&self, index: usize) -> usize {
+        let sorted_table = self.get_sorted_table();
+        let other_space: usize = sorted_table.iter().enumerate()
+            .filter(|(i, _)| *i != index)
+            .map(|(_, key_offset)| self.get_required_space(self.get_key(*key_offset)))
+            .sum();
+        self.get_usable_space().saturating_sub(other_space + self.get_required_space(&[]))
+    }
+
+    // 与右侧相邻的节点合并，separator为父节点中两个节点之间的分隔key，合并时下移到左侧节点
+    // 合并后超出一页时两个节点重新均分分隔key，中间的分隔key作为父节点新的分隔key
+    pub(crate) fn rebalance(&mut self, right: &mut InnerPage, separator: &[u8], max_separator_length: usize) -> Rebalance {
+        let mut separators = self.get_separators();
+        separators.push((Vec::from(separator), self.get_last_pointer()));
+        separators.extend(right.get_separators());
+        let last_pointer = right.get_last_pointer();
+        let usable_space = self.get_usable_space();
+        let total_space: usize = separators.iter().map(|(key, _)| self.get_required_space(key)).sum();
+        if total_space <= usable_space {
+            self.rewrite(&separators, last_pointer);
+            return Rebalance::Merged;
+        }
+        let middle = separators.len() / 2;
+        let left_space: usize = separators[..middle].iter().map(|(key, _)| self.get_required_space(key)).sum();
+        let right_space = total_space - left_space - self.get_required_space(&separators[middle].0);
+        if left_space > usable_space || right_space > usable_space || separators[middle].0.len() > max_separator_length {
+            return Rebalance::Unchanged;
+        }
+        let (middle_key, middle_child) = separators[middle].clone();
+        self.rewrite(&separators[..middle], middle_child);
+        right.rewrite(&separators[middle + 1..], last_pointer);
+        Rebalance::Redistributed(middle_key)
+    }
+
+    // key所在的子节点的位置，位置等于分隔key数量时为LAST_POINTER
+    pub(crate) fn find_child_index(&self, key: &[u8]) -> usize {
+        let sorted_table = &self.get_sorted_table()[..];
+        let (exist, index) = self.binary_search(key, sorted_table);
+        if exist { index + 1 } else { index }
+    }
+
+    // 写入分隔key和对应的子节点，并在有序列表的index位置插入
+    fn write_separator(&mut self, index: usize, sorted_table_length: usize, key: &[u8], child: u64) {
+        let key_offset = self.allocate_space_tail(4 + key.len() + 8).unwrap();
+        self.write_u32(key_offset, key.len() as u32);
+        self.write_bytes(key_offset + 4, key);
+        self.update_child(key_offset, child);
+        self.insert_sorted_table(index, sorted_table_length, key_offset);
+    }
+
+    // 插入一个分隔key需要的空间，包括key、子节点偏移以及有序列表中的key_offset
+    fn get_required_space(&self, key: &[u8]) -> usize {
+        4 + key.len() + 8 + 4
+    }
+
+    fn get_child(&self, key_offset: usize) -> u64 {
+        let key_size = self.read_u32(key_offset);
+        self.read_u64(key_offset + 4 + key_size as usize)
+    }
+
+    fn update_child(&mut self, key_offset: usize, child: u64) {
+        let key_size = self.read_u32(key_offset);
+        self.write_u64(key_offset + 4 + key_size as usize, child)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::env;
     use std::fs;
-    use std::fs::{File, OpenOptions};
+    use std::fs::OpenOptions;
+    use std::ops::Deref;
+    use std::path::PathBuf;
+    use std::process;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use crate::{error_kind, ErrorKind};
+    use crate::page::{AccessPattern, Page, Pager};
+    use crate::storage::Storage;
 
     const PAGE_LENGTH: u32 = 512;
 
-    fn create_test_file(file_name: &str) -> File {
-        let file = OpenOptions::new().read(true).write(true).create(true).open(file_name).unwrap();
-        file.set_len(PAGE_LENGTH as u64).unwrap();
-        file
+    static TEST_FILE_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+    // 测试使用的临时文件，文件名带有进程id和序号，每次运行和每个测试都使用不同的文件
+    // drop时删除文件，断言失败panic时也会删除，不会留下影响下次运行的文件
+    struct TestFile {
+        path: PathBuf,
+        storage: Storage,
     }
 
-    fn delete_test_file(file_name: &str) {
-        fs::remove_file(file_name).unwrap()
+    impl TestFile {
+        fn new(name: &str) -> TestFile {
+            let sequence = TEST_FILE_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+            let path = env::temp_dir().join(format!("mini_base_{}_{}_{}", name, process::id(), sequence));
+            let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path).unwrap();
+            file.set_len(PAGE_LENGTH as u64).unwrap();
+            TestFile { path, storage: Storage::from(file) }
+        }
+
+        // 重新打开同一个文件，用于检查修改已经写入文件
+        fn reopen(&self) -> Storage {
+            Storage::from(OpenOptions::new().read(true).write(true).open(&self.path).unwrap())
+        }
+    }
+
+    impl Deref for TestFile {
+        type Target = Storage;
+
+        fn deref(&self) -> &Storage {
+            &self.storage
+        }
+    }
+
+    impl Drop for TestFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    #[test]
+    fn page_advise() {
+        let file_name = "page_advise";
+        let test_file = TestFile::new(file_name);
+        let mut page = Page::new(&test_file, 0, PAGE_LENGTH).unwrap();
+        page.write_u32(0, 1);
+        let read_only_page = Page::new_read_only(&test_file, 0, PAGE_LENGTH).unwrap();
+        let memory = Storage::memory(PAGE_LENGTH);
+        memory.set_len(PAGE_LENGTH as u64).unwrap();
+        let memory_page = Page::new(&memory, 0, PAGE_LENGTH).unwrap();
+        for access_pattern in [AccessPattern::Sequential, AccessPattern::Random, AccessPattern::Normal] {
+            page.advise(access_pattern).unwrap();
+            read_only_page.advise(access_pattern).unwrap();
+            memory_page.advise(access_pattern).unwrap();
+        }
+        // 提示不影响页中的数据
+        assert_eq!(1, read_only_page.read_u32(0));
+    }
+
+    #[test]
+    fn page_new_exceeds_file() {
+        let file_name = "page_new_exceeds_file";
+        let test_file = TestFile::new(file_name);
+
+        assert!(Page::new(&test_file, 0, PAGE_LENGTH).is_ok());
+        let error = Page::new(&test_file, PAGE_LENGTH as u64 / 2, PAGE_LENGTH).err().unwrap();
+        assert_eq!(ErrorKind::Corruption, error_kind(error.as_ref()));
+        assert!(error.to_string().contains("mapping exceeds file length"));
+        assert!(Page::new(&test_file, PAGE_LENGTH as u64, PAGE_LENGTH).is_err());
     }
 
     #[cfg(test)]
     mod test_leaf_page {
-        use crate::page::{LeafPage, Pager};
+        use crate::comparator::Comparator;
+        use crate::{error_kind, ErrorKind};
+        use crate::page::{DataPager, InsertResult, LeafPage, PageStats, Pager};
         use super::*;
 
         #[test]
         fn leaf_page_write_read_u8() {
-            let mut leaf_page = LeafPage::new(&create_test_file("leaf_page_write_read_u8"), 0, PAGE_LENGTH).unwrap();
+            let test_file = TestFile::new("leaf_page_write_read_u8");
+            let mut leaf_page = LeafPage::new(&test_file, 0, PAGE_LENGTH, Comparator::ByteLexicographic).unwrap();
             leaf_page.write_u8(0, 1);
             let i = leaf_page.read_u8(0);
             assert_eq!(i, 1);
-            delete_test_file("leaf_page_write_read_u8")
         }
 
         #[test]
         fn leaf_page_write_read_u32() {
-            let mut leaf_page = LeafPage::new(&create_test_file("leaf_page_write_read_u32"), 0, PAGE_LENGTH).unwrap();
+            let test_file = TestFile::new("leaf_page_write_read_u32");
+            let mut leaf_page = LeafPage::new(&test_file, 0, PAGE_LENGTH, Comparator::ByteLexicographic).unwrap();
             leaf_page.write_u32(0, 1);
             let i = leaf_page.read_u32(0);
             assert_eq!(i, 1);
-            delete_test_file("leaf_page_write_read_u32")
         }
 
         #[test]
         fn leaf_page_write_read_bytes() {
-            let mut leaf_page = LeafPage::new(&create_test_file("leaf_page_write_read_bytes"), 0, PAGE_LENGTH).unwrap();
+            let test_file = TestFile::new("leaf_page_write_read_bytes");
+            let mut leaf_page = LeafPage::new(&test_file, 0, PAGE_LENGTH, Comparator::ByteLexicographic).unwrap();
             let message = "今天真热";
             let data = message.as_bytes();
             leaf_page.write_bytes(0, data);
             let read = leaf_page.read_bytes(0, data.len());
             assert_eq!(message, String::from_utf8(read.to_vec()).unwrap());
-            delete_test_file("leaf_page_write_read_bytes")
         }
 
         #[test]
         fn leaf_page_insert_get() {
             let page_capacity = PAGE_LENGTH;
             let file_name = "leaf_page_insert_get";
-            let test_file = create_test_file(file_name);
+            let test_file = TestFile::new(file_name);
 
-            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity).unwrap();
-            let ok = leaf_page.insert_key_value("test".as_bytes(), "test".as_bytes());
-            assert_eq!(true, ok);
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap();
+            let result = leaf_page.insert_key_value("test".as_bytes(), "test".as_bytes());
+            assert_eq!(InsertResult::Inserted, result);
 
-            let leaf_page = LeafPage::from(&test_file, 0, page_capacity).unwrap();
+            let leaf_page = LeafPage::from(&test_file, 0, page_capacity, true, Comparator::ByteLexicographic).unwrap();
             let value = leaf_page.get_value("test".as_bytes()).unwrap();
             let value = String::from_utf8(Vec::from(value)).unwrap();
             assert_eq!("test".to_string(), value);
 
-            let mut leaf_page = LeafPage::from(&test_file, 0, page_capacity).unwrap();
-            let ok = leaf_page.insert_key_value("asd".as_bytes(), "asd".as_bytes());
-            assert_eq!(true, ok);
+            let mut leaf_page = LeafPage::from(&test_file, 0, page_capacity, true, Comparator::ByteLexicographic).unwrap();
+            let result = leaf_page.insert_key_value("asd".as_bytes(), "asd".as_bytes());
+            assert_eq!(InsertResult::Inserted, result);
 
-            let leaf_page = LeafPage::from(&test_file, 0, page_capacity).unwrap();
+            let leaf_page = LeafPage::from(&test_file, 0, page_capacity, true, Comparator::ByteLexicographic).unwrap();
             let value = leaf_page.get_value("asd".as_bytes()).unwrap();
             let value = String::from_utf8(Vec::from(value)).unwrap();
             assert_eq!("asd".to_string(), value);
 
-            let mut leaf_page = LeafPage::from(&test_file, 0, page_capacity).unwrap();
-            let ok = leaf_page.insert_key_value("songbowen".as_bytes(), "songbowen".as_bytes());
-            assert_eq!(true, ok);
+            let mut leaf_page = LeafPage::from(&test_file, 0, page_capacity, true, Comparator::ByteLexicographic).unwrap();
+            let result = leaf_page.insert_key_value("songbowen".as_bytes(), "songbowen".as_bytes());
+            assert_eq!(InsertResult::Inserted, result);
 
-            let leaf_page = LeafPage::from(&test_file, 0, page_capacity).unwrap();
+            let leaf_page = LeafPage::from(&test_file, 0, page_capacity, true, Comparator::ByteLexicographic).unwrap();
             let value = leaf_page.get_value("songbowen".as_bytes()).unwrap();
             let value = String::from_utf8(Vec::from(value)).unwrap();
             assert_eq!("songbowen".to_string(), value);
-
-            delete_test_file(file_name)
         }
 
         #[test]
         fn leaf_page_override() {
             let page_capacity = PAGE_LENGTH;
             let file_name = "leaf_page_override";
+            let test_file = TestFile::new(file_name);
 
-            let mut leaf_page = LeafPage::new(&create_test_file(file_name), 0, page_capacity).unwrap();
-            let ok = leaf_page.insert_key_value("test".as_bytes(), "今天真热".as_bytes());
-            assert_eq!(true, ok);
-            let ok = leaf_page.insert_key_value("test".as_bytes(), "今天真热，真滴热".as_bytes());
-            assert_eq!(true, ok);
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap();
+            let result = leaf_page.insert_key_value("test".as_bytes(), "今天真热".as_bytes());
+            assert_eq!(InsertResult::Inserted, result);
+            let result = leaf_page.insert_key_value("test".as_bytes(), "今天真热，真滴热".as_bytes());
+            assert_eq!(InsertResult::Overwritten, result);
 
-            let leaf_page = LeafPage::from(&create_test_file(file_name), 0, page_capacity).unwrap();
+            let leaf_page = LeafPage::from(&test_file.reopen(), 0, page_capacity, true, Comparator::ByteLexicographic).unwrap();
             let value = leaf_page.get_value("test".as_bytes()).unwrap();
             let value = String::from_utf8(Vec::from(value)).unwrap();
             assert_eq!("今天真热，真滴热".to_string(), value);
+        }
 
-            delete_test_file(file_name)
+        #[test]
+        fn leaf_page_override_too_large() {
+            let page_capacity = PAGE_LENGTH;
+            let test_file = TestFile::new("leaf_page_override_too_large");
+
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap();
+            assert_eq!(InsertResult::Inserted, leaf_page.insert_key_value(b"live", b"old"));
+            assert_eq!(InsertResult::Inserted, leaf_page.insert_key_value(b"removed", b"old"));
+            assert!(leaf_page.delete_value(b"removed"));
+            // 写满页，只剩下几个字节
+            let filler = vec![b'x'; leaf_page.get_free_space() as usize - 4 - (4 + 6 + 1 + 4) - 4 - 8];
+            assert_eq!(InsertResult::Inserted, leaf_page.insert_key_value(b"filler", &filler));
+            assert!(leaf_page.get_free_space() < 16);
+            let before = leaf_page.get_mmap().to_vec();
+
+            // 空间不足的覆盖不修改页，已删除的key仍然是删除状态
+            for key in [b"live".as_slice(), b"removed".as_slice()] {
+                let sorted_table = leaf_page.get_sorted_table();
+                let (_, index) = leaf_page.binary_search(key, &sorted_table);
+                assert!(!leaf_page.override_value(&sorted_table, index, key, &[b'y'; 16]));
+                assert!(!leaf_page.override_value(&sorted_table, index, key, &vec![b'y'; PAGE_LENGTH as usize * 2]));
+            }
+            assert_eq!(before, leaf_page.get_mmap().to_vec());
+            assert_eq!(Some(b"old".as_slice()), leaf_page.get_value(b"live"));
+            assert_eq!(None, leaf_page.get_value(b"removed"));
+            assert_eq!(vec![(b"filler".to_vec(), false), (b"live".to_vec(), false), (b"removed".to_vec(), true)], leaf_page.get_keys_with_deleted());
+
+            // 相同的value不需要空间，只清除删除标记
+            let sorted_table = leaf_page.get_sorted_table();
+            let (_, index) = leaf_page.binary_search(b"removed", &sorted_table);
+            assert!(leaf_page.override_value(&sorted_table, index, b"removed", b"old"));
+            assert_eq!(Some(b"old".as_slice()), leaf_page.get_value(b"removed"));
         }
 
         #[test]
         fn leaf_page_remove() {
             let page_capacity = PAGE_LENGTH;
             let file_name = "leaf_page_remove";
+            let test_file = TestFile::new(file_name);
 
-            let mut leaf_page = LeafPage::new(&create_test_file(file_name), 0, page_capacity).unwrap();
-            let ok = leaf_page.insert_key_value("test".as_bytes(), "今天真热".as_bytes());
-            assert_eq!(true, ok);
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap();
+            let result = leaf_page.insert_key_value("test".as_bytes(), "今天真热".as_bytes());
+            assert_eq!(InsertResult::Inserted, result);
 
             let ok = leaf_page.delete_value("test".as_bytes());
-            assert_eq!(true, ok);
+            assert!(ok);
 
             let value = leaf_page.get_value("test".as_bytes());
             assert_eq!(None, value);
+            assert!(!leaf_page.has_live_key("test".as_bytes()));
+            assert!(!leaf_page.has_live_key("absent".as_bytes()));
 
-            let ok = leaf_page.insert_key_value("test".as_bytes(), "今天真热啊".as_bytes());
-            assert_eq!(true, ok);
+            let result = leaf_page.insert_key_value("test".as_bytes(), "今天真热啊".as_bytes());
+            assert_eq!(InsertResult::Inserted, result);
 
             let value = leaf_page.get_value("test".as_bytes()).unwrap();
             let value = String::from_utf8(Vec::from(value)).unwrap();
             assert_eq!("今天真热啊".to_string(), value);
+        }
+
+        #[test]
+        fn leaf_page_empty_key_value() {
+            let page_capacity = PAGE_LENGTH;
+            let file_name = "leaf_page_empty_key_value";
+            let test_file = TestFile::new(file_name);
+
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap();
+            assert_eq!(InsertResult::Inserted, leaf_page.insert_key_value("k".as_bytes(), "".as_bytes()));
+            assert_eq!(InsertResult::Inserted, leaf_page.insert_key_value("".as_bytes(), "x".as_bytes()));
+            // 空key排在最前面
+            let keys: Vec<Vec<u8>> = leaf_page.get_key_values().into_iter().map(|(key, _)| key).collect();
+            assert_eq!(vec!["".as_bytes().to_vec(), "k".as_bytes().to_vec()], keys);
+
+            let leaf_page = LeafPage::from(&test_file, 0, page_capacity, true, Comparator::ByteLexicographic).unwrap();
+            assert_eq!(Some("x".as_bytes()), leaf_page.get_value("".as_bytes()));
+            // 空value与不存在的key不同
+            assert_eq!(Some("".as_bytes()), leaf_page.get_value("k".as_bytes()));
+            assert!(leaf_page.has_live_key("k".as_bytes()));
+            assert_eq!(None, leaf_page.get_value("absent".as_bytes()));
+
+            let mut leaf_page = LeafPage::from(&test_file, 0, page_capacity, true, Comparator::ByteLexicographic).unwrap();
+            assert_eq!(InsertResult::Overwritten, leaf_page.insert_key_value("".as_bytes(), "".as_bytes()));
+            assert_eq!(Some("".as_bytes()), leaf_page.get_value("".as_bytes()));
+            assert!(leaf_page.delete_value("".as_bytes()));
+            assert_eq!(None, leaf_page.get_value("".as_bytes()));
+            assert!(!leaf_page.has_live_key("".as_bytes()));
+            assert_eq!(InsertResult::Inserted, leaf_page.insert_key_value("".as_bytes(), "".as_bytes()));
+            assert_eq!(Some("".as_bytes()), leaf_page.get_value("".as_bytes()));
+
+            // 空key value只占用长度、删除标记、value偏移和有序列表中的key_offset
+            assert_eq!(4 + 1 + 4 + 4 + 4, leaf_page.get_required_space(&[], &[]));
+        }
+
+        #[test]
+        fn leaf_page_checksum() {
+            let page_capacity = PAGE_LENGTH;
+            let file_name = "leaf_page_checksum";
+            let test_file = TestFile::new(file_name);
+
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap();
+            assert_eq!(InsertResult::Inserted, leaf_page.insert_key_value("test".as_bytes(), "今天真热".as_bytes()));
+            assert!(LeafPage::from(&test_file, 0, page_capacity, true, Comparator::ByteLexicographic).is_ok());
+
+            // 绕过write_u8直接修改value中的一个字节，模拟数据损坏
+            let last = page_capacity as usize - 1;
+            let byte = leaf_page.read_u8(last);
+            leaf_page.get_mmap_mut()[last] = !byte;
+            let error = LeafPage::from(&test_file, 0, page_capacity, true, Comparator::ByteLexicographic).err().unwrap();
+            assert_eq!("mini base error: page checksum mismatch", error.to_string());
+            assert!(LeafPage::from(&test_file, 0, page_capacity, false, Comparator::ByteLexicographic).is_ok());
+        }
+
+        #[test]
+        fn leaf_page_incremental_checksum() {
+            let page_capacity = PAGE_LENGTH;
+            let file_name = "leaf_page_incremental_checksum";
+            let test_file = TestFile::new(file_name);
+
+            // 每次修改后增量更新的校验和都与重新计算的结果一致
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap();
+            for i in 0..200usize {
+                let key = format!("key{}", i % 37);
+                match i % 4 {
+                    0 | 1 => { leaf_page.insert_key_value(key.as_bytes(), format!("value{}", i).as_bytes()); }
+                    2 => { leaf_page.delete_value(key.as_bytes()); }
+                    // 在空闲空间中写入，不影响页中的数据
+                    _ if leaf_page.get_free_space() >= 8 => {
+                        let offset = leaf_page.get_data_head_offset() + i * 13 % (leaf_page.get_free_space() as usize - 7);
+                        leaf_page.write_u8(offset, i as u8);
+                        leaf_page.write_u32(offset + 1, i as u32 * 31);
+                        leaf_page.write_bytes(offset + 5, &[i as u8; 3]);
+                    }
+                    _ => {}
+                }
+                assert_eq!(leaf_page.compute_checksum(), leaf_page.get_checksum());
+            }
+            leaf_page.update_next_page(7);
+            leaf_page.update_previous_page(9);
+            leaf_page.update_header(leaf_page.get_header());
+            assert_eq!(leaf_page.compute_checksum(), leaf_page.get_checksum());
+            leaf_page.compact();
+            assert_eq!(leaf_page.compute_checksum(), leaf_page.get_checksum());
+
+            // 直接写入校验和字段时不做增量更新
+            leaf_page.write_u32(LeafPage::CHECKSUM, 0);
+            assert_eq!(0, leaf_page.get_checksum());
+        }
 
-            delete_test_file(file_name)
+        #[test]
+        fn leaf_page_out_of_bounds() {
+            let page_capacity = PAGE_LENGTH;
+            let file_name = "leaf_page_out_of_bounds";
+            let test_file = TestFile::new(file_name);
+
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap();
+            assert_eq!(InsertResult::Inserted, leaf_page.insert_key_value("test".as_bytes(), "test".as_bytes()));
+            assert!(leaf_page.try_read_u32(page_capacity as usize - 4).is_ok());
+            let error = leaf_page.try_read_u32(page_capacity as usize - 2).err().unwrap();
+            assert_eq!("mini base error: read out of page bounds, offset 510 length 4 page length 512", error.to_string());
+
+            // value偏移指向页尾，模拟数据损坏
+            let key_offset = leaf_page.get_sorted_table()[0];
+            let position = leaf_page.get_value_offset_position(key_offset, 4);
+            leaf_page.write_u32(position, page_capacity - 2);
+            let error = LeafPage::from(&test_file, 0, page_capacity, false, Comparator::ByteLexicographic).err().unwrap();
+            assert_eq!(ErrorKind::Corruption, error_kind(error.as_ref()));
+        }
+
+        #[test]
+        fn leaf_page_too_small() {
+            let test_file = TestFile::new("leaf_page_too_small");
+
+            // 页头之后没有空间时返回错误，不会panic
+            for length in [25, 26, LeafPage::SORTED_TABLE as u32] {
+                let error = LeafPage::new(&test_file, 0, length, Comparator::ByteLexicographic).err().unwrap();
+                assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+                assert!(error.to_string().contains("page_size too small for header"));
+                assert!(LeafPage::new_anonymous(length, Comparator::ByteLexicographic).is_err());
+            }
+            let error = LeafPage::from(&test_file, 0, 26, false, Comparator::ByteLexicographic).err().unwrap();
+            assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+
+            let length = LeafPage::SORTED_TABLE as u32 + 1;
+            let mut leaf_page = LeafPage::new(&test_file, 0, length, Comparator::ByteLexicographic).unwrap();
+            assert_eq!(InsertResult::NeedsSplit, leaf_page.insert_key_value(b"a", b""));
+            assert!(LeafPage::from(&test_file, 0, length, true, Comparator::ByteLexicographic).is_ok());
+        }
+
+        #[test]
+        fn leaf_page_bogus_length() {
+            let page_capacity = PAGE_LENGTH;
+            let test_file = TestFile::new("leaf_page_bogus_length");
+
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap();
+            assert_eq!(InsertResult::Inserted, leaf_page.insert_key_value(b"key", b"value"));
+            leaf_page.flush().unwrap();
+            let key_offset = leaf_page.get_sorted_table()[0];
+            assert_eq!((false, b"value".as_slice()), leaf_page.try_get_value_by_key_offset(key_offset).unwrap());
+            let value_offset = leaf_page.read_u32(leaf_page.get_value_offset_position(key_offset, 3)) as usize;
+            let reload = || LeafPage::from(&test_file, 0, page_capacity, false, Comparator::ByteLexicographic);
+
+            // value长度超出页的剩余空间
+            leaf_page.write_u32(value_offset, u32::MAX);
+            let error = leaf_page.try_get_value_by_key_offset(key_offset).err().unwrap();
+            assert_eq!(ErrorKind::Corruption, error_kind(error.as_ref()));
+            assert!(error.to_string().contains("read out of page bounds"));
+            let error = reload().err().unwrap();
+            assert_eq!(ErrorKind::Corruption, error_kind(error.as_ref()));
+            leaf_page.write_u32(value_offset, (page_capacity as usize - value_offset - 3) as u32);
+            assert!(reload().is_err());
+            leaf_page.write_u32(value_offset, 5);
+            assert!(reload().is_ok());
+
+            // value偏移指向页头，长度在页内时也不能读取
+            let position = leaf_page.get_value_offset_position(key_offset, 3);
+            leaf_page.write_u32(position, LeafPage::CHECKSUM as u32);
+            let error = reload().err().unwrap();
+            assert_eq!(ErrorKind::Corruption, error_kind(error.as_ref()));
+            assert!(error.to_string().contains("before data region"));
+            leaf_page.write_u32(position, value_offset as u32);
+
+            // key长度超出页的剩余空间
+            leaf_page.write_u32(key_offset, page_capacity);
+            let error = reload().err().unwrap();
+            assert_eq!(ErrorKind::Corruption, error_kind(error.as_ref()));
+            leaf_page.write_u32(key_offset, 3);
+            assert_eq!(Some(b"value".as_slice()), reload().unwrap().get_value(b"key"));
+        }
+
+        #[test]
+        fn leaf_page_get_or_insert() {
+            let page_capacity = PAGE_LENGTH;
+            let file_name = "leaf_page_get_or_insert";
+            let test_file = TestFile::new(file_name);
+
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap();
+            assert_eq!(Some(("default".as_bytes().to_vec(), true)), leaf_page.get_or_insert("test".as_bytes(), "default".as_bytes()));
+            assert_eq!(Some(("default".as_bytes().to_vec(), false)), leaf_page.get_or_insert("test".as_bytes(), "other".as_bytes()));
+            assert!(leaf_page.delete_value("test".as_bytes()));
+            assert_eq!(Some(("other".as_bytes().to_vec(), true)), leaf_page.get_or_insert("test".as_bytes(), "other".as_bytes()));
+            assert_eq!("other".as_bytes(), leaf_page.get_value("test".as_bytes()).unwrap());
+            assert_eq!(None, leaf_page.get_or_insert("large".as_bytes(), &[0; PAGE_LENGTH as usize]));
+        }
+
+        #[test]
+        fn leaf_page_stats() {
+            let page_capacity = PAGE_LENGTH;
+            let file_name = "leaf_page_stats";
+            let test_file = TestFile::new(file_name);
+
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap();
+            leaf_page.insert_key_value("a".as_bytes(), "1111".as_bytes());
+            leaf_page.insert_key_value("b".as_bytes(), "1111".as_bytes());
+            leaf_page.insert_key_value("a".as_bytes(), "22".as_bytes());
+            leaf_page.delete_value("b".as_bytes());
+
+            // 被覆盖的value占用8字节，删除的key value占用22字节
+            let stats = leaf_page.stats();
+            assert_eq!(PageStats {
+                live_key_count: 1,
+                tombstone_count: 1,
+                free_space: 425,
+                reclaimable_garbage_bytes: 30,
+                data_tail_offset: 470,
+            }, stats);
+            leaf_page.compact();
+            assert_eq!(0, leaf_page.stats().reclaimable_garbage_bytes);
+            assert_eq!(0, leaf_page.stats().tombstone_count);
+        }
+
+        #[test]
+        fn leaf_page_compact_if_garbage_exceeds() {
+            let page_capacity = PAGE_LENGTH;
+            let file_name = "leaf_page_compact_if_garbage_exceeds";
+            let test_file = TestFile::new(file_name);
+
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap();
+            leaf_page.insert_key_value("a".as_bytes(), &[1; 100]);
+            leaf_page.insert_key_value("a".as_bytes(), &[2; 100]);
+            // 被覆盖的value占用104字节，未超过容量的0.3
+            assert!(!leaf_page.compact_if_garbage_exceeds(0.3));
+            assert!(!leaf_page.compact_if_garbage_exceeds(1.0));
+            assert!(leaf_page.compact_if_garbage_exceeds(0.2));
+            assert_eq!(0, leaf_page.stats().reclaimable_garbage_bytes);
+            assert_eq!(Some(&[2; 100][..]), leaf_page.get_value("a".as_bytes()));
+        }
+
+        #[test]
+        fn leaf_page_count() {
+            let page_capacity = PAGE_LENGTH;
+            let file_name = "leaf_page_count";
+            let test_file = TestFile::new(file_name);
+
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap();
+            for key in ["d", "b", "e", "a", "c"] {
+                assert_eq!(InsertResult::Inserted, leaf_page.insert_key_value(key.as_bytes(), key.as_bytes()));
+            }
+            assert!(leaf_page.delete_value("c".as_bytes()));
+            assert_eq!(4, leaf_page.get_live_key_count());
+
+            let mut count = 0;
+            assert!(!leaf_page.count("b".as_bytes(), "e".as_bytes(), false, &mut count));
+            assert_eq!(2, count);
+            assert!(leaf_page.count("b".as_bytes(), "e".as_bytes(), true, &mut count));
+            assert_eq!(5, count);
+        }
+
+        #[test]
+        fn leaf_page_measure() {
+            let page_capacity = PAGE_LENGTH;
+            let file_name = "leaf_page_measure";
+            let test_file = TestFile::new(file_name);
+
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap();
+            for key in ["d", "b", "e", "a", "c"] {
+                assert_eq!(InsertResult::Inserted, leaf_page.insert_key_value(key.as_bytes(), key.as_bytes()));
+            }
+            assert!(leaf_page.delete_value("c".as_bytes()));
+
+            // 每个key value占用key长度4、key 1、删除标记1、value偏移4、value长度4、value 1和排序表4字节
+            let mut size = 0;
+            assert!(!leaf_page.measure("b".as_bytes(), "e".as_bytes(), &|_| Ok(0), &mut size).unwrap());
+            assert_eq!(2 * 19, size);
+            let mut size = 0;
+            assert!(leaf_page.measure("".as_bytes(), "f".as_bytes(), &|value| Ok(value.len() as u64 * 10), &mut size).unwrap());
+            assert_eq!(4 * 29, size);
+        }
+
+        #[test]
+        fn leaf_page_compact() {
+            let page_capacity = PAGE_LENGTH;
+            let file_name = "leaf_page_compact";
+            let test_file = TestFile::new(file_name);
+
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap();
+            assert_eq!(InsertResult::Inserted, leaf_page.insert_key_value("remove".as_bytes(), "今天真热".as_bytes()));
+            assert!(leaf_page.delete_value("remove".as_bytes()));
+            // 每次覆盖都会在尾部分配新的value，不整理的话很快就会空间不足
+            for i in 0..100 {
+                let value = format!("今天真热{}", i);
+                let expected = if i == 0 { InsertResult::Inserted } else { InsertResult::Overwritten };
+                assert_eq!(expected, leaf_page.insert_key_value("test".as_bytes(), value.as_bytes()));
+                assert_eq!(value.as_bytes(), leaf_page.get_value("test".as_bytes()).unwrap());
+            }
+            assert_eq!(None, leaf_page.get_value("remove".as_bytes()));
+            assert_eq!(1, leaf_page.get_sorted_table().len());
+        }
+
+        #[test]
+        fn leaf_page_scan() {
+            let page_capacity = PAGE_LENGTH;
+            let file_name = "leaf_page_scan";
+            let test_file = TestFile::new(file_name);
+
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap();
+            for key in ["d", "b", "e", "a", "c"] {
+                assert_eq!(InsertResult::Inserted, leaf_page.insert_key_value(key.as_bytes(), key.as_bytes()));
+            }
+            assert!(leaf_page.delete_value("c".as_bytes()));
+
+            let mut result = Vec::new();
+            let has_more = leaf_page.scan("b".as_bytes(), "e".as_bytes(), false, &mut result);
+            assert!(!has_more);
+            let keys: Vec<&[u8]> = result.iter().map(|(key, _)| &key[..]).collect();
+            assert_eq!(vec!["b".as_bytes(), "d".as_bytes()], keys);
+
+            let mut result = Vec::new();
+            let has_more = leaf_page.scan("b".as_bytes(), "e".as_bytes(), true, &mut result);
+            assert!(has_more);
+            let keys: Vec<&[u8]> = result.iter().map(|(key, _)| &key[..]).collect();
+            assert_eq!(vec!["b".as_bytes(), "d".as_bytes(), "e".as_bytes()], keys);
+        }
+
+        #[test]
+        fn leaf_page_bloom_filter() {
+            let page_capacity = PAGE_LENGTH;
+            let file_name = "leaf_page_bloom_filter";
+            let test_file = TestFile::new(file_name);
+
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap().with_bloom_filter(true);
+            assert!(leaf_page.has_bloom_filter());
+            assert_eq!(page_capacity as usize - page_capacity as usize / 16, leaf_page.get_data_tail_offset());
+            for i in 0..10 {
+                let key = format!("key{}", i);
+                assert_eq!(InsertResult::Inserted, leaf_page.insert_key_value(key.as_bytes(), "value".as_bytes()));
+            }
+            // 插入的key一定通过过滤器，大部分不存在的key被过滤
+            assert!((0..10).all(|i| leaf_page.may_contain(format!("key{}", i).as_bytes())));
+            let passed = (0..100).filter(|i| leaf_page.may_contain(format!("absent{}", i).as_bytes())).count();
+            assert!(passed < 20, "{} absent keys passed", passed);
+
+            // 删除后过滤器不变，整理页后重建
+            assert!(leaf_page.delete_value("key0".as_bytes()));
+            assert!(leaf_page.may_contain("key0".as_bytes()));
+            leaf_page.compact();
+            assert!((1..10).all(|i| leaf_page.may_contain(format!("key{}", i).as_bytes())));
+            assert_eq!(None, leaf_page.get_value("key0".as_bytes()));
+
+            // 过滤器保存在页中，重新读取后仍然有效，数据区不能覆盖过滤器
+            let leaf_page = LeafPage::from(&test_file.reopen(), 0, page_capacity, true, Comparator::ByteLexicographic).unwrap();
+            assert!(leaf_page.has_bloom_filter());
+            assert!(leaf_page.may_contain("key1".as_bytes()));
+            assert!(LeafPage::is_leaf_header(leaf_page.get_header()));
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap().with_bloom_filter(true);
+            leaf_page.update_data_tail_offset(page_capacity);
+            leaf_page.update_checksum();
+            let error = LeafPage::from(&test_file, 0, page_capacity, true, Comparator::ByteLexicographic).err().unwrap();
+            assert_eq!(ErrorKind::Corruption, error_kind(error.as_ref()));
+
+            // 不区分大小写时只有大小写不同的key也通过过滤器，自定义的排序方式不使用过滤器
+            let mut leaf_page = LeafPage::new(&test_file, 0, page_capacity, Comparator::CaseInsensitiveAscii).unwrap().with_bloom_filter(true);
+            leaf_page.insert_key_value("Apple".as_bytes(), "value".as_bytes());
+            assert!(leaf_page.may_contain("aPPLE".as_bytes()));
+            assert!(!LeafPage::new(&test_file, 0, page_capacity, Comparator::ByteLexicographic).unwrap().has_bloom_filter());
         }
     }
-}
\ No newline at end of file
+
+    #[cfg(test)]
+    mod test_inner_page {
+        use crate::comparator::Comparator;
+        use crate::page::InnerPage;
+        use super::*;
+
+        #[test]
+        fn inner_page_too_small() {
+            let test_file = TestFile::new("inner_page_too_small");
+
+            let error = InnerPage::new(&test_file, 0, InnerPage::SORTED_TABLE as u32, Comparator::ByteLexicographic).err().unwrap();
+            assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+            assert!(InnerPage::new(&test_file, 0, InnerPage::SORTED_TABLE as u32 + 1, Comparator::ByteLexicographic).is_ok());
+        }
+
+        #[test]
+        fn inner_page_find_child() {
+            let file_name = "inner_page_find_child";
+            let test_file = TestFile::new(file_name);
+
+            // 三个子节点：[.., "h") -> 1, ["h", "p") -> 2, ["p", ..) -> 3
+            let mut inner_page = InnerPage::new(&test_file, 0, PAGE_LENGTH, Comparator::ByteLexicographic).unwrap();
+            inner_page.update_last_pointer(1);
+            assert!(inner_page.insert_separator("p".as_bytes(), 1, 3));
+            assert!(inner_page.insert_separator("h".as_bytes(), 1, 2));
+
+            assert_eq!(1, inner_page.find_child("".as_bytes()));
+            assert_eq!(1, inner_page.find_child("a".as_bytes()));
+            assert_eq!(2, inner_page.find_child("h".as_bytes()));
+            assert_eq!(2, inner_page.find_child("hz".as_bytes()));
+            assert_eq!(2, inner_page.find_child("o".as_bytes()));
+            assert_eq!(3, inner_page.find_child("p".as_bytes()));
+            assert_eq!(3, inner_page.find_child("z".as_bytes()));
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use crate::{ErrorKind, MeteData, MiniBaseError, MiniBaseResult};
+use crate::node::Node;
+use crate::page::{LeafPage, NULL_PAGE, Pager};
+use crate::storage::Storage;
+
+// 打开schema时对损坏的叶节点的处理方式
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum CorruptionMode {
+    // 读取到损坏的页时返回Corruption错误
+    #[default]
+    Strict,
+    // 打开时检查所有页，读取时将损坏的叶节点视为空的叶节点，只能只读打开
+    Lenient,
+    // 打开时检查所有页，将损坏的叶节点重新初始化为空的叶节点并修复叶节点之间的链表，其余数据可以继续读写
+    Repair,
+}
+
+// 损坏的叶节点及其中数据的范围，包含begin不包含end，None表示没有下界或上界
+#[derive(Debug, PartialEq, Clone)]
+pub struct LostRange {
+    pub page_offset: u64,
+    pub begin: Option<Vec<u8>>,
+    pub end: Option<Vec<u8>>,
+}
+
+// 检查B+树时发现的问题类型
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum VerificationErrorKind {
+    // 页无法读取，或者页中的校验和、记录的偏移和长度、key的顺序不正确
+    InvalidPage,
+    // key不在父节点的分隔key确定的范围内
+    KeyOutOfBounds,
+    // 叶节点与其他叶节点不在同一层
+    LeafDepth,
+    // 叶节点之间的链表与key的顺序不一致
+    LeafLinks,
+    // value在额外数据文件中的数据无法读取
+    ExtraValue,
+}
+
+// 检查B+树时发现的一个问题，begin和end为出问题的页中数据应有的范围，包含begin不包含end，None表示没有下界或上界
+#[derive(Debug, PartialEq, Clone)]
+pub struct VerificationError {
+    pub page_offset: u64,
+    pub kind: VerificationErrorKind,
+    pub message: String,
+    pub begin: Option<Vec<u8>>,
+    pub end: Option<Vec<u8>>,
+}
+
+impl Display for VerificationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "page {}: {}", self.page_offset, self.message)
+    }
+}
+
+// 检查时读取到的叶节点，以及其中数据应有的范围
+pub(crate) struct VerifiedLeaf {
+    pub(crate) offset: u64,
+    pub(crate) depth: usize,
+    pub(crate) begin: Option<Vec<u8>>,
+    pub(crate) end: Option<Vec<u8>>,
+}
+
+// 按key的顺序排列的叶节点和检查发现的问题，None表示无法读取的子树，其中的叶节点未知
+// fail_fast时发现第一个问题即返回Corruption错误，否则记录所有问题并继续检查
+pub(crate) struct VerifyScan {
+    pub(crate) fail_fast: bool,
+    pub(crate) leaves: Vec<Option<VerifiedLeaf>>,
+    pub(crate) errors: Vec<VerificationError>,
+}
+
+impl VerifyScan {
+    pub(crate) fn new(fail_fast: bool) -> VerifyScan {
+        VerifyScan { fail_fast, leaves: Vec::new(), errors: Vec::new() }
+    }
+
+    pub(crate) fn report(&mut self, page_offset: u64, kind: VerificationErrorKind, message: String, begin: Option<&[u8]>, end: Option<&[u8]>) -> MiniBaseResult<()> {
+        let error = VerificationError { page_offset, kind, message, begin: begin.map(<[u8]>::to_vec), end: end.map(<[u8]>::to_vec) };
+        if self.fail_fast {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, error.to_string())));
+        }
+        self.errors.push(error);
+        Ok(())
+    }
+
+    // 将Corruption错误记录为问题，读写文件等其他错误直接返回
+    pub(crate) fn report_error(&mut self, page_offset: u64, kind: VerificationErrorKind, error: Box<dyn Error>, begin: Option<&[u8]>, end: Option<&[u8]>) -> MiniBaseResult<()> {
+        let message = match error.downcast_ref::<MiniBaseError>() {
+            Some(error) if error.kind() == ErrorKind::Corruption => error.message().to_string(),
+            _ => return Err(error),
+        };
+        self.report(page_offset, kind, message, begin, end)
+    }
+}
+
+// 按key的顺序排列的所有叶节点，以及其中损坏的叶节点
+pub(crate) struct LeafScan {
+    pub(crate) leaf_depth: usize,
+    pub(crate) leaves: Vec<u64>,
+    pub(crate) lost_ranges: Vec<LostRange>,
+}
+
+// 检查所有页，按mode处理损坏的叶节点，返回丢失的数据范围
+// 只能处理叶节点的损坏，根节点或内部节点损坏时返回Corruption错误
+pub(crate) fn salvage(mete_data: &mut MeteData, file: &Storage, mode: CorruptionMode) -> MiniBaseResult<Vec<LostRange>> {
+    let root = Node::load(mete_data, file, mete_data.get_root_page_offset())?;
+    let scan = root.find_leaf_depth(mete_data, file, 1).and_then(|leaf_depth| {
+        let mut scan = LeafScan { leaf_depth, leaves: Vec::new(), lost_ranges: Vec::new() };
+        root.collect_leaves(mete_data, file, 1, None, None, &mut scan)?;
+        Ok(scan)
+    });
+    root.release(mete_data, false)?;
+    let scan = scan?;
+    let is_lost = |offset: u64| scan.lost_ranges.iter().any(|lost_range| lost_range.page_offset == offset);
+    for lost_range in scan.lost_ranges.iter() {
+        // 叶节点在链表中的前后节点按key的顺序确定，不依赖损坏的页中的数据
+        let index = scan.leaves.iter().position(|offset| *offset == lost_range.page_offset).unwrap();
+        let previous_page = if index == 0 { NULL_PAGE } else { scan.leaves[index - 1] };
+        let next_page = scan.leaves.get(index + 1).copied().unwrap_or(NULL_PAGE);
+        match mode {
+            CorruptionMode::Strict => {}
+            CorruptionMode::Lenient => {
+                mete_data.skipped_leaves.insert(lost_range.page_offset, (previous_page, next_page));
+            }
+            CorruptionMode::Repair => {
+                let (page_size, comparator) = (mete_data.page_size, mete_data.comparator.clone());
+                let mut page = LeafPage::new(file, lost_range.page_offset, page_size, comparator.clone())?.with_bloom_filter(mete_data.bloom_filter);
+                page.update_previous_page(previous_page);
+                page.update_next_page(next_page);
+                page.flush()?;
+                // 相邻的叶节点本身没有损坏，重新指向修复后的叶节点
+                if previous_page != NULL_PAGE && !is_lost(previous_page) {
+                    let mut page = LeafPage::from(file, previous_page, page_size, mete_data.verify_checksum, comparator.clone())?;
+                    page.update_next_page(lost_range.page_offset);
+                    page.flush()?;
+                }
+                if next_page != NULL_PAGE && !is_lost(next_page) {
+                    let mut page = LeafPage::from(file, next_page, page_size, mete_data.verify_checksum, comparator)?;
+                    page.update_previous_page(lost_range.page_offset);
+                    page.flush()?;
+                }
+            }
+        }
+    }
+    if mode == CorruptionMode::Repair && !scan.lost_ranges.is_empty() {
+        file.sync_all()?;
+    }
+    Ok(scan.lost_ranges)
+}
@@ -0,0 +1,449 @@
+use std::fs::File;
+use std::io;
+use std::ops::{Deref, DerefMut, Range};
+use std::os::unix::fs::FileExt;
+use std::{ptr, slice};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard};
+use memmap2::{MmapMut, MmapOptions};
+use crate::{ErrorKind, MiniBaseError, MiniBaseResult};
+use crate::endian::Endian;
+
+// 内存中的文件按段分配，段的大小为页大小的整数倍，页不会跨越两个段
+const MIN_SEGMENT_SIZE: u64 = 64 * 1024;
+
+// 数据文件、额外数据文件和元数据文件的存储，以及其中整数的字节序，映射的页使用同样的字节序
+pub(crate) struct Storage {
+    pub(crate) backend: Backend,
+    endian: Endian,
+}
+
+// 内存模式下所有数据保存在匿名映射中，不会创建任何文件
+// 单文件模式下数据文件和额外数据文件为元数据文件中的两个区域
+pub(crate) enum Backend {
+    File(File),
+    Memory(MemoryFile),
+    Region(Arc<SingleFile>, Region),
+}
+
+impl From<File> for Storage {
+    fn from(file: File) -> Storage {
+        Storage { backend: Backend::File(file), endian: Endian::Little }
+    }
+}
+
+impl Storage {
+    // 内存中的空文件，page_size为映射页的大小
+    pub(crate) fn memory(page_size: u32) -> Storage {
+        let segment_size = MIN_SEGMENT_SIZE.max(page_size as u64);
+        let memory = MemoryFile { segment_size, state: RwLock::new(MemoryState { segments: Vec::new(), length: 0 }) };
+        Storage { backend: Backend::Memory(memory), endian: Endian::Little }
+    }
+
+    pub(crate) fn with_endian(self, endian: Endian) -> Storage {
+        Storage { endian, ..self }
+    }
+
+    pub(crate) fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    pub(crate) fn len(&self) -> MiniBaseResult<u64> {
+        match &self.backend {
+            Backend::File(file) => Ok(file.metadata()?.len()),
+            Backend::Memory(memory) => Ok(memory.state().length),
+            Backend::Region(file, region) => Ok(file.table().get(*region).1),
+        }
+    }
+
+    pub(crate) fn set_len(&self, length: u64) -> MiniBaseResult<()> {
+        match &self.backend {
+            Backend::File(file) => Ok(file.set_len(length)?),
+            Backend::Memory(memory) => memory.set_len(length),
+            Backend::Region(file, region) => file.set_len(*region, length),
+        }
+    }
+
+    // 内存中的数据不需要写入磁盘
+    pub(crate) fn sync_all(&self) -> MiniBaseResult<()> {
+        match &self.backend {
+            Backend::File(file) => file.sync_all()?,
+            Backend::Memory(_) => {}
+            Backend::Region(file, _) => file.file.sync_all()?,
+        }
+        Ok(())
+    }
+
+    pub(crate) fn read_exact_at(&self, buffer: &mut [u8], offset: u64) -> MiniBaseResult<()> {
+        match &self.backend {
+            Backend::File(file) => Ok(file.read_exact_at(buffer, offset)?),
+            Backend::Memory(memory) => memory.read_exact_at(buffer, offset),
+            Backend::Region(file, region) => file.read_exact_at(*region, buffer, offset),
+        }
+    }
+
+    // 与文件一致，写入超出末尾时延长文件
+    pub(crate) fn write_all_at(&self, data: &[u8], offset: u64) -> MiniBaseResult<()> {
+        match &self.backend {
+            Backend::File(file) => Ok(file.write_all_at(data, offset)?),
+            Backend::Memory(memory) => memory.write_all_at(data, offset),
+            Backend::Region(file, region) => file.write_all_at(*region, data, offset),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub(crate) enum Region {
+    Data,
+    Extra,
+}
+
+// 单文件模式下文件的布局为[元数据页][区域表][数据区域][额外数据区域]，数据区域从页大小对齐的位置开始
+// 区域表依次保存数据区域和额外数据区域的u64起始位置和u64长度，不属于元数据页，回滚预写日志时不会被覆盖
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct RegionTable {
+    data_start: u64,
+    data_length: u64,
+    extra_start: u64,
+    extra_length: u64,
+}
+
+impl RegionTable {
+    const LENGTH: usize = 32;
+
+    fn get(&self, region: Region) -> (u64, u64) {
+        match region {
+            Region::Data => (self.data_start, self.data_length),
+            Region::Extra => (self.extra_start, self.extra_length),
+        }
+    }
+
+    fn to_bytes(self) -> [u8; RegionTable::LENGTH] {
+        let mut data = [0; RegionTable::LENGTH];
+        for (i, value) in [self.data_start, self.data_length, self.extra_start, self.extra_length].into_iter().enumerate() {
+            data[i * 8..i * 8 + 8].copy_from_slice(&value.to_le_bytes());
+        }
+        data
+    }
+
+    fn from_bytes(data: &[u8; RegionTable::LENGTH]) -> RegionTable {
+        let read = |i: usize| u64::from_le_bytes(data[i * 8..i * 8 + 8].try_into().unwrap());
+        RegionTable { data_start: read(0), data_length: read(1), extra_start: read(2), extra_length: read(3) }
+    }
+}
+
+// 在单文件中元数据页之后写入区域表，table_position为元数据页的长度，两个区域都为空
+pub(crate) fn init_single_file(storage: &Storage, table_position: u64, page_size: u32) -> MiniBaseResult<()> {
+    let data_start = (table_position + RegionTable::LENGTH as u64).next_multiple_of(page_size as u64);
+    let table = RegionTable { data_start, data_length: 0, extra_start: data_start, extra_length: 0 };
+    storage.write_all_at(&table.to_bytes(), table_position)?;
+    storage.set_len(data_start)
+}
+
+// 单文件模式下的文件，数据区域和额外数据区域共享文件和区域表
+pub(crate) struct SingleFile {
+    file: File,
+    table_position: u64,
+    table: RwLock<RegionTable>,
+}
+
+impl SingleFile {
+    // 打开单文件中的数据区域和额外数据区域，区域表中的范围重叠或超出文件末尾时返回Corruption错误
+    pub(crate) fn open(file: File, table_position: u64) -> MiniBaseResult<(Storage, Storage)> {
+        let mut data = [0; RegionTable::LENGTH];
+        file.read_exact_at(&mut data, table_position)?;
+        let table = RegionTable::from_bytes(&data);
+        let file_length = file.metadata()?.len();
+        let valid = table.data_start >= table_position + RegionTable::LENGTH as u64
+            && table.data_start.checked_add(table.data_length).is_some_and(|end| end <= table.extra_start)
+            && table.extra_start.checked_add(table.extra_length).is_some_and(|end| end <= file_length);
+        if !valid {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("region table invalid {:?}, file length {}", table, file_length))));
+        }
+        let single_file = Arc::new(SingleFile { file, table_position, table: RwLock::new(table) });
+        let data_file = Storage { backend: Backend::Region(Arc::clone(&single_file), Region::Data), endian: Endian::Little };
+        Ok((data_file, Storage { backend: Backend::Region(single_file, Region::Extra), endian: Endian::Little }))
+    }
+
+    fn table(&self) -> RwLockReadGuard<'_, RegionTable> {
+        self.table.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // 额外数据区域位于文件末尾，直接修改文件长度
+    // 数据区域增长到额外数据区域时，先将额外数据区域复制到文件末尾并写入磁盘，再更新区域表，复制期间崩溃时原来的区域仍然有效
+    fn set_len(&self, region: Region, length: u64) -> MiniBaseResult<()> {
+        let mut table = self.table.write().unwrap_or_else(PoisonError::into_inner);
+        match region {
+            Region::Data => {
+                let old_length = table.data_length;
+                let end = table.data_start + length;
+                if end > table.extra_start {
+                    // 数据区域的容量至少翻倍，复制的总量与额外数据区域的大小成正比
+                    let capacity = length.max(2 * (table.extra_start - table.data_start));
+                    let extra_start = (table.extra_start + table.extra_length).max(table.data_start + capacity);
+                    self.copy(table.extra_start, extra_start, table.extra_length)?;
+                    self.file.set_len(extra_start + table.extra_length)?;
+                    self.file.sync_data()?;
+                    table.extra_start = extra_start;
+                    table.data_length = length;
+                    // 之后写入数据区域的页可能覆盖原来的额外数据区域，区域表需要先写入磁盘
+                    self.file.write_all_at(&table.to_bytes(), self.table_position)?;
+                    self.file.sync_data()?;
+                } else {
+                    table.data_length = length;
+                    self.file.write_all_at(&table.to_bytes(), self.table_position)?;
+                }
+                // 数据区域增长的部分可能是额外数据区域原来的位置，与文件一致读取为0
+                if length > old_length {
+                    self.fill_zero(table.data_start + old_length, length - old_length)?;
+                }
+            }
+            Region::Extra => {
+                table.extra_length = length;
+                self.file.set_len(table.extra_start + length)?;
+                self.file.write_all_at(&table.to_bytes(), self.table_position)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn copy(&self, from: u64, to: u64, length: u64) -> MiniBaseResult<()> {
+        let mut buffer = vec![0; 64 * 1024];
+        let mut position = 0;
+        while position < length {
+            let size = (length - position).min(buffer.len() as u64) as usize;
+            self.file.read_exact_at(&mut buffer[..size], from + position)?;
+            self.file.write_all_at(&buffer[..size], to + position)?;
+            position += size as u64;
+        }
+        Ok(())
+    }
+
+    fn fill_zero(&self, offset: u64, length: u64) -> MiniBaseResult<()> {
+        let zero = vec![0; length.min(64 * 1024) as usize];
+        let mut position = 0;
+        while position < length {
+            let size = (length - position).min(zero.len() as u64) as usize;
+            self.file.write_all_at(&zero[..size], offset + position)?;
+            position += size as u64;
+        }
+        Ok(())
+    }
+
+    fn read_exact_at(&self, region: Region, buffer: &mut [u8], offset: u64) -> MiniBaseResult<()> {
+        let table = self.table();
+        let (start, length) = table.get(region);
+        if offset + buffer.len() as u64 > length {
+            return Err(Box::from(io::Error::from(io::ErrorKind::UnexpectedEof)));
+        }
+        Ok(self.file.read_exact_at(buffer, start + offset)?)
+    }
+
+    fn write_all_at(&self, region: Region, data: &[u8], offset: u64) -> MiniBaseResult<()> {
+        if offset + data.len() as u64 > self.table().get(region).1 {
+            self.set_len(region, offset + data.len() as u64)?;
+        }
+        let table = self.table();
+        Ok(self.file.write_all_at(data, table.get(region).0 + offset)?)
+    }
+
+    // 检查[offset, offset + length)位于区域中，返回映射的文件和在文件中的位置
+    pub(crate) fn map_position(&self, region: Region, offset: u64, length: u32) -> MiniBaseResult<(&File, u64)> {
+        let (start, region_length) = self.table().get(region);
+        if offset + length as u64 > region_length {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("mapping exceeds region length, offset {} length {} region length {}", offset, length, region_length))));
+        }
+        Ok((&self.file, start + offset))
+    }
+}
+
+// 保存在匿名映射中的文件，映射不会移动，已经映射的页在文件增长后仍然有效
+pub(crate) struct MemoryFile {
+    segment_size: u64,
+    state: RwLock<MemoryState>,
+}
+
+struct MemoryState {
+    segments: Vec<Arc<Segment>>,
+    length: u64,
+}
+
+impl MemoryFile {
+    fn state(&self) -> std::sync::RwLockReadGuard<'_, MemoryState> {
+        self.state.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn set_len(&self, length: u64) -> MiniBaseResult<()> {
+        let mut state = self.state.write().unwrap_or_else(PoisonError::into_inner);
+        while (state.segments.len() as u64) * self.segment_size < length {
+            let mmap = MmapOptions::new().len(self.segment_size as usize).map_anon()?;
+            state.segments.push(Arc::new(Segment::new(mmap)));
+        }
+        // 缩短后再延长的部分与文件一致读取为0
+        if length < state.length {
+            let old_length = state.length;
+            state.length = length;
+            self.for_each_range(&state, length, (old_length - length) as usize, |segment, range| {
+                segment.fill_zero(range);
+            });
+        }
+        state.length = length;
+        Ok(())
+    }
+
+    fn read_exact_at(&self, buffer: &mut [u8], offset: u64) -> MiniBaseResult<()> {
+        let state = self.state();
+        if offset + buffer.len() as u64 > state.length {
+            return Err(Box::from(io::Error::from(io::ErrorKind::UnexpectedEof)));
+        }
+        let mut position = 0;
+        self.for_each_range(&state, offset, buffer.len(), |segment, range| {
+            let length = range.len();
+            segment.read(range, &mut buffer[position..position + length]);
+            position += length;
+        });
+        Ok(())
+    }
+
+    fn write_all_at(&self, data: &[u8], offset: u64) -> MiniBaseResult<()> {
+        if offset + data.len() as u64 > self.state().length {
+            self.set_len(offset + data.len() as u64)?;
+        }
+        let state = self.state();
+        let mut position = 0;
+        self.for_each_range(&state, offset, data.len(), |segment, range| {
+            let length = range.len();
+            segment.write(range, &data[position..position + length]);
+            position += length;
+        });
+        Ok(())
+    }
+
+    // 按段拆分[offset, offset + length)，依次访问每个段中的范围
+    fn for_each_range<F>(&self, state: &MemoryState, offset: u64, length: usize, mut visit: F)
+        where F: FnMut(&Segment, Range<usize>) {
+        let mut position = offset;
+        let end = offset + length as u64;
+        while position < end {
+            let segment = &state.segments[(position / self.segment_size) as usize];
+            let start = (position % self.segment_size) as usize;
+            let length = (end - position).min(self.segment_size - start as u64) as usize;
+            visit(segment, start..start + length);
+            position += length as u64;
+        }
+    }
+
+    // 映射[offset, offset + length)，与映射文件相同，多次映射同一个页时共享同一块内存
+    pub(crate) fn map(&self, offset: u64, length: u32) -> MiniBaseResult<MemoryMap> {
+        let state = self.state();
+        let start = (offset % self.segment_size) as usize;
+        if offset + length as u64 > state.length || start + length as usize > self.segment_size as usize {
+            return Err(Box::from(MiniBaseError::new(ErrorKind::Corruption, format!("mapping exceeds memory segment, offset {} length {} file length {}", offset, length, state.length))));
+        }
+        let segment = Arc::clone(&state.segments[(offset / self.segment_size) as usize]);
+        Ok(MemoryMap { segment, start, length: length as usize })
+    }
+}
+
+// 匿名映射的一段内存，页通过指针访问，与文件映射相同，由上层的读写锁保证不会同时修改同一个页
+struct Segment {
+    // 持有映射，drop时解除映射
+    _mmap: MmapMut,
+    pointer: *mut u8,
+    length: usize,
+}
+
+// 指针只指向segment持有的映射，映射在segment drop之前一直有效
+unsafe impl Send for Segment {}
+unsafe impl Sync for Segment {}
+
+impl Segment {
+    fn new(mut mmap: MmapMut) -> Segment {
+        let pointer = mmap.as_mut_ptr();
+        let length = mmap.len();
+        Segment { _mmap: mmap, pointer, length }
+    }
+
+    // 只通过指针访问range范围内的内存，不构造覆盖整个段的引用，不会与同一个段中其它页的映射重叠
+    fn range_pointer(&self, range: &Range<usize>) -> *mut u8 {
+        assert!(range.start <= range.end && range.end <= self.length);
+        unsafe { self.pointer.add(range.start) }
+    }
+
+    fn read(&self, range: Range<usize>, buffer: &mut [u8]) {
+        assert_eq!(range.len(), buffer.len());
+        unsafe { ptr::copy_nonoverlapping(self.range_pointer(&range), buffer.as_mut_ptr(), buffer.len()) }
+    }
+
+    fn write(&self, range: Range<usize>, data: &[u8]) {
+        assert_eq!(range.len(), data.len());
+        unsafe { ptr::copy_nonoverlapping(data.as_ptr(), self.range_pointer(&range), data.len()) }
+    }
+
+    fn fill_zero(&self, range: Range<usize>) {
+        unsafe { ptr::write_bytes(self.range_pointer(&range), 0, range.len()) }
+    }
+}
+
+// 内存文件中一个页的映射，持有所在的段，文件drop之后仍然有效
+pub(crate) struct MemoryMap {
+    segment: Arc<Segment>,
+    start: usize,
+    length: usize,
+}
+
+impl Deref for MemoryMap {
+    type Target = [u8];
+
+    // 只构造这个页的切片，map时已经检查页位于段内
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.segment.pointer.add(self.start), self.length) }
+    }
+}
+
+impl DerefMut for MemoryMap {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.segment.pointer.add(self.start), self.length) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::{Backend, Storage};
+
+    #[test]
+    fn memory_storage() {
+        let storage = Storage::memory(512);
+        assert_eq!(0, storage.len().unwrap());
+        let Backend::Memory(memory) = &storage.backend else { unreachable!() };
+        assert!(memory.map(0, 512).is_err());
+
+        // 跨越段的读写
+        let data: Vec<u8> = (0..200u32).map(|i| i as u8).collect();
+        let offset = 64 * 1024 - 100;
+        storage.write_all_at(&data, offset).unwrap();
+        assert_eq!(offset + 200, storage.len().unwrap());
+        let mut buffer = vec![0; 200];
+        storage.read_exact_at(&mut buffer, offset).unwrap();
+        assert_eq!(data, buffer);
+        assert!(storage.read_exact_at(&mut buffer, offset + 1).is_err());
+
+        // 同一个页的多个映射共享内存，文件增长后映射仍然有效
+        storage.set_len(1024).unwrap();
+        let mut first = memory.map(512, 512).unwrap();
+        let second = memory.map(512, 512).unwrap();
+        first[10] = 42;
+        storage.set_len(1024 * 1024).unwrap();
+        assert_eq!(42, second[10]);
+        let mut byte = [0; 1];
+        storage.read_exact_at(&mut byte, 522).unwrap();
+        assert_eq!(42, byte[0]);
+
+        // 缩短后再延长的部分读取为0
+        storage.set_len(512).unwrap();
+        storage.set_len(1024).unwrap();
+        storage.read_exact_at(&mut byte, 522).unwrap();
+        assert_eq!(0, byte[0]);
+        drop(storage);
+        assert_eq!(0, second[10]);
+    }
+}
@@ -0,0 +1,682 @@
+use std::fmt::Display;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+use crate::{ErrorKind, MiniBaseError, MiniBaseResult};
+
+// 按字节序比较编码结果与比较原始值的顺序一致，key和value使用同一种编码
+// 整数按大端序编码，有符号整数翻转符号位，浮点数为正时翻转符号位，为负时翻转所有位
+// 字符串和字节数组中的0x00编码为0x00 0xFF，以0x00 0x01结尾，与encode_composite_key的分量相同
+// 序列和map的每个元素之前写入0x01，结尾写入0x00，因此一个序列是另一个序列的前缀时排在前面
+// Option的None为0x00，Some为0x01加上值，枚举写入u32大端序的变体序号，结构体和元组依次写入各个字段
+// 编码不包含字段名和类型，解码时必须使用编码时的类型；HashMap的遍历顺序不确定，作为key时应使用BTreeMap
+const ESCAPE: u8 = 0x00;
+const ESCAPED_ZERO: u8 = 0xFF;
+const TERMINATOR: u8 = 0x01;
+const END_MARKER: u8 = 0x00;
+const ELEMENT_MARKER: u8 = 0x01;
+
+// 将value编码为保持顺序的字节，可以作为key或value写入
+pub fn encode_typed<T: Serialize + ?Sized>(value: &T) -> MiniBaseResult<Vec<u8>> {
+    let mut encoder = Encoder { output: Vec::new() };
+    value.serialize(&mut encoder)?;
+    Ok(encoder.output)
+}
+
+// 将encode_typed编码的字节按类型T解码，数据不完整、有多余的数据或与类型不一致时返回InvalidArgument
+pub fn decode_typed<T: DeserializeOwned>(data: &[u8]) -> MiniBaseResult<T> {
+    let mut decoder = Decoder { input: data };
+    let value = T::deserialize(&mut decoder)?;
+    if !decoder.input.is_empty() {
+        return Err(Box::from(invalid(format!("{} trailing bytes after typed value", decoder.input.len()))));
+    }
+    Ok(value)
+}
+
+fn invalid(message: impl Into<String>) -> MiniBaseError {
+    MiniBaseError::new(ErrorKind::InvalidArgument, message)
+}
+
+impl ser::Error for MiniBaseError {
+    fn custom<T: Display>(message: T) -> MiniBaseError {
+        invalid(message.to_string())
+    }
+}
+
+impl de::Error for MiniBaseError {
+    fn custom<T: Display>(message: T) -> MiniBaseError {
+        invalid(message.to_string())
+    }
+}
+
+struct Encoder {
+    output: Vec<u8>,
+}
+
+impl Encoder {
+    fn write_escaped(&mut self, data: &[u8]) {
+        for byte in data {
+            self.output.push(*byte);
+            if *byte == ESCAPE {
+                self.output.push(ESCAPED_ZERO);
+            }
+        }
+        self.output.extend_from_slice(&[ESCAPE, TERMINATOR]);
+    }
+}
+
+impl ser::Serializer for &mut Encoder {
+    type Ok = ();
+    type Error = MiniBaseError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, value: bool) -> Result<(), MiniBaseError> {
+        self.output.push(value as u8);
+        Ok(())
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<(), MiniBaseError> {
+        self.serialize_u8(value as u8 ^ 0x80)
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<(), MiniBaseError> {
+        self.serialize_u16(value as u16 ^ (1 << 15))
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<(), MiniBaseError> {
+        self.serialize_u32(value as u32 ^ (1 << 31))
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<(), MiniBaseError> {
+        self.serialize_u64(value as u64 ^ (1 << 63))
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<(), MiniBaseError> {
+        self.serialize_u128(value as u128 ^ (1 << 127))
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<(), MiniBaseError> {
+        self.output.push(value);
+        Ok(())
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<(), MiniBaseError> {
+        self.output.extend_from_slice(&value.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<(), MiniBaseError> {
+        self.output.extend_from_slice(&value.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<(), MiniBaseError> {
+        self.output.extend_from_slice(&value.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<(), MiniBaseError> {
+        self.output.extend_from_slice(&value.to_be_bytes());
+        Ok(())
+    }
+
+    // 负数翻转所有位后绝对值越大越小，-0.0排在0.0之前，NaN按符号位排在两端
+    fn serialize_f32(self, value: f32) -> Result<(), MiniBaseError> {
+        let bits = value.to_bits();
+        self.serialize_u32(if bits >> 31 == 1 { !bits } else { bits ^ (1 << 31) })
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<(), MiniBaseError> {
+        let bits = value.to_bits();
+        self.serialize_u64(if bits >> 63 == 1 { !bits } else { bits ^ (1 << 63) })
+    }
+
+    fn serialize_char(self, value: char) -> Result<(), MiniBaseError> {
+        self.serialize_u32(value as u32)
+    }
+
+    fn serialize_str(self, value: &str) -> Result<(), MiniBaseError> {
+        self.write_escaped(value.as_bytes());
+        Ok(())
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<(), MiniBaseError> {
+        self.write_escaped(value);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), MiniBaseError> {
+        self.output.push(END_MARKER);
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), MiniBaseError> {
+        self.output.push(ELEMENT_MARKER);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), MiniBaseError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), MiniBaseError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str) -> Result<(), MiniBaseError> {
+        self.serialize_u32(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<(), MiniBaseError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, variant_index: u32, _variant: &'static str, value: &T) -> Result<(), MiniBaseError> {
+        self.serialize_u32(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, MiniBaseError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, MiniBaseError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, MiniBaseError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self, MiniBaseError> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self, MiniBaseError> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, MiniBaseError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self, MiniBaseError> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl ser::SerializeSeq for &mut Encoder {
+    type Ok = ();
+    type Error = MiniBaseError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MiniBaseError> {
+        self.output.push(ELEMENT_MARKER);
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), MiniBaseError> {
+        self.output.push(END_MARKER);
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut Encoder {
+    type Ok = ();
+    type Error = MiniBaseError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MiniBaseError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), MiniBaseError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut Encoder {
+    type Ok = ();
+    type Error = MiniBaseError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MiniBaseError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), MiniBaseError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut Encoder {
+    type Ok = ();
+    type Error = MiniBaseError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MiniBaseError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), MiniBaseError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut Encoder {
+    type Ok = ();
+    type Error = MiniBaseError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), MiniBaseError> {
+        self.output.push(ELEMENT_MARKER);
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MiniBaseError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), MiniBaseError> {
+        self.output.push(END_MARKER);
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut Encoder {
+    type Ok = ();
+    type Error = MiniBaseError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<(), MiniBaseError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), MiniBaseError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut Encoder {
+    type Ok = ();
+    type Error = MiniBaseError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<(), MiniBaseError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), MiniBaseError> {
+        Ok(())
+    }
+}
+
+struct Decoder<'de> {
+    input: &'de [u8],
+}
+
+impl<'de> Decoder<'de> {
+    fn read_bytes<const N: usize>(&mut self) -> Result<[u8; N], MiniBaseError> {
+        if self.input.len() < N {
+            return Err(invalid("typed value truncated"));
+        }
+        let (bytes, rest) = self.input.split_at(N);
+        self.input = rest;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn read_u8(&mut self) -> Result<u8, MiniBaseError> {
+        Ok(self.read_bytes::<1>()?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, MiniBaseError> {
+        Ok(u32::from_be_bytes(self.read_bytes()?))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, MiniBaseError> {
+        Ok(u64::from_be_bytes(self.read_bytes()?))
+    }
+
+    fn read_escaped(&mut self) -> Result<Vec<u8>, MiniBaseError> {
+        let mut data = Vec::new();
+        loop {
+            let byte = self.read_u8()?;
+            if byte != ESCAPE {
+                data.push(byte);
+                continue;
+            }
+            match self.read_u8()? {
+                ESCAPED_ZERO => data.push(ESCAPE),
+                TERMINATOR => return Ok(data),
+                _ => return Err(invalid("typed value invalid escape")),
+            }
+        }
+    }
+
+    fn read_string(&mut self) -> Result<String, MiniBaseError> {
+        String::from_utf8(self.read_escaped()?).map_err(|_| invalid("typed value string is not valid utf8"))
+    }
+
+    // 读取序列或map中元素之前的标记，返回之后是否还有元素
+    fn read_marker(&mut self) -> Result<bool, MiniBaseError> {
+        match self.read_u8()? {
+            END_MARKER => Ok(false),
+            ELEMENT_MARKER => Ok(true),
+            marker => Err(invalid(format!("typed value marker {} invalid", marker))),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Decoder<'de> {
+    type Error = MiniBaseError;
+
+    // 编码中没有类型信息，只能按调用方指定的类型解码
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, MiniBaseError> {
+        Err(invalid("typed encoding is not self-describing"))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        match self.read_u8()? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            value => Err(invalid(format!("typed value bool {} invalid", value))),
+        }
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_i8((self.read_u8()? ^ 0x80) as i8)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_i16((u16::from_be_bytes(self.read_bytes()?) ^ (1 << 15)) as i16)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_i32((self.read_u32()? ^ (1 << 31)) as i32)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_i64((self.read_u64()? ^ (1 << 63)) as i64)
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_i128((u128::from_be_bytes(self.read_bytes()?) ^ (1 << 127)) as i128)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_u8(self.read_u8()?)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_u16(u16::from_be_bytes(self.read_bytes()?))
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_u32(self.read_u32()?)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_u64(self.read_u64()?)
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_u128(u128::from_be_bytes(self.read_bytes()?))
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        let bits = self.read_u32()?;
+        visitor.visit_f32(f32::from_bits(if bits >> 31 == 1 { bits ^ (1 << 31) } else { !bits }))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        let bits = self.read_u64()?;
+        visitor.visit_f64(f64::from_bits(if bits >> 63 == 1 { bits ^ (1 << 63) } else { !bits }))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        let value = self.read_u32()?;
+        visitor.visit_char(char::from_u32(value).ok_or_else(|| invalid(format!("typed value char {} invalid", value)))?)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_string(self.read_string()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_string(self.read_string()?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_byte_buf(self.read_escaped()?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_byte_buf(self.read_escaped()?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        match self.read_marker()? {
+            false => visitor.visit_none(),
+            true => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_seq(MarkedAccess { decoder: self })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_seq(FixedAccess { decoder: self, remaining: len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_seq(FixedAccess { decoder: self, remaining: len })
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_map(MarkedAccess { decoder: self })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_seq(FixedAccess { decoder: self, remaining: fields.len() })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, MiniBaseError> {
+        Err(invalid("typed encoding does not contain identifiers"))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, MiniBaseError> {
+        Err(invalid("typed encoding can not skip values"))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+// 序列和map，每个元素之前有标记
+struct MarkedAccess<'a, 'de> {
+    decoder: &'a mut Decoder<'de>,
+}
+
+impl<'de> de::SeqAccess<'de> for MarkedAccess<'_, 'de> {
+    type Error = MiniBaseError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, MiniBaseError> {
+        match self.decoder.read_marker()? {
+            false => Ok(None),
+            true => seed.deserialize(&mut *self.decoder).map(Some),
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for MarkedAccess<'_, 'de> {
+    type Error = MiniBaseError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, MiniBaseError> {
+        match self.decoder.read_marker()? {
+            false => Ok(None),
+            true => seed.deserialize(&mut *self.decoder).map(Some),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, MiniBaseError> {
+        seed.deserialize(&mut *self.decoder)
+    }
+}
+
+// 元组和结构体，字段数量由类型确定
+struct FixedAccess<'a, 'de> {
+    decoder: &'a mut Decoder<'de>,
+    remaining: usize,
+}
+
+impl<'de> de::SeqAccess<'de> for FixedAccess<'_, 'de> {
+    type Error = MiniBaseError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, MiniBaseError> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.decoder).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut Decoder<'de> {
+    type Error = MiniBaseError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), MiniBaseError> {
+        let variant_index: de::value::U32Deserializer<MiniBaseError> = self.read_u32()?.into_deserializer();
+        Ok((seed.deserialize(variant_index)?, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut Decoder<'de> {
+    type Error = MiniBaseError;
+
+    fn unit_variant(self) -> Result<(), MiniBaseError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, MiniBaseError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_seq(FixedAccess { decoder: self, remaining: len })
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, MiniBaseError> {
+        visitor.visit_seq(FixedAccess { decoder: self, remaining: fields.len() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use serde::{Deserialize, Serialize};
+    use crate::typed::{decode_typed, encode_typed};
+    use crate::{error_kind, ErrorKind};
+
+    #[derive(Debug, PartialEq, PartialOrd, Clone, Serialize, Deserialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Rect { width: u32, height: u32 },
+    }
+
+    #[derive(Debug, PartialEq, PartialOrd, Clone, Serialize, Deserialize)]
+    struct Record {
+        id: i32,
+        name: String,
+        tags: Vec<String>,
+        parent: Option<u64>,
+        shape: Shape,
+    }
+
+    // 按值排列的序列编码后按字节序也是升序，并且可以解码还原
+    fn assert_ordered<T>(ordered: &[T])
+        where T: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug {
+        let encoded: Vec<_> = ordered.iter().map(|value| encode_typed(value).unwrap()).collect();
+        for (index, pair) in encoded.windows(2).enumerate() {
+            assert!(pair[0] < pair[1], "{:?} {:?}", ordered[index], ordered[index + 1]);
+        }
+        for (value, data) in ordered.iter().zip(encoded.iter()) {
+            assert_eq!(*value, decode_typed::<T>(data).unwrap());
+        }
+    }
+
+    #[test]
+    fn typed_order() {
+        assert_ordered(&[i64::MIN, -1000, -1, 0, 1, 255, 256, i64::MAX]);
+        assert_ordered(&[i8::MIN, -1, 0, i8::MAX]);
+        assert_ordered(&[0u16, 1, 256, u16::MAX]);
+        assert_ordered(&[i128::MIN, -1, 0, i128::MAX]);
+        assert_ordered(&[f64::NEG_INFINITY, -1.5, -0.0, 0.0, 1e-10, 1.5, 1e10, f64::INFINITY]);
+        assert_ordered(&[-2.5f32, 0.0, 2.5]);
+        assert_ordered(&[false, true]);
+        assert_ordered(&['\0', 'a', 'é', '中']);
+        assert_ordered(&["", "\0", "\0\0", "a", "a\0", "ab", "b"].map(String::from));
+        assert_ordered(&[None, Some(0u8), Some(1)]);
+        assert_ordered(&[vec![], vec![0u32], vec![0, 0], vec![0, 1], vec![1]]);
+        assert_ordered(&[(1u8, String::from("b")), (1, String::from("bb")), (2, String::new())]);
+        assert_ordered(&[Shape::Point, Shape::Circle(-1.0), Shape::Circle(2.0), Shape::Rect { width: 1, height: 5 }, Shape::Rect { width: 2, height: 0 }]);
+
+        let record = |id: i32, name: &str, tags: &[&str], parent: Option<u64>| Record {
+            id, name: name.to_string(), tags: tags.iter().map(|tag| tag.to_string()).collect(), parent, shape: Shape::Point,
+        };
+        assert_ordered(&[
+            record(-5, "z", &[], None),
+            record(3, "a", &["x"], Some(1)),
+            record(3, "ab", &[], None),
+            record(3, "ab", &["a"], None),
+            record(3, "ab", &["a", ""], Some(0)),
+            record(3, "ab", &["b"], None),
+        ]);
+
+        let map: BTreeMap<String, Vec<u8>> = [("a", vec![1]), ("b", vec![])].into_iter().map(|(key, value)| (key.to_string(), value)).collect();
+        assert_eq!(map, decode_typed::<BTreeMap<String, Vec<u8>>>(&encode_typed(&map).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn typed_invalid() {
+        let data = encode_typed(&(1u32, String::from("a"))).unwrap();
+        for length in 0..data.len() {
+            let error = decode_typed::<(u32, String)>(&data[..length]).unwrap_err();
+            assert_eq!(ErrorKind::InvalidArgument, error_kind(error.as_ref()));
+        }
+        assert!(decode_typed::<u32>(&data).is_err());
+        assert!(decode_typed::<bool>(&[2]).is_err());
+        assert!(decode_typed::<String>(&[0xFF, 0, 1]).is_err());
+        assert!(decode_typed::<String>(&[b'a', 0, 2]).is_err());
+        assert!(decode_typed::<Option<u8>>(&[2, 0]).is_err());
+        assert!(decode_typed::<char>(&0xD800u32.to_be_bytes()).is_err());
+        assert!(decode_typed::<Shape>(&3u32.to_be_bytes()).is_err());
+    }
+}
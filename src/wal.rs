@@ -0,0 +1,235 @@
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use crate::MiniBaseResult;
+use crate::checksum::crc32;
+use crate::storage::Storage;
+
+const CHECKPOINT_RECORD: u8 = 1;
+const PAGE_RECORD: u8 = 2;
+const PUT_RECORD: u8 = 3;
+const REMOVE_RECORD: u8 = 4;
+const REMOVE_VALUE_RECORD: u8 = 5;
+const PUT_WITH_EXPIRY_RECORD: u8 = 6;
+
+// 日志记录，写入文件时为[tag u8][length u32][data][crc32 u32]
+#[derive(Debug, PartialEq)]
+pub(crate) enum WalRecord {
+    // 检查点时数据文件的长度和元数据文件的内容
+    Checkpoint { data_length: u64, mete_data: Vec<u8> },
+    // 页在检查点时的原始数据
+    Page { offset: u64, data: Vec<u8> },
+    Put { key: Vec<u8>, value: Vec<u8> },
+    Remove { key: Vec<u8> },
+    // 多值模式下删除key中等于value的值
+    RemoveValue { key: Vec<u8>, value: Vec<u8> },
+    // 写入设置了过期时间的value，expiry为自UNIX_EPOCH起的毫秒数
+    PutWithExpiry { key: Vec<u8>, value: Vec<u8>, expiry: u64 },
+}
+
+impl WalRecord {
+    fn encode(&self) -> Vec<u8> {
+        let (tag, mut data) = match self {
+            WalRecord::Checkpoint { data_length, mete_data } => (CHECKPOINT_RECORD, [&data_length.to_le_bytes()[..], mete_data].concat()),
+            WalRecord::Page { offset, data } => (PAGE_RECORD, [&offset.to_le_bytes()[..], data].concat()),
+            WalRecord::Put { key, value } => (PUT_RECORD, [&(key.len() as u32).to_le_bytes()[..], key, value].concat()),
+            WalRecord::Remove { key } => (REMOVE_RECORD, key.clone()),
+            WalRecord::RemoveValue { key, value } => (REMOVE_VALUE_RECORD, [&(key.len() as u32).to_le_bytes()[..], key, value].concat()),
+            WalRecord::PutWithExpiry { key, value, expiry } => (PUT_WITH_EXPIRY_RECORD, [&expiry.to_le_bytes()[..], &(key.len() as u32).to_le_bytes(), key, value].concat()),
+        };
+        let mut record = vec![tag];
+        record.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        record.append(&mut data);
+        let checksum = crc32(&record);
+        record.extend_from_slice(&checksum.to_le_bytes());
+        record
+    }
+
+    // 解码buffer开头的一条记录，返回记录和记录的长度，记录不完整或校验失败时返回None
+    fn decode(buffer: &[u8]) -> Option<(WalRecord, usize)> {
+        let length = u32::from_le_bytes(buffer.get(1..5)?.try_into().unwrap()) as usize;
+        let end = 5usize.checked_add(length)?;
+        let checksum = u32::from_le_bytes(buffer.get(end..end.checked_add(4)?)?.try_into().unwrap());
+        if crc32(&buffer[..end]) != checksum {
+            return None;
+        }
+        let data = &buffer[5..end];
+        let record = match buffer[0] {
+            CHECKPOINT_RECORD if data.len() >= 8 => WalRecord::Checkpoint { data_length: u64::from_le_bytes(data[..8].try_into().unwrap()), mete_data: data[8..].to_vec() },
+            PAGE_RECORD if data.len() >= 8 => WalRecord::Page { offset: u64::from_le_bytes(data[..8].try_into().unwrap()), data: data[8..].to_vec() },
+            PUT_RECORD | REMOVE_VALUE_RECORD if data.len() >= 4 => {
+                let key_size = u32::from_le_bytes(data[..4].try_into().unwrap()) as usize;
+                let (key, value) = (data[4..].get(..key_size)?.to_vec(), data[4 + key_size..].to_vec());
+                if buffer[0] == PUT_RECORD {
+                    WalRecord::Put { key, value }
+                } else {
+                    WalRecord::RemoveValue { key, value }
+                }
+            }
+            REMOVE_RECORD => WalRecord::Remove { key: data.to_vec() },
+            PUT_WITH_EXPIRY_RECORD if data.len() >= 12 => {
+                let expiry = u64::from_le_bytes(data[..8].try_into().unwrap());
+                let key_size = u32::from_le_bytes(data[8..12].try_into().unwrap()) as usize;
+                let (key, value) = (data[12..].get(..key_size)?.to_vec(), data[12 + key_size..].to_vec());
+                WalRecord::PutWithExpiry { key, value, expiry }
+            }
+            _ => return None,
+        };
+        Some((record, end + 4))
+    }
+}
+
+// 预写日志，写操作中途崩溃后可以通过日志恢复
+// 检查点之后的第一个写操作开始前记录数据文件的长度和元数据文件的内容，每个页第一次被修改前记录页的原始数据
+// 写操作成功后记录操作本身，恢复时先用原始数据将数据文件回滚到检查点，再重放检查点之后的写操作
+// 所有记录写入后都会fsync，flush将所有修改写入磁盘后清空日志，作为新的检查点
+pub(crate) struct Wal {
+    file: File,
+    // 检查点时数据文件的长度，之后追加的页不需要记录原始数据，日志为空时为None
+    data_length: Option<u64>,
+    // 检查点之后已经记录原始数据的页
+    journaled_pages: HashSet<u64>,
+    // 只在写操作期间记录页的原始数据，读操作加载的页不会被修改
+    active: bool,
+}
+
+impl Wal {
+    pub(crate) fn open(path: &str) -> MiniBaseResult<Wal> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        Ok(Wal { file, data_length: None, journaled_pages: HashSet::new(), active: false })
+    }
+
+    // 读取日志中所有完整的记录，最后一条记录可能在写入中途崩溃，不完整的部分会被忽略
+    pub(crate) fn read_records(&mut self) -> MiniBaseResult<Vec<WalRecord>> {
+        let mut buffer = Vec::new();
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.read_to_end(&mut buffer)?;
+        let mut records = Vec::new();
+        let mut position = 0;
+        while let Some((record, length)) = WalRecord::decode(&buffer[position..]) {
+            records.push(record);
+            position += length;
+        }
+        Ok(records)
+    }
+
+    // 恢复时数据文件已经回滚到检查点，继续使用日志中已经记录的原始数据
+    pub(crate) fn resume(&mut self, data_length: u64, journaled_pages: HashSet<u64>) -> MiniBaseResult<()> {
+        // 截断不完整的记录，之后的记录追加在完整的记录之后
+        let length = self.read_records()?.iter().map(|record| record.encode().len() as u64).sum();
+        self.file.set_len(length)?;
+        self.data_length = Some(data_length);
+        self.journaled_pages = journaled_pages;
+        Ok(())
+    }
+
+    // 写操作开始，检查点之后的第一个写操作先记录检查点
+    pub(crate) fn begin(&mut self, data_file: &Storage, mete_data: &[u8]) -> MiniBaseResult<()> {
+        if self.data_length.is_none() {
+            let data_length = data_file.len()?;
+            self.append(&WalRecord::Checkpoint { data_length, mete_data: mete_data.to_vec() })?;
+            self.file.sync_data()?;
+            self.data_length = Some(data_length);
+        }
+        self.active = true;
+        Ok(())
+    }
+
+    // 写操作结束，records为写操作成功时需要重放的操作
+    pub(crate) fn end(&mut self, records: &[WalRecord]) -> MiniBaseResult<()> {
+        self.active = false;
+        if records.is_empty() {
+            return Ok(());
+        }
+        for record in records {
+            self.append(record)?;
+        }
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    // 页第一次被修改前记录原始数据，检查点之后追加的页不需要记录
+    pub(crate) fn journal_page(&mut self, data_file: &Storage, offset: u64, page_size: u32) -> MiniBaseResult<()> {
+        match self.data_length {
+            Some(data_length) if self.active && offset < data_length && !self.journaled_pages.contains(&offset) => {
+                let mut data = vec![0; page_size as usize];
+                data_file.read_exact_at(&mut data, offset)?;
+                self.append(&WalRecord::Page { offset, data })?;
+                self.file.sync_data()?;
+                self.journaled_pages.insert(offset);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // 所有修改已经写入磁盘，清空日志
+    pub(crate) fn checkpoint(&mut self) -> MiniBaseResult<()> {
+        self.file.set_len(0)?;
+        self.file.sync_all()?;
+        self.data_length = None;
+        self.journaled_pages.clear();
+        Ok(())
+    }
+
+    fn append(&mut self, record: &WalRecord) -> MiniBaseResult<()> {
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(&record.encode())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+    use crate::wal::{Wal, WalRecord};
+    use crate::storage::Storage;
+
+    #[test]
+    fn wal_records() {
+        let dir_name = "wal_records";
+        fs::create_dir_all(dir_name).unwrap();
+        let path = format!("{}/test.wal", dir_name);
+        let data_file = Storage::from(fs::OpenOptions::new().read(true).write(true).create(true).truncate(false)
+            .open(format!("{}/test.d", dir_name)).unwrap());
+        data_file.set_len(128).unwrap();
+
+        let mut wal = Wal::open(&path).unwrap();
+        // 不在写操作期间时不记录页
+        wal.journal_page(&data_file, 0, 64).unwrap();
+        wal.begin(&data_file, "mete".as_bytes()).unwrap();
+        wal.journal_page(&data_file, 0, 64).unwrap();
+        wal.journal_page(&data_file, 0, 64).unwrap();
+        // 检查点之后追加的页不需要记录
+        wal.journal_page(&data_file, 128, 64).unwrap();
+        let put = WalRecord::Put { key: "key".as_bytes().to_vec(), value: "value".as_bytes().to_vec() };
+        let remove = WalRecord::Remove { key: "key".as_bytes().to_vec() };
+        let remove_value = WalRecord::RemoveValue { key: "key".as_bytes().to_vec(), value: "value".as_bytes().to_vec() };
+        let put_with_expiry = WalRecord::PutWithExpiry { key: "key".as_bytes().to_vec(), value: "value".as_bytes().to_vec(), expiry: 1000 };
+        wal.end(&[put, remove, remove_value, put_with_expiry]).unwrap();
+        let expected = vec![
+            WalRecord::Checkpoint { data_length: 128, mete_data: "mete".as_bytes().to_vec() },
+            WalRecord::Page { offset: 0, data: vec![0; 64] },
+            WalRecord::Put { key: "key".as_bytes().to_vec(), value: "value".as_bytes().to_vec() },
+            WalRecord::Remove { key: "key".as_bytes().to_vec() },
+            WalRecord::RemoveValue { key: "key".as_bytes().to_vec(), value: "value".as_bytes().to_vec() },
+            WalRecord::PutWithExpiry { key: "key".as_bytes().to_vec(), value: "value".as_bytes().to_vec(), expiry: 1000 },
+        ];
+        assert_eq!(expected, wal.read_records().unwrap());
+
+        // 写入中途崩溃的记录被忽略
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(&[3, 100, 0, 0, 0, 1]).unwrap();
+        let mut wal = Wal::open(&path).unwrap();
+        assert_eq!(expected, wal.read_records().unwrap());
+        wal.resume(128, [0].into_iter().collect()).unwrap();
+        wal.begin(&data_file, "mete".as_bytes()).unwrap();
+        wal.end(&[WalRecord::Remove { key: Vec::new() }]).unwrap();
+        assert_eq!(7, wal.read_records().unwrap().len());
+
+        wal.checkpoint().unwrap();
+        assert!(wal.read_records().unwrap().is_empty());
+
+        fs::remove_dir_all(dir_name).unwrap()
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use mini_base::{create_schema, Controller};
+
+// 确定性的伪随机数生成器（xorshift64*），相同的seed生成相同的操作序列
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+const KEY_MAX_LENGTH: u32 = 16;
+const OPERATION_COUNT: usize = 3000;
+
+// key的取值范围较小，保证覆盖写和删除已存在的key
+fn random_key(rng: &mut Rng) -> Vec<u8> {
+    let length = 1 + rng.below(4) as usize;
+    (0..length).map(|_| b'a' + rng.below(6) as u8).collect()
+}
+
+// 少量较长的value写入额外数据文件
+fn random_value(rng: &mut Rng) -> Vec<u8> {
+    let length = if rng.below(10) == 0 { 100 + rng.below(200) } else { rng.below(20) } as usize;
+    (0..length).map(|_| rng.next() as u8).collect()
+}
+
+fn scan_oracle(oracle: &BTreeMap<Vec<u8>, Vec<u8>>, begin: &[u8], end: &[u8], end_inclusive: bool) -> Vec<(Vec<u8>, Vec<u8>)> {
+    oracle.iter()
+        .filter(|(key, _)| key.as_slice() >= begin && (key.as_slice() < end || end_inclusive && key.as_slice() == end))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+fn run(seed: u64) {
+    let dir_name = format!("oracle_{}", seed);
+    fs::create_dir_all(&dir_name).unwrap();
+    let controller = create_schema(&dir_name, "test", 256, KEY_MAX_LENGTH, 64).unwrap().controller().unwrap();
+    let mut oracle = BTreeMap::new();
+    let mut rng = Rng::new(seed);
+    for step in 0..OPERATION_COUNT {
+        let context = format!("seed {} step {}", seed, step);
+        match rng.below(10) {
+            0..=3 => {
+                let (key, value) = (random_key(&mut rng), random_value(&mut rng));
+                controller.put_bytes(&key, &value).unwrap_or_else(|error| panic!("{}: put failed: {}", context, error));
+                oracle.insert(key, value);
+            }
+            4..=5 => {
+                let key = random_key(&mut rng);
+                let removed = controller.remove_bytes(&key).unwrap_or_else(|error| panic!("{}: remove failed: {}", context, error));
+                assert_eq!(oracle.remove(&key).is_some(), removed, "{}: remove {:?}", context, key);
+            }
+            6..=7 => {
+                let key = random_key(&mut rng);
+                let value = controller.get_bytes(&key).unwrap_or_else(|error| panic!("{}: get failed: {}", context, error));
+                assert_eq!(oracle.get(&key).cloned(), value, "{}: get {:?}", context, key);
+            }
+            _ => {
+                let (mut begin, mut end) = (random_key(&mut rng), random_key(&mut rng));
+                if begin > end {
+                    std::mem::swap(&mut begin, &mut end);
+                }
+                let end_inclusive = rng.below(2) == 0;
+                let result = if end_inclusive {
+                    controller.scan_inclusive_bytes(&begin, &end)
+                } else {
+                    controller.scan_bytes(&begin, &end)
+                }.unwrap_or_else(|error| panic!("{}: scan failed: {}", context, error));
+                assert_eq!(scan_oracle(&oracle, &begin, &end, end_inclusive), result, "{}: scan {:?} {:?} inclusive {}", context, begin, end, end_inclusive);
+            }
+        }
+    }
+    check_all(&controller, &oracle, seed);
+    controller.verify().unwrap_or_else(|error| panic!("seed {}: verify failed: {}", seed, error));
+    drop(controller);
+    fs::remove_dir_all(&dir_name).unwrap();
+}
+
+fn check_all(controller: &Controller, oracle: &BTreeMap<Vec<u8>, Vec<u8>>, seed: u64) {
+    let result: Vec<_> = controller.iter().collect::<Result<_, _>>().unwrap_or_else(|error| panic!("seed {}: iter failed: {}", seed, error));
+    let expected: Vec<_> = oracle.iter().map(|(key, value)| (key.clone(), value.clone())).collect();
+    assert_eq!(expected, result, "seed {}: iter", seed);
+    assert_eq!(oracle.len() as u64, controller.len().unwrap(), "seed {}: len", seed);
+}
+
+// 设置MINI_BASE_SEED时只运行指定的seed，用于复现失败
+#[test]
+fn oracle_random_operations() {
+    match env::var("MINI_BASE_SEED") {
+        Ok(seed) => run(seed.parse().expect("MINI_BASE_SEED should be u64")),
+        Err(_) => {
+            for seed in 0..8 {
+                run(seed);
+            }
+        }
+    }
+}